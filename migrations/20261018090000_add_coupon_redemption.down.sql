@@ -0,0 +1,7 @@
+ALTER TABLE "shop"."user_order"
+    ALTER COLUMN payment_method_info SET DEFAULT '{}';
+UPDATE "shop"."user_order"
+SET payment_method_info = '{}'
+WHERE payment_method_info = 'null';
+
+DROP TABLE IF EXISTS "shop"."coupon_redemption";
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS "shop"."coupon_redemption"
+(
+    id          BIGSERIAL PRIMARY KEY,
+    coupon_id   INTEGER   NOT NULL REFERENCES "shop"."coupon" (id) ON DELETE CASCADE,
+    user_id     UUID      NOT NULL REFERENCES "auth"."user_account" (id) ON DELETE CASCADE,
+    order_id    UUID      NOT NULL UNIQUE REFERENCES "shop"."user_order" (id) ON DELETE CASCADE,
+    redeemed_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_coupon_redemption_coupon_user ON "shop"."coupon_redemption" (coupon_id, user_id);
+
+-- unpaid orders carry no payment info yet
+ALTER TABLE "shop"."user_order"
+    ALTER COLUMN payment_method_info SET DEFAULT 'null';
+UPDATE "shop"."user_order"
+SET payment_method_info = 'null'
+WHERE payment_method_info = '{}';
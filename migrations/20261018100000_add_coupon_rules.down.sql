@@ -0,0 +1,13 @@
+-- orders with stacked coupons keep only the first redemption
+DELETE FROM "shop"."coupon_redemption" r
+    USING "shop"."coupon_redemption" k
+WHERE r.order_id = k.order_id AND r.id > k.id;
+ALTER TABLE "shop"."coupon_redemption"
+    DROP CONSTRAINT coupon_redemption_order_coupon_key,
+    ADD CONSTRAINT coupon_redemption_order_id_key UNIQUE (order_id);
+
+ALTER TABLE "shop"."coupon"
+    DROP COLUMN limit_to_goods,
+    DROP COLUMN first_order_only,
+    DROP COLUMN new_user_within_days,
+    DROP COLUMN stackable;
@@ -0,0 +1,11 @@
+ALTER TABLE "shop"."coupon"
+    ADD COLUMN limit_to_goods       INTEGER[],
+    ADD COLUMN first_order_only     BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN new_user_within_days INTEGER;
+
+-- stackable coupons can be used together in one order, the others only on their own
+ALTER TABLE "shop"."coupon"
+    ADD COLUMN stackable BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE "shop"."coupon_redemption"
+    DROP CONSTRAINT coupon_redemption_order_id_key,
+    ADD CONSTRAINT coupon_redemption_order_coupon_key UNIQUE (order_id, coupon_id);
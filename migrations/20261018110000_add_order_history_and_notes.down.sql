@@ -0,0 +1,5 @@
+ALTER TABLE "shop"."coupon_redemption"
+    DROP COLUMN released_at;
+DROP INDEX IF EXISTS "shop"."idx_user_order_txn_hash";
+DROP TABLE IF EXISTS "shop"."order_note";
+DROP TABLE IF EXISTS "shop"."order_status_history";
@@ -0,0 +1,36 @@
+CREATE TABLE IF NOT EXISTS "shop"."order_status_history"
+(
+    id          BIGSERIAL PRIMARY KEY,
+    order_id    UUID                  NOT NULL REFERENCES "shop"."user_order" (id) ON DELETE CASCADE,
+    -- NULL when the order was created
+    from_status "shop"."order_status",
+    to_status   "shop"."order_status" NOT NULL,
+    -- NULL when the change was made by the system
+    changed_by  UUID REFERENCES "admin"."admin_account" (id) ON DELETE SET NULL,
+    reason      TEXT,
+    changed_at  TIMESTAMP             NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_order_status_history_order ON "shop"."order_status_history" (order_id, changed_at);
+
+-- existing orders start their history with the current status
+INSERT INTO "shop"."order_status_history" (order_id, to_status, changed_at)
+SELECT id, order_status, created_at
+FROM "shop"."user_order";
+
+CREATE TABLE IF NOT EXISTS "shop"."order_note"
+(
+    id         BIGSERIAL PRIMARY KEY,
+    order_id   UUID      NOT NULL REFERENCES "shop"."user_order" (id) ON DELETE CASCADE,
+    admin_id   UUID REFERENCES "admin"."admin_account" (id) ON DELETE SET NULL,
+    content    TEXT      NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_order_note_order ON "shop"."order_note" (order_id, created_at);
+
+CREATE INDEX IF NOT EXISTS idx_user_order_txn_hash ON "shop"."user_order" ((payment_method_info ->> 'txn_hash'));
+
+-- set when the order is cancelled, so that the coupon can be used again
+ALTER TABLE "shop"."coupon_redemption"
+    ADD COLUMN released_at TIMESTAMP;
@@ -0,0 +1,2 @@
+ALTER TABLE "admin"."admin_account"
+    DROP COLUMN IF EXISTS disabled_at;
@@ -0,0 +1,3 @@
+-- disabled admins can neither log in nor pass authorization
+ALTER TABLE "admin"."admin_account"
+    ADD COLUMN IF NOT EXISTS disabled_at TIMESTAMP;
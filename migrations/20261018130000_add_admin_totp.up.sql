@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS "admin"."admin_totp"
+(
+    admin_id   UUID PRIMARY KEY REFERENCES "admin"."admin_account" (id) ON DELETE CASCADE,
+    secret     BYTEA     NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,3 @@
+ALTER TABLE "admin"."admin_account"
+    DROP COLUMN IF EXISTS custom_role_id;
+DROP TABLE IF EXISTS "admin"."admin_custom_role";
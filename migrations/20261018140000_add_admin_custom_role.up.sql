@@ -0,0 +1,12 @@
+-- custom roles replace the built-in moderator rights with a set of named permissions
+CREATE TABLE IF NOT EXISTS "admin"."admin_custom_role"
+(
+    id          UUID PRIMARY KEY   DEFAULT gen_random_uuid(),
+    name        TEXT      NOT NULL UNIQUE,
+    permissions TEXT[]    NOT NULL DEFAULT '{}',
+    created_at  TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE "admin"."admin_account"
+    ADD COLUMN IF NOT EXISTS custom_role_id UUID
+        REFERENCES "admin"."admin_custom_role" (id) ON DELETE RESTRICT;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS "admin"."audit_log";
+DROP TYPE IF EXISTS "admin"."audit_result";
@@ -0,0 +1,22 @@
+CREATE TYPE "admin"."audit_result" AS ENUM (
+    'success',
+    'denied',
+    'failure'
+    );
+
+-- one row per authorized admin operation, kept after the admin is deleted
+CREATE TABLE IF NOT EXISTS "admin"."audit_log"
+(
+    id            UUID PRIMARY KEY                DEFAULT gen_random_uuid(),
+    admin_id      UUID                   NOT NULL,
+    operation     TEXT                   NOT NULL,
+    input_summary TEXT                   NOT NULL,
+    result        "admin"."audit_result" NOT NULL,
+    error         TEXT,
+    client_ip     TEXT,
+    created_at    TIMESTAMP              NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_created_at_idx ON "admin"."audit_log" (created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS audit_log_admin_idx ON "admin"."audit_log" (admin_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS audit_log_operation_idx ON "admin"."audit_log" (operation, created_at DESC);
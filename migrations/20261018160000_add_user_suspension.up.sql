@@ -0,0 +1,4 @@
+-- banned users are logged out and kept out until an admin lifts the ban
+ALTER TABLE "auth"."user_account"
+    ADD COLUMN IF NOT EXISTS suspended_at TIMESTAMP,
+    ADD COLUMN IF NOT EXISTS suspended_by UUID REFERENCES "admin"."admin_account" (id) ON DELETE SET NULL;
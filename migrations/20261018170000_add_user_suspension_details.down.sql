@@ -0,0 +1,3 @@
+ALTER TABLE "auth"."user_account"
+    DROP COLUMN IF EXISTS suspended_until,
+    DROP COLUMN IF EXISTS suspension_reason;
@@ -0,0 +1,4 @@
+-- a suspension without an expiry is a permanent ban
+ALTER TABLE "auth"."user_account"
+    ADD COLUMN IF NOT EXISTS suspension_reason TEXT,
+    ADD COLUMN IF NOT EXISTS suspended_until   TIMESTAMP;
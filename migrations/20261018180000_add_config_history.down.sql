@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS "application__config_history";
+
+ALTER TABLE "application__config"
+    DROP COLUMN IF EXISTS version;
@@ -0,0 +1,20 @@
+ALTER TABLE "application__config"
+    ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+
+-- every version of every config, including the current one
+CREATE TABLE IF NOT EXISTS "application__config_history"
+(
+    id         SERIAL PRIMARY KEY,
+    key        VARCHAR(255) NOT NULL,
+    version    INTEGER      NOT NULL,
+    content    JSONB        NOT NULL,
+    -- NULL for changes not made by an admin, or if the admin has been deleted since
+    author_id  UUID REFERENCES "admin"."admin_account" (id) ON DELETE SET NULL,
+    created_at TIMESTAMP    NOT NULL DEFAULT NOW(),
+    UNIQUE (key, version)
+);
+
+INSERT INTO "application__config_history" (key, version, content)
+SELECT key, version, content
+FROM "application__config"
+ON CONFLICT DO NOTHING;
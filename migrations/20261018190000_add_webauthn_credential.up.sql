@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS "auth"."webauthn_credential"
+(
+    id            UUID PRIMARY KEY   DEFAULT gen_random_uuid(),
+    user_id       UUID      NOT NULL REFERENCES "auth"."user_account" (id) ON DELETE CASCADE,
+    credential_id BYTEA     NOT NULL UNIQUE,
+    -- serialized webauthn-rs passkey, including the public key and the signature counter
+    passkey       JSONB     NOT NULL,
+    name          TEXT      NOT NULL,
+    created_at    TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_used_at  TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_webauthn_credential_user_id ON "auth"."webauthn_credential" (user_id);
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS "auth"."mfa_recovery_code";
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS "auth"."mfa_recovery_code"
+(
+    id         BIGSERIAL PRIMARY KEY,
+    user_id    UUID      NOT NULL REFERENCES "auth"."user_account" (id) ON DELETE CASCADE,
+    -- SHA-256 of the normalized code
+    code_hash  BYTEA     NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    used_at    TIMESTAMP,
+    UNIQUE (user_id, code_hash)
+);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS "notification"."user_preference";
+DROP SCHEMA IF EXISTS notification;
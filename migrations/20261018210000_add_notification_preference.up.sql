@@ -0,0 +1,17 @@
+CREATE SCHEMA IF NOT EXISTS notification;
+
+-- Users without a row get the defaults below
+CREATE TABLE IF NOT EXISTS "notification"."user_preference"
+(
+    user_id          UUID PRIMARY KEY REFERENCES "auth"."user_account" (id) ON DELETE CASCADE,
+    email_enabled    BOOLEAN   NOT NULL DEFAULT TRUE,
+    -- https endpoint receiving every notification as a JSON POST
+    webhook_url      TEXT,
+    -- private chat of the user with the shop's Telegram bot
+    telegram_chat_id BIGINT,
+    order_updates    BOOLEAN   NOT NULL DEFAULT TRUE,
+    account_updates  BOOLEAN   NOT NULL DEFAULT TRUE,
+    -- language tag such as `ja`, the configured default locale when NULL
+    locale           TEXT,
+    updated_at       TIMESTAMP NOT NULL DEFAULT NOW()
+);
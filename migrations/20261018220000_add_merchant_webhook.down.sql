@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS "notification"."merchant_webhook_delivery";
+DROP TABLE IF EXISTS "notification"."merchant_webhook_endpoint";
+DROP TYPE IF EXISTS "notification"."merchant_webhook_delivery_status";
+DROP TYPE IF EXISTS "notification"."merchant_webhook_event";
@@ -0,0 +1,53 @@
+CREATE TYPE "notification"."merchant_webhook_event" AS ENUM (
+    'order_paid',
+    'payment_callback',
+    'order_status_changed'
+);
+
+CREATE TYPE "notification"."merchant_webhook_delivery_status" AS ENUM (
+    'pending',
+    'succeeded',
+    'failed'
+);
+
+CREATE TABLE IF NOT EXISTS "notification"."merchant_webhook_endpoint"
+(
+    id                   SERIAL PRIMARY KEY,
+    url                  TEXT                                     NOT NULL,
+    description          TEXT                                     NOT NULL DEFAULT '',
+    -- HMAC-SHA256 key of the signatures, shared with the merchant
+    secret               TEXT                                     NOT NULL,
+    events               "notification"."merchant_webhook_event"[] NOT NULL,
+    is_enabled           BOOLEAN                                  NOT NULL DEFAULT TRUE,
+    -- failed attempts in a row, reset by a successful one
+    consecutive_failures INTEGER                                  NOT NULL DEFAULT 0,
+    -- set when the endpoint was disabled for failing too many times
+    auto_disabled_at     TIMESTAMP,
+    created_at           TIMESTAMP                                NOT NULL DEFAULT NOW(),
+    updated_at           TIMESTAMP                                NOT NULL DEFAULT NOW()
+);
+
+-- One row per event and endpoint, also serving as the delivery log
+CREATE TABLE IF NOT EXISTS "notification"."merchant_webhook_delivery"
+(
+    id               BIGSERIAL PRIMARY KEY,
+    endpoint_id      INTEGER                                           NOT NULL REFERENCES "notification"."merchant_webhook_endpoint" (id) ON DELETE CASCADE,
+    -- shared by the deliveries of the same event, including redeliveries
+    event_id         UUID                                              NOT NULL,
+    event            "notification"."merchant_webhook_event"           NOT NULL,
+    payload          JSONB                                             NOT NULL,
+    status           "notification"."merchant_webhook_delivery_status" NOT NULL DEFAULT 'pending',
+    attempts         INTEGER                                           NOT NULL DEFAULT 0,
+    -- NULL once the delivery succeeded or gave up
+    next_attempt_at  TIMESTAMP,
+    last_status_code INTEGER,
+    last_error       TEXT,
+    created_at       TIMESTAMP                                         NOT NULL DEFAULT NOW(),
+    delivered_at     TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_merchant_webhook_delivery_due
+    ON "notification"."merchant_webhook_delivery" (next_attempt_at)
+    WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_merchant_webhook_delivery_endpoint
+    ON "notification"."merchant_webhook_delivery" (endpoint_id, id DESC);
@@ -0,0 +1,23 @@
+-- The wallets users sign in with. Unlike "blockchain"."customer_addresses", an address belongs to
+-- at most one account per chain.
+CREATE TABLE IF NOT EXISTS "auth"."wallet_identity"
+(
+    id         BIGSERIAL PRIMARY KEY,
+    user_id    UUID                                  NOT NULL REFERENCES "auth"."user_account" (id) ON DELETE CASCADE,
+    chain      "blockchain"."supported_blockchains" NOT NULL,
+    address    VARCHAR(255)                          NOT NULL,
+    created_at TIMESTAMP                             NOT NULL DEFAULT NOW(),
+    UNIQUE (chain, address)
+);
+
+CREATE INDEX IF NOT EXISTS idx_wallet_identity_user_id ON "auth"."wallet_identity" (user_id);
+CREATE INDEX IF NOT EXISTS idx_wallet_identity_address ON "auth"."wallet_identity" (address);
+
+-- Accounts created by wallet login are named after their address
+INSERT INTO "auth"."wallet_identity" (user_id, chain, address)
+SELECT DISTINCT ON (ca.chain, ca.address) ca.user_id, ca.chain, ca.address
+FROM "blockchain"."customer_addresses" ca
+JOIN "auth"."user_account" ua ON ua.id = ca.user_id
+WHERE ua.email = ca.address || '@wallet.invalid'
+ORDER BY ca.chain, ca.address, ca.id
+ON CONFLICT (chain, address) DO NOTHING;
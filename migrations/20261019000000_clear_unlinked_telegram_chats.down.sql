@@ -0,0 +1 @@
+-- The cleared chat ids were never verified and are not restored
@@ -0,0 +1,5 @@
+-- Chat ids used to be typed in by users, so one could have entered the chat of someone else.
+-- They are now only set once the user opens the bot through their link, and must be linked again.
+UPDATE "notification"."user_preference"
+SET telegram_chat_id = NULL
+WHERE telegram_chat_id IS NOT NULL;
@@ -0,0 +1,69 @@
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AdminSecurityConfig {
+    /// Require every admin to pass TOTP verification at login. Admins without TOTP have to
+    /// enroll during their next login.
+    #[serde(default)]
+    pub enforce_mfa: bool,
+    /// The duration for the MFA login token to expire
+    #[serde(default = "default_mfa_ttl")]
+    pub mfa_token_ttl: time::Duration,
+    /// The duration for a pending TOTP setup to expire
+    #[serde(default = "default_mfa_ttl")]
+    pub totp_setup_ttl: time::Duration,
+    /// How many 30-second steps a TOTP code may be early or late
+    #[serde(default = "default_totp_skew_steps")]
+    pub totp_skew_steps: u8,
+    /// TOTP attempts an admin may make within `totp_attempt_window` before further codes are
+    /// rejected unchecked
+    #[serde(default = "default_totp_max_attempts")]
+    pub totp_max_attempts: u32,
+    #[serde(default = "default_mfa_ttl")]
+    pub totp_attempt_window: time::Duration,
+}
+
+impl Default for AdminSecurityConfig {
+    fn default() -> Self {
+        Self {
+            enforce_mfa: false,
+            mfa_token_ttl: default_mfa_ttl(),
+            totp_setup_ttl: default_mfa_ttl(),
+            totp_skew_steps: default_totp_skew_steps(),
+            totp_max_attempts: default_totp_max_attempts(),
+            totp_attempt_window: default_mfa_ttl(),
+        }
+    }
+}
+
+fn default_mfa_ttl() -> time::Duration {
+    time::Duration::minutes(5)
+}
+
+fn default_totp_skew_steps() -> u8 {
+    1
+}
+
+fn default_totp_max_attempts() -> u32 {
+    5
+}
+
+impl crate::utils::config_provider::ConfigJson for AdminSecurityConfig {
+    const KEY: &'static str = "admin_security_config";
+
+    fn validate(&self) -> Result<(), &'static str> {
+        if !self.mfa_token_ttl.is_positive() || !self.totp_setup_ttl.is_positive() {
+            return Err("MFA TTLs must be positive");
+        }
+        if self.totp_skew_steps > 2 {
+            return Err("TOTP skew must be at most 2 steps");
+        }
+        if self.totp_max_attempts == 0 || !self.totp_attempt_window.is_positive() {
+            return Err("TOTP attempt limit and window must be positive");
+        }
+        Ok(())
+    }
+}
+
+/// Register the config types of this module.
+pub fn register_configs(registry: &mut crate::utils::config_provider::ConfigRegistry) {
+    registry.register::<AdminSecurityConfig>();
+}
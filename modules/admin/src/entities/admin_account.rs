@@ -2,7 +2,7 @@ use compact_str::CompactString;
 use framework::sqlx::DatabaseProcessor;
 use kanau::processor::Processor;
 use time::PrimitiveDateTime;
-use tracing::instrument;
+use tracing::{Instrument, info_span, instrument};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
@@ -14,6 +14,15 @@ pub struct AdminAccount {
     pub password_hash: CompactString,
     pub email: String,
     pub avatar: Option<String>,
+    pub disabled_at: Option<PrimitiveDateTime>,
+    /// Only meaningful for moderators, see [`crate::utils::rbac::AdminOperation`].
+    pub custom_role_id: Option<Uuid>,
+}
+
+impl AdminAccount {
+    pub fn is_disabled(&self) -> bool {
+        self.disabled_at.is_some()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
@@ -36,7 +45,8 @@ impl Processor<FindAdminById> for DatabaseProcessor {
         sqlx::query_as!(
             AdminAccount,
             r#"
-            SELECT id, role as "role: AdminRole", name, created_at, password_hash, email, avatar
+            SELECT id, role as "role: AdminRole", name, created_at, password_hash, email, avatar,
+            disabled_at, custom_role_id
             FROM "admin"."admin_account"
             WHERE id = $1
             "#,
@@ -50,6 +60,7 @@ impl Processor<FindAdminById> for DatabaseProcessor {
 #[derive(Debug, Clone)]
 pub struct CreateAdminAccount {
     pub role: AdminRole,
+    pub name: CompactString,
     pub password_hash: CompactString,
     pub email: String,
     pub avatar: Option<String>,
@@ -68,7 +79,8 @@ impl Processor<FindAdminByEmail> for DatabaseProcessor {
         sqlx::query_as!(
             AdminAccount,
             r#"
-            SELECT id, role as "role: AdminRole", name, created_at, password_hash, email, avatar
+            SELECT id, role as "role: AdminRole", name, created_at, password_hash, email, avatar,
+            disabled_at, custom_role_id
             FROM "admin"."admin_account"
             WHERE email = $1
             "#,
@@ -87,12 +99,14 @@ impl Processor<CreateAdminAccount> for DatabaseProcessor {
         sqlx::query_as!(
             AdminAccount,
             r#"
-            INSERT INTO "admin"."admin_account" (role, password_hash, email, avatar) 
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO "admin"."admin_account" (role, name, password_hash, email, avatar)
+            VALUES ($1, $2, $3, $4, $5)
             RETURNING
-            id, role as "role: AdminRole", name, created_at, email, avatar, password_hash
+            id, role as "role: AdminRole", name, created_at, email, avatar, password_hash,
+            disabled_at, custom_role_id
             "#,
             input.role as AdminRole,
+            &input.name,
             &input.password_hash,
             &input.email,
             input.avatar
@@ -101,3 +115,217 @@ impl Processor<CreateAdminAccount> for DatabaseProcessor {
         .await
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListAdminAccounts {
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Processor<ListAdminAccounts> for DatabaseProcessor {
+    type Output = Vec<AdminAccount>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListAdminAccounts", err)]
+    async fn process(&self, input: ListAdminAccounts) -> Result<Vec<AdminAccount>, sqlx::Error> {
+        sqlx::query_as!(
+            AdminAccount,
+            r#"
+            SELECT id, role as "role: AdminRole", name, created_at, password_hash, email, avatar,
+            disabled_at, custom_role_id
+            FROM "admin"."admin_account"
+            ORDER BY created_at, id
+            LIMIT $1 OFFSET $2
+            "#,
+            input.limit,
+            input.offset
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateAdminPassword {
+    pub id: Uuid,
+    pub password_hash: CompactString,
+}
+
+impl Processor<UpdateAdminPassword> for DatabaseProcessor {
+    /// Whether the admin exists
+    type Output = bool;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:UpdateAdminPassword", err)]
+    async fn process(&self, input: UpdateAdminPassword) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE "admin"."admin_account"
+            SET password_hash = $2
+            WHERE id = $1
+            "#,
+            input.id,
+            &input.password_hash
+        )
+        .execute(self.db())
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Result of a change that may take away the owner role from an admin.
+pub enum OwnerGuardedChange<T> {
+    Changed(T),
+    NotFound,
+    /// The admin is the last active owner, so the change is refused.
+    LastOwner,
+}
+
+/// Lock all active owners and check whether `id` is the only one.
+///
+/// Concurrent changes to owners are serialized by the row locks, so two owners can not demote
+/// each other at the same time.
+async fn is_last_active_owner(
+    conn: &mut sqlx::PgConnection,
+    id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let owners = sqlx::query_scalar!(
+        r#"
+        SELECT id
+        FROM "admin"."admin_account"
+        WHERE role = 'owner' AND disabled_at IS NULL
+        FOR UPDATE
+        "#
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(owners == [id])
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChangeAdminRole {
+    pub id: Uuid,
+    pub role: AdminRole,
+}
+
+impl Processor<ChangeAdminRole> for DatabaseProcessor {
+    type Output = OwnerGuardedChange<AdminAccount>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL-Transaction:ChangeAdminRole", err)]
+    async fn process(
+        &self,
+        input: ChangeAdminRole,
+    ) -> Result<OwnerGuardedChange<AdminAccount>, sqlx::Error> {
+        let mut tx = self
+            .db()
+            .begin()
+            .instrument(info_span!("<Transaction Begin>"))
+            .await?;
+        if input.role != AdminRole::Owner && is_last_active_owner(&mut tx, input.id).await? {
+            return Ok(OwnerGuardedChange::LastOwner);
+        }
+        let admin = sqlx::query_as!(
+            AdminAccount,
+            r#"
+            UPDATE "admin"."admin_account"
+            SET role = $2
+            WHERE id = $1
+            RETURNING
+            id, role as "role: AdminRole", name, created_at, email, avatar, password_hash,
+            disabled_at, custom_role_id
+            "#,
+            input.id,
+            input.role as AdminRole
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        tx.commit()
+            .instrument(info_span!("<Transaction Commit>"))
+            .await?;
+        Ok(admin.map_or(OwnerGuardedChange::NotFound, OwnerGuardedChange::Changed))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SetAdminDisabled {
+    pub id: Uuid,
+    pub disabled: bool,
+}
+
+impl Processor<SetAdminDisabled> for DatabaseProcessor {
+    type Output = OwnerGuardedChange<AdminAccount>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL-Transaction:SetAdminDisabled", err)]
+    async fn process(
+        &self,
+        input: SetAdminDisabled,
+    ) -> Result<OwnerGuardedChange<AdminAccount>, sqlx::Error> {
+        let mut tx = self
+            .db()
+            .begin()
+            .instrument(info_span!("<Transaction Begin>"))
+            .await?;
+        if input.disabled && is_last_active_owner(&mut tx, input.id).await? {
+            return Ok(OwnerGuardedChange::LastOwner);
+        }
+        let admin = sqlx::query_as!(
+            AdminAccount,
+            r#"
+            UPDATE "admin"."admin_account"
+            SET disabled_at = CASE WHEN $2 THEN COALESCE(disabled_at, NOW()) END
+            WHERE id = $1
+            RETURNING
+            id, role as "role: AdminRole", name, created_at, email, avatar, password_hash,
+            disabled_at, custom_role_id
+            "#,
+            input.id,
+            input.disabled
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        tx.commit()
+            .instrument(info_span!("<Transaction Commit>"))
+            .await?;
+        Ok(admin.map_or(OwnerGuardedChange::NotFound, OwnerGuardedChange::Changed))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DeleteAdminAccount {
+    pub id: Uuid,
+}
+
+impl Processor<DeleteAdminAccount> for DatabaseProcessor {
+    type Output = OwnerGuardedChange<()>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL-Transaction:DeleteAdminAccount", err)]
+    async fn process(
+        &self,
+        input: DeleteAdminAccount,
+    ) -> Result<OwnerGuardedChange<()>, sqlx::Error> {
+        let mut tx = self
+            .db()
+            .begin()
+            .instrument(info_span!("<Transaction Begin>"))
+            .await?;
+        if is_last_active_owner(&mut tx, input.id).await? {
+            return Ok(OwnerGuardedChange::LastOwner);
+        }
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM "admin"."admin_account"
+            WHERE id = $1
+            "#,
+            input.id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit()
+            .instrument(info_span!("<Transaction Commit>"))
+            .await?;
+        Ok(if result.rows_affected() > 0 {
+            OwnerGuardedChange::Changed(())
+        } else {
+            OwnerGuardedChange::NotFound
+        })
+    }
+}
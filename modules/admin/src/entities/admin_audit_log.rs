@@ -0,0 +1,107 @@
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use time::PrimitiveDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "admin.audit_result", rename_all = "lowercase")]
+pub enum AuditResult {
+    Success,
+    /// Rejected by the authorization layer before reaching the operation.
+    Denied,
+    Failure,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct AuditLogEntry {
+    pub id: Uuid,
+    pub admin_id: Uuid,
+    pub operation: String,
+    pub input_summary: String,
+    pub result: AuditResult,
+    pub error: Option<String>,
+    pub client_ip: Option<String>,
+    pub created_at: PrimitiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordAuditLog {
+    pub admin_id: Uuid,
+    pub operation: &'static str,
+    pub input_summary: String,
+    pub result: AuditResult,
+    pub error: Option<String>,
+    pub client_ip: Option<String>,
+}
+
+impl Processor<RecordAuditLog> for DatabaseProcessor {
+    type Output = ();
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:RecordAuditLog", err)]
+    async fn process(&self, input: RecordAuditLog) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO "admin"."audit_log"
+            (admin_id, operation, input_summary, result, error, client_ip)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            input.admin_id,
+            input.operation,
+            input.input_summary,
+            input.result as AuditResult,
+            input.error,
+            input.client_ip
+        )
+        .execute(self.db())
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ListAuditLog {
+    pub admin_id: Option<Uuid>,
+    pub operation: Option<String>,
+    pub result: Option<AuditResult>,
+    pub since: Option<PrimitiveDateTime>,
+    pub until: Option<PrimitiveDateTime>,
+    /// Only return entries recorded before this `(created_at, id)` cursor.
+    pub before: Option<(PrimitiveDateTime, Uuid)>,
+    pub limit: i64,
+}
+
+impl Processor<ListAuditLog> for DatabaseProcessor {
+    type Output = Vec<AuditLogEntry>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListAuditLog", err)]
+    async fn process(&self, input: ListAuditLog) -> Result<Vec<AuditLogEntry>, sqlx::Error> {
+        let (before_created_at, before_id) = input.before.unzip();
+        sqlx::query_as!(
+            AuditLogEntry,
+            r#"
+            SELECT id, admin_id, operation, input_summary, result as "result: AuditResult",
+            error, client_ip, created_at
+            FROM "admin"."audit_log"
+            WHERE ($1::uuid IS NULL OR admin_id = $1)
+              AND ($2::text IS NULL OR operation = $2)
+              AND ($3::admin.audit_result IS NULL OR result = $3)
+              AND ($4::timestamp IS NULL OR created_at >= $4)
+              AND ($5::timestamp IS NULL OR created_at < $5)
+              AND ($6::timestamp IS NULL OR (created_at, id) < ($6, $7))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $8
+            "#,
+            input.admin_id,
+            input.operation,
+            input.result as Option<AuditResult>,
+            input.since,
+            input.until,
+            before_created_at,
+            before_id,
+            input.limit
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
@@ -0,0 +1,288 @@
+use crate::entities::admin_account::{AdminAccount, AdminRole};
+use crate::utils::rbac::Permission;
+use compact_str::CompactString;
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use time::PrimitiveDateTime;
+use tracing::{Instrument, info_span, instrument};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomRole {
+    pub id: Uuid,
+    pub name: CompactString,
+    pub permissions: Vec<Permission>,
+    pub created_at: PrimitiveDateTime,
+}
+
+#[derive(sqlx::FromRow)]
+struct CustomRoleRow {
+    id: Uuid,
+    name: CompactString,
+    permissions: Vec<String>,
+    created_at: PrimitiveDateTime,
+}
+
+/// Permissions removed from the code are ignored rather than failing the whole role.
+fn parse_permissions(permissions: &[String]) -> Vec<Permission> {
+    permissions
+        .iter()
+        .filter_map(|permission| Permission::parse(permission))
+        .collect()
+}
+
+fn permission_names(permissions: &[Permission]) -> Vec<String> {
+    permissions
+        .iter()
+        .map(|permission| permission.as_str().to_owned())
+        .collect()
+}
+
+impl From<CustomRoleRow> for CustomRole {
+    fn from(value: CustomRoleRow) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            permissions: parse_permissions(&value.permissions),
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// What an admin is allowed to do, loaded on every authorized operation.
+pub struct AdminGrant {
+    pub role: AdminRole,
+    pub disabled: bool,
+    /// Permissions of the custom role, if the admin has one.
+    pub custom_permissions: Option<Vec<Permission>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FindAdminGrant {
+    pub admin_id: Uuid,
+}
+
+impl Processor<FindAdminGrant> for DatabaseProcessor {
+    type Output = Option<AdminGrant>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:FindAdminGrant", err)]
+    async fn process(&self, input: FindAdminGrant) -> Result<Option<AdminGrant>, sqlx::Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT a.role as "role: AdminRole", a.disabled_at, r.permissions as "permissions?"
+            FROM "admin"."admin_account" a
+            LEFT JOIN "admin"."admin_custom_role" r ON r.id = a.custom_role_id
+            WHERE a.id = $1
+            "#,
+            input.admin_id
+        )
+        .fetch_optional(self.db())
+        .await?;
+        Ok(row.map(|row| AdminGrant {
+            role: row.role,
+            disabled: row.disabled_at.is_some(),
+            custom_permissions: row.permissions.as_deref().map(parse_permissions),
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateCustomRole {
+    pub name: CompactString,
+    pub permissions: Vec<Permission>,
+}
+
+impl Processor<CreateCustomRole> for DatabaseProcessor {
+    /// `None` if the name is already used
+    type Output = Option<CustomRole>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:CreateCustomRole", err)]
+    async fn process(&self, input: CreateCustomRole) -> Result<Option<CustomRole>, sqlx::Error> {
+        let row = sqlx::query_as!(
+            CustomRoleRow,
+            r#"
+            INSERT INTO "admin"."admin_custom_role" (name, permissions)
+            VALUES ($1, $2)
+            ON CONFLICT (name) DO NOTHING
+            RETURNING id, name, permissions, created_at
+            "#,
+            &input.name,
+            &permission_names(&input.permissions)
+        )
+        .fetch_optional(self.db())
+        .await?;
+        Ok(row.map(Into::into))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListCustomRoles;
+
+impl Processor<ListCustomRoles> for DatabaseProcessor {
+    type Output = Vec<CustomRole>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListCustomRoles", err)]
+    async fn process(&self, _: ListCustomRoles) -> Result<Vec<CustomRole>, sqlx::Error> {
+        let rows = sqlx::query_as!(
+            CustomRoleRow,
+            r#"
+            SELECT id, name, permissions, created_at
+            FROM "admin"."admin_custom_role"
+            ORDER BY name
+            "#
+        )
+        .fetch_all(self.db())
+        .await?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateCustomRolePermissions {
+    pub id: Uuid,
+    pub permissions: Vec<Permission>,
+}
+
+impl Processor<UpdateCustomRolePermissions> for DatabaseProcessor {
+    type Output = Option<CustomRole>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:UpdateCustomRolePermissions", err)]
+    async fn process(
+        &self,
+        input: UpdateCustomRolePermissions,
+    ) -> Result<Option<CustomRole>, sqlx::Error> {
+        let row = sqlx::query_as!(
+            CustomRoleRow,
+            r#"
+            UPDATE "admin"."admin_custom_role"
+            SET permissions = $2
+            WHERE id = $1
+            RETURNING id, name, permissions, created_at
+            "#,
+            input.id,
+            &permission_names(&input.permissions)
+        )
+        .fetch_optional(self.db())
+        .await?;
+        Ok(row.map(Into::into))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteCustomRoleResult {
+    Deleted,
+    NotFound,
+    /// Some admins still have the role.
+    InUse,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DeleteCustomRole {
+    pub id: Uuid,
+}
+
+impl Processor<DeleteCustomRole> for DatabaseProcessor {
+    type Output = DeleteCustomRoleResult;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL-Transaction:DeleteCustomRole", err)]
+    async fn process(
+        &self,
+        input: DeleteCustomRole,
+    ) -> Result<DeleteCustomRoleResult, sqlx::Error> {
+        let mut tx = self
+            .db()
+            .begin()
+            .instrument(info_span!("<Transaction Begin>"))
+            .await?;
+        let in_use = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM "admin"."admin_account" WHERE custom_role_id = $1
+            ) as "exists!"
+            "#,
+            input.id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if in_use {
+            return Ok(DeleteCustomRoleResult::InUse);
+        }
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM "admin"."admin_custom_role"
+            WHERE id = $1
+            "#,
+            input.id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit()
+            .instrument(info_span!("<Transaction Commit>"))
+            .await?;
+        if result.rows_affected() > 0 {
+            Ok(DeleteCustomRoleResult::Deleted)
+        } else {
+            Ok(DeleteCustomRoleResult::NotFound)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SetAdminCustomRole {
+    pub admin_id: Uuid,
+    /// `None` falls back to the built-in role.
+    pub role_id: Option<Uuid>,
+}
+
+impl Processor<SetAdminCustomRole> for DatabaseProcessor {
+    /// `None` if the admin does not exist
+    type Output = Option<AdminAccount>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:SetAdminCustomRole", err)]
+    async fn process(
+        &self,
+        input: SetAdminCustomRole,
+    ) -> Result<Option<AdminAccount>, sqlx::Error> {
+        sqlx::query_as!(
+            AdminAccount,
+            r#"
+            UPDATE "admin"."admin_account"
+            SET custom_role_id = $2
+            WHERE id = $1
+            RETURNING
+            id, role as "role: AdminRole", name, created_at, email, avatar, password_hash,
+            disabled_at, custom_role_id
+            "#,
+            input.admin_id,
+            input.role_id
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FindCustomRole {
+    pub id: Uuid,
+}
+
+impl Processor<FindCustomRole> for DatabaseProcessor {
+    type Output = Option<CustomRole>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:FindCustomRole", err)]
+    async fn process(&self, input: FindCustomRole) -> Result<Option<CustomRole>, sqlx::Error> {
+        let row = sqlx::query_as!(
+            CustomRoleRow,
+            r#"
+            SELECT id, name, permissions, created_at
+            FROM "admin"."admin_custom_role"
+            WHERE id = $1
+            "#,
+            input.id
+        )
+        .fetch_optional(self.db())
+        .await?;
+        Ok(row.map(Into::into))
+    }
+}
@@ -0,0 +1,137 @@
+use framework::redis::RedisKey;
+use kanau::{RkyvMessageDe, RkyvMessageSer};
+use uuid::Uuid;
+
+/// Wrapper type for the admin MFA login token key with proper namespacing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdminMfaLoginTokenKey(pub [u8; 32]);
+
+impl redis::ToSingleRedisArg for AdminMfaLoginTokenKey {}
+
+impl redis::ToRedisArgs for AdminMfaLoginTokenKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("admin_mfa_login:{}", hex::encode(self.0)));
+        key.write_redis_args(out);
+    }
+}
+
+/// Stored in Redis when an admin passed the password check but still has to pass TOTP
+/// verification, or to enroll TOTP first if MFA is enforced.
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    RkyvMessageDe,
+    RkyvMessageSer,
+)]
+pub struct AdminMfaLoginToken {
+    /// Randomly generated 256-bit token
+    pub token: [u8; 32],
+    pub admin_id: Uuid,
+}
+
+impl core::fmt::Debug for AdminMfaLoginToken {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AdminMfaLoginToken")
+            .field("token", &"[redacted]")
+            .field("admin_id", &self.admin_id)
+            .finish()
+    }
+}
+
+impl framework::redis::KeyValue for AdminMfaLoginToken {
+    type Key = AdminMfaLoginTokenKey;
+    type Value = Self;
+
+    fn key(&self) -> Self::Key {
+        AdminMfaLoginTokenKey(self.token)
+    }
+
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+
+    fn into_value(self) -> Self::Value {
+        self
+    }
+
+    fn new(key: Self::Key, mut value: Self::Value) -> Self {
+        value.token = key.0;
+        value
+    }
+}
+
+impl framework::redis::KeyValueRead for AdminMfaLoginToken {}
+impl framework::redis::KeyValueWrite for AdminMfaLoginToken {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+/// Wrap the admin ID as the key for [PendingAdminTotpSetup] in Redis and add the proper
+/// namespacing.
+pub struct PendingAdminTotpSetupKey(pub Uuid);
+
+impl redis::ToSingleRedisArg for PendingAdminTotpSetupKey {}
+
+impl redis::ToRedisArgs for PendingAdminTotpSetupKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("admin_totp_setup:{}", self.0));
+        key.write_redis_args(out);
+    }
+}
+
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    RkyvMessageDe,
+    RkyvMessageSer,
+)]
+pub struct PendingAdminTotpSetup {
+    pub admin_id: PendingAdminTotpSetupKey,
+    pub secret: Box<[u8]>,
+}
+
+impl core::fmt::Debug for PendingAdminTotpSetup {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PendingAdminTotpSetup")
+            .field("admin_id", &self.admin_id)
+            .field("secret", &"[redacted]")
+            .finish()
+    }
+}
+
+impl framework::redis::KeyValue for PendingAdminTotpSetup {
+    type Key = PendingAdminTotpSetupKey;
+    type Value = Self;
+
+    fn key(&self) -> Self::Key {
+        self.admin_id
+    }
+
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+
+    fn into_value(self) -> Self::Value {
+        self
+    }
+
+    fn new(key: Self::Key, mut value: Self::Value) -> Self {
+        value.admin_id = key;
+        value
+    }
+}
+
+impl framework::redis::KeyValueRead for PendingAdminTotpSetup {}
+impl framework::redis::KeyValueWrite for PendingAdminTotpSetup {}
@@ -0,0 +1,73 @@
+use crate::entities::admin_session::AdminSessionId;
+use framework::redis::{RedisConnection, RedisKey};
+use redis::AsyncCommands;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// The set of active session ids of an admin, stored as a Redis SET of hex encoded ids.
+///
+/// It only serves to find the sessions of an admin, the sessions themselves are the source of
+/// truth and an id in the set may point to an already deleted session.
+pub struct AdminSessions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdminSessionIndex(pub Uuid);
+
+impl redis::ToSingleRedisArg for AdminSessionIndex {}
+
+impl redis::ToRedisArgs for AdminSessionIndex {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("admin_sessions_set:{}", self.0));
+        key.write_redis_args(out);
+    }
+}
+
+impl AdminSessions {
+    /// Add a session id to the admin's session set using SADD.
+    #[instrument(skip_all, fields(admin_id = %admin_id.0))]
+    pub async fn add_session(
+        conn: &mut RedisConnection,
+        admin_id: AdminSessionIndex,
+        session_id: AdminSessionId,
+    ) -> Result<(), framework::Error> {
+        let _: () = conn.sadd(admin_id, session_id.to_ascii_string()).await?;
+        Ok(())
+    }
+
+    /// Remove a session id from the admin's session set using SREM.
+    #[instrument(skip_all, fields(admin_id = %admin_id.0))]
+    pub async fn remove_session(
+        conn: &mut RedisConnection,
+        admin_id: AdminSessionIndex,
+        session_id: AdminSessionId,
+    ) -> Result<(), framework::Error> {
+        let _: () = conn.srem(admin_id, session_id.to_ascii_string()).await?;
+        Ok(())
+    }
+
+    /// Read all session ids of the admin using SMEMBERS.
+    #[instrument(skip_all, fields(admin_id = %admin_id.0))]
+    pub async fn list_sessions(
+        conn: &mut RedisConnection,
+        admin_id: AdminSessionIndex,
+    ) -> Result<Vec<AdminSessionId>, framework::Error> {
+        let session_strings: Vec<String> = conn.smembers(admin_id).await?;
+        Ok(session_strings
+            .iter()
+            .filter_map(|s| AdminSessionId::try_from_ascii_string(s).ok())
+            .collect())
+    }
+
+    /// Delete the whole session set of the admin.
+    #[instrument(skip_all, fields(admin_id = %admin_id.0))]
+    pub async fn clear(
+        conn: &mut RedisConnection,
+        admin_id: AdminSessionIndex,
+    ) -> Result<(), framework::Error> {
+        let _: () = conn.del(admin_id).await?;
+        Ok(())
+    }
+}
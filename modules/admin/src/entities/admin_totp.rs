@@ -0,0 +1,97 @@
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Clone, Eq, PartialEq, sqlx::FromRow)]
+pub struct AdminTotp {
+    pub admin_id: Uuid,
+    pub secret: Vec<u8>,
+    pub created_at: time::PrimitiveDateTime,
+}
+
+impl core::fmt::Debug for AdminTotp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminTotp")
+            .field("admin_id", &self.admin_id)
+            .field("secret", &"[REDACTED]")
+            .field("created_at", &self.created_at)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FindAdminTotp {
+    pub admin_id: Uuid,
+}
+
+impl Processor<FindAdminTotp> for DatabaseProcessor {
+    type Output = Option<AdminTotp>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:FindAdminTotp", err)]
+    async fn process(&self, input: FindAdminTotp) -> Result<Option<AdminTotp>, sqlx::Error> {
+        sqlx::query_as!(
+            AdminTotp,
+            r#"
+            SELECT admin_id, secret, created_at
+            FROM "admin"."admin_totp"
+            WHERE admin_id = $1
+            "#,
+            input.admin_id
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Clone)]
+pub struct CreateAdminTotp {
+    pub admin_id: Uuid,
+    pub secret: Vec<u8>,
+}
+
+impl Processor<CreateAdminTotp> for DatabaseProcessor {
+    /// `None` if the admin already has TOTP configured
+    type Output = Option<AdminTotp>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:CreateAdminTotp", err)]
+    async fn process(&self, input: CreateAdminTotp) -> Result<Option<AdminTotp>, sqlx::Error> {
+        sqlx::query_as!(
+            AdminTotp,
+            r#"
+            INSERT INTO "admin"."admin_totp" (admin_id, secret)
+            VALUES ($1, $2)
+            ON CONFLICT (admin_id) DO NOTHING
+            RETURNING admin_id, secret, created_at
+            "#,
+            input.admin_id,
+            input.secret
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RemoveAdminTotp {
+    pub admin_id: Uuid,
+}
+
+impl Processor<RemoveAdminTotp> for DatabaseProcessor {
+    /// Whether a TOTP was removed
+    type Output = bool;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:RemoveAdminTotp", err)]
+    async fn process(&self, input: RemoveAdminTotp) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM "admin"."admin_totp"
+            WHERE admin_id = $1
+            "#,
+            input.admin_id
+        )
+        .execute(self.db())
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,187 @@
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use time::PrimitiveDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct ConfigVersion {
+    pub key: String,
+    pub version: i32,
+    pub content: serde_json::Value,
+    /// `None` for changes not made by an admin
+    pub author_id: Option<Uuid>,
+    pub created_at: PrimitiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct ListConfigVersions {
+    pub key: String,
+    /// Only return versions older than this one.
+    pub before_version: Option<i32>,
+    pub limit: i64,
+}
+
+impl Processor<ListConfigVersions> for DatabaseProcessor {
+    type Output = Vec<ConfigVersion>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListConfigVersions", err)]
+    async fn process(&self, input: ListConfigVersions) -> Result<Vec<ConfigVersion>, sqlx::Error> {
+        sqlx::query_as!(
+            ConfigVersion,
+            r#"
+            SELECT key, version, content, author_id, created_at
+            FROM "application__config_history"
+            WHERE key = $1 AND ($2::integer IS NULL OR version < $2)
+            ORDER BY version DESC
+            LIMIT $3
+            "#,
+            input.key,
+            input.before_version,
+            input.limit
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FindConfigVersion {
+    pub key: String,
+    pub version: i32,
+}
+
+impl Processor<FindConfigVersion> for DatabaseProcessor {
+    type Output = Option<ConfigVersion>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:FindConfigVersion", err)]
+    async fn process(
+        &self,
+        input: FindConfigVersion,
+    ) -> Result<Option<ConfigVersion>, sqlx::Error> {
+        sqlx::query_as!(
+            ConfigVersion,
+            r#"
+            SELECT key, version, content, author_id, created_at
+            FROM "application__config_history"
+            WHERE key = $1 AND version = $2
+            "#,
+            input.key,
+            input.version
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+/// A stored config document, either a current config or a history entry.
+pub struct StoredConfigContent {
+    pub id: i32,
+    pub key: String,
+    pub content: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListCurrentConfigContents;
+
+impl Processor<ListCurrentConfigContents> for DatabaseProcessor {
+    type Output = Vec<StoredConfigContent>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListCurrentConfigContents", err)]
+    async fn process(
+        &self,
+        _: ListCurrentConfigContents,
+    ) -> Result<Vec<StoredConfigContent>, sqlx::Error> {
+        sqlx::query_as!(
+            StoredConfigContent,
+            r#"
+            SELECT id, key, content
+            FROM "application__config"
+            ORDER BY id
+            "#
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListHistoryConfigContents;
+
+impl Processor<ListHistoryConfigContents> for DatabaseProcessor {
+    type Output = Vec<StoredConfigContent>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListHistoryConfigContents", err)]
+    async fn process(
+        &self,
+        _: ListHistoryConfigContents,
+    ) -> Result<Vec<StoredConfigContent>, sqlx::Error> {
+        sqlx::query_as!(
+            StoredConfigContent,
+            r#"
+            SELECT id, key, content
+            FROM "application__config_history"
+            ORDER BY id
+            "#
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Replace the content of a current config in place, without recording a new version.
+///
+/// Only for changes that keep the meaning of the config, such as re-encrypting its secrets.
+pub struct UpdateCurrentConfigContent {
+    pub id: i32,
+    pub content: serde_json::Value,
+}
+
+impl Processor<UpdateCurrentConfigContent> for DatabaseProcessor {
+    type Output = ();
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:UpdateCurrentConfigContent", err)]
+    async fn process(&self, input: UpdateCurrentConfigContent) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE "application__config"
+            SET content = $2
+            WHERE id = $1
+            "#,
+            input.id,
+            input.content
+        )
+        .execute(self.db())
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Replace the content of a history entry in place. See [`UpdateCurrentConfigContent`].
+pub struct UpdateHistoryConfigContent {
+    pub id: i32,
+    pub content: serde_json::Value,
+}
+
+impl Processor<UpdateHistoryConfigContent> for DatabaseProcessor {
+    type Output = ();
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:UpdateHistoryConfigContent", err)]
+    async fn process(&self, input: UpdateHistoryConfigContent) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE "application__config_history"
+            SET content = $2
+            WHERE id = $1
+            "#,
+            input.id,
+            input.content
+        )
+        .execute(self.db())
+        .await?;
+        Ok(())
+    }
+}
@@ -1,2 +1,9 @@
 pub mod admin_account;
+pub mod admin_audit_log;
+pub mod admin_custom_role;
+pub mod admin_mfa;
 pub mod admin_session;
+pub mod admin_session_list;
+pub mod admin_totp;
+pub mod config_history;
+pub mod totp_guard;
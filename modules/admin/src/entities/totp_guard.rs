@@ -0,0 +1,119 @@
+use framework::redis::{RedisConnection, RedisKey};
+use redis::AsyncCommands;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Counter of TOTP attempts of an admin in the current window, stored as a plain Redis integer.
+pub struct AdminTotpAttemptsKey(pub Uuid);
+
+impl redis::ToSingleRedisArg for AdminTotpAttemptsKey {}
+
+impl redis::ToRedisArgs for AdminTotpAttemptsKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("admin_totp_attempts:{}", self.0));
+        key.write_redis_args(out);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The last TOTP time-step accepted for an admin, stored as a plain Redis integer.
+pub struct AdminTotpLastStepKey(pub Uuid);
+
+impl redis::ToSingleRedisArg for AdminTotpLastStepKey {}
+
+impl redis::ToRedisArgs for AdminTotpLastStepKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("admin_totp_last_step:{}", self.0));
+        key.write_redis_args(out);
+    }
+}
+
+/// Count an attempt and start the window with the first one.
+const COUNT_ATTEMPT_SCRIPT: &str = r"
+local attempts = redis.call('INCR', KEYS[1])
+if attempts == 1 then
+    redis.call('EXPIRE', KEYS[1], ARGV[1])
+end
+return attempts
+";
+
+/// Store the step only if it is later than the stored one.
+const ADVANCE_STEP_SCRIPT: &str = r"
+local last = tonumber(redis.call('GET', KEYS[1]))
+local step = tonumber(ARGV[1])
+if last and step <= last then
+    return 0
+end
+redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2])
+return 1
+";
+
+/// Brute-force and replay protection for TOTP codes, shared by admins and users.
+///
+/// Each account has a counter key and a last step key, both holding plain Redis integers.
+pub struct TotpGuard;
+
+impl TotpGuard {
+    /// Count a verification attempt of the account and return the number of attempts in the
+    /// current window, this one included.
+    #[instrument(skip_all)]
+    pub async fn count_attempt<K>(
+        conn: &mut RedisConnection,
+        key: K,
+        window: std::time::Duration,
+    ) -> Result<u64, framework::Error>
+    where
+        K: redis::ToSingleRedisArg + Send + Sync,
+    {
+        let attempts: u64 = redis::Script::new(COUNT_ATTEMPT_SCRIPT)
+            .key(key)
+            .arg(window.as_secs().max(1))
+            .invoke_async(conn)
+            .await?;
+        Ok(attempts)
+    }
+
+    /// Forget the attempts of the account after a successful verification.
+    #[instrument(skip_all)]
+    pub async fn reset_attempts<K>(
+        conn: &mut RedisConnection,
+        key: K,
+    ) -> Result<(), framework::Error>
+    where
+        K: redis::ToSingleRedisArg + Send + Sync,
+    {
+        let _: () = conn.del(key).await?;
+        Ok(())
+    }
+
+    /// Atomically record `step` as the last accepted step of the account. `false` if the same or
+    /// a later step was already accepted, i.e. the code is being replayed.
+    ///
+    /// The record only has to outlive the window in which the code is accepted, so `ttl` should
+    /// cover the whole skew window.
+    #[instrument(skip_all)]
+    pub async fn advance_step<K>(
+        conn: &mut RedisConnection,
+        key: K,
+        step: u64,
+        ttl: std::time::Duration,
+    ) -> Result<bool, framework::Error>
+    where
+        K: redis::ToSingleRedisArg + Send + Sync,
+    {
+        let advanced: bool = redis::Script::new(ADVANCE_STEP_SCRIPT)
+            .key(key)
+            .arg(step)
+            .arg(ttl.as_secs().max(1))
+            .invoke_async(conn)
+            .await?;
+        Ok(advanced)
+    }
+}
@@ -3,6 +3,7 @@
 #![forbid(clippy::expect_used)]
 #![forbid(clippy::panic)]
 
+pub mod config;
 pub mod entities;
 pub mod rpc;
 pub mod services;
@@ -1,10 +1,21 @@
 use crate::entities::admin_session::AdminSessionId;
-use crate::rpc::middleware::ADMIN_AUTHORIZATION_HEADER;
+use crate::rpc::middleware::{ADMIN_AUTHORIZATION_HEADER, AdminId};
 use crate::services::admin_auth::{
     AdminAuthService as InnerService, AdminLogin, AdminLoginResult, AdminLogout,
+    AdminVerifyMfaLogin, AdminVerifyMfaLoginResult, FinishAdminTotpSetup,
+    FinishAdminTotpSetupResult, RemoveOwnAdminTotp, RemoveOwnAdminTotpResult, ShowAdminMfaStatus,
+    StartAdminTotpSetup, StartAdminTotpSetupResult,
 };
 use kanau::processor::Processor;
-use phantom_shop_proto::v1::admin::{AdminLoginRequest, AdminLoginResponse};
+use phantom_shop_proto::v1::admin::{
+    AdminFinishTotpSetupRequest, AdminFinishTotpSetupResponse,
+    AdminFinishTotpSetupResult as ProtoFinishTotpSetupResult, AdminLoginRequest,
+    AdminLoginResponse, AdminLoginStatus, AdminMfaStatus, AdminRemoveTotpRequest,
+    AdminRemoveTotpResponse, AdminRemoveTotpResult as ProtoRemoveTotpResult,
+    AdminStartTotpSetupRequest, AdminStartTotpSetupResponse,
+    AdminStartTotpSetupResult as ProtoStartTotpSetupResult, AdminVerifyMfaLoginRequest,
+    AdminVerifyMfaLoginResponse, AdminVerifyMfaLoginResult as ProtoVerifyMfaLoginResult,
+};
 use phantom_shop_proto::v1::common::Empty;
 use tonic::{Request, Response, Status};
 
@@ -40,6 +51,24 @@ impl phantom_shop_proto::v1::admin::admin_auth_service_server::AdminAuthService
         match result {
             AdminLoginResult::Success(session_id) => Ok(Response::new(AdminLoginResponse {
                 session_id: session_id.to_ascii_string(),
+                status: AdminLoginStatus::Success.into(),
+                mfa_token: None,
+                totp_secret: None,
+            })),
+            AdminLoginResult::MfaRequired(mfa_token) => Ok(Response::new(AdminLoginResponse {
+                session_id: String::new(),
+                status: AdminLoginStatus::MfaRequired.into(),
+                mfa_token: Some(mfa_token.to_vec()),
+                totp_secret: None,
+            })),
+            AdminLoginResult::MfaEnrollmentRequired {
+                mfa_token,
+                totp_secret,
+            } => Ok(Response::new(AdminLoginResponse {
+                session_id: String::new(),
+                status: AdminLoginStatus::MfaEnrollmentRequired.into(),
+                mfa_token: Some(mfa_token.to_vec()),
+                totp_secret: Some(totp_secret.into_vec()),
             })),
             AdminLoginResult::WrongCredential => {
                 Err(Status::unauthenticated("Invalid email or password"))
@@ -65,4 +94,129 @@ impl phantom_shop_proto::v1::admin::admin_auth_service_server::AdminAuthService
 
         Ok(Response::new(Empty {}))
     }
+
+    async fn admin_verify_mfa_login(
+        &self,
+        request: Request<AdminVerifyMfaLoginRequest>,
+    ) -> Result<Response<AdminVerifyMfaLoginResponse>, Status> {
+        let req = request.into_inner();
+        let mfa_token: [u8; 32] = req
+            .mfa_token
+            .try_into()
+            .map_err(|_| Status::invalid_argument("Invalid mfa token length"))?;
+        let result = self
+            .inner_service
+            .process(AdminVerifyMfaLogin {
+                mfa_token,
+                code: req.totp_code,
+            })
+            .await?;
+        let response = match result {
+            AdminVerifyMfaLoginResult::Success(session_id) => AdminVerifyMfaLoginResponse {
+                result: ProtoVerifyMfaLoginResult::Success.into(),
+                session_id: Some(session_id.to_ascii_string()),
+            },
+            AdminVerifyMfaLoginResult::InvalidToken => AdminVerifyMfaLoginResponse {
+                result: ProtoVerifyMfaLoginResult::InvalidToken.into(),
+                session_id: None,
+            },
+            AdminVerifyMfaLoginResult::InvalidCode => AdminVerifyMfaLoginResponse {
+                result: ProtoVerifyMfaLoginResult::InvalidCode.into(),
+                session_id: None,
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn show_mfa_status(
+        &self,
+        request: Request<Empty>,
+    ) -> Result<Response<AdminMfaStatus>, Status> {
+        let admin_id = AdminId::read_from_request(&request)?;
+        let status = self
+            .inner_service
+            .process(ShowAdminMfaStatus {
+                admin_id: admin_id.into_inner(),
+            })
+            .await?;
+        Ok(Response::new(AdminMfaStatus {
+            has_totp: status.has_totp,
+            enforced: status.enforced,
+        }))
+    }
+
+    async fn start_totp_setup(
+        &self,
+        request: Request<AdminStartTotpSetupRequest>,
+    ) -> Result<Response<AdminStartTotpSetupResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let result = self
+            .inner_service
+            .process(StartAdminTotpSetup {
+                admin_id: admin_id.into_inner(),
+                password: req.password,
+            })
+            .await?;
+        let response = match result {
+            StartAdminTotpSetupResult::Started(secret) => AdminStartTotpSetupResponse {
+                result: ProtoStartTotpSetupResult::Started.into(),
+                secret: Some(secret.into_vec()),
+            },
+            StartAdminTotpSetupResult::WrongPassword => AdminStartTotpSetupResponse {
+                result: ProtoStartTotpSetupResult::WrongPassword.into(),
+                secret: None,
+            },
+            StartAdminTotpSetupResult::AlreadyConfigured => AdminStartTotpSetupResponse {
+                result: ProtoStartTotpSetupResult::AlreadyConfigured.into(),
+                secret: None,
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn finish_totp_setup(
+        &self,
+        request: Request<AdminFinishTotpSetupRequest>,
+    ) -> Result<Response<AdminFinishTotpSetupResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let result = self
+            .inner_service
+            .process(FinishAdminTotpSetup {
+                admin_id: admin_id.into_inner(),
+                code: req.totp_code,
+            })
+            .await?;
+        let result = match result {
+            FinishAdminTotpSetupResult::Success => ProtoFinishTotpSetupResult::Success,
+            FinishAdminTotpSetupResult::InvalidCode => ProtoFinishTotpSetupResult::InvalidCode,
+            FinishAdminTotpSetupResult::Duplicate => ProtoFinishTotpSetupResult::Duplicate,
+            FinishAdminTotpSetupResult::Expired => ProtoFinishTotpSetupResult::Expired,
+        };
+        Ok(Response::new(AdminFinishTotpSetupResponse {
+            result: result.into(),
+        }))
+    }
+
+    async fn remove_totp(
+        &self,
+        request: Request<AdminRemoveTotpRequest>,
+    ) -> Result<Response<AdminRemoveTotpResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let result = self
+            .inner_service
+            .process(RemoveOwnAdminTotp {
+                admin_id: admin_id.into_inner(),
+                password: req.password,
+                code: req.totp_code,
+            })
+            .await?;
+        let result = match result {
+            RemoveOwnAdminTotpResult::Success => ProtoRemoveTotpResult::Success,
+            RemoveOwnAdminTotpResult::WrongCredential => ProtoRemoveTotpResult::WrongCredential,
+            RemoveOwnAdminTotpResult::Enforced => ProtoRemoveTotpResult::Enforced,
+        };
+        Ok(Response::new(AdminRemoveTotpResponse {
+            result: result.into(),
+        }))
+    }
 }
@@ -0,0 +1,480 @@
+use crate::entities::admin_account::{AdminAccount, AdminRole, OwnerGuardedChange};
+use crate::entities::admin_custom_role::{CustomRole, DeleteCustomRoleResult};
+use crate::rpc::middleware::AdminId;
+use crate::services::admin_manage::{
+    AdminManageService, AssignRole, ChangeRole, CreateRole, CreateRoleResult, DeleteAdmin,
+    DeleteRole, DisableOrEnableAdmin, InviteAdmin, InviteAdminResult, ListAdmins, ListRoles,
+    ResetAdminMfa, ResetAdminPassword, SetMfaEnforcement, SetMfaEnforcementResult,
+    UpdateRolePermissions,
+};
+use crate::utils::rbac::{
+    AuthenticatedAdminOperation, AuthorizationAdapter, AuthorizationLayer, Permission,
+};
+use phantom_shop_proto::v1::admin::{
+    AdminAccount as ProtoAdminAccount, AdminRole as ProtoAdminRole, AssignCustomRoleRequest,
+    ChangeAdminRoleRequest, CreateCustomRoleRequest, CreateCustomRoleResponse,
+    CreateCustomRoleResult as ProtoCreateCustomRoleResult, CustomRole as ProtoCustomRole,
+    DeleteAdminRequest, DeleteCustomRoleRequest, DeleteCustomRoleResponse,
+    DeleteCustomRoleResult as ProtoDeleteCustomRoleResult, DisableOrEnableAdminRequest,
+    InviteAdminRequest, InviteAdminResponse, InviteAdminResult as ProtoInviteAdminResult,
+    ListAdminsRequest, ListAdminsResponse, ListCustomRolesResponse, ManageAdminResponse,
+    ManageAdminResult, ResetAdminMfaRequest, ResetAdminPasswordRequest, ResetAdminPasswordResponse,
+    SetMfaEnforcementRequest, SetMfaEnforcementResponse,
+    SetMfaEnforcementResult as ProtoSetMfaEnforcementResult, UpdateCustomRolePermissionsRequest,
+};
+use phantom_shop_proto::v1::common::Empty;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+pub struct AdminManageServiceImpl {
+    pub inner: AdminManageService,
+    pub authorization: AuthorizationAdapter,
+}
+
+impl AdminManageServiceImpl {
+    pub fn new(inner: AdminManageService, authorization: AuthorizationLayer) -> Self {
+        Self {
+            inner,
+            authorization: authorization.into_adapter(),
+        }
+    }
+}
+
+fn parse_admin_id(value: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(value).map_err(|_| Status::invalid_argument("Invalid admin id"))
+}
+
+fn parse_admin_role(value: i32) -> Result<AdminRole, Status> {
+    match ProtoAdminRole::try_from(value) {
+        Ok(ProtoAdminRole::Owner) => Ok(AdminRole::Owner),
+        Ok(ProtoAdminRole::Moderator) => Ok(AdminRole::Moderator),
+        Err(_) => Err(Status::invalid_argument("Invalid admin role")),
+    }
+}
+
+impl From<AdminAccount> for ProtoAdminAccount {
+    fn from(value: AdminAccount) -> Self {
+        let role = match value.role {
+            AdminRole::Owner => ProtoAdminRole::Owner,
+            AdminRole::Moderator => ProtoAdminRole::Moderator,
+        };
+        Self {
+            id: value.id.to_string(),
+            role: role.into(),
+            name: value.name.to_string(),
+            email: value.email,
+            avatar: value.avatar,
+            created_at: Some(value.created_at.into()),
+            disabled_at: value.disabled_at.map(Into::into),
+            custom_role_id: value.custom_role_id.map(|id| id.to_string()),
+        }
+    }
+}
+
+fn parse_role_id(value: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(value).map_err(|_| Status::invalid_argument("Invalid role id"))
+}
+
+fn parse_permissions(values: &[String]) -> Result<Vec<Permission>, Status> {
+    values
+        .iter()
+        .map(|value| {
+            Permission::parse(value)
+                .ok_or_else(|| Status::invalid_argument(format!("Unknown permission: {value}")))
+        })
+        .collect()
+}
+
+impl From<CustomRole> for ProtoCustomRole {
+    fn from(value: CustomRole) -> Self {
+        Self {
+            id: value.id.to_string(),
+            name: value.name.to_string(),
+            permissions: value
+                .permissions
+                .into_iter()
+                .map(|permission| permission.as_str().to_owned())
+                .collect(),
+            created_at: Some(value.created_at.into()),
+        }
+    }
+}
+
+fn manage_admin_response(
+    result: OwnerGuardedChange<Option<AdminAccount>>,
+) -> Result<ManageAdminResponse, Status> {
+    match result {
+        OwnerGuardedChange::Changed(admin) => Ok(ManageAdminResponse {
+            result: ManageAdminResult::Success.into(),
+            admin: admin.map(Into::into),
+        }),
+        OwnerGuardedChange::LastOwner => Ok(ManageAdminResponse {
+            result: ManageAdminResult::LastOwner.into(),
+            admin: None,
+        }),
+        OwnerGuardedChange::NotFound => Err(Status::not_found("Admin not found")),
+    }
+}
+
+fn with_admin(
+    result: OwnerGuardedChange<AdminAccount>,
+) -> OwnerGuardedChange<Option<AdminAccount>> {
+    match result {
+        OwnerGuardedChange::Changed(admin) => OwnerGuardedChange::Changed(Some(admin)),
+        OwnerGuardedChange::NotFound => OwnerGuardedChange::NotFound,
+        OwnerGuardedChange::LastOwner => OwnerGuardedChange::LastOwner,
+    }
+}
+
+#[tonic::async_trait]
+impl phantom_shop_proto::v1::admin::admin_manage_service_server::AdminManageService
+    for AdminManageServiceImpl
+{
+    async fn invite_admin(
+        &self,
+        request: Request<InviteAdminRequest>,
+    ) -> Result<Response<InviteAdminResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = InviteAdmin {
+            email: req.email,
+            name: req.name.into(),
+            role: parse_admin_role(req.role)?,
+        };
+        let result = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        let response = match result {
+            InviteAdminResult::Success {
+                admin,
+                initial_password,
+            } => InviteAdminResponse {
+                result: ProtoInviteAdminResult::Success.into(),
+                admin: Some(admin.into()),
+                initial_password: Some(initial_password),
+            },
+            InviteAdminResult::EmailAlreadyUsed => InviteAdminResponse {
+                result: ProtoInviteAdminResult::EmailAlreadyUsed.into(),
+                admin: None,
+                initial_password: None,
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn list_admins(
+        &self,
+        request: Request<ListAdminsRequest>,
+    ) -> Result<Response<ListAdminsResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let limit = match req.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            limit => limit.min(MAX_PAGE_SIZE),
+        };
+        let operation = ListAdmins {
+            limit: limit.into(),
+            offset: req.offset.into(),
+        };
+        let admins = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(ListAdminsResponse {
+            admins: admins.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn change_admin_role(
+        &self,
+        request: Request<ChangeAdminRoleRequest>,
+    ) -> Result<Response<ManageAdminResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = ChangeRole {
+            admin_id: parse_admin_id(&req.admin_id)?,
+            role: parse_admin_role(req.role)?,
+        };
+        let result = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(manage_admin_response(with_admin(result))?))
+    }
+
+    async fn disable_or_enable_admin(
+        &self,
+        request: Request<DisableOrEnableAdminRequest>,
+    ) -> Result<Response<ManageAdminResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = DisableOrEnableAdmin {
+            admin_id: parse_admin_id(&req.admin_id)?,
+            disabled: req.disabled,
+        };
+        let result = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(manage_admin_response(with_admin(result))?))
+    }
+
+    async fn delete_admin(
+        &self,
+        request: Request<DeleteAdminRequest>,
+    ) -> Result<Response<ManageAdminResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = DeleteAdmin {
+            admin_id: parse_admin_id(&req.admin_id)?,
+        };
+        let result = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        let result = match result {
+            OwnerGuardedChange::Changed(()) => OwnerGuardedChange::Changed(None),
+            OwnerGuardedChange::NotFound => OwnerGuardedChange::NotFound,
+            OwnerGuardedChange::LastOwner => OwnerGuardedChange::LastOwner,
+        };
+        Ok(Response::new(manage_admin_response(result)?))
+    }
+
+    async fn reset_admin_password(
+        &self,
+        request: Request<ResetAdminPasswordRequest>,
+    ) -> Result<Response<ResetAdminPasswordResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = ResetAdminPassword {
+            admin_id: parse_admin_id(&req.admin_id)?,
+        };
+        let new_password = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(ResetAdminPasswordResponse { new_password }))
+    }
+
+    async fn set_mfa_enforcement(
+        &self,
+        request: Request<SetMfaEnforcementRequest>,
+    ) -> Result<Response<SetMfaEnforcementResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = SetMfaEnforcement {
+            admin_id: admin_id.into_inner(),
+            enforced: req.enforced,
+        };
+        let result = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        let result = match result {
+            SetMfaEnforcementResult::Success => ProtoSetMfaEnforcementResult::Success,
+            SetMfaEnforcementResult::EnrollFirst => ProtoSetMfaEnforcementResult::EnrollFirst,
+        };
+        Ok(Response::new(SetMfaEnforcementResponse {
+            result: result.into(),
+        }))
+    }
+
+    async fn reset_admin_mfa(
+        &self,
+        request: Request<ResetAdminMfaRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = ResetAdminMfa {
+            admin_id: parse_admin_id(&req.admin_id)?,
+        };
+        self.authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn create_custom_role(
+        &self,
+        request: Request<CreateCustomRoleRequest>,
+    ) -> Result<Response<CreateCustomRoleResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        if req.name.trim().is_empty() {
+            return Err(Status::invalid_argument("Role name is empty"));
+        }
+        let operation = CreateRole {
+            name: req.name.trim().into(),
+            permissions: parse_permissions(&req.permissions)?,
+        };
+        let result = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        let response = match result {
+            CreateRoleResult::Success(role) => CreateCustomRoleResponse {
+                result: ProtoCreateCustomRoleResult::Success.into(),
+                role: Some(role.into()),
+            },
+            CreateRoleResult::NameAlreadyUsed => CreateCustomRoleResponse {
+                result: ProtoCreateCustomRoleResult::NameAlreadyUsed.into(),
+                role: None,
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn list_custom_roles(
+        &self,
+        request: Request<Empty>,
+    ) -> Result<Response<ListCustomRolesResponse>, Status> {
+        let admin_id = AdminId::read_from_request(&request)?;
+        let roles = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: ListRoles,
+                },
+            )
+            .await?;
+        Ok(Response::new(ListCustomRolesResponse {
+            roles: roles.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn update_custom_role_permissions(
+        &self,
+        request: Request<UpdateCustomRolePermissionsRequest>,
+    ) -> Result<Response<ProtoCustomRole>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = UpdateRolePermissions {
+            role_id: parse_role_id(&req.role_id)?,
+            permissions: parse_permissions(&req.permissions)?,
+        };
+        let role = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(role.into()))
+    }
+
+    async fn delete_custom_role(
+        &self,
+        request: Request<DeleteCustomRoleRequest>,
+    ) -> Result<Response<DeleteCustomRoleResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = DeleteRole {
+            role_id: parse_role_id(&req.role_id)?,
+        };
+        let result = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        let result = match result {
+            DeleteCustomRoleResult::Deleted => ProtoDeleteCustomRoleResult::Success,
+            DeleteCustomRoleResult::InUse => ProtoDeleteCustomRoleResult::InUse,
+            DeleteCustomRoleResult::NotFound => return Err(Status::not_found("Role not found")),
+        };
+        Ok(Response::new(DeleteCustomRoleResponse {
+            result: result.into(),
+        }))
+    }
+
+    async fn assign_custom_role(
+        &self,
+        request: Request<AssignCustomRoleRequest>,
+    ) -> Result<Response<ProtoAdminAccount>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = AssignRole {
+            admin_id: parse_admin_id(&req.admin_id)?,
+            role_id: req.role_id.as_deref().map(parse_role_id).transpose()?,
+        };
+        let admin = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(admin.into()))
+    }
+}
@@ -0,0 +1,122 @@
+use crate::entities::admin_audit_log::{AuditLogEntry, AuditResult};
+use crate::rpc::middleware::AdminId;
+use crate::services::audit_log::{AuditLogService, QueryAuditLog};
+use crate::utils::rbac::{AuthenticatedAdminOperation, AuthorizationAdapter, AuthorizationLayer};
+use phantom_shop_proto::v1::admin::{
+    AuditLogCursor, AuditLogEntry as ProtoAuditLogEntry, AuditResult as ProtoAuditResult,
+    ListAuditLogRequest, ListAuditLogResponse,
+};
+use phantom_shop_proto::v1::common::Timestamp;
+use time::PrimitiveDateTime;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+pub struct AuditLogServiceImpl {
+    pub inner: AuditLogService,
+    pub authorization: AuthorizationAdapter,
+}
+
+impl AuditLogServiceImpl {
+    pub fn new(inner: AuditLogService, authorization: AuthorizationLayer) -> Self {
+        Self {
+            inner,
+            authorization: authorization.into_adapter(),
+        }
+    }
+}
+
+fn parse_timestamp(value: Timestamp) -> Result<PrimitiveDateTime, Status> {
+    value
+        .try_into()
+        .map_err(|_| Status::invalid_argument("Invalid timestamp"))
+}
+
+fn parse_uuid(value: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(value).map_err(|_| Status::invalid_argument("Invalid id"))
+}
+
+fn parse_audit_result(value: i32) -> Result<AuditResult, Status> {
+    match ProtoAuditResult::try_from(value) {
+        Ok(ProtoAuditResult::Success) => Ok(AuditResult::Success),
+        Ok(ProtoAuditResult::Denied) => Ok(AuditResult::Denied),
+        Ok(ProtoAuditResult::Failure) => Ok(AuditResult::Failure),
+        Err(_) => Err(Status::invalid_argument("Invalid audit result")),
+    }
+}
+
+fn parse_cursor(value: AuditLogCursor) -> Result<(PrimitiveDateTime, Uuid), Status> {
+    let created_at = value
+        .created_at
+        .ok_or_else(|| Status::invalid_argument("Missing cursor timestamp"))?;
+    Ok((parse_timestamp(created_at)?, parse_uuid(&value.entry_id)?))
+}
+
+impl From<AuditLogEntry> for ProtoAuditLogEntry {
+    fn from(value: AuditLogEntry) -> Self {
+        let result = match value.result {
+            AuditResult::Success => ProtoAuditResult::Success,
+            AuditResult::Denied => ProtoAuditResult::Denied,
+            AuditResult::Failure => ProtoAuditResult::Failure,
+        };
+        Self {
+            id: value.id.to_string(),
+            admin_id: value.admin_id.to_string(),
+            operation: value.operation,
+            input_summary: value.input_summary,
+            result: result.into(),
+            error: value.error,
+            client_ip: value.client_ip,
+            created_at: Some(value.created_at.into()),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl phantom_shop_proto::v1::admin::audit_log_service_server::AuditLogService
+    for AuditLogServiceImpl
+{
+    async fn list_audit_log(
+        &self,
+        request: Request<ListAuditLogRequest>,
+    ) -> Result<Response<ListAuditLogResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let limit = match req.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            limit => limit.min(MAX_PAGE_SIZE),
+        };
+        let operation = QueryAuditLog {
+            admin_id: req.admin_id.as_deref().map(parse_uuid).transpose()?,
+            operation: req.operation,
+            result: req.result.map(parse_audit_result).transpose()?,
+            since: req.since.map(parse_timestamp).transpose()?,
+            until: req.until.map(parse_timestamp).transpose()?,
+            before: req.after.map(parse_cursor).transpose()?,
+            limit: limit.into(),
+        };
+        let entries = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        let next = match entries.last() {
+            Some(last) if entries.len() == limit as usize => Some(AuditLogCursor {
+                created_at: Some(last.created_at.into()),
+                entry_id: last.id.to_string(),
+            }),
+            _ => None,
+        };
+        Ok(Response::new(ListAuditLogResponse {
+            entries: entries.into_iter().map(Into::into).collect(),
+            next,
+        }))
+    }
+}
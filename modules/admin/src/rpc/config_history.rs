@@ -0,0 +1,183 @@
+use crate::entities::config_history::ConfigVersion;
+use crate::rpc::middleware::AdminId;
+use crate::services::config_history::{
+    ConfigHistoryService, DiffConfigVersions, ListConfigHistory, RollbackConfig,
+    RotateConfigSecrets, ShowConfigVersion,
+};
+use crate::utils::json_diff::JsonChange;
+use crate::utils::rbac::{AuthenticatedAdminOperation, AuthorizationAdapter, AuthorizationLayer};
+use phantom_shop_proto::v1::admin::{
+    ConfigChange, ConfigVersion as ProtoConfigVersion, DiffConfigVersionsRequest,
+    DiffConfigVersionsResponse, ListConfigHistoryRequest, ListConfigHistoryResponse,
+    RollbackConfigRequest, RollbackConfigResponse, RotateConfigSecretsRequest,
+    RotateConfigSecretsResponse, ShowConfigVersionRequest,
+};
+use tonic::{Request, Response, Status};
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+pub struct ConfigHistoryServiceImpl {
+    pub inner: ConfigHistoryService,
+    pub authorization: AuthorizationAdapter,
+}
+
+impl ConfigHistoryServiceImpl {
+    pub fn new(inner: ConfigHistoryService, authorization: AuthorizationLayer) -> Self {
+        Self {
+            inner,
+            authorization: authorization.into_adapter(),
+        }
+    }
+}
+
+impl From<ConfigVersion> for ProtoConfigVersion {
+    fn from(value: ConfigVersion) -> Self {
+        Self {
+            key: value.key,
+            version: value.version,
+            content: value.content.to_string(),
+            author_id: value.author_id.map(|id| id.to_string()),
+            created_at: Some(value.created_at.into()),
+        }
+    }
+}
+
+impl From<JsonChange> for ConfigChange {
+    fn from(value: JsonChange) -> Self {
+        Self {
+            path: value.path,
+            old_value: value.old.map(|value| value.to_string()),
+            new_value: value.new.map(|value| value.to_string()),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl phantom_shop_proto::v1::admin::config_history_service_server::ConfigHistoryService
+    for ConfigHistoryServiceImpl
+{
+    async fn list_config_history(
+        &self,
+        request: Request<ListConfigHistoryRequest>,
+    ) -> Result<Response<ListConfigHistoryResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let limit = match req.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            limit => limit.min(MAX_PAGE_SIZE),
+        };
+        let operation = ListConfigHistory {
+            key: req.key,
+            before_version: req.before_version,
+            limit: limit.into(),
+        };
+        let versions = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(ListConfigHistoryResponse {
+            versions: versions.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn show_config_version(
+        &self,
+        request: Request<ShowConfigVersionRequest>,
+    ) -> Result<Response<ProtoConfigVersion>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = ShowConfigVersion {
+            key: req.key,
+            version: req.version,
+        };
+        let version = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(version.into()))
+    }
+
+    async fn diff_config_versions(
+        &self,
+        request: Request<DiffConfigVersionsRequest>,
+    ) -> Result<Response<DiffConfigVersionsResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = DiffConfigVersions {
+            key: req.key,
+            from_version: req.from_version,
+            to_version: req.to_version,
+        };
+        let changes = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(DiffConfigVersionsResponse {
+            changes: changes.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn rollback_config(
+        &self,
+        request: Request<RollbackConfigRequest>,
+    ) -> Result<Response<RollbackConfigResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = RollbackConfig {
+            admin_id: admin_id.into_inner(),
+            key: req.key,
+            version: req.version,
+        };
+        let new_version = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(RollbackConfigResponse { new_version }))
+    }
+
+    async fn rotate_config_secrets(
+        &self,
+        request: Request<RotateConfigSecretsRequest>,
+    ) -> Result<Response<RotateConfigSecretsResponse>, Status> {
+        let (admin_id, _) = AdminId::from_request(request)?;
+        let rewrapped_count = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: RotateConfigSecrets,
+                },
+            )
+            .await?;
+        Ok(Response::new(RotateConfigSecretsResponse {
+            rewrapped_count,
+        }))
+    }
+}
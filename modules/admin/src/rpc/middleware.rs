@@ -1,6 +1,9 @@
 use crate::entities::admin_session::{AdminSession, AdminSessionId};
 use framework::redis::{KeyValueRead, RedisConnection};
+use std::net::IpAddr;
 use tonic::codegen::BoxFuture;
+use tonic::codegen::http::HeaderMap;
+use tonic::transport::server::TcpConnectInfo;
 use uuid::Uuid;
 
 #[derive(Clone)]
@@ -56,43 +59,66 @@ where
         let mut inner = std::mem::replace(&mut self.inner, inner_clone);
         let redis = self.redis.clone();
         Box::pin(async move {
-            match admin_auth(req.headers(), redis).await {
-                Ok(admin_id) => {
-                    req.extensions_mut().insert(admin_id);
-                }
-                Err(_) => {}
-            };
+            if let Ok(id) = admin_auth(req.headers(), redis).await {
+                let client_ip = client_ip(req.headers()).or_else(|| {
+                    req.extensions()
+                        .get::<TcpConnectInfo>()
+                        .and_then(TcpConnectInfo::remote_addr)
+                        .map(|addr| addr.ip())
+                });
+                req.extensions_mut().insert(AdminId { id, client_ip });
+            }
             inner.call(req).await
         })
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct AdminId(pub Uuid);
+pub struct AdminId {
+    pub id: Uuid,
+    /// Where the request came from, kept for the audit log.
+    pub client_ip: Option<IpAddr>,
+}
 
 impl AdminId {
     pub fn into_inner(self) -> Uuid {
-        self.0
+        self.id
     }
-    pub fn read_from_request<T>(req: &tonic::codegen::http::Request<T>) -> Option<Self> {
-        req.extensions().get::<Self>().cloned()
+    pub fn client_ip(self) -> Option<IpAddr> {
+        self.client_ip
     }
-    pub fn from_request<T>(
-        req: tonic::codegen::http::Request<T>,
-    ) -> Result<(AdminId, T), tonic::Status> {
-        let id = Self::read_from_request(&req).ok_or(tonic::Status::unauthenticated(
-            "Missing admin id in request",
-        ))?;
-        Ok((id, req.into_body()))
+    pub fn read_from_request<T>(req: &tonic::Request<T>) -> Result<Self, tonic::Status> {
+        req.extensions()
+            .get::<Self>()
+            .cloned()
+            .ok_or_else(|| tonic::Status::unauthenticated("Missing admin id in request"))
+    }
+    pub fn from_request<T>(req: tonic::Request<T>) -> Result<(AdminId, T), tonic::Status> {
+        let id = Self::read_from_request(&req)?;
+        Ok((id, req.into_inner()))
     }
 }
 
 pub const ADMIN_AUTHORIZATION_HEADER: &str = "x-admin-authorization";
 
+/// The services are served behind a reverse proxy, so `x-forwarded-for` takes precedence over
+/// the address of the TCP peer, which callers fall back to.
+///
+/// Clients may send their own `x-forwarded-for`, and the proxy appends the address it sees, so
+/// only the right-most entry can be trusted. Other headers such as `x-real-ip` are passed through
+/// from the client as they are and are not read.
+pub fn client_ip(header_map: &HeaderMap) -> Option<IpAddr> {
+    header_map
+        .get("x-forwarded-for")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.rsplit(',').next())
+        .and_then(|ip| ip.trim().parse().ok())
+}
+
 async fn admin_auth(
-    header_map: &tonic::codegen::http::HeaderMap,
+    header_map: &HeaderMap,
     mut redis: RedisConnection,
-) -> Result<AdminId, tonic::Status> {
+) -> Result<Uuid, tonic::Status> {
     let auth_header = header_map
         .get(ADMIN_AUTHORIZATION_HEADER)
         .and_then(|h| h.to_str().ok())
@@ -105,5 +131,28 @@ async fn admin_auth(
         .await
         .map_err(|_| tonic::Status::internal("Internal server error"))?
         .ok_or(tonic::Status::unauthenticated("Invalid admin session"))?;
-    Ok(AdminId(session.admin_id))
+    Ok(session.admin_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::codegen::http::HeaderValue;
+
+    #[test]
+    fn client_ip_uses_the_hop_added_by_the_proxy() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("1.1.1.1, 10.0.0.1, 203.0.113.7"),
+        );
+        assert_eq!(client_ip(&headers), "203.0.113.7".parse().ok());
+    }
+
+    #[test]
+    fn client_ip_ignores_x_real_ip() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-real-ip", HeaderValue::from_static("203.0.113.7"));
+        assert_eq!(client_ip(&headers), None);
+    }
 }
@@ -1,2 +1,5 @@
 pub mod admin_auth;
+pub mod admin_manage;
+pub mod audit_log;
+pub mod config_history;
 pub mod middleware;
@@ -1,15 +1,93 @@
-use crate::entities::admin_account::FindAdminByEmail;
+use crate::config::AdminSecurityConfig;
+use crate::entities::admin_account::{FindAdminByEmail, FindAdminById};
+use crate::entities::admin_mfa::{
+    AdminMfaLoginToken, AdminMfaLoginTokenKey, PendingAdminTotpSetup, PendingAdminTotpSetupKey,
+};
 use crate::entities::admin_session::{AdminSession, AdminSessionId};
+use crate::entities::admin_session_list::{AdminSessionIndex, AdminSessions};
+use crate::entities::admin_totp::{CreateAdminTotp, FindAdminTotp, RemoveAdminTotp};
+use crate::entities::totp_guard::{AdminTotpAttemptsKey, AdminTotpLastStepKey};
+use crate::utils::config_provider::LiveConfig;
 use crate::utils::password::verify_password;
-use framework::redis::{KeyValue, KeyValueWrite, RedisConnection};
+use crate::utils::totp::{TotpLimits, verify_totp};
+use framework::redis::{KeyValue, KeyValueRead, KeyValueWrite, RedisConnection};
 use framework::sqlx::DatabaseProcessor;
+use kanau::message::MessageDe;
 use kanau::processor::Processor;
+use redis::AsyncCommands;
 use tracing::instrument;
+use uuid::Uuid;
+
+const RFC6238_TOTP_KEY_LENGTH: usize = 20;
 
 #[derive(Clone)]
 pub struct AdminAuthService {
     pub db: DatabaseProcessor,
     pub redis: RedisConnection,
+    pub security_config: LiveConfig<AdminSecurityConfig>,
+}
+
+impl AdminAuthService {
+    async fn create_session(&self, admin_id: Uuid) -> Result<AdminSessionId, framework::Error> {
+        let session_id = AdminSessionId::generate();
+        let session = AdminSession::new(
+            session_id,
+            AdminSession {
+                id: session_id,
+                admin_id,
+            },
+        );
+
+        // Store session in Redis
+        let mut redis = self.redis.clone();
+        session.write(&mut redis).await?;
+        AdminSessions::add_session(&mut redis, AdminSessionIndex(admin_id), session_id).await?;
+        Ok(session_id)
+    }
+
+    /// Check a TOTP code of the admin, see [`verify_totp`].
+    async fn verify_admin_totp(
+        &self,
+        admin_id: Uuid,
+        secret: &[u8],
+        code: u32,
+    ) -> Result<bool, framework::Error> {
+        let limits = {
+            let config = self.security_config.load();
+            TotpLimits {
+                skew_steps: config.totp_skew_steps,
+                max_attempts: config.totp_max_attempts,
+                attempt_window: config.totp_attempt_window,
+            }
+        };
+        let mut redis = self.redis.clone();
+        verify_totp(
+            &mut redis,
+            AdminTotpAttemptsKey(admin_id),
+            AdminTotpLastStepKey(admin_id),
+            secret,
+            code,
+            limits,
+        )
+        .await
+    }
+
+    async fn create_pending_totp_setup(
+        &self,
+        admin_id: Uuid,
+        config: &AdminSecurityConfig,
+    ) -> Result<PendingAdminTotpSetup, framework::Error> {
+        let ttl: std::time::Duration = config.totp_setup_ttl.try_into().map_err(|e| {
+            framework::Error::BusinessPanic(anyhow::anyhow!("Invalid totp setup ttl: {e}"))
+        })?;
+        let secret: [u8; RFC6238_TOTP_KEY_LENGTH] = rand::random();
+        let pending = PendingAdminTotpSetup {
+            admin_id: PendingAdminTotpSetupKey(admin_id),
+            secret: secret.into(),
+        };
+        pending.write_with_ttl(&mut self.redis.clone(), ttl).await?;
+        Ok(pending)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -21,6 +99,14 @@ pub struct AdminLogin {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AdminLoginResult {
     Success(AdminSessionId),
+    /// The password is correct, finish the login with [`AdminVerifyMfaLogin`].
+    MfaRequired([u8; 32]),
+    /// MFA is enforced but the admin has no TOTP yet. Add the secret to an authenticator app and
+    /// finish the login with [`AdminVerifyMfaLogin`], which also completes the enrollment.
+    MfaEnrollmentRequired {
+        mfa_token: [u8; 32],
+        totp_secret: Box<[u8]>,
+    },
     WrongCredential,
 }
 
@@ -38,25 +124,129 @@ impl Processor<AdminLogin> for AdminAuthService {
             return Ok(AdminLoginResult::WrongCredential);
         };
 
+        // Disabled admins can not log in
+        if admin.is_disabled() {
+            return Ok(AdminLoginResult::WrongCredential);
+        }
+
         // Verify password
         if verify_password(&input.password, &admin.password_hash).is_err() {
             return Ok(AdminLoginResult::WrongCredential);
         }
 
-        // Create session
-        let session_id = AdminSessionId::generate();
-        let session = AdminSession::new(
-            session_id,
-            AdminSession {
-                id: session_id,
-                admin_id: admin.id,
-            },
-        );
+        // Second step if the admin has TOTP or MFA is enforced
+        let has_totp = self
+            .db
+            .process(FindAdminTotp { admin_id: admin.id })
+            .await?
+            .is_some();
+        let config = self.security_config.load();
+        if !has_totp && !config.enforce_mfa {
+            let session_id = self.create_session(admin.id).await?;
+            return Ok(AdminLoginResult::Success(session_id));
+        }
+        let token_ttl: std::time::Duration = config.mfa_token_ttl.try_into().map_err(|e| {
+            framework::Error::BusinessPanic(anyhow::anyhow!("Invalid mfa token ttl: {e}"))
+        })?;
+        let token = AdminMfaLoginToken {
+            token: rand::random(),
+            admin_id: admin.id,
+        };
+        token
+            .write_with_ttl(&mut self.redis.clone(), token_ttl)
+            .await?;
+        if has_totp {
+            return Ok(AdminLoginResult::MfaRequired(token.token));
+        }
+        let pending = self.create_pending_totp_setup(admin.id, &config).await?;
+        Ok(AdminLoginResult::MfaEnrollmentRequired {
+            mfa_token: token.token,
+            totp_secret: pending.secret,
+        })
+    }
+}
 
-        // Store session in Redis
-        session.write(&mut self.redis.clone()).await?;
+#[derive(Debug, Clone)]
+pub struct AdminVerifyMfaLogin {
+    pub mfa_token: [u8; 32],
+    pub code: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdminVerifyMfaLoginResult {
+    Success(AdminSessionId),
+    /// The token is unknown, expired or already used. Log in again.
+    InvalidToken,
+    InvalidCode,
+}
+
+impl Processor<AdminVerifyMfaLogin> for AdminAuthService {
+    type Output = AdminVerifyMfaLoginResult;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: AdminVerifyMfaLogin,
+    ) -> Result<AdminVerifyMfaLoginResult, framework::Error> {
+        let mut redis = self.redis.clone();
+
+        // The token is consumed by GETDEL, so a token can only be tried once
+        let data: Option<Vec<u8>> = redis
+            .get_del(AdminMfaLoginTokenKey(input.mfa_token))
+            .await?;
+        let Some(bytes) = data else {
+            return Ok(AdminVerifyMfaLoginResult::InvalidToken);
+        };
+        let token = AdminMfaLoginToken::from_bytes(&bytes)
+            .map_err(|e| framework::Error::DeserializeError(e.into()))?;
 
-        Ok(AdminLoginResult::Success(session_id))
+        // The admin may have been disabled in the meantime
+        let Some(admin) = self
+            .db
+            .process(FindAdminById { id: token.admin_id })
+            .await?
+            .filter(|admin| !admin.is_disabled())
+        else {
+            return Ok(AdminVerifyMfaLoginResult::InvalidToken);
+        };
+
+        match self
+            .db
+            .process(FindAdminTotp { admin_id: admin.id })
+            .await?
+        {
+            Some(totp) => {
+                if !self
+                    .verify_admin_totp(admin.id, &totp.secret, input.code)
+                    .await?
+                {
+                    return Ok(AdminVerifyMfaLoginResult::InvalidCode);
+                }
+            }
+            // enrollment during login
+            None => {
+                let key = PendingAdminTotpSetupKey(admin.id);
+                let Some(pending) = PendingAdminTotpSetup::read(&mut redis, key).await? else {
+                    return Ok(AdminVerifyMfaLoginResult::InvalidToken);
+                };
+                if !self
+                    .verify_admin_totp(admin.id, &pending.secret, input.code)
+                    .await?
+                {
+                    return Ok(AdminVerifyMfaLoginResult::InvalidCode);
+                }
+                self.db
+                    .process(CreateAdminTotp {
+                        admin_id: admin.id,
+                        secret: pending.secret.to_vec(),
+                    })
+                    .await?;
+                PendingAdminTotpSetup::delete(&mut redis, key).await?;
+            }
+        }
+
+        let session_id = self.create_session(admin.id).await?;
+        Ok(AdminVerifyMfaLoginResult::Success(session_id))
     }
 }
 
@@ -70,7 +260,207 @@ impl Processor<AdminLogout> for AdminAuthService {
     #[instrument(skip_all, err)]
     async fn process(&self, input: AdminLogout) -> Result<(), framework::Error> {
         // Delete session from Redis
-        AdminSession::delete(&mut self.redis.clone(), input.session_id).await?;
+        let mut redis = self.redis.clone();
+        if let Some(session) = AdminSession::read(&mut redis, input.session_id).await? {
+            AdminSessions::remove_session(
+                &mut redis,
+                AdminSessionIndex(session.admin_id),
+                input.session_id,
+            )
+            .await?;
+        }
+        AdminSession::delete(&mut redis, input.session_id).await?;
         Ok(())
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShowAdminMfaStatus {
+    pub admin_id: Uuid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdminMfaStatus {
+    pub has_totp: bool,
+    pub enforced: bool,
+}
+
+impl Processor<ShowAdminMfaStatus> for AdminAuthService {
+    type Output = AdminMfaStatus;
+    type Error = framework::Error;
+    async fn process(&self, input: ShowAdminMfaStatus) -> Result<AdminMfaStatus, framework::Error> {
+        let has_totp = self
+            .db
+            .process(FindAdminTotp {
+                admin_id: input.admin_id,
+            })
+            .await?
+            .is_some();
+        let enforced = self.security_config.load().enforce_mfa;
+        Ok(AdminMfaStatus { has_totp, enforced })
+    }
+}
+
+#[derive(Clone)]
+/// Start enrolling TOTP for a logged-in admin. The password is asked again since admins have no
+/// sudo mode.
+pub struct StartAdminTotpSetup {
+    pub admin_id: Uuid,
+    pub password: String,
+}
+
+impl core::fmt::Debug for StartAdminTotpSetup {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StartAdminTotpSetup")
+            .field("admin_id", &self.admin_id)
+            .field("password", &"[redacted]")
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartAdminTotpSetupResult {
+    /// The secret to add to an authenticator app
+    Started(Box<[u8]>),
+    WrongPassword,
+    AlreadyConfigured,
+}
+
+impl Processor<StartAdminTotpSetup> for AdminAuthService {
+    type Output = StartAdminTotpSetupResult;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: StartAdminTotpSetup,
+    ) -> Result<StartAdminTotpSetupResult, framework::Error> {
+        let admin = self
+            .db
+            .process(FindAdminById { id: input.admin_id })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        if verify_password(&input.password, &admin.password_hash).is_err() {
+            return Ok(StartAdminTotpSetupResult::WrongPassword);
+        }
+        let has_totp = self
+            .db
+            .process(FindAdminTotp { admin_id: admin.id })
+            .await?
+            .is_some();
+        if has_totp {
+            return Ok(StartAdminTotpSetupResult::AlreadyConfigured);
+        }
+        let config = self.security_config.load();
+        let pending = self.create_pending_totp_setup(admin.id, &config).await?;
+        Ok(StartAdminTotpSetupResult::Started(pending.secret))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FinishAdminTotpSetup {
+    pub admin_id: Uuid,
+    pub code: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinishAdminTotpSetupResult {
+    Success,
+    InvalidCode,
+    Duplicate,
+    Expired,
+}
+
+impl Processor<FinishAdminTotpSetup> for AdminAuthService {
+    type Output = FinishAdminTotpSetupResult;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: FinishAdminTotpSetup,
+    ) -> Result<FinishAdminTotpSetupResult, framework::Error> {
+        let mut redis = self.redis.clone();
+        let key = PendingAdminTotpSetupKey(input.admin_id);
+        let Some(pending) = PendingAdminTotpSetup::read(&mut redis, key).await? else {
+            return Ok(FinishAdminTotpSetupResult::Expired);
+        };
+        if !self
+            .verify_admin_totp(input.admin_id, &pending.secret, input.code)
+            .await?
+        {
+            return Ok(FinishAdminTotpSetupResult::InvalidCode);
+        }
+        let created = self
+            .db
+            .process(CreateAdminTotp {
+                admin_id: input.admin_id,
+                secret: pending.secret.to_vec(),
+            })
+            .await?;
+        PendingAdminTotpSetup::delete(&mut redis, key).await?;
+        Ok(match created {
+            Some(_) => FinishAdminTotpSetupResult::Success,
+            None => FinishAdminTotpSetupResult::Duplicate,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct RemoveOwnAdminTotp {
+    pub admin_id: Uuid,
+    pub password: String,
+    pub code: u32,
+}
+
+impl core::fmt::Debug for RemoveOwnAdminTotp {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RemoveOwnAdminTotp")
+            .field("admin_id", &self.admin_id)
+            .field("password", &"[redacted]")
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoveOwnAdminTotpResult {
+    Success,
+    WrongCredential,
+    /// MFA is enforced, the TOTP can only be reset by an owner.
+    Enforced,
+}
+
+impl Processor<RemoveOwnAdminTotp> for AdminAuthService {
+    type Output = RemoveOwnAdminTotpResult;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: RemoveOwnAdminTotp,
+    ) -> Result<RemoveOwnAdminTotpResult, framework::Error> {
+        if self.security_config.load().enforce_mfa {
+            return Ok(RemoveOwnAdminTotpResult::Enforced);
+        }
+        let admin = self
+            .db
+            .process(FindAdminById { id: input.admin_id })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        let Some(totp) = self
+            .db
+            .process(FindAdminTotp { admin_id: admin.id })
+            .await?
+        else {
+            return Ok(RemoveOwnAdminTotpResult::WrongCredential);
+        };
+        if verify_password(&input.password, &admin.password_hash).is_err()
+            || !self
+                .verify_admin_totp(admin.id, &totp.secret, input.code)
+                .await?
+        {
+            return Ok(RemoveOwnAdminTotpResult::WrongCredential);
+        }
+        self.db
+            .process(RemoveAdminTotp { admin_id: admin.id })
+            .await?;
+        Ok(RemoveOwnAdminTotpResult::Success)
+    }
+}
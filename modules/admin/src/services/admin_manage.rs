@@ -0,0 +1,436 @@
+use crate::config::AdminSecurityConfig;
+use crate::entities::admin_account::{
+    AdminAccount, AdminRole, ChangeAdminRole, CreateAdminAccount, DeleteAdminAccount,
+    FindAdminByEmail, ListAdminAccounts, OwnerGuardedChange, SetAdminDisabled, UpdateAdminPassword,
+};
+use crate::entities::admin_custom_role::{
+    CreateCustomRole, CustomRole, DeleteCustomRole, DeleteCustomRoleResult, FindCustomRole,
+    ListCustomRoles, SetAdminCustomRole, UpdateCustomRolePermissions,
+};
+use crate::entities::admin_session::AdminSession;
+use crate::entities::admin_session_list::{AdminSessionIndex, AdminSessions};
+use crate::entities::admin_totp::{FindAdminTotp, RemoveAdminTotp};
+use crate::rbac;
+use crate::utils::config_provider::{find_config_from_db, insert_config_into_db};
+use crate::utils::password::hash_password;
+use crate::utils::rbac::Permission;
+use compact_str::CompactString;
+use framework::redis::{KeyValue, RedisConnection};
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use rand::Rng;
+use tracing::instrument;
+use uuid::Uuid;
+
+const GENERATED_PASSWORD_LENGTH: usize = 20;
+
+/// A random password handed to the admin out of band, who is expected to change it.
+fn generate_password() -> String {
+    rand::rng()
+        .sample_iter(rand::distr::Alphanumeric)
+        .take(GENERATED_PASSWORD_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+fn hash_generated_password(password: &str) -> Result<CompactString, framework::Error> {
+    hash_password(password)
+        .map(CompactString::from)
+        .map_err(|e| framework::Error::BusinessPanic(anyhow::anyhow!("Hash password failed: {e}")))
+}
+
+#[derive(Clone)]
+pub struct AdminManageService {
+    pub db: DatabaseProcessor,
+    pub redis: RedisConnection,
+}
+
+impl AdminManageService {
+    /// Log the admin out everywhere.
+    #[instrument(skip(self), err)]
+    async fn terminate_admin_sessions(&self, admin_id: Uuid) -> Result<(), framework::Error> {
+        let mut redis = self.redis.clone();
+        let index = AdminSessionIndex(admin_id);
+        for session_id in AdminSessions::list_sessions(&mut redis, index).await? {
+            AdminSession::delete(&mut redis, session_id).await?;
+        }
+        AdminSessions::clear(&mut redis, index).await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InviteAdmin {
+    pub email: String,
+    pub name: CompactString,
+    pub role: AdminRole,
+}
+
+#[derive(Debug, Clone)]
+pub enum InviteAdminResult {
+    Success {
+        admin: AdminAccount,
+        /// Shown once to the inviting owner.
+        initial_password: String,
+    },
+    EmailAlreadyUsed,
+}
+
+impl Processor<InviteAdmin> for AdminManageService {
+    type Output = InviteAdminResult;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: InviteAdmin) -> Result<InviteAdminResult, framework::Error> {
+        if self
+            .db
+            .process(FindAdminByEmail {
+                email: input.email.clone(),
+            })
+            .await?
+            .is_some()
+        {
+            return Ok(InviteAdminResult::EmailAlreadyUsed);
+        }
+        let initial_password = generate_password();
+        let admin = self
+            .db
+            .process(CreateAdminAccount {
+                role: input.role,
+                name: input.name,
+                password_hash: hash_generated_password(&initial_password)?,
+                email: input.email,
+                avatar: None,
+            })
+            .await?;
+        Ok(InviteAdminResult::Success {
+            admin,
+            initial_password,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListAdmins {
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Processor<ListAdmins> for AdminManageService {
+    type Output = Vec<AdminAccount>;
+    type Error = framework::Error;
+    async fn process(&self, input: ListAdmins) -> Result<Vec<AdminAccount>, framework::Error> {
+        self.db
+            .process(ListAdminAccounts {
+                limit: input.limit,
+                offset: input.offset,
+            })
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChangeRole {
+    pub admin_id: Uuid,
+    pub role: AdminRole,
+}
+
+impl Processor<ChangeRole> for AdminManageService {
+    type Output = OwnerGuardedChange<AdminAccount>;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: ChangeRole,
+    ) -> Result<OwnerGuardedChange<AdminAccount>, framework::Error> {
+        self.db
+            .process(ChangeAdminRole {
+                id: input.admin_id,
+                role: input.role,
+            })
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Disabling an admin also terminates all of their sessions.
+pub struct DisableOrEnableAdmin {
+    pub admin_id: Uuid,
+    pub disabled: bool,
+}
+
+impl Processor<DisableOrEnableAdmin> for AdminManageService {
+    type Output = OwnerGuardedChange<AdminAccount>;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: DisableOrEnableAdmin,
+    ) -> Result<OwnerGuardedChange<AdminAccount>, framework::Error> {
+        let result = self
+            .db
+            .process(SetAdminDisabled {
+                id: input.admin_id,
+                disabled: input.disabled,
+            })
+            .await?;
+        if input.disabled && matches!(result, OwnerGuardedChange::Changed(_)) {
+            self.terminate_admin_sessions(input.admin_id).await?;
+        }
+        Ok(result)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DeleteAdmin {
+    pub admin_id: Uuid,
+}
+
+impl Processor<DeleteAdmin> for AdminManageService {
+    type Output = OwnerGuardedChange<()>;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: DeleteAdmin,
+    ) -> Result<OwnerGuardedChange<()>, framework::Error> {
+        let result = self
+            .db
+            .process(DeleteAdminAccount { id: input.admin_id })
+            .await?;
+        if matches!(result, OwnerGuardedChange::Changed(())) {
+            self.terminate_admin_sessions(input.admin_id).await?;
+        }
+        Ok(result)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Replace the password of an admin with a generated one and log them out everywhere.
+pub struct ResetAdminPassword {
+    pub admin_id: Uuid,
+}
+
+impl Processor<ResetAdminPassword> for AdminManageService {
+    /// The new password
+    type Output = String;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: ResetAdminPassword) -> Result<String, framework::Error> {
+        let password = generate_password();
+        let updated = self
+            .db
+            .process(UpdateAdminPassword {
+                id: input.admin_id,
+                password_hash: hash_generated_password(&password)?,
+            })
+            .await?;
+        if !updated {
+            return Err(framework::Error::NotFound);
+        }
+        self.terminate_admin_sessions(input.admin_id).await?;
+        Ok(password)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Require TOTP for every admin at login.
+pub struct SetMfaEnforcement {
+    /// The owner making the change
+    pub admin_id: Uuid,
+    pub enforced: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetMfaEnforcementResult {
+    Success,
+    /// The owner must configure TOTP before enforcing it on everyone.
+    EnrollFirst,
+}
+
+impl Processor<SetMfaEnforcement> for AdminManageService {
+    type Output = SetMfaEnforcementResult;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: SetMfaEnforcement,
+    ) -> Result<SetMfaEnforcementResult, framework::Error> {
+        if input.enforced
+            && self
+                .db
+                .process(FindAdminTotp {
+                    admin_id: input.admin_id,
+                })
+                .await?
+                .is_none()
+        {
+            return Ok(SetMfaEnforcementResult::EnrollFirst);
+        }
+        let mut config = find_config_from_db::<AdminSecurityConfig>(self.db.db()).await?;
+        config.enforce_mfa = input.enforced;
+        insert_config_into_db(
+            self.db.db(),
+            &mut self.redis.clone(),
+            &config,
+            Some(input.admin_id),
+        )
+        .await?;
+        Ok(SetMfaEnforcementResult::Success)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Remove the TOTP of an admin who lost their device. If MFA is enforced they enroll again at
+/// their next login.
+pub struct ResetAdminMfa {
+    pub admin_id: Uuid,
+}
+
+impl Processor<ResetAdminMfa> for AdminManageService {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: ResetAdminMfa) -> Result<(), framework::Error> {
+        let removed = self
+            .db
+            .process(RemoveAdminTotp {
+                admin_id: input.admin_id,
+            })
+            .await?;
+        if !removed {
+            return Err(framework::Error::NotFound);
+        }
+        self.terminate_admin_sessions(input.admin_id).await
+    }
+}
+
+/// Sort and deduplicate the permissions of a custom role.
+fn normalize_permissions(mut permissions: Vec<Permission>) -> Vec<Permission> {
+    permissions.sort_by_key(|permission| permission.as_str());
+    permissions.dedup();
+    permissions
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateRole {
+    pub name: CompactString,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Clone)]
+pub enum CreateRoleResult {
+    Success(CustomRole),
+    NameAlreadyUsed,
+}
+
+impl Processor<CreateRole> for AdminManageService {
+    type Output = CreateRoleResult;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: CreateRole) -> Result<CreateRoleResult, framework::Error> {
+        let role = self
+            .db
+            .process(CreateCustomRole {
+                name: input.name,
+                permissions: normalize_permissions(input.permissions),
+            })
+            .await?;
+        Ok(role.map_or(CreateRoleResult::NameAlreadyUsed, CreateRoleResult::Success))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListRoles;
+
+impl Processor<ListRoles> for AdminManageService {
+    type Output = Vec<CustomRole>;
+    type Error = framework::Error;
+    async fn process(&self, _: ListRoles) -> Result<Vec<CustomRole>, framework::Error> {
+        self.db.process(ListCustomRoles).await.map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Replace the permissions of a custom role. Takes effect on the next operation of its admins.
+pub struct UpdateRolePermissions {
+    pub role_id: Uuid,
+    pub permissions: Vec<Permission>,
+}
+
+impl Processor<UpdateRolePermissions> for AdminManageService {
+    type Output = CustomRole;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: UpdateRolePermissions) -> Result<CustomRole, framework::Error> {
+        self.db
+            .process(UpdateCustomRolePermissions {
+                id: input.role_id,
+                permissions: normalize_permissions(input.permissions),
+            })
+            .await?
+            .ok_or(framework::Error::NotFound)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DeleteRole {
+    pub role_id: Uuid,
+}
+
+impl Processor<DeleteRole> for AdminManageService {
+    type Output = DeleteCustomRoleResult;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: DeleteRole) -> Result<DeleteCustomRoleResult, framework::Error> {
+        self.db
+            .process(DeleteCustomRole { id: input.role_id })
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Give a moderator a custom role, or take it away with `role_id: None`.
+pub struct AssignRole {
+    pub admin_id: Uuid,
+    pub role_id: Option<Uuid>,
+}
+
+impl Processor<AssignRole> for AdminManageService {
+    type Output = AdminAccount;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: AssignRole) -> Result<AdminAccount, framework::Error> {
+        if let Some(role_id) = input.role_id
+            && self
+                .db
+                .process(FindCustomRole { id: role_id })
+                .await?
+                .is_none()
+        {
+            return Err(framework::Error::NotFound);
+        }
+        self.db
+            .process(SetAdminCustomRole {
+                admin_id: input.admin_id,
+                role_id: input.role_id,
+            })
+            .await?
+            .ok_or(framework::Error::NotFound)
+    }
+}
+
+rbac! { AdminManageService : InviteAdmin => InviteAdminResult | [AdminRole::Owner] }
+rbac! { AdminManageService : ListAdmins => Vec<AdminAccount> | [AdminRole::Owner] }
+rbac! { AdminManageService : ChangeRole => OwnerGuardedChange<AdminAccount> | [AdminRole::Owner] }
+rbac! { AdminManageService : DisableOrEnableAdmin => OwnerGuardedChange<AdminAccount> | [AdminRole::Owner] }
+rbac! { AdminManageService : DeleteAdmin => OwnerGuardedChange<()> | [AdminRole::Owner] }
+rbac! { AdminManageService : ResetAdminPassword => String | [AdminRole::Owner] }
+rbac! { AdminManageService : SetMfaEnforcement => SetMfaEnforcementResult | [AdminRole::Owner] }
+rbac! { AdminManageService : ResetAdminMfa => () | [AdminRole::Owner] }
+rbac! { AdminManageService : CreateRole => CreateRoleResult | [AdminRole::Owner] }
+rbac! { AdminManageService : ListRoles => Vec<CustomRole> | [AdminRole::Owner] }
+rbac! { AdminManageService : UpdateRolePermissions => CustomRole | [AdminRole::Owner] }
+rbac! { AdminManageService : DeleteRole => DeleteCustomRoleResult | [AdminRole::Owner] }
+rbac! { AdminManageService : AssignRole => AdminAccount | [AdminRole::Owner] }
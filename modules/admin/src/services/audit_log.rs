@@ -0,0 +1,44 @@
+use crate::entities::admin_account::AdminRole;
+use crate::entities::admin_audit_log::{AuditLogEntry, AuditResult, ListAuditLog};
+use crate::rbac;
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use time::PrimitiveDateTime;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct AuditLogService {
+    pub db: DatabaseProcessor,
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryAuditLog {
+    pub admin_id: Option<Uuid>,
+    pub operation: Option<String>,
+    pub result: Option<AuditResult>,
+    pub since: Option<PrimitiveDateTime>,
+    pub until: Option<PrimitiveDateTime>,
+    pub before: Option<(PrimitiveDateTime, Uuid)>,
+    pub limit: i64,
+}
+
+impl Processor<QueryAuditLog> for AuditLogService {
+    type Output = Vec<AuditLogEntry>;
+    type Error = framework::Error;
+    async fn process(&self, input: QueryAuditLog) -> Result<Vec<AuditLogEntry>, framework::Error> {
+        self.db
+            .process(ListAuditLog {
+                admin_id: input.admin_id,
+                operation: input.operation,
+                result: input.result,
+                since: input.since,
+                until: input.until,
+                before: input.before,
+                limit: input.limit,
+            })
+            .await
+            .map_err(Into::into)
+    }
+}
+
+rbac! { AuditLogService : QueryAuditLog => Vec<AuditLogEntry> | [AdminRole::Owner] }
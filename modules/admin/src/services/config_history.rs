@@ -0,0 +1,224 @@
+use crate::entities::admin_account::AdminRole;
+use crate::entities::config_history::{
+    ConfigVersion, FindConfigVersion, ListConfigVersions, ListCurrentConfigContents,
+    ListHistoryConfigContents, UpdateCurrentConfigContent, UpdateHistoryConfigContent,
+};
+use crate::rbac;
+use crate::utils::config_provider::{ConfigRegistry, publish_config, save_config_content};
+use crate::utils::config_secret::{
+    ConfigKeyring, REDACTED, SECRET_MARKER, open_config_content, redact_secrets, rewrap_secrets,
+};
+use crate::utils::json_diff::{JsonChange, diff_json};
+use framework::redis::RedisConnection;
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use std::sync::Arc;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct ConfigHistoryService {
+    pub db: DatabaseProcessor,
+    pub redis: RedisConnection,
+    pub registry: Arc<ConfigRegistry>,
+}
+
+impl ConfigHistoryService {
+    async fn find_version(
+        &self,
+        key: &str,
+        version: i32,
+    ) -> Result<ConfigVersion, framework::Error> {
+        self.db
+            .process(FindConfigVersion {
+                key: key.to_owned(),
+                version,
+            })
+            .await?
+            .ok_or(framework::Error::NotFound)
+    }
+}
+
+fn redact_version(mut version: ConfigVersion) -> ConfigVersion {
+    redact_secrets(&mut version.content);
+    version
+}
+
+/// Hide the values of a change to or inside a secret.
+fn redact_change(mut change: JsonChange) -> JsonChange {
+    let marker = format!("/{SECRET_MARKER}");
+    if let Some(at) = change.path.find(&marker) {
+        change.path.truncate(at);
+        let redacted = || serde_json::Value::String(REDACTED.to_owned());
+        change.old = change.old.map(|_| redacted());
+        change.new = change.new.map(|_| redacted());
+    }
+    change.old.iter_mut().for_each(redact_secrets);
+    change.new.iter_mut().for_each(redact_secrets);
+    change
+}
+
+#[derive(Debug, Clone)]
+pub struct ListConfigHistory {
+    pub key: String,
+    pub before_version: Option<i32>,
+    pub limit: i64,
+}
+
+impl Processor<ListConfigHistory> for ConfigHistoryService {
+    type Output = Vec<ConfigVersion>;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: ListConfigHistory,
+    ) -> Result<Vec<ConfigVersion>, framework::Error> {
+        self.db
+            .process(ListConfigVersions {
+                key: input.key,
+                before_version: input.before_version,
+                limit: input.limit,
+            })
+            .await
+            .map(|versions| versions.into_iter().map(redact_version).collect())
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShowConfigVersion {
+    pub key: String,
+    pub version: i32,
+}
+
+impl Processor<ShowConfigVersion> for ConfigHistoryService {
+    type Output = ConfigVersion;
+    type Error = framework::Error;
+    async fn process(&self, input: ShowConfigVersion) -> Result<ConfigVersion, framework::Error> {
+        self.find_version(&input.key, input.version)
+            .await
+            .map(redact_version)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffConfigVersions {
+    pub key: String,
+    pub from_version: i32,
+    pub to_version: i32,
+}
+
+impl Processor<DiffConfigVersions> for ConfigHistoryService {
+    type Output = Vec<JsonChange>;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: DiffConfigVersions,
+    ) -> Result<Vec<JsonChange>, framework::Error> {
+        let (mut from, mut to) = tokio::try_join!(
+            self.find_version(&input.key, input.from_version),
+            self.find_version(&input.key, input.to_version)
+        )?;
+        // sealing is randomized, so compare the plaintexts to tell whether a secret changed
+        open_config_content(&mut from.content)?;
+        open_config_content(&mut to.content)?;
+        Ok(diff_json(&from.content, &to.content)
+            .into_iter()
+            .map(redact_change)
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Restore an earlier version of a config. The restored content is saved as a new version, so
+/// the rollback itself can be rolled back. Content that no longer matches the schema of a
+/// registered config type is rejected.
+pub struct RollbackConfig {
+    /// The owner making the change
+    pub admin_id: Uuid,
+    pub key: String,
+    pub version: i32,
+}
+
+impl Processor<RollbackConfig> for ConfigHistoryService {
+    /// The new version
+    type Output = i32;
+    type Error = framework::Error;
+    #[instrument(skip_all, err, fields(key = %input.key, version = input.version))]
+    async fn process(&self, input: RollbackConfig) -> Result<i32, framework::Error> {
+        let target = self.find_version(&input.key, input.version).await?;
+        let data = serde_json::to_vec(&target.content)
+            .map_err(|e| framework::Error::SerializeError(e.into()))?;
+        if let Some(Err(e)) = self.registry.validate(&input.key, &data) {
+            tracing::warn!(error = %e, "Refused to restore an invalid config");
+            return Err(framework::Error::InvalidInput);
+        }
+        let version = save_config_content(
+            self.db.db(),
+            &input.key,
+            &target.content,
+            Some(input.admin_id),
+        )
+        .await?;
+        publish_config(&mut self.redis.clone(), &input.key, &data).await?;
+        Ok(version)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Re-encrypt the data keys of secrets sealed with a key other than the current one, in every
+/// config and config version. Run after adding a new key at the front of the keyring, and only
+/// then remove the old key.
+///
+/// Rows are updated one by one. If the rotation fails halfway, running it again picks up the
+/// remaining rows.
+pub struct RotateConfigSecrets;
+
+impl Processor<RotateConfigSecrets> for ConfigHistoryService {
+    /// Number of rewrapped documents
+    type Output = u32;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, _: RotateConfigSecrets) -> Result<u32, framework::Error> {
+        let keyring = ConfigKeyring::global()?;
+        let mut rewrapped = 0;
+        for mut config in self.db.process(ListCurrentConfigContents).await? {
+            if !rewrap_secrets(&mut config.content, keyring)? {
+                continue;
+            }
+            self.db
+                .process(UpdateCurrentConfigContent {
+                    id: config.id,
+                    content: config.content.clone(),
+                })
+                .await?;
+            let data = serde_json::to_vec(&config.content)
+                .map_err(|e| framework::Error::SerializeError(e.into()))?;
+            publish_config(&mut self.redis.clone(), &config.key, &data).await?;
+            rewrapped += 1;
+        }
+        for mut version in self.db.process(ListHistoryConfigContents).await? {
+            if !rewrap_secrets(&mut version.content, keyring)? {
+                continue;
+            }
+            self.db
+                .process(UpdateHistoryConfigContent {
+                    id: version.id,
+                    content: version.content,
+                })
+                .await?;
+            rewrapped += 1;
+        }
+        tracing::info!(
+            rewrapped,
+            key_id = keyring.current_key_id(),
+            "Rotated config secrets"
+        );
+        Ok(rewrapped)
+    }
+}
+
+rbac! { ConfigHistoryService : ListConfigHistory => Vec<ConfigVersion> | [AdminRole::Owner] }
+rbac! { ConfigHistoryService : ShowConfigVersion => ConfigVersion | [AdminRole::Owner] }
+rbac! { ConfigHistoryService : DiffConfigVersions => Vec<JsonChange> | [AdminRole::Owner] }
+rbac! { ConfigHistoryService : RollbackConfig => i32 | [AdminRole::Owner] }
+rbac! { ConfigHistoryService : RotateConfigSecrets => u32 | [AdminRole::Owner] }
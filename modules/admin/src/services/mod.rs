@@ -1 +1,4 @@
 pub mod admin_auth;
+pub mod admin_manage;
+pub mod audit_log;
+pub mod config_history;
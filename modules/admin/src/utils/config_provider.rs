@@ -1,7 +1,19 @@
+use crate::utils::config_secret::{open_config_content, seal_config_content};
+use arc_swap::ArcSwap;
 use framework::redis::RedisConnection;
 use redis::AsyncCommands;
 use sqlx::PgPool;
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use tokio_stream::StreamExt;
 use tracing::instrument;
+use uuid::Uuid;
+
+/// Redis channel on which the key of a config is published whenever it changes.
+pub const CONFIG_CHANGE_CHANNEL: &str = "config:changed";
+
+/// Delay before resubscribing after the pub/sub connection is lost.
+const RESUBSCRIBE_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
 
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct ApplicationConfig {
@@ -10,10 +22,52 @@ pub struct ApplicationConfig {
     #[allow(unused)]
     pub key: String,
     pub content: serde_json::Value,
+    /// Incremented on every change, see `application__config_history`.
+    #[allow(unused)]
+    pub version: i32,
 }
 
 pub trait ConfigJson: Default + for<'de> serde::Deserialize<'de> + serde::Serialize {
     const KEY: &'static str;
+
+    /// Reject values that deserialize fine but would break the application, such as a zero TTL.
+    fn validate(&self) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+/// A stored config that does not match the schema of its type.
+pub struct InvalidConfig {
+    pub key: &'static str,
+    pub reason: String,
+}
+
+impl std::fmt::Display for InvalidConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid config `{}`: {}", self.key, self.reason)
+    }
+}
+
+impl std::error::Error for InvalidConfig {}
+
+/// Deserialize and validate a stored config.
+///
+/// Only a missing config falls back to `Default`. A malformed one is an error, so that a bad
+/// push cannot silently reset every setting. Sealed secrets are decrypted on the way.
+pub fn parse_config<T: ConfigJson>(content: &[u8]) -> Result<T, InvalidConfig> {
+    let invalid = |reason: String| InvalidConfig {
+        key: T::KEY,
+        reason,
+    };
+    let mut value: serde_json::Value =
+        serde_json::from_slice(content).map_err(|e| invalid(e.to_string()))?;
+    open_config_content(&mut value).map_err(|e| invalid(e.to_string()))?;
+    let config: T = serde_json::from_value(value).map_err(|e| invalid(e.to_string()))?;
+    config
+        .validate()
+        .map_err(|reason| invalid(reason.to_owned()))?;
+    Ok(config)
 }
 
 #[instrument(
@@ -25,19 +79,18 @@ pub trait ConfigJson: Default + for<'de> serde::Deserialize<'de> + serde::Serial
     )
 )]
 pub async fn find_config_from_db<T: ConfigJson>(db: impl sqlx::PgExecutor<'_>) -> sqlx::Result<T> {
-    let result: T = sqlx::query_as!(
+    let Some(result) = sqlx::query_as!(
         ApplicationConfig,
         "SELECT * FROM \"application__config\" WHERE key = $1",
         T::KEY
     )
     .fetch_optional(db)
     .await?
-    .map(|result| serde_json::from_value(result.content))
-    .transpose()
-    .ok()
-    .flatten()
-    .unwrap_or_default();
-    Ok(result)
+    else {
+        return Ok(T::default());
+    };
+    let content = serde_json::to_vec(&result.content).map_err(|e| sqlx::Error::Decode(e.into()))?;
+    parse_config(&content).map_err(|e| sqlx::Error::Decode(e.into()))
 }
 
 #[instrument(
@@ -53,10 +106,30 @@ pub async fn find_config_from_redis<T: ConfigJson>(
 ) -> redis::RedisResult<T> {
     let key = format!("config:{}", T::KEY);
     let data: Option<Vec<u8>> = redis.get(key).await?;
-    let cfg = data
-        .and_then(|d| serde_json::from_slice(&d).ok())
-        .unwrap_or_default();
-    Ok(cfg)
+    let Some(data) = data else {
+        return Ok(T::default());
+    };
+    parse_config(&data)
+        .map_err(|e| (redis::ErrorKind::Parse, "Invalid config", e.to_string()).into())
+}
+
+/// Serialize a config for storage, with its secrets sealed.
+fn serialize_config<T: ConfigJson>(config: &T) -> Result<serde_json::Value, framework::Error> {
+    let mut content =
+        serde_json::to_value(config).map_err(|e| framework::Error::SerializeError(e.into()))?;
+    seal_config_content(&mut content)?;
+    Ok(content)
+}
+
+/// Write a serialized config into the Redis cache and notify the subscribers.
+pub(crate) async fn publish_config(
+    redis: &mut RedisConnection,
+    key: &str,
+    content: &[u8],
+) -> Result<(), framework::Error> {
+    let _: () = redis.set(format!("config:{key}"), content).await?;
+    let _: () = redis.publish(CONFIG_CHANGE_CHANNEL, key).await?;
+    Ok(())
 }
 
 #[instrument(
@@ -71,50 +144,391 @@ pub async fn refresh_config_cache<T: ConfigJson>(
     mut redis: RedisConnection,
 ) -> Result<(), framework::Error> {
     let cfg = find_config_from_db::<T>(&db).await?;
-    let key = format!("config:{}", T::KEY);
-    let data = serde_json::to_vec(&cfg).map_err(|e| framework::Error::SerializeError(e.into()))?;
-    let _: () = redis.set(key, data).await?;
-    Ok(())
+    let data = serde_json::to_vec(&serialize_config(&cfg)?)
+        .map_err(|e| framework::Error::SerializeError(e.into()))?;
+    publish_config(&mut redis, T::KEY, &data).await
+}
+
+#[instrument(skip(db, content), err)]
+/// Replace the content of a config and record it as a new version.
+///
+/// Returns the new version.
+pub(crate) async fn save_config_content(
+    db: impl sqlx::PgExecutor<'_>,
+    key: &str,
+    content: &serde_json::Value,
+    author_id: Option<Uuid>,
+) -> sqlx::Result<i32> {
+    sqlx::query_scalar!(
+        r#"
+        WITH saved AS (
+            INSERT INTO application__config (key, content) VALUES ($1, $2)
+            ON CONFLICT (key) DO UPDATE
+            SET content = EXCLUDED.content, version = application__config.version + 1
+            RETURNING key, version, content
+        )
+        INSERT INTO application__config_history (key, version, content, author_id)
+        SELECT key, version, content, $3 FROM saved
+        RETURNING version
+        "#,
+        key,
+        content,
+        author_id
+    )
+    .fetch_one(db)
+    .await
 }
 
 #[instrument(
-    skip(db),
+    skip(db, redis),
     err,
     fields(
         config_key = T::KEY
     )
 )]
+/// Save a config as a new version, refresh its cache and notify the subscribers.
+///
+/// `author_id` is the admin making the change, `None` for changes made by the system.
+/// Returns the new version.
 pub async fn insert_config_into_db<T: ConfigJson + std::fmt::Debug>(
     db: impl sqlx::PgExecutor<'_>,
+    redis: &mut RedisConnection,
     config: &T,
-) -> Result<(), framework::Error> {
-    let content =
-        serde_json::to_value(config).map_err(|e| framework::Error::SerializeError(e.into()))?;
-    sqlx::query!(
-        r#"INSERT INTO application__config (key, content) VALUES ($1, $2)
-         ON CONFLICT (key) DO UPDATE SET content = EXCLUDED.content"#,
-        T::KEY,
-        content
-    )
-    .execute(db)
-    .await?;
-    Ok(())
+    author_id: Option<Uuid>,
+) -> Result<i32, framework::Error> {
+    if let Err(reason) = config.validate() {
+        tracing::warn!(config_key = T::KEY, reason, "Rejected invalid config");
+        return Err(framework::Error::InvalidInput);
+    }
+    let content = serialize_config(config)?;
+    let version = save_config_content(db, T::KEY, &content, author_id).await?;
+    let data =
+        serde_json::to_vec(&content).map_err(|e| framework::Error::SerializeError(e.into()))?;
+    publish_config(redis, T::KEY, &data).await?;
+    Ok(version)
+}
+
+/// A config value that follows the changes published on [`CONFIG_CHANGE_CHANNEL`].
+///
+/// Cloning is cheap and all clones share the same value. The background subscription stops once
+/// every clone is dropped.
+pub struct LiveConfig<T> {
+    inner: Arc<ArcSwap<T>>,
+}
+
+impl<T> Clone for LiveConfig<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for LiveConfig<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LiveConfig")
+            .field("config", &std::any::type_name::<T>())
+            .finish()
+    }
+}
+
+impl<T> LiveConfig<T> {
+    /// A value that never changes, for tests and tools.
+    pub fn fixed(config: T) -> Self {
+        Self {
+            inner: Arc::new(ArcSwap::from_pointee(config)),
+        }
+    }
+
+    /// The current value.
+    pub fn load(&self) -> Arc<T> {
+        self.inner.load_full()
+    }
+}
+
+impl<T: ConfigJson + Send + Sync + 'static> LiveConfig<T> {
+    /// Load the config from the Redis cache and keep it up to date in the background.
+    ///
+    /// The pub/sub subscription needs its own connection, hence the client.
+    pub async fn subscribe(
+        client: redis::Client,
+        mut redis: RedisConnection,
+    ) -> Result<Self, framework::Error> {
+        let live = Self::fixed(find_config_from_redis::<T>(&mut redis).await?);
+        let mut pubsub = client.get_async_pubsub().await?;
+        pubsub.subscribe(CONFIG_CHANGE_CHANNEL).await?;
+        tokio::spawn(watch_config_changes(
+            client,
+            redis,
+            pubsub,
+            Arc::downgrade(&live.inner),
+        ));
+        Ok(live)
+    }
+}
+
+#[instrument(skip_all, fields(config_key = T::KEY))]
+async fn reload_config<T: ConfigJson>(redis: &mut RedisConnection, target: &ArcSwap<T>) {
+    match find_config_from_redis::<T>(redis).await {
+        Ok(config) => target.store(Arc::new(config)),
+        // keep serving the last good value
+        Err(e) => tracing::error!(error = %e, "Failed to reload config"),
+    }
+}
+
+async fn watch_config_changes<T: ConfigJson>(
+    client: redis::Client,
+    mut redis: RedisConnection,
+    mut pubsub: redis::aio::PubSub,
+    target: Weak<ArcSwap<T>>,
+) {
+    loop {
+        let mut messages = pubsub.on_message();
+        while let Some(message) = messages.next().await {
+            let Some(target) = target.upgrade() else {
+                return;
+            };
+            if message.get_payload_bytes() == T::KEY.as_bytes() {
+                reload_config(&mut redis, &target).await;
+            }
+        }
+        drop(messages);
+        tracing::warn!(
+            config_key = T::KEY,
+            "Config subscription lost, resubscribing"
+        );
+        loop {
+            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            if target.strong_count() == 0 {
+                return;
+            }
+            let resubscribed = async {
+                let mut pubsub = client.get_async_pubsub().await?;
+                pubsub.subscribe(CONFIG_CHANGE_CHANNEL).await?;
+                redis::RedisResult::Ok(pubsub)
+            }
+            .await;
+            match resubscribed {
+                Ok(new_pubsub) => {
+                    pubsub = new_pubsub;
+                    break;
+                }
+                Err(e) => tracing::error!(error = %e, "Failed to resubscribe to config changes"),
+            }
+        }
+        // changes published while disconnected were missed
+        let Some(target) = target.upgrade() else {
+            return;
+        };
+        reload_config(&mut redis, &target).await;
+    }
+}
+
+type RefreshFuture = Pin<Box<dyn Future<Output = Result<(), framework::Error>> + Send>>;
+
+#[derive(Clone, Copy)]
+struct RegisteredConfig {
+    key: &'static str,
+    validate: fn(&[u8]) -> Result<(), InvalidConfig>,
+    refresh: fn(PgPool, RedisConnection) -> RefreshFuture,
+}
+
+#[derive(Clone, Default)]
+/// The config types known to the application, so that they can be handled by key.
+pub struct ConfigRegistry {
+    configs: Vec<RegisteredConfig>,
+}
+
+impl std::fmt::Debug for ConfigRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.keys()).finish()
+    }
+}
+
+impl ConfigRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registering the same type twice has no effect.
+    pub fn register<T: ConfigJson + Send + 'static>(&mut self) -> &mut Self {
+        if self.configs.iter().all(|config| config.key != T::KEY) {
+            self.configs.push(RegisteredConfig {
+                key: T::KEY,
+                validate: |content| parse_config::<T>(content).map(|_| ()),
+                refresh: |db, redis| Box::pin(refresh_config_cache::<T>(db, redis)),
+            });
+        }
+        self
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.configs.iter().map(|config| config.key)
+    }
+
+    /// Check a serialized config against the schema of its type.
+    ///
+    /// `None` if no type is registered under the key.
+    pub fn validate(&self, key: &str, content: &[u8]) -> Option<Result<(), InvalidConfig>> {
+        self.configs
+            .iter()
+            .find(|config| config.key == key)
+            .map(|config| (config.validate)(content))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ConfigRefreshReport {
+    pub refreshed: Vec<&'static str>,
+    pub failed: Vec<(&'static str, framework::Error)>,
+}
+
+impl ConfigRefreshReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
 }
 
 #[derive(Debug, Clone)]
+/// Copies every registered config from Postgres into the Redis cache.
 pub struct ConfigCronExecutor {
     pub db: PgPool,
     pub redis: RedisConnection,
+    pub registry: Arc<ConfigRegistry>,
+}
+
+impl ConfigCronExecutor {
+    /// Refresh all configs concurrently. A failing config does not stop the others.
+    #[instrument(skip_all)]
+    pub async fn refresh_all(&self) -> ConfigRefreshReport {
+        let mut join_set = tokio::task::JoinSet::new();
+        for config in &self.registry.configs {
+            let refresh = (config.refresh)(self.db.clone(), self.redis.clone());
+            let key = config.key;
+            join_set.spawn(async move { (key, refresh.await) });
+        }
+        let mut report = ConfigRefreshReport::default();
+        while let Some(joined) = join_set.join_next().await {
+            match joined {
+                Ok((key, Ok(()))) => report.refreshed.push(key),
+                Ok((key, Err(e))) => {
+                    tracing::error!(config_key = key, error = %e, "Failed to refresh config");
+                    report.failed.push((key, e));
+                }
+                Err(e) => tracing::error!(error = %e, "Config refresh task panicked"),
+            }
+        }
+        report
+    }
+
+    /// Refresh all configs every `period` in the background.
+    ///
+    /// The returned handle triggers an extra refresh on demand. The job keeps running after the
+    /// handle is dropped.
+    pub fn spawn(self, period: std::time::Duration) -> ConfigRefreshHandle {
+        let (sender, mut requests) =
+            tokio::sync::mpsc::channel::<tokio::sync::oneshot::Sender<ConfigRefreshReport>>(8);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        self.refresh_all().await;
+                    }
+                    Some(reply) = requests.recv() => {
+                        // the caller may have given up waiting
+                        let _ = reply.send(self.refresh_all().await);
+                    }
+                }
+            }
+        });
+        ConfigRefreshHandle { sender }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigRefreshHandle {
+    sender: tokio::sync::mpsc::Sender<tokio::sync::oneshot::Sender<ConfigRefreshReport>>,
+}
+
+impl ConfigRefreshHandle {
+    /// Refresh all configs now and wait for the result.
+    ///
+    /// `None` if the job is no longer running.
+    pub async fn refresh_now(&self) -> Option<ConfigRefreshReport> {
+        let (reply, report) = tokio::sync::oneshot::channel();
+        self.sender.send(reply).await.ok()?;
+        report.await.ok()
+    }
 }
 
+/// Refresh the given config types once, concurrently.
+///
+/// ```ignore
+/// let report = refresh_configs!(executor, [AdminSecurityConfig, AuthConfig]);
+/// ```
 #[macro_export]
 macro_rules! refresh_configs {
-    ($executor:expr, [$($config:ty),+]) => {{
-        let ConfigCronExecutor { db, redis } = $executor;
-        let mut join_set = tokio::task::JoinSet::<Result<(), helium_framework::error::Error>>::new();
+    ($executor:expr, [$($config:ty),+ $(,)?]) => {{
+        let executor: &$crate::utils::config_provider::ConfigCronExecutor = &$executor;
+        let mut registry = $crate::utils::config_provider::ConfigRegistry::new();
         $(
-            join_set.spawn($crate::config_provider::refresh_config::<$config>(db.clone(), redis.clone()));
+            registry.register::<$config>();
         )+
-        join_set.join_all().await
+        $crate::utils::config_provider::ConfigCronExecutor {
+            db: executor.db.clone(),
+            redis: executor.redis.clone(),
+            registry: ::std::sync::Arc::new(registry),
+        }
+        .refresh_all()
+        .await
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AdminSecurityConfig;
+
+    #[test]
+    fn parse_config_fills_missing_fields() {
+        let config = parse_config::<AdminSecurityConfig>(b"{}");
+        assert!(matches!(config, Ok(config) if !config.enforce_mfa));
+    }
+
+    #[test]
+    fn parse_config_rejects_malformed_json() {
+        assert!(parse_config::<AdminSecurityConfig>(br#"{"enforce_mfa": "yes"}"#).is_err());
+        assert!(parse_config::<AdminSecurityConfig>(b"not json").is_err());
+    }
+
+    #[test]
+    fn parse_config_rejects_invalid_values() {
+        let config = AdminSecurityConfig {
+            mfa_token_ttl: time::Duration::ZERO,
+            ..Default::default()
+        };
+        let content = serde_json::to_vec(&config).unwrap_or_default();
+        assert!(!content.is_empty());
+        assert!(parse_config::<AdminSecurityConfig>(&content).is_err());
+    }
+
+    #[test]
+    fn registry_validates_by_key() {
+        let mut registry = ConfigRegistry::new();
+        registry
+            .register::<AdminSecurityConfig>()
+            .register::<AdminSecurityConfig>();
+        assert_eq!(registry.keys().count(), 1);
+        assert!(matches!(
+            registry.validate(AdminSecurityConfig::KEY, b"{}"),
+            Some(Ok(()))
+        ));
+        assert!(matches!(
+            registry.validate(AdminSecurityConfig::KEY, b"1"),
+            Some(Err(_))
+        ));
+        assert!(registry.validate("unknown", b"{}").is_none());
+    }
+}
@@ -0,0 +1,525 @@
+//! Encryption at rest for secret config values.
+//!
+//! Secret fields are typed [`SecretString`], which serializes as `{"$secret": "..."}`, or as a
+//! plain `""` while the secret is not set, so that configs without secrets need no keyring. Before a
+//! config is stored, every such marker is sealed into `{"$encrypted": "<envelope>"}`: the value is
+//! encrypted with a random data key, and the data key is encrypted ("wrapped") with a key from the
+//! [`ConfigKeyring`]. Rotating the keyring only has to rewrap the data keys.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use compact_str::CompactString;
+use serde_json::{Map, Value};
+use std::sync::OnceLock;
+
+/// Comma or newline separated `<key id>:<hex encoded 32 byte key>` entries. The first entry
+/// encrypts new values, the others are only used to decrypt values sealed before a rotation.
+pub const CONFIG_KEYS_ENV: &str = "PHANTOM_CONFIG_KEYS";
+/// Path to a file in the format of [`CONFIG_KEYS_ENV`], used if that variable is not set.
+pub const CONFIG_KEY_FILE_ENV: &str = "PHANTOM_CONFIG_KEY_FILE";
+
+pub(crate) const SECRET_MARKER: &str = "$secret";
+const ENCRYPTED_MARKER: &str = "$encrypted";
+const ENVELOPE_VERSION: &str = "v1";
+/// Shown in place of secrets in admin APIs.
+pub const REDACTED: &str = "[REDACTED]";
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Clone, Default, PartialEq, Eq)]
+/// A config value that is encrypted at rest and never printed.
+pub struct SecretString(CompactString);
+
+impl SecretString {
+    pub fn new(value: impl Into<CompactString>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl serde::Serialize for SecretString {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        if self.0.is_empty() {
+            return serializer.serialize_str("");
+        }
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(SECRET_MARKER, self.expose())?;
+        map.end()
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for SecretString {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Marked {
+                #[serde(rename = "$secret")]
+                secret: CompactString,
+            },
+            /// Stored before the field was marked as secret. Sealed on the next save.
+            Plain(CompactString),
+        }
+        match Stored::deserialize(deserializer)? {
+            Stored::Marked { secret } | Stored::Plain(secret) => Ok(Self(secret)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SecretError {
+    /// A secret has to be sealed or opened but no keyring is configured.
+    MissingKeyring,
+    /// The keyring could not be loaded.
+    InvalidKeyring(String),
+    /// The value was sealed with a key that is no longer in the keyring.
+    UnknownKey(CompactString),
+    MalformedEnvelope,
+    /// The envelope failed authentication.
+    Decryption,
+}
+
+impl std::fmt::Display for SecretError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingKeyring => write!(f, "no config encryption key is configured"),
+            Self::InvalidKeyring(reason) => write!(f, "invalid config encryption keys: {reason}"),
+            Self::UnknownKey(id) => write!(f, "unknown config encryption key `{id}`"),
+            Self::MalformedEnvelope => write!(f, "malformed encrypted config value"),
+            Self::Decryption => write!(f, "failed to decrypt config value"),
+        }
+    }
+}
+
+impl std::error::Error for SecretError {}
+
+impl From<SecretError> for framework::Error {
+    fn from(value: SecretError) -> Self {
+        framework::Error::BusinessPanic(value.into())
+    }
+}
+
+/// Key encryption keys for config secrets.
+pub struct ConfigKeyring {
+    /// The first key is the current one.
+    keys: Vec<(CompactString, Aes256Gcm)>,
+}
+
+impl std::fmt::Debug for ConfigKeyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigKeyring")
+            .field(
+                "key_ids",
+                &self.keys.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl ConfigKeyring {
+    /// Parse keys in the format of [`CONFIG_KEYS_ENV`].
+    pub fn parse(spec: &str) -> Result<Self, SecretError> {
+        let mut keys: Vec<(CompactString, Aes256Gcm)> = Vec::new();
+        for entry in spec
+            .split([',', '\n'])
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+        {
+            let (id, key) = entry
+                .split_once(':')
+                .ok_or_else(|| SecretError::InvalidKeyring("expected `<id>:<key>`".to_owned()))?;
+            if id.is_empty() || id.contains('.') {
+                return Err(SecretError::InvalidKeyring(format!(
+                    "invalid key id `{id}`"
+                )));
+            }
+            if keys.iter().any(|(existing, _)| existing == id) {
+                return Err(SecretError::InvalidKeyring(format!(
+                    "duplicated key id `{id}`"
+                )));
+            }
+            let key: [u8; 32] = hex::decode(key.trim())
+                .ok()
+                .and_then(|key| key.try_into().ok())
+                .ok_or_else(|| {
+                    SecretError::InvalidKeyring(format!("key `{id}` is not 32 hex encoded bytes"))
+                })?;
+            keys.push((id.into(), Aes256Gcm::new(&Key::<Aes256Gcm>::from(key))));
+        }
+        if keys.is_empty() {
+            return Err(SecretError::InvalidKeyring("no key".to_owned()));
+        }
+        Ok(Self { keys })
+    }
+
+    /// Load the keyring from [`CONFIG_KEYS_ENV`] or [`CONFIG_KEY_FILE_ENV`].
+    ///
+    /// `Ok(None)` if neither is set.
+    pub fn from_env() -> Result<Option<Self>, SecretError> {
+        if let Ok(spec) = std::env::var(CONFIG_KEYS_ENV) {
+            return Self::parse(&spec).map(Some);
+        }
+        let Ok(path) = std::env::var(CONFIG_KEY_FILE_ENV) else {
+            return Ok(None);
+        };
+        let spec = std::fs::read_to_string(&path)
+            .map_err(|e| SecretError::InvalidKeyring(format!("cannot read {path}: {e}")))?;
+        Self::parse(&spec).map(Some)
+    }
+
+    /// The keyring of the process, loaded from the environment on first use.
+    pub fn global() -> Result<&'static Self, SecretError> {
+        static GLOBAL: OnceLock<Result<ConfigKeyring, String>> = OnceLock::new();
+        let loaded = GLOBAL.get_or_init(|| match Self::from_env() {
+            Ok(Some(keyring)) => Ok(keyring),
+            Ok(None) => Err(String::new()),
+            Err(e) => Err(e.to_string()),
+        });
+        match loaded {
+            Ok(keyring) => Ok(keyring),
+            Err(reason) if reason.is_empty() => Err(SecretError::MissingKeyring),
+            Err(reason) => Err(SecretError::InvalidKeyring(reason.clone())),
+        }
+    }
+
+    pub fn current_key_id(&self) -> &str {
+        &self.keys[0].0
+    }
+
+    fn key(&self, id: &str) -> Result<&Aes256Gcm, SecretError> {
+        self.keys
+            .iter()
+            .find(|(key_id, _)| key_id == id)
+            .map(|(_, key)| key)
+            .ok_or_else(|| SecretError::UnknownKey(id.into()))
+    }
+
+    fn seal(&self, plaintext: &str) -> Result<String, SecretError> {
+        let (key_id, key) = &self.keys[0];
+        let data_key: [u8; 32] = rand::random();
+        let wrapped = encrypt(key, &data_key)?;
+        let data_cipher = Aes256Gcm::new(&Key::<Aes256Gcm>::from(data_key));
+        let ciphertext = encrypt(&data_cipher, plaintext.as_bytes())?;
+        Ok(format!(
+            "{ENVELOPE_VERSION}.{key_id}.{}.{}",
+            URL_SAFE_NO_PAD.encode(wrapped),
+            URL_SAFE_NO_PAD.encode(ciphertext)
+        ))
+    }
+
+    fn open(&self, envelope: &str) -> Result<String, SecretError> {
+        let envelope = Envelope::parse(envelope)?;
+        let data_key = decrypt(self.key(envelope.key_id)?, &envelope.wrapped_key)?;
+        let data_cipher =
+            Aes256Gcm::new_from_slice(&data_key).map_err(|_| SecretError::MalformedEnvelope)?;
+        let plaintext = decrypt(&data_cipher, &envelope.ciphertext)?;
+        String::from_utf8(plaintext).map_err(|_| SecretError::Decryption)
+    }
+
+    /// Rewrap the data key with the current key. `None` if it already uses the current key.
+    fn rewrap(&self, envelope: &str) -> Result<Option<String>, SecretError> {
+        let parsed = Envelope::parse(envelope)?;
+        let (current_id, current) = &self.keys[0];
+        if parsed.key_id == current_id.as_str() {
+            return Ok(None);
+        }
+        let data_key = decrypt(self.key(parsed.key_id)?, &parsed.wrapped_key)?;
+        let wrapped = encrypt(current, &data_key)?;
+        Ok(Some(format!(
+            "{ENVELOPE_VERSION}.{current_id}.{}.{}",
+            URL_SAFE_NO_PAD.encode(wrapped),
+            URL_SAFE_NO_PAD.encode(parsed.ciphertext)
+        )))
+    }
+}
+
+struct Envelope<'a> {
+    key_id: &'a str,
+    wrapped_key: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl<'a> Envelope<'a> {
+    fn parse(envelope: &'a str) -> Result<Self, SecretError> {
+        let mut parts = envelope.split('.');
+        let (Some(ENVELOPE_VERSION), Some(key_id), Some(wrapped_key), Some(ciphertext), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return Err(SecretError::MalformedEnvelope);
+        };
+        let decode = |part: &str| {
+            URL_SAFE_NO_PAD
+                .decode(part)
+                .map_err(|_| SecretError::MalformedEnvelope)
+        };
+        Ok(Self {
+            key_id,
+            wrapped_key: decode(wrapped_key)?,
+            ciphertext: decode(ciphertext)?,
+        })
+    }
+}
+
+/// `nonce || ciphertext`
+fn encrypt(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<Vec<u8>, SecretError> {
+    let nonce: [u8; NONCE_LENGTH] = rand::random();
+    let ciphertext = cipher
+        .encrypt(&Nonce::from(nonce), plaintext)
+        .map_err(|_| SecretError::Decryption)?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+fn decrypt(cipher: &Aes256Gcm, sealed: &[u8]) -> Result<Vec<u8>, SecretError> {
+    let (nonce, ciphertext) = sealed
+        .split_first_chunk::<NONCE_LENGTH>()
+        .ok_or(SecretError::MalformedEnvelope)?;
+    cipher
+        .decrypt(&Nonce::from(*nonce), ciphertext)
+        .map_err(|_| SecretError::Decryption)
+}
+
+/// The string under `marker` if the object is a secret marker.
+fn marked<'a>(object: &'a Map<String, Value>, marker: &str) -> Option<&'a str> {
+    match object.get(marker) {
+        Some(Value::String(value)) if object.len() == 1 => Some(value),
+        _ => None,
+    }
+}
+
+/// Apply `f` to every secret marker object, depth first.
+fn visit_markers(
+    value: &mut Value,
+    f: &mut impl FnMut(&mut Map<String, Value>) -> Result<(), SecretError>,
+) -> Result<(), SecretError> {
+    match value {
+        Value::Object(object)
+            if marked(object, SECRET_MARKER).is_some()
+                || marked(object, ENCRYPTED_MARKER).is_some() =>
+        {
+            f(object)
+        }
+        Value::Object(object) => object.values_mut().try_for_each(|v| visit_markers(v, f)),
+        Value::Array(array) => array.iter_mut().try_for_each(|v| visit_markers(v, f)),
+        _ => Ok(()),
+    }
+}
+
+fn replace_marker(object: &mut Map<String, Value>, marker: &str, value: String) {
+    object.clear();
+    object.insert(marker.to_owned(), Value::String(value));
+}
+
+/// Whether the value contains secrets, sealed or not, so that a keyring is needed to handle it.
+/// Empty secrets are not sealed and do not count.
+pub fn contains_secrets(value: &Value) -> bool {
+    match value {
+        Value::Object(object) => {
+            marked(object, SECRET_MARKER).is_some_and(|secret| !secret.is_empty())
+                || marked(object, ENCRYPTED_MARKER).is_some()
+                || object.values().any(contains_secrets)
+        }
+        Value::Array(array) => array.iter().any(contains_secrets),
+        _ => false,
+    }
+}
+
+/// Seal the secrets of a config with the keyring of the process before storing it.
+pub fn seal_config_content(content: &mut Value) -> Result<(), SecretError> {
+    if contains_secrets(content) {
+        seal_secrets(content, ConfigKeyring::global()?)?;
+    }
+    Ok(())
+}
+
+/// Open the secrets of a stored config with the keyring of the process.
+pub fn open_config_content(content: &mut Value) -> Result<(), SecretError> {
+    if contains_secrets(content) {
+        open_secrets(content, ConfigKeyring::global()?)?;
+    }
+    Ok(())
+}
+
+/// Seal a secret stored outside of configs, such as in a table column, with the keyring of the
+/// process. Such secrets are not covered by config rotation, see [`rewrap_stored_secret`].
+pub fn seal_stored_secret(plaintext: &str) -> Result<String, SecretError> {
+    ConfigKeyring::global()?.seal(plaintext)
+}
+
+/// Open a secret sealed by [`seal_stored_secret`].
+pub fn open_stored_secret(sealed: &str) -> Result<String, SecretError> {
+    ConfigKeyring::global()?.open(sealed)
+}
+
+/// Rewrap a secret sealed by [`seal_stored_secret`] with the current key. `None` if it already
+/// uses the current key.
+pub fn rewrap_stored_secret(
+    sealed: &str,
+    keyring: &ConfigKeyring,
+) -> Result<Option<String>, SecretError> {
+    keyring.rewrap(sealed)
+}
+
+/// Encrypt every non-empty plaintext secret. Already sealed secrets are kept as is.
+pub fn seal_secrets(value: &mut Value, keyring: &ConfigKeyring) -> Result<(), SecretError> {
+    visit_markers(value, &mut |object| {
+        if let Some(plaintext) = marked(object, SECRET_MARKER)
+            && !plaintext.is_empty()
+        {
+            let sealed = keyring.seal(plaintext)?;
+            replace_marker(object, ENCRYPTED_MARKER, sealed);
+        }
+        Ok(())
+    })
+}
+
+/// Decrypt every sealed secret back to the form [`SecretString`] deserializes from.
+pub fn open_secrets(value: &mut Value, keyring: &ConfigKeyring) -> Result<(), SecretError> {
+    visit_markers(value, &mut |object| {
+        if let Some(envelope) = marked(object, ENCRYPTED_MARKER) {
+            let plaintext = keyring.open(envelope)?;
+            replace_marker(object, SECRET_MARKER, plaintext);
+        }
+        Ok(())
+    })
+}
+
+/// Replace every secret, sealed or not, with a placeholder. Used before showing configs to admins.
+pub fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(object)
+            if marked(object, SECRET_MARKER).is_some()
+                || marked(object, ENCRYPTED_MARKER).is_some() =>
+        {
+            *value = Value::String(REDACTED.to_owned());
+        }
+        Value::Object(object) => object.values_mut().for_each(redact_secrets),
+        Value::Array(array) => array.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+/// Rewrap the data keys of secrets sealed with an old key. Returns whether anything changed.
+pub fn rewrap_secrets(value: &mut Value, keyring: &ConfigKeyring) -> Result<bool, SecretError> {
+    let mut changed = false;
+    visit_markers(value, &mut |object| {
+        if let Some(envelope) = marked(object, ENCRYPTED_MARKER)
+            && let Some(rewrapped) = keyring.rewrap(envelope)?
+        {
+            replace_marker(object, ENCRYPTED_MARKER, rewrapped);
+            changed = true;
+        }
+        Ok(())
+    })?;
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const OLD_KEY: &str = "old:0000000000000000000000000000000000000000000000000000000000000001";
+    const NEW_KEY: &str = "new:0000000000000000000000000000000000000000000000000000000000000002";
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct WithSecret {
+        name: String,
+        token: SecretString,
+    }
+
+    fn sealed_config(keyring: &ConfigKeyring) -> Result<Value, SecretError> {
+        let config = WithSecret {
+            name: "github".to_owned(),
+            token: SecretString::new("hunter2"),
+        };
+        let mut value = serde_json::to_value(&config).unwrap_or_default();
+        seal_secrets(&mut value, keyring)?;
+        Ok(value)
+    }
+
+    #[test]
+    fn sealed_secrets_open_to_the_original_value() -> Result<(), SecretError> {
+        let keyring = ConfigKeyring::parse(OLD_KEY)?;
+        let mut value = sealed_config(&keyring)?;
+        assert_eq!(value["name"], "github");
+        assert!(value["token"]["$encrypted"].is_string());
+        assert!(!value.to_string().contains("hunter2"));
+
+        open_secrets(&mut value, &keyring)?;
+        let config: Option<WithSecret> = serde_json::from_value(value).ok();
+        assert_eq!(config.as_ref().map(|c| c.token.expose()), Some("hunter2"));
+        Ok(())
+    }
+
+    #[test]
+    fn rotation_rewraps_old_secrets() -> Result<(), SecretError> {
+        let old = ConfigKeyring::parse(OLD_KEY)?;
+        let rotated = ConfigKeyring::parse(&format!("{NEW_KEY},{OLD_KEY}"))?;
+        let new_only = ConfigKeyring::parse(NEW_KEY)?;
+        let mut value = sealed_config(&old)?;
+        assert!(open_secrets(&mut value.clone(), &new_only).is_err());
+
+        assert!(matches!(rewrap_secrets(&mut value, &rotated), Ok(true)));
+        assert!(matches!(rewrap_secrets(&mut value, &rotated), Ok(false)));
+        open_secrets(&mut value, &new_only)?;
+        assert_eq!(value["token"]["$secret"], "hunter2");
+        Ok(())
+    }
+
+    #[test]
+    fn redaction_hides_plain_and_sealed_secrets() -> Result<(), SecretError> {
+        let keyring = ConfigKeyring::parse(OLD_KEY)?;
+        let mut value = json!({
+            "plain": {"$secret": "a"},
+            "sealed": sealed_config(&keyring)?["token"],
+            "nested": [{"other": "b"}],
+        });
+        assert!(contains_secrets(&value));
+        redact_secrets(&mut value);
+        assert_eq!(
+            value,
+            json!({"plain": REDACTED, "sealed": REDACTED, "nested": [{"other": "b"}]})
+        );
+        assert!(!contains_secrets(&value));
+        Ok(())
+    }
+
+    #[test]
+    fn empty_secrets_need_no_keyring() -> Result<(), SecretError> {
+        let mut value = serde_json::to_value(WithSecret {
+            name: "x".to_owned(),
+            token: SecretString::default(),
+        })
+        .map_err(|_| SecretError::MalformedEnvelope)?;
+        assert_eq!(value, json!({"name": "x", "token": ""}));
+        // stored before empty secrets were left unmarked
+        let mut marked = json!({"name": "x", "token": {"$secret": ""}});
+        assert!(!contains_secrets(&value) && !contains_secrets(&marked));
+        seal_config_content(&mut value)?;
+        seal_config_content(&mut marked)?;
+        assert_eq!(marked, json!({"name": "x", "token": {"$secret": ""}}));
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_plain_strings_deserialize() {
+        let config: Option<WithSecret> =
+            serde_json::from_value(json!({"name": "x", "token": "plain"})).ok();
+        assert_eq!(config.as_ref().map(|c| c.token.expose()), Some("plain"));
+    }
+}
@@ -0,0 +1,93 @@
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A value that differs between two JSON documents.
+pub struct JsonChange {
+    /// JSON pointer (RFC 6901) to the value, empty for the root.
+    pub path: String,
+    /// `None` if the value was added
+    pub old: Option<Value>,
+    /// `None` if the value was removed
+    pub new: Option<Value>,
+}
+
+/// List the changes between two JSON documents, down to the leaves of objects.
+///
+/// Arrays are compared as a whole, since elements have no stable identity.
+pub fn diff_json(old: &Value, new: &Value) -> Vec<JsonChange> {
+    let mut changes = Vec::new();
+    diff_at(String::new(), Some(old), Some(new), &mut changes);
+    changes
+}
+
+fn diff_at(path: String, old: Option<&Value>, new: Option<&Value>, out: &mut Vec<JsonChange>) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                diff_at(format!("{path}/{escaped}"), old.get(key), new.get(key), out);
+            }
+        }
+        (old, new) if old != new => out.push(JsonChange {
+            path,
+            old: old.cloned(),
+            new: new.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn identical_documents_have_no_changes() {
+        let doc = json!({"a": 1, "b": {"c": [1, 2]}});
+        assert!(diff_json(&doc, &doc).is_empty());
+    }
+
+    #[test]
+    fn nested_changes_are_reported_by_path() {
+        let old = json!({"session": {"ttl": 10, "a/b": true}, "removed": 1});
+        let new = json!({"session": {"ttl": 20, "a/b": true}, "added": [1]});
+        assert_eq!(
+            diff_json(&old, &new),
+            vec![
+                JsonChange {
+                    path: "/added".to_owned(),
+                    old: None,
+                    new: Some(json!([1])),
+                },
+                JsonChange {
+                    path: "/removed".to_owned(),
+                    old: Some(json!(1)),
+                    new: None,
+                },
+                JsonChange {
+                    path: "/session/ttl".to_owned(),
+                    old: Some(json!(10)),
+                    new: Some(json!(20)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn type_changes_replace_the_whole_value() {
+        let old = json!({"a": {"b": 1}});
+        let new = json!({"a": 1});
+        assert_eq!(
+            diff_json(&old, &new),
+            vec![JsonChange {
+                path: "/a".to_owned(),
+                old: Some(json!({"b": 1})),
+                new: Some(json!(1)),
+            }]
+        );
+    }
+}
@@ -1,3 +1,6 @@
 pub mod config_provider;
+pub mod config_secret;
+pub mod json_diff;
 pub(crate) mod password;
 pub mod rbac;
+pub mod totp;
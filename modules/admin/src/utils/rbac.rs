@@ -1,18 +1,106 @@
-use crate::entities::admin_account::{AdminRole, FindAdminById};
+use crate::entities::admin_account::AdminRole;
+use crate::entities::admin_audit_log::{AuditResult, RecordAuditLog};
+use crate::entities::admin_custom_role::FindAdminGrant;
 use framework::sqlx::DatabaseProcessor;
 use kanau::processor::Processor;
+use std::net::IpAddr;
 use tracing::{Span, instrument};
 use uuid::Uuid;
 
+/// Longer input summaries are cut off in the audit log.
+const MAX_AUDIT_SUMMARY_LENGTH: usize = 4096;
+
 pub struct AuthenticatedAdminOperation<T: AdminOperation> {
     pub admin_id: Uuid,
+    pub client_ip: Option<IpAddr>,
     pub operation: T,
 }
 
-pub trait AdminOperation {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A named right that can be granted to a custom role.
+pub enum Permission {
+    /// `catalog.read`
+    CatalogRead,
+    /// `catalog.write`
+    CatalogWrite,
+    /// `orders.read`
+    OrdersRead,
+    /// `orders.write`
+    OrdersWrite,
+    /// `orders.refund`
+    OrdersRefund,
+    /// `config.read`
+    ConfigRead,
+    /// `config.write`
+    ConfigWrite,
+    /// `users.manage`
+    UsersManage,
+}
+
+impl Permission {
+    pub const ALL: &'static [Permission] = &[
+        Permission::CatalogRead,
+        Permission::CatalogWrite,
+        Permission::OrdersRead,
+        Permission::OrdersWrite,
+        Permission::OrdersRefund,
+        Permission::ConfigRead,
+        Permission::ConfigWrite,
+        Permission::UsersManage,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Permission::CatalogRead => "catalog.read",
+            Permission::CatalogWrite => "catalog.write",
+            Permission::OrdersRead => "orders.read",
+            Permission::OrdersWrite => "orders.write",
+            Permission::OrdersRefund => "orders.refund",
+            Permission::ConfigRead => "config.read",
+            Permission::ConfigWrite => "config.write",
+            Permission::UsersManage => "users.manage",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|permission| permission.as_str() == value)
+    }
+}
+
+pub trait AdminOperation: std::fmt::Debug {
+    /// Operation type recorded in the audit log.
+    const NAME: &'static str;
+    /// Built-in roles allowed to perform the operation.
     const ALLOWED_ROLES: &'static [AdminRole];
-    fn check_permission(role: AdminRole) -> bool {
-        Self::ALLOWED_ROLES.contains(&role)
+    /// The permission a moderator with a custom role needs. Operations without one are only
+    /// available to the built-in roles.
+    const PERMISSION: Option<Permission> = None;
+
+    /// A custom role replaces the built-in rights of a moderator, while owners keep theirs.
+    fn check_permission(role: AdminRole, custom_permissions: Option<&[Permission]>) -> bool {
+        match (role, custom_permissions) {
+            (AdminRole::Moderator, Some(granted)) => {
+                Self::PERMISSION.is_some_and(|permission| granted.contains(&permission))
+            }
+            _ => Self::ALLOWED_ROLES.contains(&role),
+        }
+    }
+
+    /// Input summary recorded in the audit log. Operations carrying secrets must redact them in
+    /// their `Debug` implementation.
+    fn audit_summary(&self) -> String {
+        let mut summary = format!("{self:?}");
+        if summary.len() > MAX_AUDIT_SUMMARY_LENGTH {
+            let mut end = MAX_AUDIT_SUMMARY_LENGTH;
+            while !summary.is_char_boundary(end) {
+                end -= 1;
+            }
+            summary.truncate(end);
+        }
+        summary
     }
 }
 
@@ -25,10 +113,53 @@ impl AuthorizationLayer {
     pub fn new(database_processor: DatabaseProcessor) -> Self {
         Self { database_processor }
     }
-    pub fn into_adapter(
-        self,
-    ) -> kanau::layer::Adapter<Self, kanau::processor::IdentityFunctor<framework::Error>> {
-        kanau::layer::Adapter::new(self, kanau::processor::IdentityFunctor::new())
+    pub fn into_adapter(self) -> AuthorizationAdapter {
+        AuthorizationAdapter { layer: self }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Checks permissions like [`AuthorizationLayer`] and records every attempt in the audit log,
+/// including the denied ones.
+pub struct AuthorizationAdapter {
+    layer: AuthorizationLayer,
+}
+
+impl AuthorizationAdapter {
+    pub async fn wrap<Oper, O>(
+        &self,
+        processor: &impl Processor<Oper, Output = O, Error = framework::Error>,
+        input: AuthenticatedAdminOperation<Oper>,
+    ) -> Result<O, framework::Error>
+    where
+        Oper: AdminOperation + Send,
+        O: Send,
+    {
+        let admin_id = input.admin_id;
+        let client_ip = input.client_ip;
+        let input_summary = input.operation.audit_summary();
+        let result = match self.layer.process(input).await {
+            Ok(operation) => processor.process(operation).await,
+            Err(e) => Err(e),
+        };
+        let (audit_result, error) = match &result {
+            Ok(_) => (AuditResult::Success, None),
+            Err(framework::Error::PermissionsDenied) => (AuditResult::Denied, None),
+            Err(e) => (AuditResult::Failure, Some(e.to_string())),
+        };
+        let record = RecordAuditLog {
+            admin_id,
+            operation: Oper::NAME,
+            input_summary,
+            result: audit_result,
+            error,
+            client_ip: client_ip.map(|ip| ip.to_string()),
+        };
+        // The operation already took effect, so a failed audit write must not hide its result.
+        if let Err(e) = self.layer.database_processor.process(record).await {
+            tracing::error!(error = %e, operation = Oper::NAME, "Failed to record audit log");
+        }
+        result
     }
 }
 
@@ -43,18 +174,21 @@ where
         &self,
         input: AuthenticatedAdminOperation<Oper>,
     ) -> Result<Oper, framework::Error> {
-        let Some(admin) = self
+        let Some(grant) = self
             .database_processor
-            .process(FindAdminById { id: input.admin_id })
+            .process(FindAdminGrant {
+                admin_id: input.admin_id,
+            })
             .await?
+            .filter(|grant| !grant.disabled)
         else {
             return Err(framework::Error::PermissionsDenied);
         };
 
-        Span::current().record("admin_id", admin.id.to_string());
-        Span::current().record("admin_role", &format!("{:?}", admin.role));
+        Span::current().record("admin_id", input.admin_id.to_string());
+        Span::current().record("admin_role", format!("{:?}", grant.role));
 
-        let allowed = Oper::check_permission(admin.role);
+        let allowed = Oper::check_permission(grant.role, grant.custom_permissions.as_deref());
         if !allowed {
             Err(framework::Error::PermissionsDenied)
         } else {
@@ -68,32 +202,103 @@ where
 ///
 /// It will:
 ///
-/// 1. Implement the `AdminOperation` trait for the operation type, specifying the allowed roles.
-/// 2. Implement the `Processor<AuthenticatedAdminOperation<Oper>, Result<Output, framework::Error>>` trait for the processor type,
+/// 1. Implement the `AdminOperation` trait for the operation type, specifying its name in the audit
+///    log, the allowed roles and optionally the [`Permission`] that custom roles need.
+/// 2. Implement `Processor<AuthenticatedAdminOperation<Oper>>` for the processor type by forwarding
+///    the inner operation to `Processor<Oper>`. Permission is checked by [`AuthorizationLayer`]
+///    before the operation reaches the processor.
 ///
 /// # Example
 /// ```ignore
-/// rbac! {MyProcessor : MyOperation => MyOutput | [AdminRole::SuperAdmin, AdminRole::UserManager]}
+/// rbac! {MyProcessor : MyOperation => MyOutput | [AdminRole::Owner, AdminRole::Moderator]}
+/// rbac! {MyProcessor : MyOperation => MyOutput | [AdminRole::Owner], Permission::OrdersRefund}
 /// ```
 macro_rules! rbac {
-    ($processor:ty : $oper:ty => $output:ty | $roles:expr ) => {
+    ($processor:ty : $oper:ty => $output:ty | $roles:expr $(, $permission:expr)? ) => {
         impl $crate::utils::rbac::AdminOperation for $oper {
+            const NAME: &'static str = stringify!($oper);
             const ALLOWED_ROLES: &'static [$crate::entities::admin_account::AdminRole] = &$roles;
+            $(
+                const PERMISSION: Option<$crate::utils::rbac::Permission> = Some($permission);
+            )?
         }
-        impl kanau::processor::Processor<
-            $crate::utils::rbac::AuthenticatedAdminOperation<$oper>,
-            Result<$output, framework::Error>,
-        > for $processor {
+        impl kanau::processor::Processor<$crate::utils::rbac::AuthenticatedAdminOperation<$oper>>
+            for $processor
+        {
+            type Output = $output;
+            type Error = framework::Error;
             async fn process(
                 &self,
                 input: $crate::utils::rbac::AuthenticatedAdminOperation<$oper>,
             ) -> Result<$output, framework::Error> {
-                <Self as kanau::processor::Processor<$oper, Result<$output, framework::Error>>>::process(
-                    self,
-                    input.operation,
-                )
-                .await
+                <Self as kanau::processor::Processor<$oper>>::process(self, input.operation).await
             }
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Refund;
+    impl AdminOperation for Refund {
+        const NAME: &'static str = "Refund";
+        const ALLOWED_ROLES: &'static [AdminRole] = &[AdminRole::Owner, AdminRole::Moderator];
+        const PERMISSION: Option<Permission> = Some(Permission::OrdersRefund);
+    }
+
+    #[derive(Debug)]
+    struct EditCatalog;
+    impl AdminOperation for EditCatalog {
+        const NAME: &'static str = "EditCatalog";
+        const ALLOWED_ROLES: &'static [AdminRole] = &[AdminRole::Owner, AdminRole::Moderator];
+        const PERMISSION: Option<Permission> = Some(Permission::CatalogWrite);
+    }
+
+    #[derive(Debug)]
+    struct OwnerOnly;
+    impl AdminOperation for OwnerOnly {
+        const NAME: &'static str = "OwnerOnly";
+        const ALLOWED_ROLES: &'static [AdminRole] = &[AdminRole::Owner];
+    }
+
+    #[test]
+    fn built_in_roles_use_allowed_roles() {
+        assert!(Refund::check_permission(AdminRole::Moderator, None));
+        assert!(OwnerOnly::check_permission(AdminRole::Owner, None));
+        assert!(!OwnerOnly::check_permission(AdminRole::Moderator, None));
+    }
+
+    #[test]
+    fn custom_role_replaces_moderator_rights() {
+        let support = [Permission::OrdersRead, Permission::OrdersRefund];
+        assert!(Refund::check_permission(
+            AdminRole::Moderator,
+            Some(&support)
+        ));
+        assert!(!EditCatalog::check_permission(
+            AdminRole::Moderator,
+            Some(&support)
+        ));
+        assert!(!OwnerOnly::check_permission(
+            AdminRole::Moderator,
+            Some(Permission::ALL)
+        ));
+    }
+
+    #[test]
+    fn owners_ignore_custom_roles() {
+        assert!(EditCatalog::check_permission(AdminRole::Owner, Some(&[])));
+        assert!(OwnerOnly::check_permission(AdminRole::Owner, Some(&[])));
+    }
+
+    #[test]
+    fn permission_names_round_trip() {
+        for permission in Permission::ALL {
+            assert_eq!(Permission::parse(permission.as_str()), Some(*permission));
+        }
+        assert_eq!(Permission::parse("catalog.delete"), None);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::entities::totp_guard::TotpGuard;
+use framework::redis::RedisConnection;
+
+/// Length of a TOTP time-step in seconds, as in RFC 6238.
+pub const TOTP_STEP_SECONDS: u64 = 30;
+
+/// The time-step whose code equals `code`, looking up to `skew` steps before and after the step
+/// of `now` (unix seconds) to tolerate clocks that drift a little.
+pub fn matching_totp_step(secret: &[u8], code: u32, now: u64, skew: u8) -> Option<u64> {
+    if code > 999_999 {
+        return None;
+    }
+    let rfc6238 = totp_rs::Rfc6238::with_defaults(secret.to_vec()).ok()?;
+    let totp = totp_rs::TOTP::from_rfc6238(rfc6238).ok()?;
+    // Codes are six digits, leading zeros included
+    let code = format!("{code:06}");
+    let current = now / TOTP_STEP_SECONDS;
+    let first = current.saturating_sub(skew as u64);
+    (first..=current + skew as u64).find(|step| totp.generate(step * TOTP_STEP_SECONDS) == code)
+}
+
+/// Limits on checking the TOTP codes of an account, from the config.
+#[derive(Debug, Clone, Copy)]
+pub struct TotpLimits {
+    /// How many steps a code may be early or late
+    pub skew_steps: u8,
+    /// Attempts an account may make within `attempt_window` before further codes are rejected
+    /// unchecked
+    pub max_attempts: u32,
+    pub attempt_window: time::Duration,
+}
+
+/// Check a TOTP code of an account. Every call counts against the attempt limit, and once it is
+/// reached codes are rejected without being checked. A code is accepted at most once, so neither
+/// it nor an older one can be replayed.
+pub async fn verify_totp<A, L>(
+    conn: &mut RedisConnection,
+    attempts_key: A,
+    last_step_key: L,
+    secret: &[u8],
+    code: u32,
+    limits: TotpLimits,
+) -> Result<bool, framework::Error>
+where
+    A: redis::ToSingleRedisArg + Clone + Send + Sync,
+    L: redis::ToSingleRedisArg + Send + Sync,
+{
+    let attempt_window: std::time::Duration = limits.attempt_window.try_into().map_err(|e| {
+        framework::Error::BusinessPanic(anyhow::anyhow!("Invalid TOTP attempt window: {e}"))
+    })?;
+    let attempts = TotpGuard::count_attempt(conn, attempts_key.clone(), attempt_window).await?;
+    if attempts > u64::from(limits.max_attempts) {
+        tracing::warn!(attempts, "TOTP attempt limit reached");
+        return Ok(false);
+    }
+    let skew = limits.skew_steps;
+    let now = framework::now_time().assume_utc().unix_timestamp();
+    let Some(step) = u64::try_from(now)
+        .ok()
+        .and_then(|now| matching_totp_step(secret, code, now, skew))
+    else {
+        return Ok(false);
+    };
+    // The step stays acceptable until the clock has moved `skew` steps past it
+    let replay_ttl = std::time::Duration::from_secs((2 * u64::from(skew) + 2) * TOTP_STEP_SECONDS);
+    if !TotpGuard::advance_step(conn, last_step_key, step, replay_ttl).await? {
+        return Ok(false);
+    }
+    TotpGuard::reset_attempts(conn, attempts_key).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SHA-1 seed from the test vectors of RFC 6238
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn leading_zeros_are_kept() {
+        // 89005924 and 07081804 in the eight-digit test vectors
+        assert_eq!(
+            matching_totp_step(SECRET, 5924, 1234567890, 0),
+            Some(1234567890 / TOTP_STEP_SECONDS)
+        );
+        assert_eq!(
+            matching_totp_step(SECRET, 81804, 1111111109, 0),
+            Some(1111111109 / TOTP_STEP_SECONDS)
+        );
+    }
+
+    #[test]
+    fn codes_are_accepted_within_skew_window() {
+        let step = 1111111109 / TOTP_STEP_SECONDS;
+        let next = 1111111109 + TOTP_STEP_SECONDS;
+        assert_eq!(matching_totp_step(SECRET, 81804, next, 0), None);
+        assert_eq!(matching_totp_step(SECRET, 81804, next, 1), Some(step));
+        let previous = 1111111109 - TOTP_STEP_SECONDS;
+        assert_eq!(matching_totp_step(SECRET, 81804, previous, 1), Some(step));
+        let too_late = 1111111109 + 2 * TOTP_STEP_SECONDS;
+        assert_eq!(matching_totp_step(SECRET, 81804, too_late, 1), None);
+        assert_eq!(matching_totp_step(SECRET, 1_081_804, 1111111109, 1), None);
+    }
+}
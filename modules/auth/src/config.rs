@@ -1,5 +1,8 @@
+use crate::utils::email_template::EmailLocale;
 use crate::utils::oauth::client_config::OAuthProviderClientConfig;
 use crate::utils::oauth::providers::OAuthProviderName;
+use crate::utils::wallet::is_wallet_email;
+use admin::utils::config_secret::SecretString;
 use compact_str::CompactString;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
@@ -11,9 +14,13 @@ pub struct EmailDomainConfig {
 }
 
 impl EmailDomainConfig {
-    /// Check if the email address is allowed to use.
+    /// Check if the email address is allowed to use. The placeholder addresses of wallet
+    /// accounts never are.
     pub fn check_addr(&self, addr: impl AsRef<str>) -> bool {
         let addr = addr.as_ref();
+        if is_wallet_email(addr) {
+            return false;
+        }
         let Ok(address): Result<lettre::Address, _> = addr.parse() else {
             return false;
         };
@@ -41,6 +48,12 @@ pub struct EmailOtpConfig {
     pub expire_after: time::Duration,
     pub delete_before: time::Duration,
     pub resend_interval: time::Duration,
+    /// OTP attempts an email address may get within `attempt_window` before further codes are
+    /// rejected unchecked
+    #[serde(default = "default_email_otp_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_email_otp_attempt_window")]
+    pub attempt_window: time::Duration,
 }
 
 impl Default for EmailOtpConfig {
@@ -49,6 +62,8 @@ impl Default for EmailOtpConfig {
             expire_after: time::Duration::minutes(10),
             delete_before: time::Duration::hours(2),
             resend_interval: time::Duration::minutes(1),
+            max_attempts: default_email_otp_max_attempts(),
+            attempt_window: default_email_otp_attempt_window(),
         }
     }
 }
@@ -78,6 +93,26 @@ pub struct OAuthProviderConfig {
 
     #[serde(flatten)]
     pub config: OAuthProviderClientConfig,
+
+    /// Required for `oidc:<id>` providers and not allowed for the others
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oidc: Option<OidcProviderConfig>,
+}
+
+/// A generic OpenID Connect provider such as Keycloak, found through its discovery document.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OidcProviderConfig {
+    /// The issuer identifier, exactly as in the `iss` claim of its ID tokens. The discovery
+    /// document is read from `{issuer}/.well-known/openid-configuration`.
+    pub issuer: String,
+
+    /// Scopes requested in addition to `openid`
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: Box<[String]>,
+}
+
+fn default_oidc_scopes() -> Box<[String]> {
+    Box::new(["email".to_owned(), "profile".to_owned()])
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -110,6 +145,15 @@ pub struct MfaConfig {
     pub setup_code_ttl: time::Duration,
     #[serde(default = "default_mfa_token_ttl")]
     pub token_ttl: time::Duration,
+    /// How many 30-second steps a TOTP code may be early or late
+    #[serde(default = "default_totp_skew_steps")]
+    pub totp_skew_steps: u8,
+    /// TOTP attempts a user may make within `totp_attempt_window` before further codes are
+    /// rejected unchecked
+    #[serde(default = "default_totp_max_attempts")]
+    pub totp_max_attempts: u32,
+    #[serde(default = "default_mfa_token_ttl")]
+    pub totp_attempt_window: time::Duration,
 }
 
 impl Default for MfaConfig {
@@ -117,10 +161,156 @@ impl Default for MfaConfig {
         Self {
             setup_code_ttl: default_mfa_token_ttl(),
             token_ttl: default_mfa_token_ttl(),
+            totp_skew_steps: default_totp_skew_steps(),
+            totp_max_attempts: default_totp_max_attempts(),
+            totp_attempt_window: default_mfa_token_ttl(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// Stateless access tokens. When enabled, logins also return a short-lived access JWT and a
+/// rotating refresh token, and requests may authenticate with `authorization: Bearer <jwt>`.
+pub struct TokenConfig {
+    pub enabled: bool,
+    /// HS256 key, at least 32 bytes
+    pub signing_key: SecretString,
+    pub access_token_ttl: time::Duration,
+    pub issuer: CompactString,
+    pub audience: CompactString,
+}
+
+impl Default for TokenConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            signing_key: SecretString::default(),
+            access_token_ttl: time::Duration::minutes(5),
+            issuer: CompactString::const_new("phantom-store"),
+            audience: CompactString::const_new("phantom-store"),
         }
     }
 }
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// The WebAuthn relying party. Passkeys are bound to `rp_id`, so changing it invalidates every
+/// registered passkey.
+pub struct WebauthnConfig {
+    /// Effective domain of the site, e.g. `shop.example.com`
+    pub rp_id: CompactString,
+    /// Origins the browser may report, e.g. `https://shop.example.com`. The first is the main one.
+    pub rp_origins: Box<[CompactString]>,
+    /// Shown by the authenticator while registering
+    pub rp_name: CompactString,
+    /// The expiration time for registration and assertion challenges
+    pub challenge_ttl: time::Duration,
+}
+
+impl Default for WebauthnConfig {
+    fn default() -> Self {
+        Self {
+            rp_id: CompactString::const_new("localhost"),
+            rp_origins: Box::new([CompactString::const_new("http://localhost:8080")]),
+            rp_name: CompactString::const_new("Phantom Store"),
+            challenge_ttl: time::Duration::minutes(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// Sign-In With Ethereum (EIP-4361) and its TRON counterpart. A wallet that signs in for the
+/// first time gets a new account.
+pub struct WalletLoginConfig {
+    pub enabled: bool,
+    /// Host the site is served from, e.g. `shop.example.com`. Wallets warn if it does not match
+    /// the page asking for the signature.
+    pub domain: CompactString,
+    /// The site URL, e.g. `https://shop.example.com`
+    pub uri: CompactString,
+    /// Shown to the user in the message. Must be a single line.
+    pub statement: CompactString,
+    /// The expiration time for the message to be signed
+    pub challenge_ttl: time::Duration,
+}
+
+impl Default for WalletLoginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            domain: CompactString::const_new("localhost:8080"),
+            uri: CompactString::const_new("http://localhost:8080"),
+            statement: CompactString::const_new("Sign in to Phantom Store"),
+            challenge_ttl: time::Duration::minutes(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EmailDeliveryConfig {
+    /// The `From` header, such as `Phantom Store <no-reply@example.com>`
+    pub from: CompactString,
+    /// Shown in the emails
+    pub site_name: CompactString,
+    /// Used when the client did not ask for a supported language
+    #[serde(default)]
+    pub default_locale: EmailLocale,
+    pub transport: EmailTransportConfig,
+}
+
+impl Default for EmailDeliveryConfig {
+    fn default() -> Self {
+        Self {
+            from: CompactString::const_new("Phantom Store <no-reply@localhost>"),
+            site_name: CompactString::const_new("Phantom Store"),
+            default_locale: EmailLocale::default(),
+            transport: EmailTransportConfig::File {
+                directory: std::env::temp_dir().join("phantom-store-mail"),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EmailTransportConfig {
+    Smtp {
+        host: CompactString,
+        /// Defaults to the port of the security mode
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        port: Option<u16>,
+        security: SmtpSecurity,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        credentials: Option<SmtpCredentials>,
+        #[serde(default = "default_smtp_timeout")]
+        timeout: time::Duration,
+    },
+    /// Write every email as an `.eml` file into the directory instead of sending it, for
+    /// development and tests
+    File { directory: std::path::PathBuf },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Implicit TLS, port 465 by default
+    Tls,
+    /// Upgrade with `STARTTLS`, port 587 by default
+    StartTls,
+    /// Plain text, only for a relay on the same host. Port 25 by default
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SmtpCredentials {
+    pub username: CompactString,
+    /// Encrypted when the config is stored
+    pub password: SecretString,
+}
+
+fn default_smtp_timeout() -> time::Duration {
+    time::Duration::seconds(30)
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct AuthConfig {
     pub email_provider: EmailProviderConfig,
@@ -131,6 +321,14 @@ pub struct AuthConfig {
     #[serde(default = "default_sudo_token_ttl")]
     pub sudo_token_ttl: time::Duration,
     pub mfa: MfaConfig,
+    #[serde(default)]
+    pub token: TokenConfig,
+    #[serde(default)]
+    pub webauthn: WebauthnConfig,
+    #[serde(default)]
+    pub wallet_login: WalletLoginConfig,
+    #[serde(default)]
+    pub email_delivery: EmailDeliveryConfig,
 }
 
 impl Default for AuthConfig {
@@ -141,6 +339,10 @@ impl Default for AuthConfig {
             session: SessionConfig::default(),
             oauth_providers: OAuthProvidersConfig::default(),
             mfa: MfaConfig::default(),
+            token: TokenConfig::default(),
+            webauthn: WebauthnConfig::default(),
+            wallet_login: WalletLoginConfig::default(),
+            email_delivery: EmailDeliveryConfig::default(),
         }
     }
 }
@@ -149,10 +351,122 @@ fn default_mfa_token_ttl() -> time::Duration {
     time::Duration::minutes(5)
 }
 
+fn default_totp_skew_steps() -> u8 {
+    1
+}
+
+fn default_totp_max_attempts() -> u32 {
+    5
+}
+
+fn default_email_otp_max_attempts() -> u32 {
+    5
+}
+
+fn default_email_otp_attempt_window() -> time::Duration {
+    time::Duration::minutes(10)
+}
+
 fn default_sudo_token_ttl() -> time::Duration {
     time::Duration::minutes(5)
 }
 
 impl admin::utils::config_provider::ConfigJson for AuthConfig {
     const KEY: &'static str = "auth_config";
+
+    fn validate(&self) -> Result<(), &'static str> {
+        if !self.session.session_ttl.is_positive() {
+            return Err("session TTL must be positive");
+        }
+        if !self.sudo_token_ttl.is_positive()
+            || !self.mfa.token_ttl.is_positive()
+            || !self.mfa.setup_code_ttl.is_positive()
+        {
+            return Err("token TTLs must be positive");
+        }
+        if self.mfa.totp_skew_steps > 2 {
+            return Err("TOTP skew must be at most 2 steps");
+        }
+        if self.mfa.totp_max_attempts == 0 || !self.mfa.totp_attempt_window.is_positive() {
+            return Err("TOTP attempt limit and window must be positive");
+        }
+        if !self.email_provider.otp.expire_after.is_positive() {
+            return Err("OTP expiry must be positive");
+        }
+        if self.email_provider.otp.max_attempts == 0
+            || !self.email_provider.otp.attempt_window.is_positive()
+        {
+            return Err("OTP attempt limit and window must be positive");
+        }
+        if self.token.enabled {
+            if self.token.signing_key.expose().len() < 32 {
+                return Err("token signing key must be at least 32 bytes");
+            }
+            if !self.token.access_token_ttl.is_positive()
+                || self.token.access_token_ttl >= self.session.session_ttl
+            {
+                return Err("access token TTL must be positive and shorter than the session TTL");
+            }
+        }
+        if !self.webauthn.challenge_ttl.is_positive() {
+            return Err("token TTLs must be positive");
+        }
+        if crate::utils::webauthn::build_webauthn(&self.webauthn).is_err() {
+            return Err("WebAuthn relying party ID must be a suffix of every origin");
+        }
+        if self.wallet_login.enabled {
+            let wallet_login = &self.wallet_login;
+            if wallet_login.domain.is_empty()
+                || url::Url::parse(&wallet_login.uri).is_err()
+                || wallet_login.statement.contains('\n')
+            {
+                return Err("wallet login needs a domain, a valid URI and a single-line statement");
+            }
+            if !wallet_login.challenge_ttl.is_positive() {
+                return Err("token TTLs must be positive");
+            }
+        }
+        if self
+            .email_delivery
+            .from
+            .parse::<lettre::message::Mailbox>()
+            .is_err()
+        {
+            return Err("email sender must be a valid mailbox");
+        }
+        if let EmailTransportConfig::Smtp { host, timeout, .. } = &self.email_delivery.transport
+            && (host.is_empty() || !timeout.is_positive())
+        {
+            return Err("SMTP needs a host and a positive timeout");
+        }
+        let providers = &self.oauth_providers.providers;
+        if providers
+            .iter()
+            .enumerate()
+            .any(|(i, provider)| providers[..i].iter().any(|p| p.name == provider.name))
+        {
+            return Err("OAuth providers must be unique");
+        }
+        for provider in providers.iter() {
+            match (provider.name, &provider.oidc) {
+                (OAuthProviderName::Oidc(_), Some(oidc)) => {
+                    let issuer_is_https = url::Url::parse(&oidc.issuer)
+                        .is_ok_and(|url| url.scheme() == "https" && url.query().is_none());
+                    if !issuer_is_https {
+                        return Err("OpenID Connect issuers must be https URLs without a query");
+                    }
+                }
+                (OAuthProviderName::Oidc(_), None) | (_, Some(_)) => {
+                    return Err("`oidc` must be set for, and only for, `oidc:<id>` providers");
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Register the config types of this module.
+pub fn register_configs(registry: &mut admin::utils::config_provider::ConfigRegistry) {
+    registry.register::<AuthConfig>();
 }
@@ -67,22 +67,24 @@ impl Processor<CreateEmailOtp> for DatabaseProcessor {
     }
 }
 
+/// Use an OTP up. `None` if it has already been used, so that concurrent requests cannot use the
+/// same code twice.
 #[derive(Debug, Clone, Copy)]
 pub struct MarkEmailOtpAsUsed {
     pub id: i64,
 }
 
 impl Processor<MarkEmailOtpAsUsed> for DatabaseProcessor {
-    type Output = EmailOtp;
+    type Output = Option<EmailOtp>;
     type Error = sqlx::Error;
     #[instrument(skip_all, name = "SQL:MarkEmailOtpAsUsed", err)]
-    async fn process(&self, input: MarkEmailOtpAsUsed) -> Result<EmailOtp, sqlx::Error> {
+    async fn process(&self, input: MarkEmailOtpAsUsed) -> Result<Option<EmailOtp>, sqlx::Error> {
         sqlx::query_as!(
             EmailOtp,
             r#"
             UPDATE "auth"."email_otp"
             SET has_been_used = TRUE, used_at = NOW()
-            WHERE id = $1
+            WHERE id = $1 AND NOT has_been_used
             RETURNING 
             id, user_id, email, otp_code, created_at, 
             expires_at, has_been_used, used_at, 
@@ -90,7 +92,7 @@ impl Processor<MarkEmailOtpAsUsed> for DatabaseProcessor {
             "#,
             input.id
         )
-        .fetch_one(self.db())
+        .fetch_optional(self.db())
         .await
     }
 }
@@ -160,6 +162,43 @@ impl Processor<FindValidEmailOtp> for DatabaseProcessor {
     }
 }
 
+/// Find a valid OTP sent to an email address before the user signed in, i.e. one not bound to
+/// any user.
+#[derive(Debug, Clone)]
+pub struct FindValidUnboundEmailOtp {
+    pub email: String,
+    pub usage: EmailOtpUsage,
+    pub otp_code: String,
+}
+
+impl Processor<FindValidUnboundEmailOtp> for DatabaseProcessor {
+    type Output = Option<EmailOtp>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:FindValidUnboundEmailOtp", err)]
+    async fn process(
+        &self,
+        input: FindValidUnboundEmailOtp,
+    ) -> Result<Option<EmailOtp>, sqlx::Error> {
+        sqlx::query_as!(
+            EmailOtp,
+            r#"
+            SELECT 
+            id, user_id, email, otp_code, created_at, 
+            expires_at, has_been_used, used_at, 
+            usage as "usage: EmailOtpUsage"
+            FROM "auth"."email_otp"
+            WHERE user_id IS NULL AND email = $1 AND usage = $2 AND otp_code = $3
+            AND expires_at > NOW() AND has_been_used = FALSE
+            "#,
+            &input.email,
+            input.usage as EmailOtpUsage,
+            &input.otp_code
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct DeleteEmailOtpBefore {
     pub before: time::PrimitiveDateTime,
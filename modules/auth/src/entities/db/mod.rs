@@ -1,5 +1,8 @@
 pub mod email_otp;
 pub mod oauth_account;
+pub mod recovery_code;
 pub mod totp;
 pub mod user_account;
 pub mod user_password;
+pub mod wallet_identity;
+pub mod webauthn_credential;
@@ -0,0 +1,121 @@
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use tracing::{Instrument, info_span, instrument};
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+/// Replace all recovery codes of the user, used or not.
+pub struct ReplaceRecoveryCodes {
+    pub user_id: Uuid,
+    pub code_hashes: Vec<[u8; 32]>,
+}
+
+impl Processor<ReplaceRecoveryCodes> for DatabaseProcessor {
+    type Output = ();
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL-Transaction:ReplaceRecoveryCodes", err)]
+    async fn process(&self, input: ReplaceRecoveryCodes) -> Result<(), sqlx::Error> {
+        let code_hashes: Vec<Vec<u8>> = input.code_hashes.iter().map(|h| h.to_vec()).collect();
+        let mut tx = self
+            .db()
+            .begin()
+            .instrument(info_span!("<Transaction Begin>"))
+            .await?;
+        sqlx::query!(
+            r#"
+            DELETE FROM "auth"."mfa_recovery_code"
+            WHERE user_id = $1
+            "#,
+            input.user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO "auth"."mfa_recovery_code" (user_id, code_hash)
+            SELECT $1, code_hash FROM UNNEST($2::bytea[]) AS code_hash
+            "#,
+            input.user_id,
+            &code_hashes
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit()
+            .instrument(info_span!("<Transaction Commit>"))
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ConsumeRecoveryCode {
+    pub user_id: Uuid,
+    pub code_hash: [u8; 32],
+}
+
+impl Processor<ConsumeRecoveryCode> for DatabaseProcessor {
+    /// `false` if the code does not exist or was already used
+    type Output = bool;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ConsumeRecoveryCode", err)]
+    async fn process(&self, input: ConsumeRecoveryCode) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE "auth"."mfa_recovery_code"
+            SET used_at = NOW()
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            "#,
+            input.user_id,
+            &input.code_hash[..]
+        )
+        .execute(self.db())
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CountUnusedRecoveryCodes {
+    pub user_id: Uuid,
+}
+
+impl Processor<CountUnusedRecoveryCodes> for DatabaseProcessor {
+    type Output = i64;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:CountUnusedRecoveryCodes", err)]
+    async fn process(&self, input: CountUnusedRecoveryCodes) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "count!"
+            FROM "auth"."mfa_recovery_code"
+            WHERE user_id = $1 AND used_at IS NULL
+            "#,
+            input.user_id
+        )
+        .fetch_one(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RemoveRecoveryCodesByUserId {
+    pub user_id: Uuid,
+}
+
+impl Processor<RemoveRecoveryCodesByUserId> for DatabaseProcessor {
+    type Output = ();
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:RemoveRecoveryCodesByUserId", err)]
+    async fn process(&self, input: RemoveRecoveryCodesByUserId) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM "auth"."mfa_recovery_code"
+            WHERE user_id = $1
+            "#,
+            input.user_id
+        )
+        .execute(self.db())
+        .await
+        .map(|_| ())
+    }
+}
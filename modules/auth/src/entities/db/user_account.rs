@@ -148,3 +148,153 @@ impl Processor<RegisterPasswordlessUserAccount> for DatabaseProcessor {
         .await
     }
 }
+
+#[derive(Debug, Clone)]
+/// Case-insensitive substring search over email and name.
+pub struct SearchUserAccounts {
+    pub query: String,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Processor<SearchUserAccounts> for DatabaseProcessor {
+    type Output = Vec<UserAccount>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:SearchUserAccounts", err)]
+    async fn process(&self, input: SearchUserAccounts) -> Result<Vec<UserAccount>, sqlx::Error> {
+        let pattern = format!(
+            "%{}%",
+            input
+                .query
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        sqlx::query_as!(
+            UserAccount,
+            r#"
+            SELECT id, name, email, created_at, updated_at
+            FROM "auth"."user_account"
+            WHERE email ILIKE $1 OR name ILIKE $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            &pattern,
+            input.limit,
+            input.offset
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserSuspension {
+    pub suspended_at: time::PrimitiveDateTime,
+    /// `None` if the admin has been deleted since.
+    pub suspended_by: Option<Uuid>,
+    pub reason: String,
+    /// `None` for a permanent ban
+    pub until: Option<time::PrimitiveDateTime>,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Find the suspension of a user that has not expired yet.
+pub struct FindUserSuspension {
+    pub user_id: Uuid,
+}
+
+impl Processor<FindUserSuspension> for DatabaseProcessor {
+    /// `None` if the user is not suspended
+    type Output = Option<UserSuspension>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:FindUserSuspension", err)]
+    async fn process(
+        &self,
+        input: FindUserSuspension,
+    ) -> Result<Option<UserSuspension>, sqlx::Error> {
+        sqlx::query_as!(
+            UserSuspension,
+            r#"
+            SELECT suspended_at as "suspended_at!", suspended_by,
+            COALESCE(suspension_reason, '') as "reason!", suspended_until as until
+            FROM "auth"."user_account"
+            WHERE id = $1
+              AND suspended_at IS NOT NULL
+              AND (suspended_until IS NULL OR suspended_until > NOW())
+            "#,
+            input.user_id
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SuspendUserAccount {
+    pub user_id: Uuid,
+    pub suspended_by: Uuid,
+    pub reason: String,
+    pub until: Option<time::PrimitiveDateTime>,
+}
+
+impl Processor<SuspendUserAccount> for DatabaseProcessor {
+    /// `None` if the user does not exist
+    type Output = Option<UserSuspension>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:SuspendUserAccount", err)]
+    async fn process(
+        &self,
+        input: SuspendUserAccount,
+    ) -> Result<Option<UserSuspension>, sqlx::Error> {
+        sqlx::query_as!(
+            UserSuspension,
+            r#"
+            UPDATE "auth"."user_account"
+            SET suspended_at = NOW(),
+                suspended_by = $2,
+                suspension_reason = $3,
+                suspended_until = $4,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING suspended_at as "suspended_at!", suspended_by,
+            suspension_reason as "reason!", suspended_until as until
+            "#,
+            input.user_id,
+            input.suspended_by,
+            &input.reason,
+            input.until
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LiftUserSuspension {
+    pub user_id: Uuid,
+}
+
+impl Processor<LiftUserSuspension> for DatabaseProcessor {
+    /// Whether the user exists
+    type Output = bool;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:LiftUserSuspension", err)]
+    async fn process(&self, input: LiftUserSuspension) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE "auth"."user_account"
+            SET suspended_at = NULL,
+                suspended_by = NULL,
+                suspension_reason = NULL,
+                suspended_until = NULL,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            input.user_id
+        )
+        .execute(self.db())
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,131 @@
+use crate::utils::wallet::WALLET_EMAIL_DOMAIN;
+use blockchain_sync::utils::supported_tokens::FlattenSupportedBlockchains;
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use tracing::{Instrument, info_span, instrument};
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct FindWalletIdentityUser {
+    pub chain: FlattenSupportedBlockchains,
+    pub address: String,
+}
+
+impl Processor<FindWalletIdentityUser> for DatabaseProcessor {
+    type Output = Option<Uuid>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:FindWalletIdentityUser", err)]
+    async fn process(&self, input: FindWalletIdentityUser) -> Result<Option<Uuid>, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT user_id FROM "auth"."wallet_identity"
+            WHERE chain = $1 AND address = $2
+            "#,
+            input.chain as FlattenSupportedBlockchains,
+            &input.address
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The account of a wallet, created on its first login. An EVM address that signed in on another
+/// chain before is given the same account.
+///
+/// The account has no email address, so it gets `<account id>@`[`WALLET_EMAIL_DOMAIN`] as a
+/// placeholder, which no other account can have. Concurrent logins of a new wallet all end up
+/// with the account of whichever committed first.
+pub struct FindOrRegisterWalletUser {
+    pub chain: FlattenSupportedBlockchains,
+    pub address: String,
+}
+
+impl Processor<FindOrRegisterWalletUser> for DatabaseProcessor {
+    type Output = Uuid;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL-Transaction:FindOrRegisterWalletUser", err)]
+    async fn process(&self, input: FindOrRegisterWalletUser) -> Result<Uuid, sqlx::Error> {
+        let find = FindWalletIdentityUser {
+            chain: input.chain,
+            address: input.address.clone(),
+        };
+        if let Some(user_id) = self.process(find.clone()).await? {
+            return Ok(user_id);
+        }
+        let mut tx = self
+            .db()
+            .begin()
+            .instrument(info_span!("<Transaction Begin>"))
+            .await?;
+        // waits for a concurrent registration of the same address to finish
+        sqlx::query!(
+            r#"SELECT pg_advisory_xact_lock(hashtextextended($1, 0))"#,
+            &input.address
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let other_chain_user = sqlx::query_scalar!(
+            r#"
+            SELECT user_id FROM "auth"."wallet_identity"
+            WHERE address = $1
+            ORDER BY id
+            LIMIT 1
+            "#,
+            &input.address
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let user_id = match other_chain_user {
+            Some(user_id) => user_id,
+            None => {
+                sqlx::query_scalar!(
+                    r#"
+                    INSERT INTO "auth"."user_account" (id, email)
+                    SELECT id, id::TEXT || '@' || $1
+                    FROM (SELECT gen_random_uuid() AS id) AS new_account
+                    RETURNING id
+                    "#,
+                    WALLET_EMAIL_DOMAIN
+                )
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+        let linked = sqlx::query_scalar!(
+            r#"
+            INSERT INTO "auth"."wallet_identity" (user_id, chain, address)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (chain, address) DO NOTHING
+            RETURNING user_id
+            "#,
+            user_id,
+            input.chain as FlattenSupportedBlockchains,
+            &input.address
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(user_id) = linked else {
+            // lost the race, the wallet is registered by now
+            tx.rollback().await?;
+            return self.process(find).await?.ok_or(sqlx::Error::RowNotFound);
+        };
+        // also usable as a payment address of the customer
+        sqlx::query!(
+            r#"
+            INSERT INTO "blockchain"."customer_addresses" (user_id, chain, address)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, chain, address) DO NOTHING
+            "#,
+            user_id,
+            input.chain as FlattenSupportedBlockchains,
+            &input.address
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit()
+            .instrument(info_span!("<Transaction Commit>"))
+            .await?;
+        Ok(user_id)
+    }
+}
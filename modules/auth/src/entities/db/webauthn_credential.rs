@@ -0,0 +1,183 @@
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use sqlx::types::Json;
+use tracing::instrument;
+use uuid::Uuid;
+use webauthn_rs::prelude::Passkey;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct WebauthnCredential {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub credential_id: Vec<u8>,
+    pub passkey: Json<Passkey>,
+    pub name: String,
+    pub created_at: time::PrimitiveDateTime,
+    pub last_used_at: Option<time::PrimitiveDateTime>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListWebauthnCredentialsByUserId {
+    pub user_id: Uuid,
+}
+
+impl Processor<ListWebauthnCredentialsByUserId> for DatabaseProcessor {
+    type Output = Vec<WebauthnCredential>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListWebauthnCredentialsByUserId", err)]
+    async fn process(
+        &self,
+        input: ListWebauthnCredentialsByUserId,
+    ) -> Result<Vec<WebauthnCredential>, sqlx::Error> {
+        sqlx::query_as!(
+            WebauthnCredential,
+            r#"
+            SELECT id, user_id, credential_id, passkey as "passkey: Json<Passkey>", name,
+            created_at, last_used_at
+            FROM "auth"."webauthn_credential"
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+            input.user_id
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CheckWebauthnCredentialExists {
+    pub user_id: Uuid,
+}
+
+impl Processor<CheckWebauthnCredentialExists> for DatabaseProcessor {
+    type Output = bool;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:CheckWebauthnCredentialExists", err)]
+    async fn process(&self, input: CheckWebauthnCredentialExists) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM "auth"."webauthn_credential" WHERE user_id = $1
+            ) as "exists!"
+            "#,
+            input.user_id
+        )
+        .fetch_one(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateWebauthnCredential {
+    pub user_id: Uuid,
+    pub name: String,
+    pub passkey: Passkey,
+}
+
+impl Processor<CreateWebauthnCredential> for DatabaseProcessor {
+    /// `None` if the credential is already registered
+    type Output = Option<WebauthnCredential>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:CreateWebauthnCredential", err)]
+    async fn process(
+        &self,
+        input: CreateWebauthnCredential,
+    ) -> Result<Option<WebauthnCredential>, sqlx::Error> {
+        let credential_id: &[u8] = input.passkey.cred_id().as_ref();
+        sqlx::query_as!(
+            WebauthnCredential,
+            r#"
+            INSERT INTO "auth"."webauthn_credential" (user_id, credential_id, passkey, name)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (credential_id) DO NOTHING
+            RETURNING id, user_id, credential_id, passkey as "passkey: Json<Passkey>", name,
+            created_at, last_used_at
+            "#,
+            input.user_id,
+            credential_id,
+            Json(&input.passkey) as _,
+            input.name
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Record a successful assertion.
+pub struct MarkWebauthnCredentialUsed {
+    pub id: Uuid,
+    /// The passkey with its updated signature counter and backup state, if they changed.
+    pub updated_passkey: Option<Passkey>,
+}
+
+impl Processor<MarkWebauthnCredentialUsed> for DatabaseProcessor {
+    type Output = ();
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:MarkWebauthnCredentialUsed", err)]
+    async fn process(&self, input: MarkWebauthnCredentialUsed) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE "auth"."webauthn_credential"
+            SET last_used_at = NOW(), passkey = COALESCE($2, passkey)
+            WHERE id = $1
+            "#,
+            input.id,
+            input.updated_passkey.as_ref().map(Json) as _
+        )
+        .execute(self.db())
+        .await
+        .map(|_| ())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RemoveWebauthnCredential {
+    pub user_id: Uuid,
+    pub id: Uuid,
+}
+
+impl Processor<RemoveWebauthnCredential> for DatabaseProcessor {
+    /// `false` if the user has no such credential
+    type Output = bool;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:RemoveWebauthnCredential", err)]
+    async fn process(&self, input: RemoveWebauthnCredential) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM "auth"."webauthn_credential"
+            WHERE id = $1 AND user_id = $2
+            "#,
+            input.id,
+            input.user_id
+        )
+        .execute(self.db())
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RemoveWebauthnCredentialsByUserId {
+    pub user_id: Uuid,
+}
+
+impl Processor<RemoveWebauthnCredentialsByUserId> for DatabaseProcessor {
+    /// The number of removed credentials
+    type Output = u64;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:RemoveWebauthnCredentialsByUserId", err)]
+    async fn process(&self, input: RemoveWebauthnCredentialsByUserId) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM "auth"."webauthn_credential"
+            WHERE user_id = $1
+            "#,
+            input.user_id
+        )
+        .execute(self.db())
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
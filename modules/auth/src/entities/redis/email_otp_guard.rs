@@ -0,0 +1,18 @@
+use framework::redis::RedisKey;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Counter of OTP attempts made for an email address in the current window, stored as a plain
+/// Redis integer.
+pub struct EmailOtpAttemptsKey(pub String);
+
+impl redis::ToSingleRedisArg for EmailOtpAttemptsKey {}
+
+impl redis::ToRedisArgs for EmailOtpAttemptsKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("email_otp_attempts:{}", self.0));
+        key.write_redis_args(out);
+    }
+}
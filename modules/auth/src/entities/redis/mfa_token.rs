@@ -1,3 +1,4 @@
+use crate::entities::redis::session::LoginMethod;
 use framework::redis::RedisKey;
 use uuid::Uuid;
 
@@ -36,6 +37,8 @@ pub struct MfaLoginToken {
     pub token: [u8; 32],
     /// User ID attempting to log in
     pub user_id: Uuid,
+    /// The verified first factor, recorded on the session once MFA passes
+    pub login_method: LoginMethod,
 }
 
 impl core::fmt::Debug for MfaLoginToken {
@@ -43,6 +46,7 @@ impl core::fmt::Debug for MfaLoginToken {
         f.debug_struct("MfaLoginToken")
             .field("token", &"[redacted]")
             .field("user_id", &self.user_id)
+            .field("login_method", &self.login_method)
             .finish()
     }
 }
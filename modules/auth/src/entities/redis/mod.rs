@@ -1,6 +1,14 @@
+pub mod email_otp_guard;
 pub mod mfa_token;
 pub mod oauth_challenge;
+pub mod refresh_token;
+pub mod revoked_session;
 pub mod session;
+pub mod session_handle;
 pub mod sudo_token;
+pub mod suspended_user;
+pub mod totp_guard;
 pub mod totp_setup;
 pub mod user_session_list;
+pub mod wallet_challenge;
+pub mod webauthn_challenge;
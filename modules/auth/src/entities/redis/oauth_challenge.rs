@@ -25,6 +25,8 @@ pub struct OAuthChallenge {
     pub action: OAuthAction,
     pub redirect_uri: String,
     pub pkce_verifier: Option<String>,
+    /// Expected in the ID token of OpenID Connect providers
+    pub nonce: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
@@ -0,0 +1,103 @@
+use framework::redis::{KeyValue, KeyValueRead, KeyValueWrite, RedisKey};
+use kanau::{RkyvMessageDe, RkyvMessageSer};
+use redis::AsyncCommands;
+use uuid::Uuid;
+
+/// How many rotated refresh tokens are remembered for reuse detection.
+pub const REMEMBERED_REFRESH_TOKENS: usize = 16;
+
+/// The chain of refresh tokens issued for a session. Only the latest one is accepted. Presenting
+/// an earlier one means the chain leaked, so the whole session is revoked.
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    RkyvMessageSer,
+    RkyvMessageDe,
+)]
+pub struct RefreshTokenFamily {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    /// SHA-256 of the secret of the latest token
+    pub current: [u8; 32],
+    /// SHA-256 of rotated secrets, newest last
+    pub rotated: Vec<[u8; 32]>,
+}
+
+impl std::fmt::Debug for RefreshTokenFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RefreshTokenFamily")
+            .field("session_id", &self.session_id)
+            .field("user_id", &self.user_id)
+            .field("rotated", &self.rotated.len())
+            .finish()
+    }
+}
+
+impl RefreshTokenFamily {
+    /// Replace the latest token, remembering the previous one.
+    pub fn rotate(&mut self, next: [u8; 32]) {
+        let previous = std::mem::replace(&mut self.current, next);
+        self.rotated.push(previous);
+        if self.rotated.len() > REMEMBERED_REFRESH_TOKENS {
+            self.rotated.remove(0);
+        }
+    }
+
+    /// Atomically take the family out of Redis, so that concurrent refreshes cannot both rotate
+    /// the same token.
+    pub async fn take(
+        conn: &mut framework::redis::RedisConnection,
+        key: RefreshTokenFamilyKey,
+    ) -> Result<Option<Self>, framework::Error> {
+        use kanau::message::MessageDe;
+        let data: Option<Vec<u8>> = conn.get_del(key).await?;
+        data.map(|bytes| {
+            Self::from_bytes(&bytes).map_err(|e| framework::Error::DeserializeError(e.into()))
+        })
+        .transpose()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefreshTokenFamilyKey(pub Uuid);
+
+impl redis::ToSingleRedisArg for RefreshTokenFamilyKey {}
+
+impl redis::ToRedisArgs for RefreshTokenFamilyKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("refresh_family:{}", self.0));
+        key.write_redis_args(out);
+    }
+}
+
+impl KeyValue for RefreshTokenFamily {
+    type Key = RefreshTokenFamilyKey;
+    type Value = Self;
+
+    fn key(&self) -> Self::Key {
+        RefreshTokenFamilyKey(self.session_id)
+    }
+
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+
+    fn into_value(self) -> Self::Value {
+        self
+    }
+
+    fn new(key: Self::Key, mut value: Self::Value) -> Self {
+        value.session_id = key.0;
+        value
+    }
+}
+
+impl KeyValueRead for RefreshTokenFamily {}
+impl KeyValueWrite for RefreshTokenFamily {}
@@ -0,0 +1,70 @@
+use framework::redis::{KeyValue, KeyValueRead, KeyValueWrite, RedisKey};
+use kanau::{RkyvMessageDe, RkyvMessageSer};
+use redis::AsyncCommands;
+use uuid::Uuid;
+
+/// Written when a session is terminated and kept as long as access tokens live, so that access
+/// tokens of the session are rejected without looking the session up.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    RkyvMessageSer,
+    RkyvMessageDe,
+)]
+pub struct RevokedSession {
+    pub session_id: Uuid,
+}
+
+impl RevokedSession {
+    pub async fn is_revoked(
+        conn: &mut framework::redis::RedisConnection,
+        session_id: Uuid,
+    ) -> Result<bool, framework::Error> {
+        Ok(conn.exists(RevokedSessionKey(session_id)).await?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RevokedSessionKey(pub Uuid);
+
+impl redis::ToSingleRedisArg for RevokedSessionKey {}
+
+impl redis::ToRedisArgs for RevokedSessionKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("session_revoked:{}", self.0));
+        key.write_redis_args(out);
+    }
+}
+
+impl KeyValue for RevokedSession {
+    type Key = RevokedSessionKey;
+    type Value = Self;
+
+    fn key(&self) -> Self::Key {
+        RevokedSessionKey(self.session_id)
+    }
+
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+
+    fn into_value(self) -> Self::Value {
+        self
+    }
+
+    fn new(key: Self::Key, mut value: Self::Value) -> Self {
+        value.session_id = key.0;
+        value
+    }
+}
+
+impl KeyValueRead for RevokedSession {}
+impl KeyValueWrite for RevokedSession {}
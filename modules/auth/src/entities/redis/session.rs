@@ -1,7 +1,11 @@
+use crate::utils::oauth::providers::OAuthProviderName;
 use framework::redis::{KeyValue, KeyValueRead, KeyValueWrite, RedisKey};
 use kanau::{RkyvMessageDe, RkyvMessageSer};
 use uuid::Uuid;
 
+/// Longer user agents are truncated before being stored.
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
 #[derive(
     Debug,
     Clone,
@@ -18,6 +22,49 @@ pub struct Session {
     pub user_id: Uuid,
     pub terminated: bool,
     pub last_refreshed: u64,
+    pub created_at: u64,
+    pub login_method: LoginMethod,
+    pub client: SessionClient,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+/// The first factor the session was created with.
+pub enum LoginMethod {
+    EmailOtp,
+    EmailPassword,
+    OAuth(OAuthProviderName),
+    /// Logged in right after registering with an email OTP.
+    Registration,
+    /// Logged in right after resetting the password.
+    PasswordReset,
+    /// Passwordless login with a passkey.
+    Passkey,
+    /// Signed a login message with an Ethereum or TRON wallet.
+    Wallet,
+}
+
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize,
+)]
+/// The device a session was created from, as reported by the request.
+pub struct SessionClient {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl SessionClient {
+    pub fn new(user_agent: Option<&str>, ip: Option<std::net::IpAddr>) -> Self {
+        Self {
+            user_agent: user_agent.map(|user_agent| {
+                let mut end = user_agent.len().min(MAX_USER_AGENT_LENGTH);
+                while !user_agent.is_char_boundary(end) {
+                    end -= 1;
+                }
+                user_agent[..end].to_owned()
+            }),
+            ip: ip.map(|ip| ip.to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
@@ -0,0 +1,76 @@
+use crate::entities::redis::session::SessionId;
+use framework::redis::{RedisConnection, RedisKey};
+use hmac::{Hmac, Mac};
+use redis::AsyncCommands;
+use sha2::Sha256;
+
+/// Opaque id of a session, shown when sessions are listed and accepted to revoke one. Unlike the
+/// session id it cannot be used to authenticate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionHandle(pub String);
+
+impl SessionHandle {
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionHandleSecretKey;
+
+impl redis::ToSingleRedisArg for SessionHandleSecretKey {}
+
+impl redis::ToRedisArgs for SessionHandleSecretKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from("session_handle_secret");
+        key.write_redis_args(out);
+    }
+}
+
+/// The key session handles are derived with. It is created by the first instance that needs it
+/// and shared through Redis, so that every instance derives the same handles.
+pub struct SessionHandleSecret(Vec<u8>);
+
+impl SessionHandleSecret {
+    pub async fn load_or_create(conn: &mut RedisConnection) -> Result<Self, framework::Error> {
+        let candidate: [u8; 32] = rand::random();
+        let _: bool = conn
+            .set_nx(SessionHandleSecretKey, candidate.as_slice())
+            .await?;
+        let secret: Option<Vec<u8>> = conn.get(SessionHandleSecretKey).await?;
+        secret
+            .filter(|secret| !secret.is_empty())
+            .map(Self)
+            .ok_or_else(|| {
+                framework::Error::BusinessPanic(anyhow::anyhow!("Session handle secret is missing"))
+            })
+    }
+
+    /// The truncated HMAC-SHA256 of the session id.
+    pub fn handle(&self, session_id: SessionId) -> SessionHandle {
+        let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(&self.0) else {
+            unreachable!("HMAC accepts keys of any length")
+        };
+        mac.update(session_id.0.as_bytes());
+        SessionHandle(hex::encode(&mac.finalize().into_bytes()[..16]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn handle_does_not_reveal_the_session_id() {
+        let secret = SessionHandleSecret(vec![7; 32]);
+        let session_id = SessionId(Uuid::new_v4());
+        let handle = secret.handle(session_id);
+        assert_eq!(handle, secret.handle(session_id));
+        assert_eq!(handle.0.len(), 32);
+        assert_ne!(handle, SessionHandleSecret(vec![8; 32]).handle(session_id));
+    }
+}
@@ -0,0 +1,62 @@
+use framework::redis::{KeyValue, KeyValueRead, KeyValueWrite, RedisKey};
+use kanau::{RkyvMessageDe, RkyvMessageSer};
+use uuid::Uuid;
+
+/// Mirror of an active suspension in `auth.user_account`, so that refreshing a session does not
+/// need a database round trip.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    RkyvMessageSer,
+    RkyvMessageDe,
+)]
+pub struct SuspendedUser {
+    pub user_id: Uuid,
+    /// Unix timestamp, `None` for a permanent ban
+    pub until: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuspendedUserKey(pub Uuid);
+
+impl redis::ToSingleRedisArg for SuspendedUserKey {}
+
+impl redis::ToRedisArgs for SuspendedUserKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("user_suspended:{}", self.0));
+        key.write_redis_args(out);
+    }
+}
+
+impl KeyValue for SuspendedUser {
+    type Key = SuspendedUserKey;
+    type Value = Self;
+
+    fn key(&self) -> Self::Key {
+        SuspendedUserKey(self.user_id)
+    }
+
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+
+    fn into_value(self) -> Self::Value {
+        self
+    }
+
+    fn new(key: Self::Key, mut value: Self::Value) -> Self {
+        value.user_id = key.0;
+        value
+    }
+}
+
+impl KeyValueRead for SuspendedUser {}
+impl KeyValueWrite for SuspendedUser {}
@@ -0,0 +1,34 @@
+use framework::redis::RedisKey;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Counter of TOTP attempts of a user in the current window, stored as a plain Redis integer.
+pub struct TotpAttemptsKey(pub Uuid);
+
+impl redis::ToSingleRedisArg for TotpAttemptsKey {}
+
+impl redis::ToRedisArgs for TotpAttemptsKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("totp_attempts:{}", self.0));
+        key.write_redis_args(out);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The last TOTP time-step accepted for a user, stored as a plain Redis integer.
+pub struct TotpLastStepKey(pub Uuid);
+
+impl redis::ToSingleRedisArg for TotpLastStepKey {}
+
+impl redis::ToRedisArgs for TotpLastStepKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("totp_last_step:{}", self.0));
+        key.write_redis_args(out);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::utils::wallet::WalletKind;
+use framework::redis::{KeyValue, KeyValueRead, KeyValueWrite, RedisKey};
+use kanau::{RkyvMessageDe, RkyvMessageSer};
+use redis::AsyncCommands;
+
+/// A sign-in message handed to a wallet, waiting for its signature.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    RkyvMessageDe,
+    RkyvMessageSer,
+)]
+pub struct WalletLoginChallenge {
+    pub nonce: WalletLoginChallengeKey,
+    pub kind: WalletKind,
+    /// The address the message was issued for
+    pub address: [u8; 20],
+    /// The exact message the wallet has to sign
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+/// Random nonce of the message, which also identifies it.
+pub struct WalletLoginChallengeKey(pub [u8; 16]);
+
+impl redis::ToSingleRedisArg for WalletLoginChallengeKey {}
+
+impl redis::ToRedisArgs for WalletLoginChallengeKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("wallet_login:{}", hex::encode(self.0)));
+        key.write_redis_args(out);
+    }
+}
+
+impl WalletLoginChallenge {
+    /// Atomically take the challenge out of Redis, so that a signature logs in at most once.
+    pub async fn take(
+        conn: &mut framework::redis::RedisConnection,
+        key: WalletLoginChallengeKey,
+    ) -> Result<Option<Self>, framework::Error> {
+        use kanau::message::MessageDe;
+        let data: Option<Vec<u8>> = conn.get_del(key).await?;
+        data.map(|bytes| {
+            Self::from_bytes(&bytes).map_err(|e| framework::Error::DeserializeError(e.into()))
+        })
+        .transpose()
+    }
+}
+
+impl KeyValue for WalletLoginChallenge {
+    type Key = WalletLoginChallengeKey;
+    type Value = Self;
+
+    fn key(&self) -> Self::Key {
+        self.nonce
+    }
+
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+
+    fn into_value(self) -> Self::Value {
+        self
+    }
+
+    fn new(key: Self::Key, mut value: Self::Value) -> Self {
+        value.nonce = key;
+        value
+    }
+}
+
+impl KeyValueRead for WalletLoginChallenge {}
+impl KeyValueWrite for WalletLoginChallenge {}
@@ -0,0 +1,170 @@
+use framework::redis::{KeyValue, KeyValueRead, KeyValueWrite, RedisKey};
+use kanau::{RkyvMessageDe, RkyvMessageSer};
+use redis::AsyncCommands;
+use uuid::Uuid;
+
+/// A passkey registration waiting for the authenticator response.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    RkyvMessageDe,
+    RkyvMessageSer,
+)]
+pub struct PendingPasskeyRegistration {
+    pub user_id: PendingPasskeyRegistrationKey,
+    /// Display name chosen by the user for the new passkey
+    pub name: String,
+    /// Serialized `webauthn_rs::prelude::PasskeyRegistration`
+    pub state: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+/// Wrap the user ID as the key for [PendingPasskeyRegistration] in Redis and add the proper
+/// namespacing.
+pub struct PendingPasskeyRegistrationKey(pub Uuid);
+
+impl redis::ToSingleRedisArg for PendingPasskeyRegistrationKey {}
+
+impl redis::ToRedisArgs for PendingPasskeyRegistrationKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("passkey_registration:{}", self.0));
+        key.write_redis_args(out);
+    }
+}
+
+impl PendingPasskeyRegistration {
+    /// Atomically take the registration out of Redis, so that one challenge registers at most
+    /// one credential.
+    pub async fn take(
+        conn: &mut framework::redis::RedisConnection,
+        key: PendingPasskeyRegistrationKey,
+    ) -> Result<Option<Self>, framework::Error> {
+        use kanau::message::MessageDe;
+        let data: Option<Vec<u8>> = conn.get_del(key).await?;
+        data.map(|bytes| {
+            Self::from_bytes(&bytes).map_err(|e| framework::Error::DeserializeError(e.into()))
+        })
+        .transpose()
+    }
+}
+
+impl KeyValue for PendingPasskeyRegistration {
+    type Key = PendingPasskeyRegistrationKey;
+    type Value = Self;
+
+    fn key(&self) -> Self::Key {
+        self.user_id
+    }
+
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+
+    fn into_value(self) -> Self::Value {
+        self
+    }
+
+    fn new(key: Self::Key, mut value: Self::Value) -> Self {
+        value.user_id = key;
+        value
+    }
+}
+
+impl KeyValueRead for PendingPasskeyRegistration {}
+impl KeyValueWrite for PendingPasskeyRegistration {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+/// What a passkey assertion is going to be used for. An assertion requested for one purpose is
+/// rejected everywhere else.
+pub enum PasskeyChallengePurpose {
+    /// Passwordless login with a discoverable credential. The user is not known in advance.
+    Login,
+    /// Second factor of a login of this user
+    Mfa(Uuid),
+    /// Entering sudo mode as this user
+    Sudo(Uuid),
+}
+
+/// An assertion challenge waiting for the authenticator response.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    RkyvMessageDe,
+    RkyvMessageSer,
+)]
+pub struct PendingPasskeyChallenge {
+    pub challenge_id: PendingPasskeyChallengeKey,
+    pub purpose: PasskeyChallengePurpose,
+    /// Serialized `DiscoverableAuthentication` for [PasskeyChallengePurpose::Login], and
+    /// `PasskeyAuthentication` otherwise
+    pub state: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+/// Random ID handed to the client together with the challenge.
+pub struct PendingPasskeyChallengeKey(pub [u8; 16]);
+
+impl redis::ToSingleRedisArg for PendingPasskeyChallengeKey {}
+
+impl redis::ToRedisArgs for PendingPasskeyChallengeKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key: RedisKey = RedisKey::from(format!("passkey_challenge:{}", hex::encode(self.0)));
+        key.write_redis_args(out);
+    }
+}
+
+impl PendingPasskeyChallenge {
+    /// Atomically take the challenge out of Redis, so that it can only be answered once.
+    pub async fn take(
+        conn: &mut framework::redis::RedisConnection,
+        key: PendingPasskeyChallengeKey,
+    ) -> Result<Option<Self>, framework::Error> {
+        use kanau::message::MessageDe;
+        let data: Option<Vec<u8>> = conn.get_del(key).await?;
+        data.map(|bytes| {
+            Self::from_bytes(&bytes).map_err(|e| framework::Error::DeserializeError(e.into()))
+        })
+        .transpose()
+    }
+}
+
+impl KeyValue for PendingPasskeyChallenge {
+    type Key = PendingPasskeyChallengeKey;
+    type Value = Self;
+
+    fn key(&self) -> Self::Key {
+        self.challenge_id
+    }
+
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+
+    fn into_value(self) -> Self::Value {
+        self
+    }
+
+    fn new(key: Self::Key, mut value: Self::Value) -> Self {
+        value.challenge_id = key;
+        value
+    }
+}
+
+impl KeyValueRead for PendingPasskeyChallenge {}
+impl KeyValueWrite for PendingPasskeyChallenge {}
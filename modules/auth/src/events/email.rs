@@ -1,4 +1,5 @@
 use crate::entities::db::email_otp::EmailOtpUsage;
+use crate::utils::email_template::EmailLocale;
 
 #[derive(
     Debug,
@@ -17,6 +18,8 @@ pub struct OtpEmailSendCall {
     pub otp_usage: EmailOtpUsage,
     pub expire_after: std::time::Duration,
     pub sent_at: u64,
+    /// The language the client asked for, the configured default otherwise
+    pub locale: Option<EmailLocale>,
 }
 
 impl framework::rabbitmq::AmqpRouting for OtpEmailSendCall {
@@ -1,2 +1,3 @@
 pub mod account;
 pub mod email;
+pub mod security;
@@ -0,0 +1,40 @@
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub enum SecurityNotificationKind {
+    /// A recovery code was used to complete a login.
+    RecoveryCodeUsed {
+        /// Unused codes left afterwards
+        remaining_codes: u32,
+    },
+}
+
+/// Something happened to the account that the user should be told about, in case it was not
+/// them.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    kanau::RkyvMessageSer,
+    kanau::RkyvMessageDe,
+)]
+pub struct SecurityNotificationEvent {
+    pub user_id: Uuid,
+    pub occurred_at: u64,
+    pub kind: SecurityNotificationKind,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl framework::rabbitmq::AmqpRouting for SecurityNotificationEvent {
+    const EXCHANGE: &'static str = "auth";
+    const EXCHANGE_TYPE: framework::rabbitmq::AmqpExchangeType =
+        framework::rabbitmq::AmqpExchangeType::Direct;
+    const ROUTING_KEY: &'static str = "security_notification";
+}
+
+impl framework::rabbitmq::AmqpMessageSend for SecurityNotificationEvent {}
@@ -0,0 +1 @@
+pub mod otp_email;
@@ -0,0 +1,129 @@
+use crate::config::AuthConfig;
+use crate::events::email::OtpEmailSendCall;
+use crate::utils::email_template::render_otp_email;
+use crate::utils::mailer::Mailer;
+use crate::utils::wallet::is_wallet_email;
+use admin::utils::config_provider::LiveConfig;
+use framework::rabbitmq::AmqpMessageProcessor;
+use kanau::processor::Processor;
+use lettre::Message;
+use lettre::message::{Mailbox, MultiPart};
+use tracing::instrument;
+
+/// Render OTP emails in the language of the client and send them.
+#[derive(Clone)]
+pub struct OtpEmailHook {
+    pub auth_config: LiveConfig<AuthConfig>,
+    pub mailer: Mailer,
+}
+
+impl Processor<OtpEmailSendCall> for OtpEmailHook {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, fields(usage = ?input.otp_usage), err)]
+    async fn process(&self, input: OtpEmailSendCall) -> Result<(), framework::Error> {
+        if is_wallet_email(&input.email_address) {
+            return Ok(());
+        }
+        // A backlog of retries may outlive the code
+        let expires_at = input.sent_at.saturating_add(input.expire_after.as_secs());
+        if expires_at <= framework::now_time().assume_utc().unix_timestamp() as u64 {
+            tracing::warn!("OTP expired before its email was sent");
+            return Ok(());
+        }
+
+        let config = self.auth_config.load();
+        let delivery = &config.email_delivery;
+        let email = render_otp_email(
+            input.locale.unwrap_or(delivery.default_locale),
+            input.otp_usage,
+            &delivery.site_name,
+            &input.otp_code,
+            input.expire_after,
+        );
+        let from: Mailbox = delivery
+            .from
+            .parse()
+            .map_err(|e| framework::Error::BusinessPanic(anyhow::anyhow!("Invalid sender: {e}")))?;
+        let to: Mailbox = input
+            .email_address
+            .parse()
+            .map_err(|_| framework::Error::InvalidInput)?;
+        let message = Message::builder()
+            .from(from)
+            .to(to)
+            .subject(email.subject)
+            .multipart(MultiPart::alternative_plain_html(email.text, email.html))
+            .map_err(|e| framework::Error::BusinessPanic(e.into()))?;
+        self.mailer.send(&delivery.transport, message).await
+    }
+}
+
+impl AmqpMessageProcessor<OtpEmailSendCall> for OtpEmailHook {
+    const QUEUE: &'static str = "auth.otp_email";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{EmailDeliveryConfig, EmailTransportConfig};
+    use crate::entities::db::email_otp::EmailOtpUsage;
+    use crate::utils::email_template::EmailLocale;
+    use crate::utils::wallet::WALLET_EMAIL_DOMAIN;
+
+    fn send_call(email_address: &str, sent_at: u64) -> OtpEmailSendCall {
+        OtpEmailSendCall {
+            email_address: email_address.to_owned(),
+            otp_code: "482916".to_owned(),
+            otp_usage: EmailOtpUsage::Login,
+            expire_after: std::time::Duration::from_secs(600),
+            sent_at,
+            locale: Some(EmailLocale::En),
+        }
+    }
+
+    #[test]
+    fn otp_email_is_sent_to_file() -> anyhow::Result<()> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(send_to_file())
+    }
+
+    async fn send_to_file() -> anyhow::Result<()> {
+        let directory = std::env::temp_dir().join(format!("otp-email-{}", uuid::Uuid::new_v4()));
+        let hook = OtpEmailHook {
+            auth_config: LiveConfig::fixed(AuthConfig {
+                email_delivery: EmailDeliveryConfig {
+                    transport: EmailTransportConfig::File {
+                        directory: directory.clone(),
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            mailer: Mailer::default(),
+        };
+        let now = framework::now_time().assume_utc().unix_timestamp() as u64;
+
+        hook.process(send_call("user@example.com", now)).await?;
+        // Neither is delivered
+        hook.process(send_call("user@example.com", now - 3600))
+            .await?;
+        hook.process(send_call(&format!("0xabc@{WALLET_EMAIL_DOMAIN}"), now))
+            .await?;
+
+        let mut emails = Vec::new();
+        for entry in std::fs::read_dir(&directory)? {
+            emails.push(std::fs::read_to_string(entry?.path())?);
+        }
+        std::fs::remove_dir_all(&directory)?;
+        assert_eq!(emails.len(), 1);
+        let email = emails.concat();
+        assert!(email.contains("To: user@example.com"));
+        assert!(email.contains("Subject: Your sign-in code for Phantom Store"));
+        assert!(email.contains("482916"));
+        assert!(email.contains("text/html"));
+        Ok(())
+    }
+}
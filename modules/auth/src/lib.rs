@@ -6,6 +6,7 @@
 pub mod config;
 pub mod entities;
 pub mod events;
+pub mod hooks;
 pub mod rpc;
 pub mod services;
 pub mod utils;
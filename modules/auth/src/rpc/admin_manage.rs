@@ -0,0 +1,177 @@
+use crate::services::admin_manage::{
+    BanOrUnbanUser, ForceLogoutUser, ResetUserMfa, SearchUsers, ShowUserForAdmin,
+    UserAuthManageService,
+};
+use admin::rpc::middleware::AdminId;
+use admin::utils::rbac::{AuthenticatedAdminOperation, AuthorizationAdapter, AuthorizationLayer};
+use phantom_shop_proto::v1::auth::admin::{
+    BanOrUnbanUserRequest, ForceLogoutUserRequest, ResetUserMfaRequest, SearchUsersRequest,
+    SearchUsersResponse, ShowUserRequest, UserDetail, UserSuspension,
+};
+use phantom_shop_proto::v1::common::Empty;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+pub struct UserManageServiceImpl {
+    pub inner: UserAuthManageService,
+    pub authorization: AuthorizationAdapter,
+}
+
+impl UserManageServiceImpl {
+    pub fn new(inner: UserAuthManageService, authorization: AuthorizationLayer) -> Self {
+        Self {
+            inner,
+            authorization: authorization.into_adapter(),
+        }
+    }
+}
+
+fn parse_user_id(value: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(value).map_err(|_| Status::invalid_argument("Invalid user id"))
+}
+
+#[tonic::async_trait]
+impl phantom_shop_proto::v1::auth::admin::user_manage_service_server::UserManageService
+    for UserManageServiceImpl
+{
+    async fn search_users(
+        &self,
+        request: Request<SearchUsersRequest>,
+    ) -> Result<Response<SearchUsersResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let limit = match req.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            limit => limit.min(MAX_PAGE_SIZE),
+        };
+        let operation = SearchUsers {
+            query: req.query.trim().to_owned(),
+            limit: limit.into(),
+            offset: req.offset.into(),
+        };
+        let users = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(SearchUsersResponse {
+            users: users.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn show_user(
+        &self,
+        request: Request<ShowUserRequest>,
+    ) -> Result<Response<UserDetail>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = ShowUserForAdmin {
+            user_id: parse_user_id(&req.user_id)?,
+        };
+        let detail = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(UserDetail {
+            account: Some(detail.account.into()),
+            oauth_accounts: detail.oauth_accounts.into_iter().map(Into::into).collect(),
+            has_totp: detail.has_totp,
+            sessions: detail.sessions.into_iter().map(Into::into).collect(),
+            suspension: detail.suspension.map(|suspension| UserSuspension {
+                suspended_at: Some(suspension.suspended_at.into()),
+                suspended_by: suspension.suspended_by.map(|id| id.to_string()),
+                reason: suspension.reason,
+                until: suspension.until.map(Into::into),
+            }),
+        }))
+    }
+
+    async fn force_logout_user(
+        &self,
+        request: Request<ForceLogoutUserRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = ForceLogoutUser {
+            user_id: parse_user_id(&req.user_id)?,
+        };
+        self.authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn reset_user_mfa(
+        &self,
+        request: Request<ResetUserMfaRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        if req.verification_note.trim().is_empty() {
+            return Err(Status::invalid_argument("Missing verification note"));
+        }
+        let operation = ResetUserMfa {
+            user_id: parse_user_id(&req.user_id)?,
+            verification_note: req.verification_note,
+        };
+        self.authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn ban_or_unban_user(
+        &self,
+        request: Request<BanOrUnbanUserRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = BanOrUnbanUser {
+            admin_id: admin_id.into_inner(),
+            user_id: parse_user_id(&req.user_id)?,
+            banned: req.banned,
+            reason: req.reason,
+            until: req
+                .until
+                .map(time::PrimitiveDateTime::try_from)
+                .transpose()
+                .map_err(|_| Status::invalid_argument("Invalid suspension end"))?,
+        };
+        self.authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(Empty {}))
+    }
+}
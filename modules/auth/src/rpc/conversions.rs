@@ -1,10 +1,13 @@
 //! Type conversions between service/entity types and proto types.
 
 use crate::entities::db::oauth_account::OAuthAccount;
+use crate::entities::db::user_account::{UserAccount, UserSuspension};
+use crate::entities::db::webauthn_credential::WebauthnCredential;
+use crate::entities::redis::session::LoginMethod;
 use crate::services::email_provider::{
     ChangeEmailAddressResult as ServiceChangeEmailAddressResult,
     ChangePasswordResult as ServiceChangePasswordResult, EmailLoginResult, RegisterUserResult,
-    RemovePasswordResult as ServiceRemovePasswordResult, ResetPasswordResult,
+    RemovePasswordResult as ServiceRemovePasswordResult, ResetPasswordResult, SendLoginEmailResult,
     SendPasswordResetEmailResult, SendRegisterEmailResult,
 };
 use crate::services::mfa::{FinishConfiguringTotpResult, VerifyAndEnterSudoResult};
@@ -12,12 +15,24 @@ use crate::services::oauth_provider::{
     CreateOAuthChallengeResult, LinkOAuthAccountResult, OAuthLoginResult,
     UnlinkOAuthAccountResult as ServiceUnlinkOAuthAccountResult,
 };
+use crate::services::passkey::{
+    FinishPasskeyRegistrationResult, LoginWithPasskeyResult, PasskeyChallenge,
+};
+use crate::services::session::ListedSession;
+use crate::services::token::TokenPair;
+use crate::services::wallet_login::WalletLoginResult;
+use crate::utils::oauth::oidc::OidcProviderId;
 use crate::utils::oauth::providers::OAuthProviderName;
 use phantom_shop_proto::v1::auth::common::{
     EmailSendResult, LoginResult, OAuthAccount as ProtoOAuthAccount,
-    OAuthProviderName as ProtoOAuthProviderName,
+    OAuthProviderName as ProtoOAuthProviderName, Passkey as ProtoPasskey,
+    PasskeyAssertion as ProtoPasskeyAssertion, PasskeyChallenge as ProtoPasskeyChallenge,
+    SessionInfo, SessionLoginMethod, SuspensionNotice, TokenPair as ProtoTokenPair,
+    UserAccount as ProtoUserAccount,
 };
 use phantom_shop_proto::v1::auth::user as user_proto;
+use tonic::Status;
+use webauthn_rs::prelude::PublicKeyCredential;
 
 impl From<OAuthProviderName> for ProtoOAuthProviderName {
     fn from(name: OAuthProviderName) -> Self {
@@ -26,17 +41,74 @@ impl From<OAuthProviderName> for ProtoOAuthProviderName {
             OAuthProviderName::Microsoft => ProtoOAuthProviderName::OauthProviderNameMicrosoft,
             OAuthProviderName::Github => ProtoOAuthProviderName::OauthProviderNameGithub,
             OAuthProviderName::Discord => ProtoOAuthProviderName::OauthProviderNameDiscord,
+            OAuthProviderName::Oidc(_) => ProtoOAuthProviderName::OauthProviderNameOidc,
         }
     }
 }
 
-impl From<ProtoOAuthProviderName> for OAuthProviderName {
-    fn from(name: ProtoOAuthProviderName) -> Self {
-        match name {
-            ProtoOAuthProviderName::OauthProviderNameGoogle => OAuthProviderName::Google,
-            ProtoOAuthProviderName::OauthProviderNameMicrosoft => OAuthProviderName::Microsoft,
-            ProtoOAuthProviderName::OauthProviderNameGithub => OAuthProviderName::Github,
-            ProtoOAuthProviderName::OauthProviderNameDiscord => OAuthProviderName::Discord,
+/// The provider named by a request. OpenID Connect providers are named by `oidc_provider`.
+pub(super) fn parse_oauth_provider_name(
+    name: ProtoOAuthProviderName,
+    oidc_provider: Option<&str>,
+) -> Result<OAuthProviderName, Status> {
+    match name {
+        ProtoOAuthProviderName::OauthProviderNameGoogle => Ok(OAuthProviderName::Google),
+        ProtoOAuthProviderName::OauthProviderNameMicrosoft => Ok(OAuthProviderName::Microsoft),
+        ProtoOAuthProviderName::OauthProviderNameGithub => Ok(OAuthProviderName::Github),
+        ProtoOAuthProviderName::OauthProviderNameDiscord => Ok(OAuthProviderName::Discord),
+        ProtoOAuthProviderName::OauthProviderNameOidc => oidc_provider
+            .and_then(OidcProviderId::new)
+            .map(OAuthProviderName::Oidc)
+            .ok_or_else(|| Status::invalid_argument("Invalid OpenID Connect provider")),
+    }
+}
+
+/// The `oidc_provider` field that goes with the provider name.
+fn oidc_provider(name: OAuthProviderName) -> Option<String> {
+    match name {
+        OAuthProviderName::Oidc(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+impl From<ListedSession> for SessionInfo {
+    fn from(listed: ListedSession) -> Self {
+        let ListedSession { handle, session } = listed;
+        let oidc_provider = match session.login_method {
+            LoginMethod::OAuth(provider) => oidc_provider(provider),
+            _ => None,
+        };
+        let (login_method, oauth_provider) = match session.login_method {
+            LoginMethod::EmailOtp => (SessionLoginMethod::EmailOtp, None),
+            LoginMethod::EmailPassword => (SessionLoginMethod::EmailPassword, None),
+            LoginMethod::OAuth(provider) => (
+                SessionLoginMethod::Oauth,
+                Some(ProtoOAuthProviderName::from(provider).into()),
+            ),
+            LoginMethod::Registration => (SessionLoginMethod::Registration, None),
+            LoginMethod::PasswordReset => (SessionLoginMethod::PasswordReset, None),
+            LoginMethod::Passkey => (SessionLoginMethod::Passkey, None),
+            LoginMethod::Wallet => (SessionLoginMethod::Wallet, None),
+        };
+        SessionInfo {
+            session_id: handle.into_inner(),
+            user_agent: session.client.user_agent,
+            ip: session.client.ip,
+            created_at: session.created_at as i64,
+            last_refreshed: session.last_refreshed as i64,
+            login_method: login_method.into(),
+            oauth_provider,
+            oidc_provider,
+        }
+    }
+}
+
+impl From<TokenPair> for ProtoTokenPair {
+    fn from(tokens: TokenPair) -> Self {
+        ProtoTokenPair {
+            access_token: tokens.access_token.into_inner(),
+            refresh_token: tokens.refresh_token.into_inner(),
+            access_token_expires_at: tokens.access_token_expires_at as i64,
         }
     }
 }
@@ -47,12 +119,62 @@ impl From<OAuthAccount> for ProtoOAuthAccount {
             id: account.id,
             user_id: account.user_id.to_string(),
             provider_name: ProtoOAuthProviderName::from(account.provider_name).into(),
+            oidc_provider: oidc_provider(account.provider_name),
             registered_at: account.registered_at.assume_utc().unix_timestamp(),
             token_updated_at: account.token_updated_at.assume_utc().unix_timestamp(),
         }
     }
 }
 
+impl From<UserAccount> for ProtoUserAccount {
+    fn from(account: UserAccount) -> Self {
+        ProtoUserAccount {
+            id: account.id.to_string(),
+            name: account.name.unwrap_or_default(),
+            email: account.email,
+            created_at: account.created_at.assume_utc().unix_timestamp(),
+            updated_at: account.updated_at.assume_utc().unix_timestamp(),
+        }
+    }
+}
+
+impl From<WebauthnCredential> for ProtoPasskey {
+    fn from(credential: WebauthnCredential) -> Self {
+        ProtoPasskey {
+            id: credential.id.to_string(),
+            name: credential.name,
+            created_at: credential.created_at.assume_utc().unix_timestamp(),
+            last_used_at: credential
+                .last_used_at
+                .map(|at| at.assume_utc().unix_timestamp()),
+        }
+    }
+}
+
+impl TryFrom<PasskeyChallenge> for ProtoPasskeyChallenge {
+    type Error = Status;
+    fn try_from(challenge: PasskeyChallenge) -> Result<Self, Status> {
+        Ok(ProtoPasskeyChallenge {
+            challenge_id: challenge.challenge_id.to_vec(),
+            options_json: serde_json::to_string(&challenge.options)
+                .map_err(|e| Status::internal(e.to_string()))?,
+        })
+    }
+}
+
+/// Split an assertion into the challenge ID and the parsed credential.
+pub(super) fn parse_passkey_assertion(
+    assertion: ProtoPasskeyAssertion,
+) -> Result<([u8; 16], Box<PublicKeyCredential>), Status> {
+    let challenge_id: [u8; 16] = assertion
+        .challenge_id
+        .try_into()
+        .map_err(|_| Status::invalid_argument("Invalid challenge ID length"))?;
+    let credential = serde_json::from_str(&assertion.credential_json)
+        .map_err(|_| Status::invalid_argument("Malformed passkey credential"))?;
+    Ok((challenge_id, Box::new(credential)))
+}
+
 impl From<ServiceChangePasswordResult> for user_proto::ChangePasswordResult {
     fn from(result: ServiceChangePasswordResult) -> Self {
         match result {
@@ -127,6 +249,26 @@ impl From<SendPasswordResetEmailResult> for EmailSendResult {
     }
 }
 
+impl From<SendLoginEmailResult> for EmailSendResult {
+    fn from(result: SendLoginEmailResult) -> Self {
+        match result {
+            SendLoginEmailResult::MaybeSent => EmailSendResult::Sent,
+            SendLoginEmailResult::InvalidEmailAddress => EmailSendResult::NotAllowed,
+        }
+    }
+}
+
+impl From<UserSuspension> for SuspensionNotice {
+    fn from(suspension: UserSuspension) -> Self {
+        SuspensionNotice {
+            reason: suspension.reason,
+            until: suspension
+                .until
+                .map(|until| until.assume_utc().unix_timestamp()),
+        }
+    }
+}
+
 impl From<SendRegisterEmailResult> for EmailSendResult {
     fn from(result: SendRegisterEmailResult) -> Self {
         match result {
@@ -154,15 +296,112 @@ impl From<LinkOAuthAccountResult> for user_proto::OAuthAccountLinkingResult {
     }
 }
 
-impl From<FinishConfiguringTotpResult> for user_proto::FinishTotpSetupResult {
+impl From<FinishConfiguringTotpResult> for user_proto::FinishTotpSetupResponse {
     fn from(result: FinishConfiguringTotpResult) -> Self {
-        match result {
-            FinishConfiguringTotpResult::Success => user_proto::FinishTotpSetupResult::Success,
+        let (result, recovery_codes) = match result {
+            FinishConfiguringTotpResult::Success(recovery_codes) => {
+                (user_proto::FinishTotpSetupResult::Success, recovery_codes)
+            }
             FinishConfiguringTotpResult::InvalidCode => {
-                user_proto::FinishTotpSetupResult::InvalidCode
+                (user_proto::FinishTotpSetupResult::InvalidCode, Vec::new())
+            }
+            FinishConfiguringTotpResult::Duplicate => {
+                (user_proto::FinishTotpSetupResult::Duplicate, Vec::new())
+            }
+            FinishConfiguringTotpResult::Expired => {
+                (user_proto::FinishTotpSetupResult::Expired, Vec::new())
             }
-            FinishConfiguringTotpResult::Duplicate => user_proto::FinishTotpSetupResult::Duplicate,
-            FinishConfiguringTotpResult::Expired => user_proto::FinishTotpSetupResult::Expired,
+        };
+        user_proto::FinishTotpSetupResponse {
+            result: result.into(),
+            recovery_codes,
+        }
+    }
+}
+
+impl From<FinishPasskeyRegistrationResult> for user_proto::FinishPasskeyRegistrationResponse {
+    fn from(result: FinishPasskeyRegistrationResult) -> Self {
+        let (result, passkey) = match result {
+            FinishPasskeyRegistrationResult::Success(credential) => (
+                user_proto::FinishPasskeyRegistrationResult::Success,
+                Some((*credential).into()),
+            ),
+            FinishPasskeyRegistrationResult::InvalidCredential => (
+                user_proto::FinishPasskeyRegistrationResult::InvalidCredential,
+                None,
+            ),
+            FinishPasskeyRegistrationResult::Duplicate => {
+                (user_proto::FinishPasskeyRegistrationResult::Duplicate, None)
+            }
+            FinishPasskeyRegistrationResult::Expired => {
+                (user_proto::FinishPasskeyRegistrationResult::Expired, None)
+            }
+        };
+        user_proto::FinishPasskeyRegistrationResponse {
+            result: result.into(),
+            passkey,
+        }
+    }
+}
+
+impl From<WalletLoginResult> for user_proto::WalletLoginResponse {
+    fn from(result: WalletLoginResult) -> Self {
+        match result {
+            WalletLoginResult::LoggedIn(session_id) => user_proto::WalletLoginResponse {
+                login_result: LoginResult::Success.into(),
+                session_id: Some(session_id.to_ascii_string()),
+                mfa_token: None,
+                suspension: None,
+                tokens: None,
+            },
+            WalletLoginResult::RequiredMfa(mfa_token) => user_proto::WalletLoginResponse {
+                login_result: LoginResult::MfaRequired.into(),
+                session_id: None,
+                mfa_token: Some(mfa_token.to_vec()),
+                suspension: None,
+                tokens: None,
+            },
+            WalletLoginResult::Suspended(suspension) => user_proto::WalletLoginResponse {
+                login_result: LoginResult::Blocked.into(),
+                session_id: None,
+                mfa_token: None,
+                suspension: Some(suspension.into()),
+                tokens: None,
+            },
+            WalletLoginResult::InvalidSignature | WalletLoginResult::Expired => {
+                user_proto::WalletLoginResponse {
+                    login_result: LoginResult::AccountNotFound.into(),
+                    session_id: None,
+                    mfa_token: None,
+                    suspension: None,
+                    tokens: None,
+                }
+            }
+        }
+    }
+}
+
+impl From<LoginWithPasskeyResult> for user_proto::PasskeyLoginResponse {
+    fn from(result: LoginWithPasskeyResult) -> Self {
+        match result {
+            LoginWithPasskeyResult::Success(session_id) => user_proto::PasskeyLoginResponse {
+                login_result: LoginResult::Success.into(),
+                session_id: Some(session_id.to_ascii_string()),
+                suspension: None,
+                tokens: None,
+            },
+            LoginWithPasskeyResult::InvalidCredential => user_proto::PasskeyLoginResponse {
+                login_result: LoginResult::AccountNotFound.into(),
+                session_id: None,
+                suspension: None,
+                tokens: None,
+            },
+            LoginWithPasskeyResult::Suspended(suspension) => user_proto::PasskeyLoginResponse {
+                login_result: LoginResult::Blocked.into(),
+                session_id: None,
+                suspension: Some(suspension.into()),
+                tokens: None,
+            },
         }
     }
 }
@@ -241,12 +480,14 @@ impl From<RegisterUserResult> for user_proto::RegisterEmailAccountResponse {
                 login_result: LoginResult::Success.into(),
                 user_account: None,
                 session_id: None,
+                tokens: None,
             },
             RegisterUserResult::RegisteredWithSession(session_id) => {
                 user_proto::RegisterEmailAccountResponse {
                     login_result: LoginResult::Success.into(),
                     user_account: None,
                     session_id: Some(session_id.to_ascii_string()),
+                    tokens: None,
                 }
             }
         }
@@ -259,22 +500,79 @@ impl From<EmailLoginResult> for user_proto::EmailPasswordLoginResponse {
             EmailLoginResult::Success(session_id) => user_proto::EmailPasswordLoginResponse {
                 login_result: LoginResult::Success.into(),
                 session_id: Some(session_id.to_ascii_string()),
+                tokens: None,
                 mfa_token: None,
+                suspension: None,
             },
             EmailLoginResult::WrongCredential => user_proto::EmailPasswordLoginResponse {
                 login_result: LoginResult::AccountNotFound.into(),
                 session_id: None,
+                tokens: None,
                 mfa_token: None,
+                suspension: None,
             },
             EmailLoginResult::MethodNotAvailable => user_proto::EmailPasswordLoginResponse {
                 login_result: LoginResult::AccountNotFound.into(),
                 session_id: None,
+                tokens: None,
                 mfa_token: None,
+                suspension: None,
             },
             EmailLoginResult::MfaRequired(mfa_token) => user_proto::EmailPasswordLoginResponse {
                 login_result: LoginResult::MfaRequired.into(),
                 session_id: None,
+                tokens: None,
+                mfa_token: Some(mfa_token.to_vec()),
+                suspension: None,
+            },
+            EmailLoginResult::Suspended(suspension) => user_proto::EmailPasswordLoginResponse {
+                login_result: LoginResult::Blocked.into(),
+                session_id: None,
+                tokens: None,
+                mfa_token: None,
+                suspension: Some(suspension.into()),
+            },
+        }
+    }
+}
+
+impl From<EmailLoginResult> for user_proto::EmailOtpLoginResponse {
+    fn from(result: EmailLoginResult) -> Self {
+        match result {
+            EmailLoginResult::Success(session_id) => user_proto::EmailOtpLoginResponse {
+                login_result: LoginResult::Success.into(),
+                session_id: Some(session_id.to_ascii_string()),
+                tokens: None,
+                mfa_token: None,
+                suspension: None,
+            },
+            EmailLoginResult::WrongCredential => user_proto::EmailOtpLoginResponse {
+                login_result: LoginResult::AccountNotFound.into(),
+                session_id: None,
+                tokens: None,
+                mfa_token: None,
+                suspension: None,
+            },
+            EmailLoginResult::MethodNotAvailable => user_proto::EmailOtpLoginResponse {
+                login_result: LoginResult::AccountNotFound.into(),
+                session_id: None,
+                tokens: None,
+                mfa_token: None,
+                suspension: None,
+            },
+            EmailLoginResult::MfaRequired(mfa_token) => user_proto::EmailOtpLoginResponse {
+                login_result: LoginResult::MfaRequired.into(),
+                session_id: None,
+                tokens: None,
                 mfa_token: Some(mfa_token.to_vec()),
+                suspension: None,
+            },
+            EmailLoginResult::Suspended(suspension) => user_proto::EmailOtpLoginResponse {
+                login_result: LoginResult::Blocked.into(),
+                session_id: None,
+                tokens: None,
+                mfa_token: None,
+                suspension: Some(suspension.into()),
             },
         }
     }
@@ -282,31 +580,38 @@ impl From<EmailLoginResult> for user_proto::EmailPasswordLoginResponse {
 
 impl From<ResetPasswordResult> for user_proto::ResetPasswordResponse {
     fn from(result: ResetPasswordResult) -> Self {
+        let failed = user_proto::ResetPasswordResponse {
+            success: false,
+            session_id: None,
+            tokens: None,
+            mfa_token: None,
+            suspension: None,
+        };
+        let succeeded = user_proto::ResetPasswordResponse {
+            success: true,
+            ..failed.clone()
+        };
         match result {
-            crate::services::email_provider::ResetPasswordResult::Success => {
+            ResetPasswordResult::Success => succeeded,
+            ResetPasswordResult::SuccessWithSession(session_id) => {
                 user_proto::ResetPasswordResponse {
-                    success: true,
-                    session_id: None,
+                    session_id: Some(session_id.to_ascii_string()),
+                    ..succeeded
                 }
             }
-            crate::services::email_provider::ResetPasswordResult::SuccessWithSession(
-                session_id,
-            ) => user_proto::ResetPasswordResponse {
-                success: true,
-                session_id: Some(session_id.to_ascii_string()),
-            },
-            crate::services::email_provider::ResetPasswordResult::InvalidOtp => {
+            ResetPasswordResult::SuccessWithMfaRequired(mfa_token) => {
                 user_proto::ResetPasswordResponse {
-                    success: false,
-                    session_id: None,
+                    mfa_token: Some(mfa_token.to_vec()),
+                    ..succeeded
                 }
             }
-            crate::services::email_provider::ResetPasswordResult::AccountNotFound => {
+            ResetPasswordResult::SuccessButSuspended(suspension) => {
                 user_proto::ResetPasswordResponse {
-                    success: false,
-                    session_id: None,
+                    suspension: Some(suspension.into()),
+                    ..succeeded
                 }
             }
+            ResetPasswordResult::InvalidOtp | ResetPasswordResult::AccountNotFound => failed,
         }
     }
 }
@@ -319,7 +624,9 @@ impl From<OAuthLoginResult> for user_proto::OAuthCallbackResponse {
                     user_proto::OAuthCallbackLoginBranchResult {
                         login_result: LoginResult::Success.into(),
                         session_id: Some(session_id.to_ascii_string()),
+                        tokens: None,
                         mfa_token: None,
+                        suspension: None,
                     },
                 )),
             },
@@ -328,7 +635,20 @@ impl From<OAuthLoginResult> for user_proto::OAuthCallbackResponse {
                     user_proto::OAuthCallbackLoginBranchResult {
                         login_result: LoginResult::MfaRequired.into(),
                         session_id: None,
+                        tokens: None,
                         mfa_token: Some(mfa_token.to_vec()),
+                        suspension: None,
+                    },
+                )),
+            },
+            OAuthLoginResult::Suspended(suspension) => user_proto::OAuthCallbackResponse {
+                result: Some(user_proto::o_auth_callback_response::Result::LoginResult(
+                    user_proto::OAuthCallbackLoginBranchResult {
+                        login_result: LoginResult::Blocked.into(),
+                        session_id: None,
+                        tokens: None,
+                        mfa_token: None,
+                        suspension: Some(suspension.into()),
                     },
                 )),
             },
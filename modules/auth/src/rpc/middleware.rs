@@ -1,5 +1,8 @@
-use crate::entities::redis::session::SessionId;
+use crate::entities::redis::session::{SessionClient, SessionId};
 use crate::services::session::{RefreshSession, SessionService};
+use crate::services::token::{TokenService, VerifyAccessToken};
+use crate::utils::email_template::EmailLocale;
+use crate::utils::jwt::AccessToken;
 use kanau::processor::Processor;
 use std::sync::Arc;
 use tonic::codegen::BoxFuture;
@@ -8,11 +11,22 @@ use uuid::Uuid;
 #[derive(Clone)]
 pub struct UserAuthLayer {
     session_service: Arc<SessionService>,
+    token_service: Option<Arc<TokenService>>,
 }
 
 impl UserAuthLayer {
     pub fn new(session_service: Arc<SessionService>) -> Self {
-        Self { session_service }
+        Self {
+            session_service,
+            token_service: None,
+        }
+    }
+
+    /// Also accept access tokens in the `authorization` header, verified without reading the
+    /// session.
+    pub fn with_token_service(mut self, token_service: Arc<TokenService>) -> Self {
+        self.token_service = Some(token_service);
+        self
     }
 }
 
@@ -22,6 +36,7 @@ impl<S> tower::Layer<S> for UserAuthLayer {
         UserAuthMiddleware {
             inner,
             session_service: self.session_service.clone(),
+            token_service: self.token_service.clone(),
         }
     }
 }
@@ -30,6 +45,7 @@ impl<S> tower::Layer<S> for UserAuthLayer {
 pub struct UserAuthMiddleware<S> {
     inner: S,
     session_service: Arc<SessionService>,
+    token_service: Option<Arc<TokenService>>,
 }
 
 impl<S, ReqBody, ResBody> tower::Service<tonic::codegen::http::Request<ReqBody>>
@@ -57,11 +73,17 @@ where
 
     fn call(&mut self, mut req: tonic::codegen::http::Request<ReqBody>) -> Self::Future {
         let session_service = self.session_service.clone();
+        let token_service = self.token_service.clone();
         let inner_clone = self.inner.clone();
         let mut inner = std::mem::replace(&mut self.inner, inner_clone);
         Box::pin(async move {
-            if let Ok(user_id) = user_auth(req.headers(), session_service).await {
+            let authenticated = match (bearer_token(req.headers()), token_service) {
+                (Some(token), Some(token_service)) => token_auth(token, token_service).await,
+                _ => user_auth(req.headers(), session_service).await,
+            };
+            if let Ok((user_id, session_id)) = authenticated {
                 req.extensions_mut().insert(user_id);
+                req.extensions_mut().insert(session_id);
             }
             inner.call(req).await
         })
@@ -72,6 +94,7 @@ where
 pub struct UserId(pub Uuid);
 
 pub const SESSION_ID_HEADER: &str = "x-user-authorization";
+pub const ACCESS_TOKEN_HEADER: &str = "authorization";
 
 impl UserId {
     pub fn into_inner(self) -> Uuid {
@@ -90,10 +113,59 @@ impl UserId {
     }
 }
 
+/// The device making a request, recorded on the sessions it creates.
+pub fn session_client<T>(req: &tonic::Request<T>) -> SessionClient {
+    let user_agent = req
+        .metadata()
+        .get("user-agent")
+        .and_then(|v| v.to_str().ok());
+    let ip = admin::rpc::middleware::client_ip(&req.metadata().clone().into_headers())
+        .or_else(|| req.remote_addr().map(|addr| addr.ip()));
+    SessionClient::new(user_agent, ip)
+}
+
+/// The email language the client asked for with `accept-language`.
+pub fn request_locale<T>(req: &tonic::Request<T>) -> Option<EmailLocale> {
+    req.metadata()
+        .get("accept-language")
+        .and_then(|v| v.to_str().ok())
+        .and_then(EmailLocale::from_accept_language)
+}
+
+/// The session the request is authenticated with, by session id or by access token.
+pub fn current_session_id<T>(req: &tonic::Request<T>) -> Result<SessionId, tonic::Status> {
+    req.extensions()
+        .get::<SessionId>()
+        .copied()
+        .ok_or_else(|| tonic::Status::unauthenticated("Missing Identity"))
+}
+
+fn bearer_token(headers: &tonic::codegen::http::HeaderMap) -> Option<AccessToken> {
+    headers
+        .get(ACCESS_TOKEN_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(AccessToken::new)
+}
+
+async fn token_auth(
+    token: AccessToken,
+    token_service: Arc<TokenService>,
+) -> Result<(UserId, SessionId), tonic::Status> {
+    let claims = token_service
+        .process(VerifyAccessToken {
+            access_token: token,
+        })
+        .await
+        .map_err(|_| tonic::Status::unauthenticated("Invalid access token"))?
+        .ok_or_else(|| tonic::Status::unauthenticated("Invalid access token"))?;
+    Ok((UserId(claims.sub), SessionId(claims.sid)))
+}
+
 async fn user_auth(
     headers: &tonic::codegen::http::HeaderMap,
     session_service: Arc<SessionService>,
-) -> Result<UserId, tonic::Status> {
+) -> Result<(UserId, SessionId), tonic::Status> {
     let header = headers
         .get(SESSION_ID_HEADER)
         .and_then(|h| h.to_str().ok())
@@ -106,5 +178,5 @@ async fn user_auth(
         .process(RefreshSession { session_id })
         .await
         .map_err(|_| tonic::Status::unauthenticated("Invalid session"))?;
-    Ok(UserId(session.user_id))
+    Ok((UserId(session.user_id), session_id))
 }
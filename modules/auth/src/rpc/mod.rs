@@ -1,5 +1,7 @@
+pub mod admin_manage;
 mod conversions;
 pub mod middleware;
+pub mod passkey;
 pub mod session;
 pub mod sudo;
 pub mod totp;
@@ -0,0 +1,123 @@
+use crate::rpc::middleware::UserId;
+use crate::services::mfa::{MfaService, StartConfiguringPasskey};
+use crate::services::passkey::{
+    FinishPasskeyRegistration, ListPasskeys, PasskeyService, RemovePasskey,
+};
+use kanau::processor::Processor;
+use phantom_shop_proto::v1::auth::user as user_proto;
+use phantom_shop_proto::v1::common::Empty;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+pub struct PasskeyServiceImpl {
+    pub mfa_service: MfaService,
+    pub passkey_service: PasskeyService,
+}
+
+impl PasskeyServiceImpl {
+    pub fn new(mfa_service: MfaService, passkey_service: PasskeyService) -> Self {
+        Self {
+            mfa_service,
+            passkey_service,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl user_proto::passkey_service_server::PasskeyService for PasskeyServiceImpl {
+    async fn list_passkeys(
+        &self,
+        request: Request<Empty>,
+    ) -> Result<Response<user_proto::ListPasskeysResponse>, Status> {
+        let user_id = UserId::read_from_request(&request)?;
+
+        let passkeys = self
+            .passkey_service
+            .process(ListPasskeys {
+                user_id: user_id.into_inner(),
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(user_proto::ListPasskeysResponse {
+            passkeys: passkeys.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn remove_passkey(
+        &self,
+        request: Request<user_proto::RemovePasskeyRequest>,
+    ) -> Result<Response<user_proto::RemovePasskeyResponse>, Status> {
+        let (user_id, req) = UserId::from_request(request)?;
+        let id =
+            Uuid::parse_str(&req.id).map_err(|_| Status::invalid_argument("Invalid passkey ID"))?;
+
+        let removed = self
+            .passkey_service
+            .process(RemovePasskey {
+                user_id: user_id.into_inner(),
+                id,
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(user_proto::RemovePasskeyResponse { removed }))
+    }
+
+    async fn start_passkey_registration(
+        &self,
+        request: Request<user_proto::StartPasskeyRegistrationRequest>,
+    ) -> Result<Response<user_proto::StartPasskeyRegistrationResponse>, Status> {
+        let (user_id, req) = UserId::from_request(request)?;
+
+        let sudo_token: [u8; 16] = req
+            .sudo_token
+            .ok_or_else(|| Status::invalid_argument("Missing sudo token"))?
+            .token
+            .try_into()
+            .map_err(|_| Status::invalid_argument("Invalid sudo token length"))?;
+
+        let options = self
+            .mfa_service
+            .process(StartConfiguringPasskey {
+                user_id: user_id.into_inner(),
+                sudo_token,
+                name: req.name,
+            })
+            .await
+            .map_err(|e| match e {
+                framework::Error::PermissionsDenied => {
+                    Status::permission_denied("Sudo verification failed")
+                }
+                framework::Error::InvalidInput => Status::invalid_argument("Invalid passkey name"),
+                _ => Status::internal(e.to_string()),
+            })?;
+
+        Ok(Response::new(
+            user_proto::StartPasskeyRegistrationResponse {
+                options_json: serde_json::to_string(&options)
+                    .map_err(|e| Status::internal(e.to_string()))?,
+            },
+        ))
+    }
+
+    async fn finish_passkey_registration(
+        &self,
+        request: Request<user_proto::FinishPasskeyRegistrationRequest>,
+    ) -> Result<Response<user_proto::FinishPasskeyRegistrationResponse>, Status> {
+        let (user_id, req) = UserId::from_request(request)?;
+        let credential = serde_json::from_str(&req.credential_json)
+            .map_err(|_| Status::invalid_argument("Malformed passkey credential"))?;
+
+        let result = self
+            .passkey_service
+            .process(FinishPasskeyRegistration {
+                user_id: user_id.into_inner(),
+                credential: Box::new(credential),
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(result.into()))
+    }
+}
@@ -1,7 +1,13 @@
-use crate::entities::redis::session::SessionId;
-use crate::rpc::middleware::SESSION_ID_HEADER;
-use crate::services::session::{SessionService as InnerSessionService, TerminateSession};
+use crate::entities::redis::session_handle::SessionHandle;
+use crate::rpc::middleware::{UserId, current_session_id};
+use crate::services::session::{
+    FindSessionHandle, ListUserSessionsWithHandles, RevokeUserSession,
+    SessionService as InnerSessionService, TerminateOtherUserSessions, TerminateSession,
+};
 use kanau::processor::Processor;
+use phantom_shop_proto::v1::auth::user::{
+    ListSessionsResponse, RevokeOtherSessionsResponse, RevokeSessionRequest, RevokeSessionResponse,
+};
 use phantom_shop_proto::v1::common::Empty;
 use tonic::{Request, Response, Status};
 
@@ -23,15 +29,7 @@ impl phantom_shop_proto::v1::auth::user::session_service_server::SessionService
         &self,
         request: Request<Empty>,
     ) -> Result<Response<Empty>, Status> {
-        // Extract session ID from the authorization header
-        let session_id_str = request
-            .metadata()
-            .get(SESSION_ID_HEADER)
-            .and_then(|v| v.to_str().ok())
-            .ok_or_else(|| Status::unauthenticated("Missing authorization header"))?;
-
-        let session_id = SessionId::try_from_ascii_string(session_id_str)
-            .map_err(|_| Status::unauthenticated("Invalid session id format"))?;
+        let session_id = current_session_id(&request)?;
 
         self.session_service
             .process(TerminateSession { session_id })
@@ -40,4 +38,66 @@ impl phantom_shop_proto::v1::auth::user::session_service_server::SessionService
 
         Ok(Response::new(Empty {}))
     }
+
+    async fn list_sessions(
+        &self,
+        request: Request<Empty>,
+    ) -> Result<Response<ListSessionsResponse>, Status> {
+        let session_id = current_session_id(&request)?;
+        let (user_id, _) = UserId::from_request(request)?;
+
+        let sessions = self
+            .session_service
+            .process(ListUserSessionsWithHandles {
+                user_id: user_id.into_inner(),
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let current = self
+            .session_service
+            .process(FindSessionHandle { session_id })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(ListSessionsResponse {
+            sessions: sessions.into_iter().map(Into::into).collect(),
+            current_session_id: current.into_inner(),
+        }))
+    }
+
+    async fn revoke_session(
+        &self,
+        request: Request<RevokeSessionRequest>,
+    ) -> Result<Response<RevokeSessionResponse>, Status> {
+        let (user_id, req) = UserId::from_request(request)?;
+        let revoked = self
+            .session_service
+            .process(RevokeUserSession {
+                user_id: user_id.into_inner(),
+                handle: SessionHandle(req.session_id),
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(RevokeSessionResponse { revoked }))
+    }
+
+    async fn revoke_other_sessions(
+        &self,
+        request: Request<Empty>,
+    ) -> Result<Response<RevokeOtherSessionsResponse>, Status> {
+        let current_session_id = current_session_id(&request)?;
+        let (user_id, _) = UserId::from_request(request)?;
+
+        let revoked_count = self
+            .session_service
+            .process(TerminateOtherUserSessions {
+                user_id: user_id.into_inner(),
+                current_session_id,
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(RevokeOtherSessionsResponse { revoked_count }))
+    }
 }
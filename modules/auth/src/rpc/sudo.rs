@@ -1,7 +1,8 @@
+use crate::rpc::conversions::parse_passkey_assertion;
 use crate::rpc::middleware::UserId;
 use crate::services::mfa::{
-    ListSudoMethods as ServiceListSudoMethods, MfaService, SudoMethod, SudoVerificationMethod,
-    VerifyAndEnterSudo,
+    ListSudoMethods as ServiceListSudoMethods, MfaService, StartPasskeySudo, SudoMethod,
+    SudoVerificationMethod, VerifyAndEnterSudo,
 };
 use kanau::processor::Processor;
 use phantom_shop_proto::v1::auth::user as user_proto;
@@ -46,10 +47,12 @@ impl phantom_shop_proto::v1::auth::user::sudo_service_server::SudoService for Su
 
         let has_totp = methods.contains(&SudoMethod::Totp);
         let has_email_otp = methods.contains(&SudoMethod::Email);
+        let has_passkey = methods.contains(&SudoMethod::Passkey);
 
         Ok(Response::new(user_proto::ListSudoMethodsResponse {
             has_totp,
             has_email_otp,
+            has_passkey,
         }))
     }
 
@@ -66,9 +69,18 @@ impl phantom_shop_proto::v1::auth::user::sudo_service_server::SudoService for Su
             Some(
                 phantom_shop_proto::v1::auth::user::enter_sudo_mode_request::Method::EmailOtp(otp),
             ) => SudoVerificationMethod::EmailOtp(otp),
+            Some(phantom_shop_proto::v1::auth::user::enter_sudo_mode_request::Method::Passkey(
+                assertion,
+            )) => {
+                let (challenge_id, credential) = parse_passkey_assertion(assertion)?;
+                SudoVerificationMethod::Passkey {
+                    challenge_id,
+                    credential,
+                }
+            }
             None => {
                 return Err(Status::invalid_argument(
-                    "Must provide either TOTP code or email OTP",
+                    "Must provide a TOTP code, an email OTP or a passkey",
                 ));
             }
         };
@@ -84,4 +96,23 @@ impl phantom_shop_proto::v1::auth::user::sudo_service_server::SudoService for Su
 
         Ok(Response::new(result.into()))
     }
+
+    async fn start_passkey_sudo(
+        &self,
+        request: Request<Empty>,
+    ) -> Result<Response<user_proto::StartPasskeySudoResponse>, Status> {
+        let user_id = UserId::read_from_request(&request)?;
+
+        let challenge = self
+            .mfa_service
+            .process(StartPasskeySudo {
+                user_id: user_id.into_inner(),
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(user_proto::StartPasskeySudoResponse {
+            challenge: challenge.map(TryInto::try_into).transpose()?,
+        }))
+    }
 }
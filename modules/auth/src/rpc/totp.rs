@@ -1,12 +1,13 @@
 use crate::rpc::middleware::UserId;
 use crate::services::mfa::{
-    CheckMfaEnabled, FinishConfiguringTotp, MfaService, RemoveMfa, StartConfiguringTotp,
+    CheckMfaEnabled, CountRecoveryCodes, FinishConfiguringTotp, MfaService,
+    RegenerateRecoveryCodes, RemoveMfa, StartConfiguringTotp,
 };
 use kanau::processor::Processor;
 use phantom_shop_proto::v1::auth::common::UserTotpStatus;
 use phantom_shop_proto::v1::auth::user::{
-    FinishTotpSetupRequest, FinishTotpSetupResponse, FinishTotpSetupResult, StartTotpSetupRequest,
-    StartTotpSetupResponse,
+    FinishTotpSetupRequest, FinishTotpSetupResponse, RegenerateRecoveryCodesRequest,
+    RegenerateRecoveryCodesResponse, StartTotpSetupRequest, StartTotpSetupResponse,
 };
 use phantom_shop_proto::v1::common::Empty;
 use tonic::{Request, Response, Status};
@@ -37,7 +38,18 @@ impl phantom_shop_proto::v1::auth::user::totp_service_server::TotpService for To
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
 
-        Ok(Response::new(UserTotpStatus { has_totp }))
+        let remaining_recovery_codes = self
+            .mfa_service
+            .process(CountRecoveryCodes {
+                user_id: user_id.into_inner(),
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(UserTotpStatus {
+            has_totp,
+            remaining_recovery_codes,
+        }))
     }
 
     async fn remove_totp(&self, request: Request<Empty>) -> Result<Response<Empty>, Status> {
@@ -100,10 +112,38 @@ impl phantom_shop_proto::v1::auth::user::totp_service_server::TotpService for To
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
 
-        let proto_result: FinishTotpSetupResult = result.into();
+        Ok(Response::new(result.into()))
+    }
+
+    async fn regenerate_recovery_codes(
+        &self,
+        request: Request<RegenerateRecoveryCodesRequest>,
+    ) -> Result<Response<RegenerateRecoveryCodesResponse>, Status> {
+        let (user_id, req) = UserId::from_request(request)?;
+
+        let sudo_token: [u8; 16] = req
+            .sudo_token
+            .ok_or_else(|| Status::invalid_argument("Missing sudo token"))?
+            .token
+            .try_into()
+            .map_err(|_| Status::invalid_argument("Invalid sudo token length"))?;
+
+        let recovery_codes = self
+            .mfa_service
+            .process(RegenerateRecoveryCodes {
+                user_id: user_id.into_inner(),
+                sudo_token,
+            })
+            .await
+            .map_err(|e| match e {
+                framework::Error::PermissionsDenied => {
+                    Status::permission_denied("Sudo verification failed")
+                }
+                _ => Status::internal(e.to_string()),
+            })?;
 
-        Ok(Response::new(FinishTotpSetupResponse {
-            result: proto_result.into(),
+        Ok(Response::new(RegenerateRecoveryCodesResponse {
+            recovery_codes: recovery_codes.unwrap_or_default(),
         }))
     }
 }
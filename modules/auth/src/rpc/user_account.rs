@@ -1,5 +1,6 @@
 use crate::entities::db::user_password::FindUserPasswordByUserId;
 use crate::entities::redis::oauth_challenge::OAuthAction;
+use crate::rpc::conversions::parse_oauth_provider_name;
 use crate::rpc::middleware::UserId;
 use crate::services::email_provider::{
     EmailProviderService, UserChangeEmailAddress, UserChangePassword, UserRemovePassword,
@@ -138,7 +139,8 @@ impl phantom_shop_proto::v1::auth::user::user_account_service_server::UserAccoun
     ) -> Result<Response<user_proto::CreateOAuthLinkingChallengeResponse>, Status> {
         let (user_id, req) = UserId::from_request(request)?;
 
-        let provider_name = req.provider_name().into();
+        let provider_name =
+            parse_oauth_provider_name(req.provider_name(), req.oidc_provider.as_deref())?;
 
         let return_to: Url = req
             .return_to
@@ -179,7 +181,8 @@ impl phantom_shop_proto::v1::auth::user::user_account_service_server::UserAccoun
     ) -> Result<Response<user_proto::UnlinkOAuthAccountResponse>, Status> {
         let (user_id, req) = UserId::from_request(request)?;
 
-        let provider_name = req.provider_name().into();
+        let provider_name =
+            parse_oauth_provider_name(req.provider_name(), req.oidc_provider.as_deref())?;
 
         let sudo_token_bytes: [u8; 16] = req
             .sudo_token
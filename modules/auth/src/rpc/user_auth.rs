@@ -1,15 +1,31 @@
 use crate::entities::redis::oauth_challenge::OAuthChallengeKey;
+use crate::entities::redis::session::SessionId;
+use crate::entities::redis::webauthn_challenge::PasskeyChallengePurpose;
+use crate::rpc::conversions::parse_passkey_assertion;
+use crate::rpc::middleware::{request_locale, session_client};
 use crate::services::email_provider::{
-    EmailProviderService, LoginUserWithPassword, RegisterUser, RouteRegisterUserResult,
-    SendPasswordResetEmail, SendRegisterEmail,
+    EmailProviderService, LoginUserWithEmailOtp, LoginUserWithPassword, RegisterUser,
+    RouteRegisterUserResult, SendLoginEmail, SendPasswordResetEmail, SendRegisterEmail,
+};
+use crate::services::mfa::{
+    MfaFactor, MfaService, StartPasskeyMfa, VerifyMfaLogin, VerifyMfaLoginResult,
 };
-use crate::services::mfa::{MfaService, VerifyMfaLogin, VerifyMfaLoginResult};
 use crate::services::oauth_provider::{
     OAuthCallback, OAuthCallbackRoute, OAuthLoginRouteResult, OAuthProviderService,
 };
+use crate::services::passkey::{LoginWithPasskey, PasskeyService, StartPasskeyChallenge};
+use crate::services::token::{
+    IssueSessionTokens, RotateRefreshToken, RotateRefreshTokenResult, TokenService,
+};
+use crate::services::wallet_login::{StartWalletLogin, WalletLogin, WalletLoginService};
+use crate::utils::jwt::RefreshToken;
+use crate::utils::wallet::WalletKind;
 use kanau::processor::Processor;
-use phantom_shop_proto::v1::auth::common::{EmailSendResult, LoginResult};
+use phantom_shop_proto::v1::auth::common::{
+    EmailSendResult, LoginResult, PasskeyAssertion, PasskeyChallenge, TokenPair,
+};
 use phantom_shop_proto::v1::auth::user as user_proto;
+use phantom_shop_proto::v1::common::Empty;
 use tonic::{Request, Response, Status};
 use url::Url;
 
@@ -17,6 +33,9 @@ pub struct UserAuthServiceImpl {
     pub email_provider_service: EmailProviderService,
     pub oauth_provider_service: OAuthProviderService,
     pub mfa_service: MfaService,
+    pub token_service: TokenService,
+    pub passkey_service: PasskeyService,
+    pub wallet_login_service: WalletLoginService,
 }
 
 impl UserAuthServiceImpl {
@@ -24,13 +43,33 @@ impl UserAuthServiceImpl {
         email_provider_service: EmailProviderService,
         oauth_provider_service: OAuthProviderService,
         mfa_service: MfaService,
+        token_service: TokenService,
+        passkey_service: PasskeyService,
+        wallet_login_service: WalletLoginService,
     ) -> Self {
         Self {
             email_provider_service,
             oauth_provider_service,
             mfa_service,
+            token_service,
+            passkey_service,
+            wallet_login_service,
         }
     }
+
+    /// Tokens for the session created by a login, if the token mode is enabled.
+    async fn issue_tokens(&self, session_id: Option<&str>) -> Result<Option<TokenPair>, Status> {
+        let Some(session_id) = session_id else {
+            return Ok(None);
+        };
+        let session_id = SessionId::try_from_ascii_string(session_id)?;
+        let tokens = self
+            .token_service
+            .process(IssueSessionTokens { session_id })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(tokens.map(Into::into))
+    }
 }
 
 #[tonic::async_trait]
@@ -41,6 +80,7 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
         &self,
         request: Request<user_proto::RegisterEmailAccountRequest>,
     ) -> Result<Response<user_proto::RegisterEmailAccountResponse>, Status> {
+        let client = session_client(&request);
         let req = request.into_inner();
 
         // First, route the registration to determine if password or passwordless
@@ -52,6 +92,7 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
                 password: req.password,
                 name: req.name,
                 auto_login: req.auto_login,
+                client,
             })
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
@@ -64,7 +105,10 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
                     .await
                     .map_err(|e| Status::internal(e.to_string()))?;
 
-                let proto_result: user_proto::RegisterEmailAccountResponse = result.into();
+                let mut proto_result: user_proto::RegisterEmailAccountResponse = result.into();
+                proto_result.tokens = self
+                    .issue_tokens(proto_result.session_id.as_deref())
+                    .await?;
                 Ok(Response::new(proto_result))
             }
             RouteRegisterUserResult::Passwordless(register_passwordless) => {
@@ -74,7 +118,10 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
                     .await
                     .map_err(|e| Status::internal(e.to_string()))?;
 
-                let proto_result: user_proto::RegisterEmailAccountResponse = result.into();
+                let mut proto_result: user_proto::RegisterEmailAccountResponse = result.into();
+                proto_result.tokens = self
+                    .issue_tokens(proto_result.session_id.as_deref())
+                    .await?;
                 Ok(Response::new(proto_result))
             }
             RouteRegisterUserResult::InvalidOtp => {
@@ -82,6 +129,7 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
                     login_result: LoginResult::AccountNotFound.into(),
                     user_account: None,
                     session_id: None,
+                    tokens: None,
                 }))
             }
             RouteRegisterUserResult::DuplicatedEmail => {
@@ -89,6 +137,7 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
                     login_result: LoginResult::Blocked.into(),
                     user_account: None,
                     session_id: None,
+                    tokens: None,
                 }))
             }
         }
@@ -98,16 +147,18 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
         &self,
         request: Request<user_proto::SendPreAuthorizeEmailOtpRequest>,
     ) -> Result<Response<user_proto::SendPreAuthorizeEmailOtpResponse>, Status> {
+        let locale = request_locale(&request);
         let req = request.into_inner();
 
-        // Only handle registration and password reset OTP
-        // Login OTP requires a private processor that isn't available
         let usage = req.usage();
         match usage {
             phantom_shop_proto::v1::auth::common::EmailOtpUsage::PasswordReset => {
                 let result = self
                     .email_provider_service
-                    .process(SendPasswordResetEmail { email: req.email })
+                    .process(SendPasswordResetEmail {
+                        email: req.email,
+                        locale,
+                    })
                     .await
                     .map_err(|e| Status::internal(e.to_string()))?;
 
@@ -122,7 +173,10 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
             phantom_shop_proto::v1::auth::common::EmailOtpUsage::EmailOtpRegistration => {
                 let result = self
                     .email_provider_service
-                    .process(SendRegisterEmail { email: req.email })
+                    .process(SendRegisterEmail {
+                        email: req.email,
+                        locale,
+                    })
                     .await
                     .map_err(|e| Status::internal(e.to_string()))?;
 
@@ -134,27 +188,59 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
                     },
                 ))
             }
-            _ => {
-                // Login OTP is not implemented (private processor)
-                Err(Status::unimplemented("This OTP usage is not supported"))
+            phantom_shop_proto::v1::auth::common::EmailOtpUsage::Login => {
+                let result = self
+                    .email_provider_service
+                    .process(SendLoginEmail {
+                        email: req.email,
+                        locale,
+                    })
+                    .await
+                    .map_err(|e| Status::internal(e.to_string()))?;
+
+                let proto_result: EmailSendResult = result.into();
+
+                Ok(Response::new(
+                    user_proto::SendPreAuthorizeEmailOtpResponse {
+                        result: proto_result.into(),
+                    },
+                ))
             }
+            _ => Err(Status::invalid_argument(
+                "This OTP usage requires an authenticated user",
+            )),
         }
     }
 
     async fn email_otp_login(
         &self,
-        _request: Request<phantom_shop_proto::v1::auth::user::EmailOtpLoginRequest>,
-    ) -> Result<Response<phantom_shop_proto::v1::auth::user::EmailOtpLoginResponse>, Status> {
-        // EmailOtpLogin requires a private processor (SendEmailOtp is private)
-        Err(Status::unimplemented(
-            "Email OTP login is not implemented - requires public OTP login processor",
-        ))
+        request: Request<user_proto::EmailOtpLoginRequest>,
+    ) -> Result<Response<user_proto::EmailOtpLoginResponse>, Status> {
+        let client = session_client(&request);
+        let req = request.into_inner();
+
+        let result = self
+            .email_provider_service
+            .process(LoginUserWithEmailOtp {
+                email: req.email,
+                otp: req.otp,
+                client,
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let mut proto_result: user_proto::EmailOtpLoginResponse = result.into();
+        proto_result.tokens = self
+            .issue_tokens(proto_result.session_id.as_deref())
+            .await?;
+        Ok(Response::new(proto_result))
     }
 
     async fn email_password_login(
         &self,
         request: Request<user_proto::EmailPasswordLoginRequest>,
     ) -> Result<Response<user_proto::EmailPasswordLoginResponse>, Status> {
+        let client = session_client(&request);
         let req = request.into_inner();
 
         let result = self
@@ -162,11 +248,15 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
             .process(LoginUserWithPassword {
                 email: req.email,
                 password: req.password,
+                client,
             })
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
 
-        let proto_result: user_proto::EmailPasswordLoginResponse = result.into();
+        let mut proto_result: user_proto::EmailPasswordLoginResponse = result.into();
+        proto_result.tokens = self
+            .issue_tokens(proto_result.session_id.as_deref())
+            .await?;
         Ok(Response::new(proto_result))
     }
 
@@ -174,6 +264,7 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
         &self,
         request: Request<user_proto::ResetPasswordRequest>,
     ) -> Result<Response<user_proto::ResetPasswordResponse>, Status> {
+        let client = session_client(&request);
         let req = request.into_inner();
 
         let result = self
@@ -183,11 +274,15 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
                 otp: req.otp,
                 new_password: req.new_password,
                 auto_login: req.auto_login,
+                client,
             })
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
 
-        let proto_result: user_proto::ResetPasswordResponse = result.into();
+        let mut proto_result: user_proto::ResetPasswordResponse = result.into();
+        proto_result.tokens = self
+            .issue_tokens(proto_result.session_id.as_deref())
+            .await?;
         Ok(Response::new(proto_result))
     }
 
@@ -195,6 +290,7 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
         &self,
         request: Request<user_proto::VerifyMfaTokenRequest>,
     ) -> Result<Response<user_proto::VerifyMfaTokenResponse>, Status> {
+        let client = session_client(&request);
         let req = request.into_inner();
 
         let mfa_token: [u8; 32] = req
@@ -202,22 +298,49 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
             .try_into()
             .map_err(|_| Status::invalid_argument("Invalid MFA token length"))?;
 
-        let code: u32 = req
-            .mfa_code
-            .parse()
-            .map_err(|_| Status::invalid_argument("Invalid MFA code format"))?;
+        let factor = match (req.passkey, req.recovery_code) {
+            (Some(assertion), _) => {
+                let (challenge_id, credential) = parse_passkey_assertion(assertion)?;
+                MfaFactor::Passkey {
+                    challenge_id,
+                    credential,
+                }
+            }
+            (None, Some(recovery_code)) => MfaFactor::RecoveryCode(recovery_code),
+            (None, None) => MfaFactor::Totp(
+                req.mfa_code
+                    .parse()
+                    .map_err(|_| Status::invalid_argument("Invalid MFA code format"))?,
+            ),
+        };
 
         let result = self
             .mfa_service
-            .process(VerifyMfaLogin { mfa_token, code })
+            .process(VerifyMfaLogin {
+                mfa_token,
+                factor,
+                client,
+            })
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
 
         match result {
             VerifyMfaLoginResult::Success(session_id) => {
+                let session_id = session_id.to_ascii_string();
+                let tokens = self.issue_tokens(Some(&session_id)).await?;
                 Ok(Response::new(user_proto::VerifyMfaTokenResponse {
                     success: true,
-                    session_id: Some(session_id.to_ascii_string()),
+                    session_id: Some(session_id),
+                    tokens,
+                    suspension: None,
+                }))
+            }
+            VerifyMfaLoginResult::Suspended(suspension) => {
+                Ok(Response::new(user_proto::VerifyMfaTokenResponse {
+                    success: false,
+                    session_id: None,
+                    tokens: None,
+                    suspension: Some(suspension.into()),
                 }))
             }
             VerifyMfaLoginResult::InvalidToken
@@ -226,15 +349,138 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
                 Ok(Response::new(user_proto::VerifyMfaTokenResponse {
                     success: false,
                     session_id: None,
+                    tokens: None,
+                    suspension: None,
                 }))
             }
         }
     }
 
+    async fn start_passkey_mfa(
+        &self,
+        request: Request<user_proto::StartPasskeyMfaRequest>,
+    ) -> Result<Response<user_proto::StartPasskeyMfaResponse>, Status> {
+        let mfa_token: [u8; 32] = request
+            .into_inner()
+            .mfa_token
+            .try_into()
+            .map_err(|_| Status::invalid_argument("Invalid MFA token length"))?;
+
+        let challenge = self
+            .mfa_service
+            .process(StartPasskeyMfa { mfa_token })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(user_proto::StartPasskeyMfaResponse {
+            challenge: challenge.map(TryInto::try_into).transpose()?,
+        }))
+    }
+
+    async fn start_passkey_login(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<PasskeyChallenge>, Status> {
+        let challenge = self
+            .passkey_service
+            .process(StartPasskeyChallenge {
+                purpose: PasskeyChallengePurpose::Login,
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .ok_or_else(|| Status::internal("Passkey login challenge was not created"))?;
+
+        Ok(Response::new(challenge.try_into()?))
+    }
+
+    async fn passkey_login(
+        &self,
+        request: Request<PasskeyAssertion>,
+    ) -> Result<Response<user_proto::PasskeyLoginResponse>, Status> {
+        let client = session_client(&request);
+        let (challenge_id, credential) = parse_passkey_assertion(request.into_inner())?;
+
+        let result = self
+            .passkey_service
+            .process(LoginWithPasskey {
+                challenge_id,
+                credential,
+                client,
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let mut proto_result: user_proto::PasskeyLoginResponse = result.into();
+        proto_result.tokens = self
+            .issue_tokens(proto_result.session_id.as_deref())
+            .await?;
+        Ok(Response::new(proto_result))
+    }
+
+    async fn start_wallet_login(
+        &self,
+        request: Request<user_proto::StartWalletLoginRequest>,
+    ) -> Result<Response<user_proto::StartWalletLoginResponse>, Status> {
+        let req = request.into_inner();
+        let kind = match req.kind() {
+            user_proto::WalletKind::Ethereum => WalletKind::Ethereum {
+                chain_id: req.chain_id,
+            },
+            user_proto::WalletKind::Tron => WalletKind::Tron,
+        };
+
+        let message = self
+            .wallet_login_service
+            .process(StartWalletLogin {
+                kind,
+                address: req.address,
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .ok_or_else(|| Status::invalid_argument("Wallet login is not available"))?;
+
+        Ok(Response::new(user_proto::StartWalletLoginResponse {
+            nonce: message.nonce.to_vec(),
+            message: message.message,
+        }))
+    }
+
+    async fn wallet_login(
+        &self,
+        request: Request<user_proto::WalletLoginRequest>,
+    ) -> Result<Response<user_proto::WalletLoginResponse>, Status> {
+        let client = session_client(&request);
+        let req = request.into_inner();
+        let nonce: [u8; 16] = req
+            .nonce
+            .try_into()
+            .map_err(|_| Status::invalid_argument("Invalid nonce length"))?;
+        let signature = req.signature.trim();
+        let signature = hex::decode(signature.strip_prefix("0x").unwrap_or(signature))
+            .map_err(|_| Status::invalid_argument("Malformed signature"))?;
+
+        let result = self
+            .wallet_login_service
+            .process(WalletLogin {
+                nonce,
+                signature,
+                client,
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let mut proto_result: user_proto::WalletLoginResponse = result.into();
+        proto_result.tokens = self
+            .issue_tokens(proto_result.session_id.as_deref())
+            .await?;
+        Ok(Response::new(proto_result))
+    }
+
     async fn o_auth_callback(
         &self,
         request: Request<user_proto::OAuthCallbackRequest>,
     ) -> Result<Response<user_proto::OAuthCallbackResponse>, Status> {
+        let client = session_client(&request);
         let req = request.into_inner();
 
         let state: OAuthChallengeKey = req
@@ -255,6 +501,7 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
                 state,
                 redirect_uri: redirect_uri.clone(),
                 user_id: None, // No authenticated user for login flow
+                client,
             })
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
@@ -276,7 +523,14 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
                             .await
                             .map_err(|e| Status::internal(e.to_string()))?;
 
-                        let proto_result: user_proto::OAuthCallbackResponse = login_result.into();
+                        let mut proto_result: user_proto::OAuthCallbackResponse =
+                            login_result.into();
+                        if let Some(user_proto::o_auth_callback_response::Result::LoginResult(
+                            branch,
+                        )) = &mut proto_result.result
+                        {
+                            branch.tokens = self.issue_tokens(branch.session_id.as_deref()).await?;
+                        }
                         Ok(Response::new(proto_result))
                     }
                     OAuthLoginRouteResult::Register(oauth_register) => {
@@ -285,16 +539,18 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
                             .process(oauth_register)
                             .await
                             .map_err(|e| Status::internal(e.to_string()))?;
+                        let session_id = register_result.session_id.to_ascii_string();
+                        let tokens = self.issue_tokens(Some(&session_id)).await?;
 
                         Ok(Response::new(user_proto::OAuthCallbackResponse {
                             result: Some(
                                 user_proto::o_auth_callback_response::Result::LoginResult(
                                     user_proto::OAuthCallbackLoginBranchResult {
                                         login_result: LoginResult::Success.into(),
-                                        session_id: Some(
-                                            register_result.session_id.to_ascii_string(),
-                                        ),
+                                        session_id: Some(session_id),
+                                        tokens,
                                         mfa_token: None,
+                                        suspension: None,
                                     },
                                 ),
                             ),
@@ -333,4 +589,32 @@ impl phantom_shop_proto::v1::auth::user::user_auth_service_server::UserAuthServi
             }
         }
     }
+
+    async fn refresh_tokens(
+        &self,
+        request: Request<user_proto::RefreshTokensRequest>,
+    ) -> Result<Response<user_proto::RefreshTokensResponse>, Status> {
+        let req = request.into_inner();
+
+        let result = self
+            .token_service
+            .process(RotateRefreshToken {
+                refresh_token: RefreshToken::new(req.refresh_token),
+            })
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let (result, tokens) = match result {
+            RotateRefreshTokenResult::Rotated(tokens) => (
+                user_proto::RefreshTokensResult::Success,
+                Some(tokens.into()),
+            ),
+            RotateRefreshTokenResult::Invalid => (user_proto::RefreshTokensResult::Invalid, None),
+            RotateRefreshTokenResult::Reused => (user_proto::RefreshTokensResult::Reused, None),
+        };
+        Ok(Response::new(user_proto::RefreshTokensResponse {
+            result: result.into(),
+            tokens,
+        }))
+    }
 }
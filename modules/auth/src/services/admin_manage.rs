@@ -1,6 +1,244 @@
+use crate::entities::db::oauth_account::{FindOAuthAccountsByUserId, OAuthAccount};
+use crate::entities::db::recovery_code::RemoveRecoveryCodesByUserId;
+use crate::entities::db::totp::{FindTotpByUserId, RemoveTotpByUserId};
+use crate::entities::db::user_account::{
+    FindUserAccountById, FindUserSuspension, LiftUserSuspension, SearchUserAccounts,
+    SuspendUserAccount, UserAccount, UserSuspension,
+};
+use crate::entities::db::webauthn_credential::RemoveWebauthnCredentialsByUserId;
+use crate::services::session::{
+    ApplyUserSuspension, LiftUserSuspensionMarker, ListUserSessionsWithHandles, ListedSession,
+    SessionService, TerminateAllUserSessions,
+};
+use admin::entities::admin_account::AdminRole;
+use admin::rbac;
+use admin::utils::rbac::Permission;
 use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use tracing::instrument;
+use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct UserAuthManageService {
     pub db: DatabaseProcessor,
+    pub session: SessionService,
 }
+
+#[derive(Debug, Clone)]
+pub struct SearchUsers {
+    /// Matched against email and name
+    pub query: String,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Processor<SearchUsers> for UserAuthManageService {
+    type Output = Vec<UserAccount>;
+    type Error = framework::Error;
+    async fn process(&self, input: SearchUsers) -> Result<Vec<UserAccount>, framework::Error> {
+        self.db
+            .process(SearchUserAccounts {
+                query: input.query,
+                limit: input.limit,
+                offset: input.offset,
+            })
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UserDetailForAdmin {
+    pub account: UserAccount,
+    pub oauth_accounts: Vec<OAuthAccount>,
+    pub has_totp: bool,
+    pub sessions: Vec<ListedSession>,
+    pub suspension: Option<UserSuspension>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShowUserForAdmin {
+    pub user_id: Uuid,
+}
+
+impl Processor<ShowUserForAdmin> for UserAuthManageService {
+    type Output = UserDetailForAdmin;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: ShowUserForAdmin,
+    ) -> Result<UserDetailForAdmin, framework::Error> {
+        let account = self
+            .db
+            .process(FindUserAccountById { id: input.user_id })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        let oauth_accounts = self
+            .db
+            .process(FindOAuthAccountsByUserId {
+                user_id: input.user_id,
+            })
+            .await?;
+        let has_totp = self
+            .db
+            .process(FindTotpByUserId {
+                user_id: input.user_id,
+            })
+            .await?
+            .is_some();
+        let suspension = self
+            .db
+            .process(FindUserSuspension {
+                user_id: input.user_id,
+            })
+            .await?;
+        let sessions = self
+            .session
+            .process(ListUserSessionsWithHandles {
+                user_id: input.user_id,
+            })
+            .await?;
+        Ok(UserDetailForAdmin {
+            account,
+            oauth_accounts,
+            has_totp,
+            sessions,
+            suspension,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Terminate every session of the user.
+pub struct ForceLogoutUser {
+    pub user_id: Uuid,
+}
+
+impl Processor<ForceLogoutUser> for UserAuthManageService {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: ForceLogoutUser) -> Result<(), framework::Error> {
+        self.session
+            .process(TerminateAllUserSessions {
+                user_id: input.user_id,
+            })
+            .await
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Remove the TOTP, passkeys and recovery codes of a user who lost their device.
+///
+/// The admin must verify the identity of the user out of band first and describe how in
+/// `verification_note`, which ends up in the audit log.
+pub struct ResetUserMfa {
+    pub user_id: Uuid,
+    pub verification_note: String,
+}
+
+impl Processor<ResetUserMfa> for UserAuthManageService {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: ResetUserMfa) -> Result<(), framework::Error> {
+        if input.verification_note.trim().is_empty() {
+            return Err(framework::Error::InvalidInput);
+        }
+        let has_totp = self
+            .db
+            .process(FindTotpByUserId {
+                user_id: input.user_id,
+            })
+            .await?
+            .is_some();
+        self.db
+            .process(RemoveTotpByUserId {
+                user_id: input.user_id,
+            })
+            .await?;
+        let removed_passkeys = self
+            .db
+            .process(RemoveWebauthnCredentialsByUserId {
+                user_id: input.user_id,
+            })
+            .await?;
+        if !has_totp && removed_passkeys == 0 {
+            return Err(framework::Error::NotFound);
+        }
+        self.db
+            .process(RemoveRecoveryCodesByUserId {
+                user_id: input.user_id,
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Banning a user also terminates all of their sessions.
+pub struct BanOrUnbanUser {
+    /// The admin making the change
+    pub admin_id: Uuid,
+    pub user_id: Uuid,
+    pub banned: bool,
+    /// Shown to the user when they try to log in. Required when banning.
+    pub reason: String,
+    /// `None` for a permanent ban. Ignored when unbanning.
+    pub until: Option<time::PrimitiveDateTime>,
+}
+
+impl Processor<BanOrUnbanUser> for UserAuthManageService {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: BanOrUnbanUser) -> Result<(), framework::Error> {
+        if !input.banned {
+            let found = self
+                .db
+                .process(LiftUserSuspension {
+                    user_id: input.user_id,
+                })
+                .await?;
+            if !found {
+                return Err(framework::Error::NotFound);
+            }
+            return self
+                .session
+                .process(LiftUserSuspensionMarker {
+                    user_id: input.user_id,
+                })
+                .await;
+        }
+        if input.reason.trim().is_empty()
+            || input
+                .until
+                .is_some_and(|until| until <= framework::now_time())
+        {
+            return Err(framework::Error::InvalidInput);
+        }
+        let suspension = self
+            .db
+            .process(SuspendUserAccount {
+                user_id: input.user_id,
+                suspended_by: input.admin_id,
+                reason: input.reason,
+                until: input.until,
+            })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        self.session
+            .process(ApplyUserSuspension {
+                user_id: input.user_id,
+                until: suspension.until,
+            })
+            .await
+    }
+}
+
+rbac! { UserAuthManageService : SearchUsers => Vec<UserAccount> | [AdminRole::Owner, AdminRole::Moderator], Permission::UsersManage }
+rbac! { UserAuthManageService : ShowUserForAdmin => UserDetailForAdmin | [AdminRole::Owner, AdminRole::Moderator], Permission::UsersManage }
+rbac! { UserAuthManageService : ForceLogoutUser => () | [AdminRole::Owner, AdminRole::Moderator], Permission::UsersManage }
+rbac! { UserAuthManageService : ResetUserMfa => () | [AdminRole::Owner, AdminRole::Moderator], Permission::UsersManage }
+rbac! { UserAuthManageService : BanOrUnbanUser => () | [AdminRole::Owner, AdminRole::Moderator], Permission::UsersManage }
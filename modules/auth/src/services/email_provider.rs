@@ -1,22 +1,26 @@
 use crate::config::AuthConfig;
 use crate::entities::db::email_otp::{
     CheckEmailFrequency, CreateEmailOtp, EmailOtp, EmailOtpUsage, FindValidEmailOtp,
-    MarkEmailOtpAsUsed,
+    FindValidUnboundEmailOtp, MarkEmailOtpAsUsed,
 };
 use crate::entities::db::user_account::{
-    FindUserAccountByEmail, FindUserAccountById, RegisterPasswordlessUserAccount, UpdateUserEmail,
+    FindUserAccountByEmail, FindUserAccountById, FindUserSuspension,
+    RegisterPasswordlessUserAccount, UpdateUserEmail, UserSuspension,
 };
 use crate::entities::db::user_password::{
     DeleteUserPasswordByUserId, FindUserPasswordByEmail, FindUserPasswordByUserId,
     RegisterUserWithPassword, UpdateUserPassword,
 };
-use crate::entities::redis::session::SessionId;
+use crate::entities::redis::email_otp_guard::EmailOtpAttemptsKey;
+use crate::entities::redis::session::{LoginMethod, SessionClient, SessionId};
 use crate::events::account::{RegisterMethod, UserRegisterEvent};
 use crate::events::email::OtpEmailSendCall;
 use crate::services::mfa::{CheckMfaEnabled, CreateLoginMfaSession, MfaService, VerifySudoToken};
 use crate::services::session::{CreateSession, SessionService, TerminateAllUserSessions};
+use crate::utils::email_template::EmailLocale;
 use crate::utils::password::{hash_password, verify_password};
-use admin::utils::config_provider::find_config_from_redis;
+use admin::entities::totp_guard::TotpGuard;
+use admin::utils::config_provider::LiveConfig;
 use framework::rabbitmq::{AmqpMessageSend, AmqpPool};
 use framework::redis::RedisConnection;
 use framework::sqlx::DatabaseProcessor;
@@ -26,7 +30,7 @@ use uuid::Uuid;
 #[derive(Clone)]
 pub struct EmailProviderService {
     pub db: DatabaseProcessor,
-    pub config_store: RedisConnection,
+    pub auth_config: LiveConfig<AuthConfig>,
     pub redis: RedisConnection,
     pub mq: AmqpPool,
     pub session_service: SessionService,
@@ -77,6 +81,7 @@ impl Processor<ListEmailLoginMethod> for EmailProviderService {
 pub struct LoginUserWithPassword {
     pub email: String,
     pub password: String,
+    pub client: SessionClient,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -85,6 +90,8 @@ pub enum EmailLoginResult {
     WrongCredential,
     MethodNotAvailable,
     MfaRequired([u8; 32]),
+    /// The credential is valid but the account is suspended.
+    Suspended(UserSuspension),
 }
 
 impl Processor<LoginUserWithPassword> for EmailProviderService {
@@ -107,11 +114,90 @@ impl Processor<LoginUserWithPassword> for EmailProviderService {
         if verify_password(&input.password, &password.password_hash).is_err() {
             return Ok(EmailLoginResult::WrongCredential);
         };
+        self.process(FinishEmailLogin {
+            user_id: password.user_id,
+            login_method: LoginMethod::EmailPassword,
+            client: input.client,
+        })
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoginUserWithEmailOtp {
+    pub email: String,
+    pub otp: String,
+    pub client: SessionClient,
+}
+
+impl Processor<LoginUserWithEmailOtp> for EmailProviderService {
+    type Output = EmailLoginResult;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: LoginUserWithEmailOtp,
+    ) -> Result<EmailLoginResult, framework::Error> {
+        let Some(otp) = self
+            .process(VerifyEmailOtp {
+                user_id: None,
+                email: input.email.clone(),
+                otp: input.otp,
+                usage: EmailOtpUsage::Login,
+            })
+            .await?
+        else {
+            return Ok(EmailLoginResult::WrongCredential);
+        };
+        let Some(account) = self
+            .db
+            .process(FindUserAccountByEmail { email: input.email })
+            .await?
+        else {
+            return Ok(EmailLoginResult::WrongCredential);
+        };
+        if self
+            .db
+            .process(MarkEmailOtpAsUsed { id: otp.id })
+            .await?
+            .is_none()
+        {
+            return Ok(EmailLoginResult::WrongCredential);
+        }
+        self.process(FinishEmailLogin {
+            user_id: account.id,
+            login_method: LoginMethod::EmailOtp,
+            client: input.client,
+        })
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Everything after the first factor has been verified.
+struct FinishEmailLogin {
+    user_id: Uuid,
+    login_method: LoginMethod,
+    client: SessionClient,
+}
+
+impl Processor<FinishEmailLogin> for EmailProviderService {
+    type Output = EmailLoginResult;
+    type Error = framework::Error;
+    async fn process(&self, input: FinishEmailLogin) -> Result<EmailLoginResult, framework::Error> {
+        if let Some(suspension) = self
+            .db
+            .process(FindUserSuspension {
+                user_id: input.user_id,
+            })
+            .await?
+        {
+            return Ok(EmailLoginResult::Suspended(suspension));
+        }
 
         let mfa_enabled = self
             .mfa_service
             .process(CheckMfaEnabled {
-                user_id: password.user_id,
+                user_id: input.user_id,
             })
             .await?;
 
@@ -119,7 +205,8 @@ impl Processor<LoginUserWithPassword> for EmailProviderService {
             let token = self
                 .mfa_service
                 .process(CreateLoginMfaSession {
-                    user_id: password.user_id,
+                    user_id: input.user_id,
+                    login_method: input.login_method,
                 })
                 .await?;
             Ok(EmailLoginResult::MfaRequired(token.token))
@@ -127,7 +214,9 @@ impl Processor<LoginUserWithPassword> for EmailProviderService {
             let session_id = self
                 .session_service
                 .process(CreateSession {
-                    user_id: password.user_id,
+                    user_id: input.user_id,
+                    login_method: input.login_method,
+                    client: input.client,
                 })
                 .await?;
             Ok(EmailLoginResult::Success(session_id))
@@ -139,6 +228,7 @@ impl Processor<LoginUserWithPassword> for EmailProviderService {
 struct SendEmailOtp {
     pub email: String,
     pub usage: EmailOtpUsage,
+    pub locale: Option<EmailLocale>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -152,7 +242,7 @@ impl Processor<SendEmailOtp> for EmailProviderService {
     type Output = SendEmailOtpResult;
     type Error = framework::Error;
     async fn process(&self, input: SendEmailOtp) -> Result<SendEmailOtpResult, framework::Error> {
-        let config = find_config_from_redis::<AuthConfig>(&mut self.config_store.clone()).await?;
+        let config = self.auth_config.load();
         let email_allowed = config.email_provider.domain.check_addr(&input.email);
         if !email_allowed {
             return Ok(SendEmailOtpResult::InvalidEmailAddress);
@@ -197,6 +287,7 @@ impl Processor<SendEmailOtp> for EmailProviderService {
                     framework::Error::BusinessPanic(anyhow::anyhow!("Invalid duration: {e}"))
                 })?,
             sent_at: now.assume_utc().unix_timestamp() as u64,
+            locale: input.locale,
         }
         .send(&self.mq)
         .await?;
@@ -207,6 +298,7 @@ impl Processor<SendEmailOtp> for EmailProviderService {
 #[derive(Debug, Clone)]
 pub struct SendRegisterEmail {
     pub email: String,
+    pub locale: Option<EmailLocale>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -242,6 +334,7 @@ impl Processor<SendRegisterEmail> for EmailProviderService {
             .process(SendEmailOtp {
                 email: input.email.clone(),
                 usage: EmailOtpUsage::Login,
+                locale: input.locale,
             })
             .await?;
         match send_result {
@@ -254,6 +347,60 @@ impl Processor<SendRegisterEmail> for EmailProviderService {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct SendLoginEmail {
+    pub email: String,
+    pub locale: Option<EmailLocale>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendLoginEmailResult {
+    /// The email is sent or the email address does not exist.
+    /// The user should not know if the email address exists.
+    MaybeSent,
+    InvalidEmailAddress,
+}
+
+impl Processor<SendLoginEmail> for EmailProviderService {
+    type Output = SendLoginEmailResult;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: SendLoginEmail,
+    ) -> Result<SendLoginEmailResult, framework::Error> {
+        let account_exists = self
+            .db
+            .process(FindUserAccountByEmail {
+                email: input.email.clone(),
+            })
+            .await?
+            .is_some();
+        if !account_exists {
+            let config = self.auth_config.load();
+            return if config.email_provider.domain.check_addr(&input.email) {
+                Ok(SendLoginEmailResult::MaybeSent)
+            } else {
+                Ok(SendLoginEmailResult::InvalidEmailAddress)
+            };
+        }
+        match self
+            .process(SendEmailOtp {
+                email: input.email,
+                usage: EmailOtpUsage::Login,
+                locale: input.locale,
+            })
+            .await?
+        {
+            SendEmailOtpResult::InvalidEmailAddress => {
+                Ok(SendLoginEmailResult::InvalidEmailAddress)
+            }
+            SendEmailOtpResult::Sent | SendEmailOtpResult::RateLimited => {
+                Ok(SendLoginEmailResult::MaybeSent)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RegisterUser {
     pub email: String,
@@ -261,6 +408,7 @@ pub struct RegisterUser {
     pub password: Option<String>,
     pub name: Option<String>,
     pub auto_login: bool,
+    pub client: SessionClient,
 }
 
 #[derive(Debug, Clone)]
@@ -277,6 +425,8 @@ pub struct RegisterWithPassword {
     pub name: Option<String>,
     pub email: String,
     pub auto_login: bool,
+    /// Recorded on the session if `auto_login` is set
+    pub client: SessionClient,
 }
 
 #[derive(Debug, Clone)]
@@ -284,6 +434,8 @@ pub struct RegisterPasswordless {
     pub name: Option<String>,
     pub email: String,
     pub auto_login: bool,
+    /// Recorded on the session if `auto_login` is set
+    pub client: SessionClient,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -302,7 +454,7 @@ impl Processor<RegisterUser> for EmailProviderService {
         // verify the otp
         let Some(otp) = self
             .process(VerifyEmailOtp {
-                user_id: Uuid::nil(),
+                user_id: None,
                 email: input.email.clone(),
                 otp: input.otp.clone(),
                 usage: EmailOtpUsage::Login,
@@ -327,22 +479,38 @@ impl Processor<RegisterUser> for EmailProviderService {
         if let Some(password) = input.password {
             let password_hash =
                 hash_password(&password).map_err(|_| framework::Error::InvalidInput)?;
-            self.db.process(MarkEmailOtpAsUsed { id: otp.id }).await?;
+            if self
+                .db
+                .process(MarkEmailOtpAsUsed { id: otp.id })
+                .await?
+                .is_none()
+            {
+                return Ok(RouteRegisterUserResult::InvalidOtp);
+            }
             Ok(RouteRegisterUserResult::WithPassword(
                 RegisterWithPassword {
                     password_hash,
                     name: input.name,
                     email: input.email,
                     auto_login: input.auto_login,
+                    client: input.client,
                 },
             ))
         } else {
-            self.db.process(MarkEmailOtpAsUsed { id: otp.id }).await?;
+            if self
+                .db
+                .process(MarkEmailOtpAsUsed { id: otp.id })
+                .await?
+                .is_none()
+            {
+                return Ok(RouteRegisterUserResult::InvalidOtp);
+            }
             Ok(RouteRegisterUserResult::Passwordless(
                 RegisterPasswordless {
                     name: input.name,
                     email: input.email,
                     auto_login: input.auto_login,
+                    client: input.client,
                 },
             ))
         }
@@ -380,6 +548,8 @@ impl Processor<RegisterWithPassword> for EmailProviderService {
                 .session_service
                 .process(CreateSession {
                     user_id: user_account.user_id,
+                    login_method: LoginMethod::Registration,
+                    client: input.client,
                 })
                 .await?;
             Ok(RegisterUserResult::RegisteredWithSession(session_id))
@@ -419,6 +589,8 @@ impl Processor<RegisterPasswordless> for EmailProviderService {
                 .session_service
                 .process(CreateSession {
                     user_id: user_account.id,
+                    login_method: LoginMethod::Registration,
+                    client: input.client,
                 })
                 .await?;
             Ok(RegisterUserResult::RegisteredWithSession(session_id))
@@ -431,6 +603,7 @@ impl Processor<RegisterPasswordless> for EmailProviderService {
 #[derive(Debug, Clone)]
 pub struct SendPasswordResetEmail {
     pub email: String,
+    pub locale: Option<EmailLocale>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -453,7 +626,7 @@ impl Processor<SendPasswordResetEmail> for EmailProviderService {
         &self,
         input: SendPasswordResetEmail,
     ) -> Result<SendPasswordResetEmailResult, framework::Error> {
-        let config = find_config_from_redis::<AuthConfig>(&mut self.config_store.clone()).await?;
+        let config = self.auth_config.load();
         let account_exists = self
             .db
             .process(FindUserAccountByEmail {
@@ -472,6 +645,7 @@ impl Processor<SendPasswordResetEmail> for EmailProviderService {
         self.process(SendEmailOtp {
             email: input.email.clone(),
             usage: EmailOtpUsage::PasswordReset,
+            locale: input.locale,
         })
         .await?;
         Ok(SendPasswordResetEmailResult::MaybeSent)
@@ -484,12 +658,17 @@ pub struct ResetPassword {
     pub otp: String,
     pub new_password: String,
     pub auto_login: bool,
+    pub client: SessionClient,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ResetPasswordResult {
     Success,
     SuccessWithSession(SessionId),
+    /// The password is reset, but the user has to pass MFA to be logged in.
+    SuccessWithMfaRequired([u8; 32]),
+    /// The password is reset, but the account is suspended so the user is not logged in.
+    SuccessButSuspended(UserSuspension),
     InvalidOtp,
     AccountNotFound,
 }
@@ -500,7 +679,7 @@ impl Processor<ResetPassword> for EmailProviderService {
     async fn process(&self, input: ResetPassword) -> Result<ResetPasswordResult, framework::Error> {
         let Some(otp) = self
             .process(VerifyEmailOtp {
-                user_id: Uuid::nil(),
+                user_id: None,
                 email: input.email.clone(),
                 otp: input.otp.clone(),
                 usage: EmailOtpUsage::PasswordReset,
@@ -520,24 +699,47 @@ impl Processor<ResetPassword> for EmailProviderService {
         };
         let password_hash =
             hash_password(&input.new_password).map_err(|_| framework::Error::InvalidInput)?;
+        if self
+            .db
+            .process(MarkEmailOtpAsUsed { id: otp.id })
+            .await?
+            .is_none()
+        {
+            return Ok(ResetPasswordResult::InvalidOtp);
+        }
         self.db
             .process(UpdateUserPassword {
                 user_id: user.id,
                 password_hash,
             })
             .await?;
-        self.db.process(MarkEmailOtpAsUsed { id: otp.id }).await?;
         self.session_service
             .process(TerminateAllUserSessions { user_id: user.id })
             .await?;
-        if input.auto_login {
-            let session_id = self
-                .session_service
-                .process(CreateSession { user_id: user.id })
-                .await?;
-            Ok(ResetPasswordResult::SuccessWithSession(session_id))
-        } else {
-            Ok(ResetPasswordResult::Success)
+        if !input.auto_login {
+            return Ok(ResetPasswordResult::Success);
+        }
+        // the reset proves access to the mailbox only, the login checks still apply
+        let login = self
+            .process(FinishEmailLogin {
+                user_id: user.id,
+                login_method: LoginMethod::PasswordReset,
+                client: input.client,
+            })
+            .await?;
+        match login {
+            EmailLoginResult::Success(session_id) => {
+                Ok(ResetPasswordResult::SuccessWithSession(session_id))
+            }
+            EmailLoginResult::MfaRequired(token) => {
+                Ok(ResetPasswordResult::SuccessWithMfaRequired(token))
+            }
+            EmailLoginResult::Suspended(suspension) => {
+                Ok(ResetPasswordResult::SuccessButSuspended(suspension))
+            }
+            EmailLoginResult::WrongCredential | EmailLoginResult::MethodNotAvailable => {
+                Ok(ResetPasswordResult::Success)
+            }
         }
     }
 }
@@ -681,7 +883,7 @@ impl Processor<UserChangeEmailAddress> for EmailProviderService {
         &self,
         input: UserChangeEmailAddress,
     ) -> Result<ChangeEmailAddressResult, framework::Error> {
-        let config = find_config_from_redis::<AuthConfig>(&mut self.config_store.clone()).await?;
+        let config = self.auth_config.load();
         if !config.email_provider.domain.check_addr(&input.new_email) {
             return Ok(ChangeEmailAddressResult::InvalidEmail);
         }
@@ -693,7 +895,7 @@ impl Processor<UserChangeEmailAddress> for EmailProviderService {
             }),
             // otp_verified
             self.process(VerifyEmailOtp {
-                user_id: input.user_id,
+                user_id: Some(input.user_id),
                 email: input.new_email.clone(),
                 otp: input.otp.clone(),
                 usage: EmailOtpUsage::ChangeEmailAddress,
@@ -729,20 +931,32 @@ impl Processor<UserChangeEmailAddress> for EmailProviderService {
         if duplicated_email {
             return Ok(ChangeEmailAddressResult::EmailAddressDuplicated);
         }
+        if self
+            .db
+            .process(MarkEmailOtpAsUsed { id: otp.id })
+            .await?
+            .is_none()
+        {
+            return Ok(ChangeEmailAddressResult::InvalidOtp);
+        }
         self.db
             .process(UpdateUserEmail {
                 id: input.user_id,
                 email: input.new_email.clone(),
             })
             .await?;
-        self.db.process(MarkEmailOtpAsUsed { id: otp.id }).await?;
         Ok(ChangeEmailAddressResult::Success)
     }
 }
 
 #[derive(Debug, Clone)]
+/// Find the valid OTP matching a code. Every call counts against the attempt limit of the email
+/// address, and once it is reached codes are rejected without being checked.
+///
+/// The caller still has to use the OTP up with [`MarkEmailOtpAsUsed`].
 struct VerifyEmailOtp {
-    pub user_id: Uuid,
+    /// `None` for the OTPs sent before the user signed in
+    pub user_id: Option<Uuid>,
     pub email: String,
     pub otp: String,
     pub usage: EmailOtpUsage,
@@ -752,14 +966,45 @@ impl Processor<VerifyEmailOtp> for EmailProviderService {
     type Output = Option<EmailOtp>;
     type Error = framework::Error;
     async fn process(&self, input: VerifyEmailOtp) -> Result<Option<EmailOtp>, framework::Error> {
-        self.db
-            .process(FindValidEmailOtp {
-                user_id: input.user_id,
-                email: input.email,
-                usage: input.usage,
-                otp_code: input.otp,
-            })
-            .await
-            .map_err(Into::into)
+        let (max_attempts, attempt_window) = {
+            let config = &self.auth_config.load().email_provider.otp;
+            (config.max_attempts, config.attempt_window)
+        };
+        let attempt_window: std::time::Duration = attempt_window.try_into().map_err(|e| {
+            framework::Error::BusinessPanic(anyhow::anyhow!("Invalid OTP attempt window: {e}"))
+        })?;
+        let mut redis = self.redis.clone();
+        let attempts_key = EmailOtpAttemptsKey(input.email.clone());
+        let attempts =
+            TotpGuard::count_attempt(&mut redis, attempts_key.clone(), attempt_window).await?;
+        if attempts > u64::from(max_attempts) {
+            tracing::warn!(attempts, "Email OTP attempt limit reached");
+            return Ok(None);
+        }
+        let otp = match input.user_id {
+            Some(user_id) => {
+                self.db
+                    .process(FindValidEmailOtp {
+                        user_id,
+                        email: input.email,
+                        usage: input.usage,
+                        otp_code: input.otp,
+                    })
+                    .await?
+            }
+            None => {
+                self.db
+                    .process(FindValidUnboundEmailOtp {
+                        email: input.email,
+                        usage: input.usage,
+                        otp_code: input.otp,
+                    })
+                    .await?
+            }
+        };
+        if otp.is_some() {
+            TotpGuard::reset_attempts(&mut redis, attempts_key).await?;
+        }
+        Ok(otp)
     }
 }
@@ -1,14 +1,28 @@
 use crate::config::AuthConfig;
 use crate::entities::db::email_otp::{EmailOtpUsage, FindValidEmailOtp};
+use crate::entities::db::recovery_code::{
+    ConsumeRecoveryCode, CountUnusedRecoveryCodes, RemoveRecoveryCodesByUserId,
+    ReplaceRecoveryCodes,
+};
 use crate::entities::db::totp::{CreateTotp, FindTotpByUserId, RemoveTotpByUserId};
-use crate::entities::db::user_account::FindUserAccountById;
+use crate::entities::db::user_account::{FindUserAccountById, FindUserSuspension, UserSuspension};
+use crate::entities::db::webauthn_credential::CheckWebauthnCredentialExists;
 use crate::entities::redis::mfa_token::{MfaLoginToken, MfaLoginTokenKey};
-use crate::entities::redis::session::SessionId;
+use crate::entities::redis::session::{LoginMethod, SessionClient, SessionId};
 use crate::entities::redis::sudo_token::{SudoToken, SudoTokenKey};
+use crate::entities::redis::totp_guard::{TotpAttemptsKey, TotpLastStepKey};
 use crate::entities::redis::totp_setup::{PendingTotpSetup, PendingTotpSetupKey};
+use crate::entities::redis::webauthn_challenge::PasskeyChallengePurpose;
+use crate::events::security::{SecurityNotificationEvent, SecurityNotificationKind};
+use crate::services::passkey::{
+    PasskeyChallenge, PasskeyService, StartPasskeyChallenge, StartPasskeyRegistration,
+    VerifyPasskeyAssertion,
+};
 use crate::services::session::{CreateSession, SessionService};
-use admin::utils::config_provider::find_config_from_redis;
-use framework::rabbitmq::AmqpPool;
+use crate::utils::recovery_code::{generate_recovery_codes, hash_recovery_code};
+use admin::utils::config_provider::LiveConfig;
+use admin::utils::totp::{TotpLimits, verify_totp};
+use framework::rabbitmq::{AmqpMessageSend, AmqpPool};
 use framework::redis::{KeyValueRead, KeyValueWrite, RedisConnection};
 use framework::sqlx::DatabaseProcessor;
 use kanau::message::MessageDe;
@@ -16,17 +30,21 @@ use kanau::processor::Processor;
 use redis::AsyncCommands;
 use tracing::instrument;
 use uuid::Uuid;
+use webauthn_rs::prelude::{CreationChallengeResponse, PublicKeyCredential};
 
 #[derive(Clone)]
 pub struct MfaService {
     pub db: DatabaseProcessor,
     pub config_store: RedisConnection,
+    pub auth_config: LiveConfig<AuthConfig>,
     pub redis: RedisConnection,
     pub mq: AmqpPool,
     pub session_service: SessionService,
+    pub passkey_service: PasskeyService,
 }
 
 #[derive(Debug, Clone)]
+/// A user has MFA enabled once they have a TOTP or a passkey.
 pub struct CheckMfaEnabled {
     pub user_id: Uuid,
 }
@@ -41,7 +59,15 @@ impl Processor<CheckMfaEnabled> for MfaService {
                 user_id: input.user_id,
             })
             .await?;
-        Ok(totp.is_some())
+        if totp.is_some() {
+            return Ok(true);
+        }
+        Ok(self
+            .db
+            .process(CheckWebauthnCredentialExists {
+                user_id: input.user_id,
+            })
+            .await?)
     }
 }
 
@@ -64,7 +90,8 @@ impl Processor<VerifyMfa> for MfaService {
         else {
             return Ok(false);
         };
-        Ok(verify_totp(totp.secret, input.code))
+        self.verify_user_totp(input.user_id, &totp.secret, input.code)
+            .await
     }
 }
 
@@ -82,6 +109,19 @@ impl Processor<RemoveMfa> for MfaService {
                 user_id: input.user_id,
             })
             .await?;
+        // Recovery codes are only useful while another factor is left
+        let mfa_enabled = self
+            .process(CheckMfaEnabled {
+                user_id: input.user_id,
+            })
+            .await?;
+        if !mfa_enabled {
+            self.db
+                .process(RemoveRecoveryCodesByUserId {
+                    user_id: input.user_id,
+                })
+                .await?;
+        }
         Ok(())
     }
 }
@@ -115,7 +155,7 @@ impl Processor<StartConfiguringTotp> for MfaService {
             secret: secret.into(),
         };
         let mut redis = self.redis.clone();
-        let config = find_config_from_redis::<AuthConfig>(&mut redis).await?.mfa;
+        let config = &self.auth_config.load().mfa;
         let setup_ttl: std::time::Duration = config.setup_code_ttl.try_into().map_err(|e| {
             framework::Error::BusinessPanic(anyhow::anyhow!("Invalid mfa token ttl: {e}"))
         })?;
@@ -130,9 +170,10 @@ pub struct FinishConfiguringTotp {
     pub code: u32,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FinishConfiguringTotpResult {
-    Success,
+    /// Carries the new recovery codes, which replace any earlier ones. They are only shown once.
+    Success(Vec<String>),
     InvalidCode,
     Duplicate,
     Expired,
@@ -161,7 +202,10 @@ impl Processor<FinishConfiguringTotp> for MfaService {
         if has_totp {
             return Ok(FinishConfiguringTotpResult::Duplicate);
         }
-        if !verify_totp(pending.secret.to_vec(), input.code) {
+        if !self
+            .verify_user_totp(input.user_id, &pending.secret, input.code)
+            .await?
+        {
             return Ok(FinishConfiguringTotpResult::InvalidCode);
         }
         self.db
@@ -170,13 +214,187 @@ impl Processor<FinishConfiguringTotp> for MfaService {
                 secret: pending.secret.to_vec(),
             })
             .await?;
-        Ok(FinishConfiguringTotpResult::Success)
+        let recovery_codes = self.replace_recovery_codes(input.user_id).await?;
+        Ok(FinishConfiguringTotpResult::Success(recovery_codes))
+    }
+}
+
+impl MfaService {
+    /// Check a TOTP code of the user, see [`verify_totp`].
+    async fn verify_user_totp(
+        &self,
+        user_id: Uuid,
+        secret: &[u8],
+        code: u32,
+    ) -> Result<bool, framework::Error> {
+        let limits = {
+            let config = &self.auth_config.load().mfa;
+            TotpLimits {
+                skew_steps: config.totp_skew_steps,
+                max_attempts: config.totp_max_attempts,
+                attempt_window: config.totp_attempt_window,
+            }
+        };
+        let mut redis = self.redis.clone();
+        verify_totp(
+            &mut redis,
+            TotpAttemptsKey(user_id),
+            TotpLastStepKey(user_id),
+            secret,
+            code,
+            limits,
+        )
+        .await
+    }
+
+    async fn replace_recovery_codes(&self, user_id: Uuid) -> Result<Vec<String>, framework::Error> {
+        let codes = generate_recovery_codes();
+        self.db
+            .process(ReplaceRecoveryCodes {
+                user_id,
+                code_hashes: codes.iter().map(|code| hash_recovery_code(code)).collect(),
+            })
+            .await?;
+        Ok(codes)
+    }
+
+    /// Consume the recovery code and tell the user about it.
+    async fn use_recovery_code(
+        &self,
+        user_id: Uuid,
+        code: &str,
+        client: &SessionClient,
+    ) -> Result<bool, framework::Error> {
+        let consumed = self
+            .db
+            .process(ConsumeRecoveryCode {
+                user_id,
+                code_hash: hash_recovery_code(code),
+            })
+            .await?;
+        if !consumed {
+            return Ok(false);
+        }
+        // the code is burnt already, so a failed notification must not fail the login
+        if let Err(e) = self.notify_recovery_code_used(user_id, client).await {
+            tracing::error!(%user_id, error = %e, "Failed to send recovery code notification");
+        }
+        Ok(true)
+    }
+
+    async fn notify_recovery_code_used(
+        &self,
+        user_id: Uuid,
+        client: &SessionClient,
+    ) -> Result<(), framework::Error> {
+        let remaining_codes = self.process(CountRecoveryCodes { user_id }).await?;
+        SecurityNotificationEvent {
+            user_id,
+            occurred_at: framework::now_time().assume_utc().unix_timestamp() as u64,
+            kind: SecurityNotificationKind::RecoveryCodeUsed { remaining_codes },
+            ip: client.ip.clone(),
+            user_agent: client.user_agent.clone(),
+        }
+        .send(&self.mq)
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CountRecoveryCodes {
+    pub user_id: Uuid,
+}
+
+impl Processor<CountRecoveryCodes> for MfaService {
+    /// Unused recovery codes left
+    type Output = u32;
+    type Error = framework::Error;
+    async fn process(&self, input: CountRecoveryCodes) -> Result<u32, framework::Error> {
+        let count = self
+            .db
+            .process(CountUnusedRecoveryCodes {
+                user_id: input.user_id,
+            })
+            .await?;
+        Ok(count.try_into().unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Invalidate all recovery codes of the user and issue new ones.
+pub struct RegenerateRecoveryCodes {
+    pub user_id: Uuid,
+    pub sudo_token: [u8; 16],
+}
+
+impl Processor<RegenerateRecoveryCodes> for MfaService {
+    /// `None` if the user has no MFA to recover from
+    type Output = Option<Vec<String>>;
+    type Error = framework::Error;
+    #[instrument(skip_all, err, fields(user_id = %input.user_id))]
+    async fn process(
+        &self,
+        input: RegenerateRecoveryCodes,
+    ) -> Result<Option<Vec<String>>, framework::Error> {
+        let verified = self
+            .process(VerifySudoToken {
+                user_id: input.user_id,
+                token: input.sudo_token,
+            })
+            .await?;
+        if !verified {
+            return Err(framework::Error::PermissionsDenied);
+        }
+        let mfa_enabled = self
+            .process(CheckMfaEnabled {
+                user_id: input.user_id,
+            })
+            .await?;
+        if !mfa_enabled {
+            return Ok(None);
+        }
+        self.replace_recovery_codes(input.user_id).await.map(Some)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StartConfiguringPasskey {
+    pub user_id: Uuid,
+    pub sudo_token: [u8; 16],
+    /// Display name of the new passkey
+    pub name: String,
+}
+
+impl Processor<StartConfiguringPasskey> for MfaService {
+    type Output = CreationChallengeResponse;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: StartConfiguringPasskey,
+    ) -> Result<CreationChallengeResponse, framework::Error> {
+        let verified = self
+            .process(VerifySudoToken {
+                user_id: input.user_id,
+                token: input.sudo_token,
+            })
+            .await?;
+        if !verified {
+            return Err(framework::Error::PermissionsDenied);
+        }
+        self.passkey_service
+            .process(StartPasskeyRegistration {
+                user_id: input.user_id,
+                name: input.name,
+            })
+            .await
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct CreateLoginMfaSession {
     pub user_id: Uuid,
+    pub login_method: LoginMethod,
 }
 
 impl Processor<CreateLoginMfaSession> for MfaService {
@@ -187,13 +405,14 @@ impl Processor<CreateLoginMfaSession> for MfaService {
         input: CreateLoginMfaSession,
     ) -> Result<MfaLoginToken, framework::Error> {
         let mut redis = self.redis.clone();
-        let config = find_config_from_redis::<AuthConfig>(&mut redis).await?;
+        let config = self.auth_config.load();
         let token_ttl: std::time::Duration = config.mfa.token_ttl.try_into().map_err(|e| {
             framework::Error::BusinessPanic(anyhow::anyhow!("Invalid mfa token ttl: {e}"))
         })?;
         let token = MfaLoginToken {
             token: rand::random(),
             user_id: input.user_id,
+            login_method: input.login_method,
         };
         token.write_with_ttl(&mut redis, token_ttl).await?;
         Ok(token)
@@ -204,6 +423,7 @@ impl Processor<CreateLoginMfaSession> for MfaService {
 pub enum SudoMethod {
     Totp,
     Email,
+    Passkey,
 }
 
 #[derive(Debug, Clone)]
@@ -222,10 +442,20 @@ impl Processor<ListSudoMethods> for MfaService {
             })
             .await?
             .is_some();
+        let has_passkey = self
+            .db
+            .process(CheckWebauthnCredentialExists {
+                user_id: input.user_id,
+            })
+            .await?;
         let mut methods = Vec::new();
         if has_totp {
             methods.push(SudoMethod::Totp);
-        } else {
+        }
+        if has_passkey {
+            methods.push(SudoMethod::Passkey);
+        }
+        if methods.is_empty() {
             methods.push(SudoMethod::Email);
         }
         Ok(methods)
@@ -242,7 +472,7 @@ impl Processor<EnterSudoModeNoCheck> for MfaService {
     type Error = framework::Error;
     async fn process(&self, input: EnterSudoModeNoCheck) -> Result<SudoToken, framework::Error> {
         let mut redis = self.config_store.clone();
-        let config = find_config_from_redis::<AuthConfig>(&mut redis).await?;
+        let config = self.auth_config.load();
         let ttl: std::time::Duration = config.sudo_token_ttl.try_into().map_err(|e| {
             framework::Error::BusinessPanic(anyhow::anyhow!("Invalid sudo token ttl: {e}"))
         })?;
@@ -271,10 +501,36 @@ impl Processor<VerifySudoToken> for MfaService {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct StartPasskeySudo {
+    pub user_id: Uuid,
+}
+
+impl Processor<StartPasskeySudo> for MfaService {
+    /// `None` if the user has no passkey
+    type Output = Option<PasskeyChallenge>;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: StartPasskeySudo,
+    ) -> Result<Option<PasskeyChallenge>, framework::Error> {
+        self.passkey_service
+            .process(StartPasskeyChallenge {
+                purpose: PasskeyChallengePurpose::Sudo(input.user_id),
+            })
+            .await
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum SudoVerificationMethod {
     Totp(u32),
     EmailOtp(String),
+    /// Answer to a challenge from [StartPasskeySudo]
+    Passkey {
+        challenge_id: [u8; 16],
+        credential: Box<PublicKeyCredential>,
+    },
 }
 
 impl SudoVerificationMethod {
@@ -282,6 +538,7 @@ impl SudoVerificationMethod {
         match self {
             SudoVerificationMethod::Totp(_) => "totp",
             SudoVerificationMethod::EmailOtp(_) => "email_otp",
+            SudoVerificationMethod::Passkey { .. } => "passkey",
         }
     }
 }
@@ -318,6 +575,9 @@ impl Processor<VerifyAndEnterSudo> for MfaService {
         let method_allowed = match &input.method {
             SudoVerificationMethod::Totp(_) => allowed_methods.contains(&SudoMethod::Totp),
             SudoVerificationMethod::EmailOtp(_) => allowed_methods.contains(&SudoMethod::Email),
+            SudoVerificationMethod::Passkey { .. } => {
+                allowed_methods.contains(&SudoMethod::Passkey)
+            }
         };
         if !method_allowed {
             return Ok(VerifyAndEnterSudoResult::MethodNotAllowed);
@@ -333,7 +593,8 @@ impl Processor<VerifyAndEnterSudo> for MfaService {
                 else {
                     return Ok(VerifyAndEnterSudoResult::InvalidCredential);
                 };
-                verify_totp(totp.secret, code)
+                self.verify_user_totp(input.user_id, &totp.secret, code)
+                    .await?
             } // SudoVerificationMethod::Totp(code) => { ... }
             SudoVerificationMethod::EmailOtp(code) => {
                 let Some(user) = self
@@ -358,6 +619,18 @@ impl Processor<VerifyAndEnterSudo> for MfaService {
                 };
                 true
             } // SudoVerificationMethod::EmailOtp(code) => { ... }
+            SudoVerificationMethod::Passkey {
+                challenge_id,
+                credential,
+            } => self
+                .passkey_service
+                .process(VerifyPasskeyAssertion {
+                    challenge_id,
+                    purpose: PasskeyChallengePurpose::Sudo(input.user_id),
+                    credential,
+                })
+                .await?
+                .is_some(),
         }; // let verified = match input.method { ... }
         if !verified {
             return Ok(VerifyAndEnterSudoResult::InvalidCredential);
@@ -371,10 +644,51 @@ impl Processor<VerifyAndEnterSudo> for MfaService {
     }
 }
 
+#[derive(Debug, Clone)]
+/// Start a passkey assertion as the second factor of a login.
+pub struct StartPasskeyMfa {
+    pub mfa_token: [u8; 32],
+}
+
+impl Processor<StartPasskeyMfa> for MfaService {
+    /// `None` if the MFA token is invalid or the user has no passkey
+    type Output = Option<PasskeyChallenge>;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: StartPasskeyMfa,
+    ) -> Result<Option<PasskeyChallenge>, framework::Error> {
+        let mut redis = self.redis.clone();
+        let Some(mfa_login_token) =
+            MfaLoginToken::read(&mut redis, MfaLoginTokenKey(input.mfa_token)).await?
+        else {
+            return Ok(None);
+        };
+        self.passkey_service
+            .process(StartPasskeyChallenge {
+                purpose: PasskeyChallengePurpose::Mfa(mfa_login_token.user_id),
+            })
+            .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MfaFactor {
+    Totp(u32),
+    /// Answer to a challenge from [StartPasskeyMfa]
+    Passkey {
+        challenge_id: [u8; 16],
+        credential: Box<PublicKeyCredential>,
+    },
+    /// Single-use fallback for a lost device. Using one notifies the user.
+    RecoveryCode(String),
+}
+
 #[derive(Debug, Clone)]
 pub struct VerifyMfaLogin {
     pub mfa_token: [u8; 32],
-    pub code: u32,
+    pub factor: MfaFactor,
+    pub client: SessionClient,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -383,6 +697,8 @@ pub enum VerifyMfaLoginResult {
     InvalidToken,
     InvalidCode,
     NoNeed,
+    /// The account was suspended after the first factor was verified.
+    Suspended(UserSuspension),
 }
 
 impl Processor<VerifyMfaLogin> for MfaService {
@@ -404,41 +720,72 @@ impl Processor<VerifyMfaLogin> for MfaService {
         let mfa_login_token = MfaLoginToken::from_bytes(&bytes)
             .map_err(|e| framework::Error::DeserializeError(e.into()))?;
 
-        // 2. Verify the TOTP code
-        let Some(totp) = self
-            .db
-            .process(FindTotpByUserId {
+        // 2. Verify the second factor
+        let mfa_enabled = self
+            .process(CheckMfaEnabled {
                 user_id: mfa_login_token.user_id,
             })
-            .await?
-        else {
+            .await?;
+        if !mfa_enabled {
             return Ok(VerifyMfaLoginResult::NoNeed);
-        };
+        }
 
-        if !verify_totp(totp.secret, input.code) {
+        let verified = match input.factor {
+            MfaFactor::Totp(code) => {
+                match self
+                    .db
+                    .process(FindTotpByUserId {
+                        user_id: mfa_login_token.user_id,
+                    })
+                    .await?
+                {
+                    Some(totp) => {
+                        self.verify_user_totp(mfa_login_token.user_id, &totp.secret, code)
+                            .await?
+                    }
+                    None => false,
+                }
+            }
+            MfaFactor::Passkey {
+                challenge_id,
+                credential,
+            } => self
+                .passkey_service
+                .process(VerifyPasskeyAssertion {
+                    challenge_id,
+                    purpose: PasskeyChallengePurpose::Mfa(mfa_login_token.user_id),
+                    credential,
+                })
+                .await?
+                .is_some(),
+            MfaFactor::RecoveryCode(code) => {
+                self.use_recovery_code(mfa_login_token.user_id, &code, &input.client)
+                    .await?
+            }
+        };
+        if !verified {
             return Ok(VerifyMfaLoginResult::InvalidCode);
         }
 
+        if let Some(suspension) = self
+            .db
+            .process(FindUserSuspension {
+                user_id: mfa_login_token.user_id,
+            })
+            .await?
+        {
+            return Ok(VerifyMfaLoginResult::Suspended(suspension));
+        }
+
         let session_id = self
             .session_service
             .process(CreateSession {
                 user_id: mfa_login_token.user_id,
+                login_method: mfa_login_token.login_method,
+                client: input.client,
             })
             .await?;
 
         Ok(VerifyMfaLoginResult::Success(session_id))
     }
 }
-
-fn verify_totp(secret: Vec<u8>, code: u32) -> bool {
-    let Ok(rfc6238) = totp_rs::Rfc6238::with_defaults(secret) else {
-        return false;
-    };
-    let Ok(totp) = totp_rs::TOTP::from_rfc6238(rfc6238) else {
-        return false;
-    };
-    let Ok(current) = totp.generate_current() else {
-        return false;
-    };
-    current == code.to_string()
-}
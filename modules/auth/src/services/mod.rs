@@ -2,5 +2,8 @@ pub mod admin_manage;
 pub mod email_provider;
 pub mod mfa;
 pub mod oauth_provider;
+pub mod passkey;
 pub mod session;
+pub mod token;
 pub mod user_account;
+pub mod wallet_login;
@@ -1,15 +1,18 @@
-use crate::config::AuthConfig;
+use crate::config::{AuthConfig, OAuthProviderConfig};
 use crate::entities::db::oauth_account::{
     AppendOAuthAccount, DeleteOAuthAccountById, FindOAuthAccountByProviderUserId,
     FindOAuthAccountsByUserId, OAuthAccount, RegisterOAuthAccount,
 };
+use crate::entities::db::user_account::{FindUserSuspension, UserSuspension};
 use crate::entities::redis::oauth_challenge::{OAuthAction, OAuthChallenge, OAuthChallengeKey};
-use crate::entities::redis::session::SessionId;
+use crate::entities::redis::session::{LoginMethod, SessionClient, SessionId};
 use crate::services::mfa::{CheckMfaEnabled, CreateLoginMfaSession, MfaService, VerifySudoToken};
 use crate::services::session::{CreateSession, SessionService};
 use crate::utils::oauth::OAuthUserInfo;
-use crate::utils::oauth::providers::{OAuthDataFetch, OAuthProviderName};
-use admin::utils::config_provider::find_config_from_redis;
+use crate::utils::oauth::oidc::{self, OidcError};
+use crate::utils::oauth::providers::{OAuthDataFetch, OAuthProviderConstants, OAuthProviderName};
+use crate::utils::wallet::is_wallet_email;
+use admin::utils::config_provider::LiveConfig;
 use framework::rabbitmq::AmqpPool;
 use framework::redis::{KeyValueWrite, RedisConnection};
 use framework::sqlx::DatabaseProcessor;
@@ -21,13 +24,105 @@ use uuid::Uuid;
 #[derive(Clone)]
 pub struct OAuthProviderService {
     pub db: DatabaseProcessor,
-    pub config_store: RedisConnection,
+    pub auth_config: LiveConfig<AuthConfig>,
     pub redis: RedisConnection,
     pub mq: AmqpPool,
     pub session_service: SessionService,
     pub mfa_service: MfaService,
 }
 
+/// The user behind an authorization code
+struct ExchangedOAuthCode {
+    access_token: String,
+    refresh_token: Option<String>,
+    user_info: OAuthUserInfo,
+}
+
+fn oidc_error(e: OidcError) -> framework::Error {
+    match e {
+        OidcError::FetchError(_) => framework::Error::Io(e.into()),
+        OidcError::IssuerMismatch(_) => framework::Error::BusinessPanic(e.into()),
+        _ => {
+            tracing::warn!(error = %e, "Rejected OpenID Connect ID token");
+            framework::Error::PermissionsDenied
+        }
+    }
+}
+
+impl OAuthProviderService {
+    /// The endpoints of the provider. Those of OpenID Connect providers come from the cached
+    /// discovery document of the issuer.
+    async fn provider_constants(
+        provider: &OAuthProviderConfig,
+    ) -> Result<OAuthProviderConstants, framework::Error> {
+        if let Some(constants) = provider.name.oauth_constants() {
+            return Ok(constants);
+        }
+        let (OAuthProviderName::Oidc(id), Some(oidc)) = (provider.name, &provider.oidc) else {
+            return Err(framework::Error::BusinessPanic(anyhow::anyhow!(
+                "OpenID Connect provider {} has no issuer",
+                provider.name
+            )));
+        };
+        let discovery = oidc::discover(&oidc.issuer).await.map_err(oidc_error)?;
+        Ok(OAuthProviderConstants::oidc(id, discovery, &oidc.scopes))
+    }
+
+    /// Exchange the authorization code for tokens and identify the user with them. OpenID
+    /// Connect providers identify the user by the ID token, which must carry the nonce of the
+    /// challenge.
+    async fn exchange_code(
+        provider: &OAuthProviderConfig,
+        challenge: &OAuthChallenge,
+        code: &str,
+    ) -> Result<ExchangedOAuthCode, framework::Error> {
+        let constants = Self::provider_constants(provider).await?;
+        let client = constants
+            .build_oauth_client(&provider.config, &challenge.redirect_uri)
+            .map_err(|e| framework::Error::BusinessPanic(anyhow::anyhow!("Invalid URL: {e}")))?;
+
+        let token_response = constants
+            .exchange_code(&client, code, challenge.pkce_verifier.as_deref())
+            .await
+            .map_err(|e| framework::Error::Io(anyhow::anyhow!("Token exchange failed: {e}")))?;
+
+        let access_token = token_response.access_token().secret().to_string();
+        let refresh_token = token_response
+            .refresh_token()
+            .map(|t| t.secret().to_string());
+
+        let user_info = match &provider.oidc {
+            Some(oidc) => {
+                let id_token = token_response
+                    .extra_fields()
+                    .id_token
+                    .as_deref()
+                    .ok_or(OidcError::MissingIdToken)
+                    .map_err(oidc_error)?;
+                let nonce = challenge
+                    .nonce
+                    .as_deref()
+                    .ok_or(framework::Error::InvalidInput)?;
+                oidc::validate_id_token(&oidc.issuer, &provider.config.client_id, nonce, id_token)
+                    .await
+                    .map_err(oidc_error)?
+                    .into()
+            }
+            None => challenge
+                .provider_name
+                .fetch_user_info(&access_token)
+                .await
+                .map_err(|e| framework::Error::Io(e.into()))?,
+        };
+
+        Ok(ExchangedOAuthCode {
+            access_token,
+            refresh_token,
+            user_info,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CreateOAuthChallenge {
     pub provider_name: OAuthProviderName,
@@ -51,8 +146,7 @@ impl Processor<CreateOAuthChallenge> for OAuthProviderService {
         &self,
         input: CreateOAuthChallenge,
     ) -> Result<CreateOAuthChallengeResult, framework::Error> {
-        let mut redis = self.config_store.clone();
-        let config = find_config_from_redis::<AuthConfig>(&mut redis).await?;
+        let config = self.auth_config.load();
 
         // Find the provider config
         let Some(provider_config) = config
@@ -87,7 +181,7 @@ impl Processor<CreateOAuthChallenge> for OAuthProviderService {
         }
 
         // Build OAuth client
-        let constants = input.provider_name.oauth_constants();
+        let constants = Self::provider_constants(provider_config).await?;
         let redirect_uri = input.return_to.as_str();
         let client = constants
             .build_oauth_client(&provider_config.config, redirect_uri)
@@ -103,6 +197,7 @@ impl Processor<CreateOAuthChallenge> for OAuthProviderService {
             action: input.action,
             redirect_uri: redirect_uri.to_string(),
             pkce_verifier: auth_essentials.pkce_verifier,
+            nonce: auth_essentials.nonce,
         };
 
         let challenge_ttl: std::time::Duration = config
@@ -130,6 +225,7 @@ pub struct OAuthCallback {
     pub state: OAuthChallengeKey,
     pub redirect_uri: Url,
     pub user_id: Option<Uuid>,
+    pub client: SessionClient,
 }
 
 #[derive(Debug, Clone)]
@@ -157,6 +253,7 @@ impl Processor<OAuthCallback> for OAuthProviderService {
                 OAuthLoginOrRegisterRoute {
                     code: input.code,
                     challenge,
+                    client: input.client,
                 },
             )),
             (OAuthAction::Login, Some(_)) => Ok(OAuthCallbackRoute::Unmatched),
@@ -177,6 +274,7 @@ impl Processor<OAuthCallback> for OAuthProviderService {
 pub struct OAuthLoginOrRegisterRoute {
     pub code: String,
     pub challenge: OAuthChallenge,
+    pub client: SessionClient,
 }
 
 #[derive(Debug, Clone)]
@@ -192,8 +290,7 @@ impl Processor<OAuthLoginOrRegisterRoute> for OAuthProviderService {
         &self,
         input: OAuthLoginOrRegisterRoute,
     ) -> Result<OAuthLoginRouteResult, framework::Error> {
-        let mut redis = self.config_store.clone();
-        let config = find_config_from_redis::<AuthConfig>(&mut redis).await?;
+        let config = self.auth_config.load();
 
         // Find the provider config
         let provider_config = config
@@ -201,34 +298,12 @@ impl Processor<OAuthLoginOrRegisterRoute> for OAuthProviderService {
             .find_provider(input.challenge.provider_name)
             .ok_or(framework::Error::InvalidInput)?;
 
-        // Build OAuth client
-        let constants = input.challenge.provider_name.oauth_constants();
-        let client = constants
-            .build_oauth_client(&provider_config.config, &input.challenge.redirect_uri)
-            .map_err(|e| framework::Error::BusinessPanic(anyhow::anyhow!("Invalid URL: {e}")))?;
-
-        // Exchange the authorization code for tokens
-        let token_response = constants
-            .exchange_code(
-                &client,
-                &input.code,
-                input.challenge.pkce_verifier.as_deref(),
-            )
-            .await
-            .map_err(|e| framework::Error::Io(e.into()))?;
-
-        let access_token = token_response.access_token().secret().to_string();
-        let refresh_token = token_response
-            .refresh_token()
-            .map(|t| t.secret().to_string());
-
-        // Fetch user info from the OAuth provider
-        let user_info = input
-            .challenge
-            .provider_name
-            .fetch_user_info(&access_token)
-            .await
-            .map_err(|e| framework::Error::Io(e.into()))?;
+        // Exchange the authorization code and fetch user info from the OAuth provider
+        let ExchangedOAuthCode {
+            access_token,
+            refresh_token,
+            user_info,
+        } = Self::exchange_code(provider_config, &input.challenge, &input.code).await?;
 
         // Check if OAuth account already exists
         let existing_account = self
@@ -244,6 +319,7 @@ impl Processor<OAuthLoginOrRegisterRoute> for OAuthProviderService {
             Ok(OAuthLoginRouteResult::Login(OAuthLogin {
                 user_id: account.user_id,
                 provider_name: input.challenge.provider_name,
+                client: input.client,
             }))
         } else {
             // New user - register
@@ -252,6 +328,7 @@ impl Processor<OAuthLoginOrRegisterRoute> for OAuthProviderService {
                 oauth_access_token: access_token,
                 oauth_refresh_token: refresh_token,
                 user_info,
+                client: input.client,
             }))
         }
     }
@@ -261,18 +338,30 @@ impl Processor<OAuthLoginOrRegisterRoute> for OAuthProviderService {
 pub struct OAuthLogin {
     pub user_id: Uuid,
     pub provider_name: OAuthProviderName,
+    pub client: SessionClient,
 }
 
 #[derive(Debug, Clone)]
 pub enum OAuthLoginResult {
     LoggedIn(SessionId),
     RequiredMfa([u8; 32]),
+    Suspended(UserSuspension),
 }
 
 impl Processor<OAuthLogin> for OAuthProviderService {
     type Output = OAuthLoginResult;
     type Error = framework::Error;
     async fn process(&self, input: OAuthLogin) -> Result<OAuthLoginResult, framework::Error> {
+        if let Some(suspension) = self
+            .db
+            .process(FindUserSuspension {
+                user_id: input.user_id,
+            })
+            .await?
+        {
+            return Ok(OAuthLoginResult::Suspended(suspension));
+        }
+
         // Check if MFA is enabled for this user
         let mfa_enabled = self
             .mfa_service
@@ -287,6 +376,7 @@ impl Processor<OAuthLogin> for OAuthProviderService {
                 .mfa_service
                 .process(CreateLoginMfaSession {
                     user_id: input.user_id,
+                    login_method: LoginMethod::OAuth(input.provider_name),
                 })
                 .await?;
             Ok(OAuthLoginResult::RequiredMfa(mfa_token.token))
@@ -296,6 +386,8 @@ impl Processor<OAuthLogin> for OAuthProviderService {
                 .session_service
                 .process(CreateSession {
                     user_id: input.user_id,
+                    login_method: LoginMethod::OAuth(input.provider_name),
+                    client: input.client,
                 })
                 .await?;
             Ok(OAuthLoginResult::LoggedIn(session_id))
@@ -309,6 +401,7 @@ pub struct OAuthRegister {
     pub oauth_access_token: String,
     pub oauth_refresh_token: Option<String>,
     pub user_info: OAuthUserInfo,
+    pub client: SessionClient,
 }
 
 #[derive(Debug, Clone)]
@@ -327,6 +420,9 @@ impl Processor<OAuthRegister> for OAuthProviderService {
             .email
             .ok_or_else(|| framework::Error::InvalidInput)?
             .to_string();
+        if is_wallet_email(&email) {
+            return Err(framework::Error::InvalidInput);
+        }
 
         // Register the OAuth account (creates user account in transaction)
         let oauth_account = self
@@ -344,6 +440,8 @@ impl Processor<OAuthRegister> for OAuthProviderService {
             .session_service
             .process(CreateSession {
                 user_id: oauth_account.user_id,
+                login_method: LoginMethod::OAuth(input.provider_name),
+                client: input.client,
             })
             .await?;
 
@@ -388,8 +486,7 @@ impl Processor<LinkOAuthAccountCallback> for OAuthProviderService {
             _ => {}
         }
 
-        let mut redis = self.config_store.clone();
-        let config = find_config_from_redis::<AuthConfig>(&mut redis).await?;
+        let config = self.auth_config.load();
 
         // Find the provider config
         let provider_config = config
@@ -397,31 +494,10 @@ impl Processor<LinkOAuthAccountCallback> for OAuthProviderService {
             .find_provider(input.challenge.provider_name)
             .ok_or(framework::Error::InvalidInput)?;
 
-        // Build OAuth client
-        let constants = input.challenge.provider_name.oauth_constants();
-        let client = constants
-            .build_oauth_client(&provider_config.config, &input.challenge.redirect_uri)
-            .map_err(|e| framework::Error::BusinessPanic(anyhow::anyhow!("Invalid URL: {e}")))?;
-
-        // Exchange the authorization code for tokens
-        let token_response = constants
-            .exchange_code(
-                &client,
-                &input.code,
-                input.challenge.pkce_verifier.as_deref(),
-            )
-            .await
-            .map_err(|e| framework::Error::Io(anyhow::anyhow!("Token exchange failed: {e}")))?;
-
-        let access_token = token_response.access_token().secret().to_string();
-
-        // Fetch user info from the OAuth provider
-        let user_info = input
-            .challenge
-            .provider_name
-            .fetch_user_info(&access_token)
-            .await
-            .map_err(|e| framework::Error::Io(e.into()))?;
+        // Exchange the authorization code and fetch user info from the OAuth provider
+        let user_info = Self::exchange_code(provider_config, &input.challenge, &input.code)
+            .await?
+            .user_info;
 
         // Check if this OAuth account is already linked to any user
         let existing_account = self
@@ -0,0 +1,378 @@
+use crate::config::AuthConfig;
+use crate::entities::db::user_account::{FindUserAccountById, FindUserSuspension, UserSuspension};
+use crate::entities::db::webauthn_credential::{
+    CreateWebauthnCredential, ListWebauthnCredentialsByUserId, MarkWebauthnCredentialUsed,
+    RemoveWebauthnCredential, WebauthnCredential,
+};
+use crate::entities::redis::session::{LoginMethod, SessionClient, SessionId};
+use crate::entities::redis::webauthn_challenge::{
+    PasskeyChallengePurpose, PendingPasskeyChallenge, PendingPasskeyChallengeKey,
+    PendingPasskeyRegistration, PendingPasskeyRegistrationKey,
+};
+use crate::services::session::{CreateSession, SessionService};
+use crate::utils::webauthn::{
+    build_webauthn, claimed_user_id, finish_assertion, finish_registration, start_assertion,
+    start_registration,
+};
+use admin::utils::config_provider::LiveConfig;
+use framework::redis::{KeyValueWrite, RedisConnection};
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use tracing::instrument;
+use uuid::Uuid;
+use webauthn_rs::Webauthn;
+use webauthn_rs::prelude::{
+    CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential,
+    RequestChallengeResponse,
+};
+
+/// Registration and verification of passkeys. Second factor and sudo flows go through
+/// [MfaService](crate::services::mfa::MfaService), which also guards the registration.
+#[derive(Clone)]
+pub struct PasskeyService {
+    pub db: DatabaseProcessor,
+    pub redis: RedisConnection,
+    pub auth_config: LiveConfig<AuthConfig>,
+    pub session_service: SessionService,
+}
+
+impl PasskeyService {
+    fn webauthn(&self) -> Result<Webauthn, framework::Error> {
+        build_webauthn(&self.auth_config.load().webauthn).map_err(|e| {
+            framework::Error::BusinessPanic(anyhow::anyhow!("Invalid WebAuthn config: {e}"))
+        })
+    }
+
+    fn challenge_ttl(&self) -> Result<std::time::Duration, framework::Error> {
+        self.auth_config
+            .load()
+            .webauthn
+            .challenge_ttl
+            .try_into()
+            .map_err(|e| {
+                framework::Error::BusinessPanic(anyhow::anyhow!("Invalid challenge ttl: {e}"))
+            })
+    }
+
+    async fn list_passkeys(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<WebauthnCredential>, framework::Error> {
+        Ok(self
+            .db
+            .process(ListWebauthnCredentialsByUserId { user_id })
+            .await?)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListPasskeys {
+    pub user_id: Uuid,
+}
+
+impl Processor<ListPasskeys> for PasskeyService {
+    type Output = Vec<WebauthnCredential>;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: ListPasskeys,
+    ) -> Result<Vec<WebauthnCredential>, framework::Error> {
+        self.list_passkeys(input.user_id).await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RemovePasskey {
+    pub user_id: Uuid,
+    pub id: Uuid,
+}
+
+impl Processor<RemovePasskey> for PasskeyService {
+    /// `false` if the user has no such passkey
+    type Output = bool;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: RemovePasskey) -> Result<bool, framework::Error> {
+        Ok(self
+            .db
+            .process(RemoveWebauthnCredential {
+                user_id: input.user_id,
+                id: input.id,
+            })
+            .await?)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Does not check sudo mode. Use
+/// [StartConfiguringPasskey](crate::services::mfa::StartConfiguringPasskey) for user requests.
+pub struct StartPasskeyRegistration {
+    pub user_id: Uuid,
+    pub name: String,
+}
+
+impl Processor<StartPasskeyRegistration> for PasskeyService {
+    type Output = CreationChallengeResponse;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: StartPasskeyRegistration,
+    ) -> Result<CreationChallengeResponse, framework::Error> {
+        let name = input.name.trim();
+        if name.is_empty() || name.len() > 64 {
+            return Err(framework::Error::InvalidInput);
+        }
+        let user = self
+            .db
+            .process(FindUserAccountById { id: input.user_id })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        let existing: Vec<_> = self
+            .list_passkeys(input.user_id)
+            .await?
+            .into_iter()
+            .map(|credential| credential.passkey.0)
+            .collect();
+        let (options, state) =
+            start_registration(&self.webauthn()?, input.user_id, &user.email, &existing)?;
+        let pending = PendingPasskeyRegistration {
+            user_id: PendingPasskeyRegistrationKey(input.user_id),
+            name: name.to_owned(),
+            state,
+        };
+        let mut redis = self.redis.clone();
+        pending
+            .write_with_ttl(&mut redis, self.challenge_ttl()?)
+            .await?;
+        Ok(options)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FinishPasskeyRegistration {
+    pub user_id: Uuid,
+    pub credential: Box<RegisterPublicKeyCredential>,
+}
+
+#[derive(Debug, Clone)]
+pub enum FinishPasskeyRegistrationResult {
+    Success(Box<WebauthnCredential>),
+    InvalidCredential,
+    /// The authenticator is already registered
+    Duplicate,
+    Expired,
+}
+
+impl Processor<FinishPasskeyRegistration> for PasskeyService {
+    type Output = FinishPasskeyRegistrationResult;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: FinishPasskeyRegistration,
+    ) -> Result<FinishPasskeyRegistrationResult, framework::Error> {
+        let mut redis = self.redis.clone();
+        let Some(pending) = PendingPasskeyRegistration::take(
+            &mut redis,
+            PendingPasskeyRegistrationKey(input.user_id),
+        )
+        .await?
+        else {
+            return Ok(FinishPasskeyRegistrationResult::Expired);
+        };
+        let Ok(passkey) = finish_registration(&self.webauthn()?, &pending.state, &input.credential)
+        else {
+            return Ok(FinishPasskeyRegistrationResult::InvalidCredential);
+        };
+        let created = self
+            .db
+            .process(CreateWebauthnCredential {
+                user_id: input.user_id,
+                name: pending.name,
+                passkey,
+            })
+            .await?;
+        Ok(match created {
+            Some(credential) => FinishPasskeyRegistrationResult::Success(Box::new(credential)),
+            None => FinishPasskeyRegistrationResult::Duplicate,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PasskeyChallenge {
+    /// Sent back together with the assertion
+    pub challenge_id: [u8; 16],
+    /// Options for `navigator.credentials.get()`
+    pub options: RequestChallengeResponse,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StartPasskeyChallenge {
+    pub purpose: PasskeyChallengePurpose,
+}
+
+impl Processor<StartPasskeyChallenge> for PasskeyService {
+    /// `None` if the user has no passkey
+    type Output = Option<PasskeyChallenge>;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: StartPasskeyChallenge,
+    ) -> Result<Option<PasskeyChallenge>, framework::Error> {
+        let passkeys: Vec<_> = match input.purpose {
+            PasskeyChallengePurpose::Login => Vec::new(),
+            PasskeyChallengePurpose::Mfa(user_id) | PasskeyChallengePurpose::Sudo(user_id) => {
+                let passkeys: Vec<_> = self
+                    .list_passkeys(user_id)
+                    .await?
+                    .into_iter()
+                    .map(|credential| credential.passkey.0)
+                    .collect();
+                if passkeys.is_empty() {
+                    return Ok(None);
+                }
+                passkeys
+            }
+        };
+        let (options, state) = start_assertion(&self.webauthn()?, input.purpose, &passkeys)?;
+        let challenge = PendingPasskeyChallenge {
+            challenge_id: PendingPasskeyChallengeKey(rand::random()),
+            purpose: input.purpose,
+            state,
+        };
+        let mut redis = self.redis.clone();
+        challenge
+            .write_with_ttl(&mut redis, self.challenge_ttl()?)
+            .await?;
+        Ok(Some(PasskeyChallenge {
+            challenge_id: challenge.challenge_id.0,
+            options,
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Verify the answer to a challenge from [StartPasskeyChallenge]. The challenge is consumed even
+/// if the assertion is invalid.
+pub struct VerifyPasskeyAssertion {
+    pub challenge_id: [u8; 16],
+    /// Must match the purpose the challenge was started for
+    pub purpose: PasskeyChallengePurpose,
+    pub credential: Box<PublicKeyCredential>,
+}
+
+impl Processor<VerifyPasskeyAssertion> for PasskeyService {
+    /// The verified user, `None` if the assertion is invalid
+    type Output = Option<Uuid>;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: VerifyPasskeyAssertion,
+    ) -> Result<Option<Uuid>, framework::Error> {
+        let mut redis = self.redis.clone();
+        let Some(challenge) = PendingPasskeyChallenge::take(
+            &mut redis,
+            PendingPasskeyChallengeKey(input.challenge_id),
+        )
+        .await?
+        else {
+            return Ok(None);
+        };
+        if challenge.purpose != input.purpose {
+            return Ok(None);
+        }
+        let webauthn = self.webauthn()?;
+        let user_id = match challenge.purpose {
+            PasskeyChallengePurpose::Login => {
+                let Some(user_id) = claimed_user_id(&webauthn, &input.credential) else {
+                    return Ok(None);
+                };
+                user_id
+            }
+            PasskeyChallengePurpose::Mfa(user_id) | PasskeyChallengePurpose::Sudo(user_id) => {
+                user_id
+            }
+        };
+        let credentials = self.list_passkeys(user_id).await?;
+        let passkeys: Vec<_> = credentials
+            .iter()
+            .map(|credential| credential.passkey.0.clone())
+            .collect();
+        let Ok(result) = finish_assertion(
+            &webauthn,
+            challenge.purpose,
+            &challenge.state,
+            &input.credential,
+            &passkeys,
+        ) else {
+            return Ok(None);
+        };
+        let Some(mut credential) = credentials
+            .into_iter()
+            .find(|credential| credential.passkey.0.cred_id() == result.cred_id())
+        else {
+            return Ok(None);
+        };
+        let updated = credential.passkey.0.update_credential(&result) == Some(true);
+        self.db
+            .process(MarkWebauthnCredentialUsed {
+                id: credential.id,
+                updated_passkey: updated.then_some(credential.passkey.0),
+            })
+            .await?;
+        Ok(Some(user_id))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Passwordless login with a challenge started for [PasskeyChallengePurpose::Login]. A passkey
+/// already verifies the user, so no second factor is asked for.
+pub struct LoginWithPasskey {
+    pub challenge_id: [u8; 16],
+    pub credential: Box<PublicKeyCredential>,
+    pub client: SessionClient,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginWithPasskeyResult {
+    Success(SessionId),
+    InvalidCredential,
+    Suspended(UserSuspension),
+}
+
+impl Processor<LoginWithPasskey> for PasskeyService {
+    type Output = LoginWithPasskeyResult;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: LoginWithPasskey,
+    ) -> Result<LoginWithPasskeyResult, framework::Error> {
+        let Some(user_id) = self
+            .process(VerifyPasskeyAssertion {
+                challenge_id: input.challenge_id,
+                purpose: PasskeyChallengePurpose::Login,
+                credential: input.credential,
+            })
+            .await?
+        else {
+            return Ok(LoginWithPasskeyResult::InvalidCredential);
+        };
+        if let Some(suspension) = self.db.process(FindUserSuspension { user_id }).await? {
+            return Ok(LoginWithPasskeyResult::Suspended(suspension));
+        }
+        let session_id = self
+            .session_service
+            .process(CreateSession {
+                user_id,
+                login_method: LoginMethod::Passkey,
+                client: input.client,
+            })
+            .await?;
+        Ok(LoginWithPasskeyResult::Success(session_id))
+    }
+}
@@ -1,7 +1,11 @@
 use crate::config::AuthConfig;
-use crate::entities::redis::session::{Session, SessionId};
+use crate::entities::redis::refresh_token::{RefreshTokenFamily, RefreshTokenFamilyKey};
+use crate::entities::redis::revoked_session::RevokedSession;
+use crate::entities::redis::session::{LoginMethod, Session, SessionClient, SessionId};
+use crate::entities::redis::session_handle::{SessionHandle, SessionHandleSecret};
+use crate::entities::redis::suspended_user::{SuspendedUser, SuspendedUserKey};
 use crate::entities::redis::user_session_list::{UserSessionIndex, UserSessions};
-use admin::utils::config_provider::find_config_from_redis;
+use admin::utils::config_provider::LiveConfig;
 use framework::now_time;
 use framework::rabbitmq::AmqpPool;
 use framework::redis::{KeyValue, KeyValueRead, KeyValueWrite, RedisConnection};
@@ -12,13 +16,15 @@ use uuid::Uuid;
 #[derive(Clone)]
 pub struct SessionService {
     pub redis: RedisConnection,
-    pub config_store: RedisConnection,
+    pub auth_config: LiveConfig<AuthConfig>,
     pub mq: AmqpPool,
 }
 
 #[derive(Debug, Clone)]
 pub struct CreateSession {
     pub user_id: Uuid,
+    pub login_method: LoginMethod,
+    pub client: SessionClient,
 }
 
 impl Processor<CreateSession> for SessionService {
@@ -29,17 +35,23 @@ impl Processor<CreateSession> for SessionService {
         let now = now_time();
         let now_timestamp = now.assume_utc().unix_timestamp() as u64;
         let mut redis = self.redis.clone();
-        let config = find_config_from_redis::<AuthConfig>(&mut redis)
-            .await?
-            .session;
-        let session_ttl = config.session_ttl.try_into().map_err(|e| {
-            framework::Error::BusinessPanic(anyhow::anyhow!("Invalid session ttl: {e}"))
-        })?;
+        let session_ttl = self
+            .auth_config
+            .load()
+            .session
+            .session_ttl
+            .try_into()
+            .map_err(|e| {
+                framework::Error::BusinessPanic(anyhow::anyhow!("Invalid session ttl: {e}"))
+            })?;
         let session = Session {
             id: session_id,
             user_id: input.user_id,
             terminated: false,
             last_refreshed: now_timestamp,
+            created_at: now_timestamp,
+            login_method: input.login_method,
+            client: input.client,
         };
         session.write_with_ttl(&mut redis, session_ttl).await?;
 
@@ -61,16 +73,29 @@ impl Processor<RefreshSession> for SessionService {
     type Error = framework::Error;
     async fn process(&self, input: RefreshSession) -> Result<Session, framework::Error> {
         let mut redis = self.redis.clone();
-        let config = find_config_from_redis::<AuthConfig>(&mut redis)
-            .await?
-            .session;
-        let session_ttl = config.session_ttl.try_into().map_err(|e| {
-            framework::Error::BusinessPanic(anyhow::anyhow!("Invalid session ttl: {e}"))
-        })?;
+        let session_ttl = self
+            .auth_config
+            .load()
+            .session
+            .session_ttl
+            .try_into()
+            .map_err(|e| {
+                framework::Error::BusinessPanic(anyhow::anyhow!("Invalid session ttl: {e}"))
+            })?;
         let now = now_time().assume_utc().unix_timestamp() as u64;
         let mut session = Session::read(&mut redis, input.session_id)
             .await?
             .ok_or(framework::Error::NotFound)?;
+        let suspended = SuspendedUser::read(&mut redis, SuspendedUserKey(session.user_id))
+            .await?
+            .is_some_and(|suspension| suspension.until.is_none_or(|until| until > now));
+        if suspended {
+            self.process(TerminateSession {
+                session_id: input.session_id,
+            })
+            .await?;
+            return Err(framework::Error::PermissionsDenied);
+        }
         session.last_refreshed = now;
         session.write_with_ttl(&mut redis, session_ttl).await?;
         Ok(session)
@@ -100,6 +125,18 @@ impl Processor<TerminateSession> for SessionService {
         }
 
         Session::delete(&mut redis, input.session_id).await?;
+        RefreshTokenFamily::delete(&mut redis, RefreshTokenFamilyKey(input.session_id.0)).await?;
+        // access tokens stay valid until they expire, plus the validation leeway, unless the
+        // session is marked as revoked
+        if let Ok(ttl) = std::time::Duration::try_from(
+            self.auth_config.load().token.access_token_ttl + time::Duration::MINUTE,
+        ) {
+            RevokedSession {
+                session_id: input.session_id.0,
+            }
+            .write_with_ttl(&mut redis, ttl)
+            .await?;
+        }
         Ok(())
     }
 }
@@ -177,3 +214,174 @@ impl Processor<TerminateAllUserSessions> for SessionService {
         Ok(())
     }
 }
+
+#[derive(Debug, Clone)]
+/// A session as it is shown, identified by its handle rather than by its id.
+pub struct ListedSession {
+    pub handle: SessionHandle,
+    pub session: Session,
+}
+
+#[derive(Debug, Clone)]
+/// The sessions of the user with their handles, least recently used first.
+pub struct ListUserSessionsWithHandles {
+    pub user_id: Uuid,
+}
+
+impl Processor<ListUserSessionsWithHandles> for SessionService {
+    type Output = Vec<ListedSession>;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: ListUserSessionsWithHandles,
+    ) -> Result<Vec<ListedSession>, framework::Error> {
+        let secret = SessionHandleSecret::load_or_create(&mut self.redis.clone()).await?;
+        let sessions = self
+            .process(ListUserSessions {
+                user_id: input.user_id,
+            })
+            .await?;
+        Ok(sessions
+            .into_iter()
+            .map(|session| ListedSession {
+                handle: secret.handle(session.id),
+                session,
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FindSessionHandle {
+    pub session_id: SessionId,
+}
+
+impl Processor<FindSessionHandle> for SessionService {
+    type Output = SessionHandle;
+    type Error = framework::Error;
+    async fn process(&self, input: FindSessionHandle) -> Result<SessionHandle, framework::Error> {
+        let secret = SessionHandleSecret::load_or_create(&mut self.redis.clone()).await?;
+        Ok(secret.handle(input.session_id))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Terminate a session of the user, such as a device they no longer use.
+pub struct RevokeUserSession {
+    pub user_id: Uuid,
+    pub handle: SessionHandle,
+}
+
+impl Processor<RevokeUserSession> for SessionService {
+    /// `false` if the user has no such session
+    type Output = bool;
+    type Error = framework::Error;
+    async fn process(&self, input: RevokeUserSession) -> Result<bool, framework::Error> {
+        let mut redis = self.redis.clone();
+        let secret = SessionHandleSecret::load_or_create(&mut redis).await?;
+        let Some(session_ids) =
+            UserSessions::read(&mut redis, UserSessionIndex(input.user_id)).await?
+        else {
+            return Ok(false);
+        };
+        let Some(session_id) = session_ids
+            .session_ids
+            .into_iter()
+            .map(SessionId)
+            .find(|&id| secret.handle(id) == input.handle)
+        else {
+            return Ok(false);
+        };
+        let session = Session::read(&mut redis, session_id).await?;
+        if session.is_none_or(|session| session.user_id != input.user_id) {
+            return Ok(false);
+        }
+        self.process(TerminateSession { session_id }).await?;
+        Ok(true)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Terminate every session of the user except the one making the request.
+pub struct TerminateOtherUserSessions {
+    pub user_id: Uuid,
+    pub current_session_id: SessionId,
+}
+
+impl Processor<TerminateOtherUserSessions> for SessionService {
+    /// Number of terminated sessions
+    type Output = u32;
+    type Error = framework::Error;
+    async fn process(&self, input: TerminateOtherUserSessions) -> Result<u32, framework::Error> {
+        let sessions = self
+            .process(ListUserSessions {
+                user_id: input.user_id,
+            })
+            .await?;
+        let mut terminate_stream = parallel_map(
+            sessions
+                .into_iter()
+                .filter(|s| s.id != input.current_session_id)
+                .map(|s| TerminateSession { session_id: s.id }),
+            self,
+        );
+        let mut terminated = 0;
+        while let Some(result) = terminate_stream.next().await {
+            result?;
+            terminated += 1;
+        }
+        Ok(terminated)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Terminate every session of a suspended user and keep them from being refreshed until the
+/// suspension ends.
+pub struct ApplyUserSuspension {
+    pub user_id: Uuid,
+    /// `None` for a permanent ban
+    pub until: Option<time::PrimitiveDateTime>,
+}
+
+impl Processor<ApplyUserSuspension> for SessionService {
+    type Output = ();
+    type Error = framework::Error;
+    async fn process(&self, input: ApplyUserSuspension) -> Result<(), framework::Error> {
+        let mut redis = self.redis.clone();
+        let marker = SuspendedUser {
+            user_id: input.user_id,
+            until: input
+                .until
+                .map(|until| until.assume_utc().unix_timestamp() as u64),
+        };
+        match input.until {
+            Some(until) => {
+                let remaining = until - now_time();
+                // an already expired suspension needs no marker
+                if let Ok(ttl) = std::time::Duration::try_from(remaining)
+                    && !ttl.is_zero()
+                {
+                    marker.write_with_ttl(&mut redis, ttl).await?;
+                }
+            }
+            None => marker.write(&mut redis).await?,
+        }
+        self.process(TerminateAllUserSessions {
+            user_id: input.user_id,
+        })
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LiftUserSuspensionMarker {
+    pub user_id: Uuid,
+}
+
+impl Processor<LiftUserSuspensionMarker> for SessionService {
+    type Output = ();
+    type Error = framework::Error;
+    async fn process(&self, input: LiftUserSuspensionMarker) -> Result<(), framework::Error> {
+        SuspendedUser::delete(&mut self.redis.clone(), SuspendedUserKey(input.user_id)).await
+    }
+}
@@ -0,0 +1,220 @@
+use crate::config::{AuthConfig, TokenConfig};
+use crate::entities::redis::refresh_token::{RefreshTokenFamily, RefreshTokenFamilyKey};
+use crate::entities::redis::revoked_session::RevokedSession;
+use crate::entities::redis::session::{Session, SessionId};
+use crate::services::session::{RefreshSession, SessionService, TerminateSession};
+use crate::utils::jwt::{AccessToken, RefreshToken, TokenClaims};
+use admin::utils::config_provider::LiveConfig;
+use framework::now_time;
+use framework::redis::{KeyValueRead, KeyValueWrite, RedisConnection};
+use kanau::processor::Processor;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Issues and verifies the tokens of the optional token mode, see [`TokenConfig`].
+///
+/// Every token pair belongs to a session. Access tokens are verified without reading the session,
+/// so terminating a session marks it as revoked for as long as its access tokens may live.
+#[derive(Clone)]
+pub struct TokenService {
+    pub redis: RedisConnection,
+    pub auth_config: LiveConfig<AuthConfig>,
+    pub session_service: SessionService,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenPair {
+    pub access_token: AccessToken,
+    pub refresh_token: RefreshToken,
+    /// Unix timestamp
+    pub access_token_expires_at: u64,
+}
+
+impl TokenService {
+    fn sign_access_token(
+        config: &TokenConfig,
+        user_id: Uuid,
+        session_id: Uuid,
+    ) -> Result<(AccessToken, u64), framework::Error> {
+        let expires_at = (now_time() + config.access_token_ttl)
+            .assume_utc()
+            .unix_timestamp() as u64;
+        let claims = TokenClaims {
+            sub: user_id,
+            sid: session_id,
+            exp: expires_at as usize,
+            iss: config.issuer.clone(),
+            aud: config.audience.clone(),
+        };
+        let token = AccessToken::sign(&claims, config.signing_key.expose().as_bytes())
+            .map_err(|e| framework::Error::BusinessPanic(e.into()))?;
+        Ok((token, expires_at))
+    }
+
+    /// Refresh tokens live as long as the session they belong to.
+    fn refresh_token_ttl(&self) -> Result<std::time::Duration, framework::Error> {
+        self.auth_config
+            .load()
+            .session
+            .session_ttl
+            .try_into()
+            .map_err(|e| {
+                framework::Error::BusinessPanic(anyhow::anyhow!("Invalid session ttl: {e}"))
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Issue the first token pair of a session right after login.
+pub struct IssueSessionTokens {
+    pub session_id: SessionId,
+}
+
+impl Processor<IssueSessionTokens> for TokenService {
+    /// `None` if the token mode is disabled
+    type Output = Option<TokenPair>;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: IssueSessionTokens,
+    ) -> Result<Option<TokenPair>, framework::Error> {
+        let config = self.auth_config.load();
+        if !config.token.enabled {
+            return Ok(None);
+        }
+        let mut redis = self.redis.clone();
+        let session = Session::read(&mut redis, input.session_id)
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        let (refresh_token, hash) = RefreshToken::generate(session.id.0);
+        RefreshTokenFamily {
+            session_id: session.id.0,
+            user_id: session.user_id,
+            current: hash,
+            rotated: Vec::new(),
+        }
+        .write_with_ttl(&mut redis, self.refresh_token_ttl()?)
+        .await?;
+        let (access_token, access_token_expires_at) =
+            Self::sign_access_token(&config.token, session.user_id, session.id.0)?;
+        Ok(Some(TokenPair {
+            access_token,
+            refresh_token,
+            access_token_expires_at,
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RotateRefreshToken {
+    pub refresh_token: RefreshToken,
+}
+
+#[derive(Debug, Clone)]
+pub enum RotateRefreshTokenResult {
+    Rotated(TokenPair),
+    /// Unknown, expired or malformed token, or the session has ended.
+    Invalid,
+    /// An already rotated token was presented. The session has been revoked.
+    Reused,
+}
+
+impl Processor<RotateRefreshToken> for TokenService {
+    type Output = RotateRefreshTokenResult;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: RotateRefreshToken,
+    ) -> Result<RotateRefreshTokenResult, framework::Error> {
+        let config = self.auth_config.load();
+        if !config.token.enabled {
+            return Ok(RotateRefreshTokenResult::Invalid);
+        }
+        let Some((session_id, hash)) = input.refresh_token.parse() else {
+            return Ok(RotateRefreshTokenResult::Invalid);
+        };
+        let mut redis = self.redis.clone();
+        let ttl = self.refresh_token_ttl()?;
+        let Some(mut family) =
+            RefreshTokenFamily::take(&mut redis, RefreshTokenFamilyKey(session_id)).await?
+        else {
+            return Ok(RotateRefreshTokenResult::Invalid);
+        };
+
+        if family.rotated.contains(&hash) {
+            tracing::warn!(%session_id, user_id = %family.user_id, "Refresh token reused, revoking the session");
+            self.session_service
+                .process(TerminateSession {
+                    session_id: SessionId(session_id),
+                })
+                .await?;
+            return Ok(RotateRefreshTokenResult::Reused);
+        }
+        if family.current != hash {
+            family.write_with_ttl(&mut redis, ttl).await?;
+            return Ok(RotateRefreshTokenResult::Invalid);
+        }
+
+        // extends the session and ends it if the user has been suspended meanwhile
+        match self
+            .session_service
+            .process(RefreshSession {
+                session_id: SessionId(session_id),
+            })
+            .await
+        {
+            Ok(_) => {}
+            Err(framework::Error::NotFound | framework::Error::PermissionsDenied) => {
+                return Ok(RotateRefreshTokenResult::Invalid);
+            }
+            Err(e) => {
+                family.write_with_ttl(&mut redis, ttl).await?;
+                return Err(e);
+            }
+        }
+
+        let (refresh_token, next) = RefreshToken::generate(session_id);
+        family.rotate(next);
+        family.write_with_ttl(&mut redis, ttl).await?;
+        let (access_token, access_token_expires_at) =
+            Self::sign_access_token(&config.token, family.user_id, session_id)?;
+        Ok(RotateRefreshTokenResult::Rotated(TokenPair {
+            access_token,
+            refresh_token,
+            access_token_expires_at,
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifyAccessToken {
+    pub access_token: AccessToken,
+}
+
+impl Processor<VerifyAccessToken> for TokenService {
+    /// `None` if the token is invalid, expired or its session has been revoked
+    type Output = Option<TokenClaims>;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: VerifyAccessToken,
+    ) -> Result<Option<TokenClaims>, framework::Error> {
+        let config = self.auth_config.load();
+        if !config.token.enabled {
+            return Ok(None);
+        }
+        let Ok(claims) = input.access_token.verify(
+            config.token.signing_key.expose().as_bytes(),
+            &config.token.issuer,
+            &config.token.audience,
+        ) else {
+            return Ok(None);
+        };
+        if RevokedSession::is_revoked(&mut self.redis.clone(), claims.sid).await? {
+            return Ok(None);
+        }
+        Ok(Some(claims))
+    }
+}
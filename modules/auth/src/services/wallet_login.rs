@@ -0,0 +1,157 @@
+use crate::config::AuthConfig;
+use crate::entities::db::user_account::{FindUserSuspension, UserSuspension};
+use crate::entities::db::wallet_identity::FindOrRegisterWalletUser;
+use crate::entities::redis::session::{LoginMethod, SessionClient, SessionId};
+use crate::entities::redis::wallet_challenge::{WalletLoginChallenge, WalletLoginChallengeKey};
+use crate::services::mfa::{CheckMfaEnabled, CreateLoginMfaSession, MfaService};
+use crate::services::session::{CreateSession, SessionService};
+use crate::utils::wallet::{SignInMessage, WalletKind, recover_signer};
+use admin::utils::config_provider::LiveConfig;
+use framework::redis::{KeyValueWrite, RedisConnection};
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use tracing::instrument;
+
+/// Login by signing a message with an Ethereum or TRON wallet. The account of the wallet is kept
+/// in `auth.wallet_identity`, and the address is also linked to it as a customer address.
+#[derive(Clone)]
+pub struct WalletLoginService {
+    pub db: DatabaseProcessor,
+    pub redis: RedisConnection,
+    pub auth_config: LiveConfig<AuthConfig>,
+    pub session_service: SessionService,
+    pub mfa_service: MfaService,
+}
+
+#[derive(Debug, Clone)]
+pub struct StartWalletLogin {
+    pub kind: WalletKind,
+    pub address: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletLoginMessage {
+    pub nonce: [u8; 16],
+    /// To be signed with `personal_sign`, or `signMessageV2` on TRON
+    pub message: String,
+}
+
+impl Processor<StartWalletLogin> for WalletLoginService {
+    /// `None` if wallet login is disabled, the chain is not supported or the address is malformed
+    type Output = Option<WalletLoginMessage>;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: StartWalletLogin,
+    ) -> Result<Option<WalletLoginMessage>, framework::Error> {
+        let config = &self.auth_config.load().wallet_login;
+        if !config.enabled || input.kind.blockchain().is_none() {
+            return Ok(None);
+        }
+        let Some(address) = input.kind.parse_address(&input.address) else {
+            return Ok(None);
+        };
+        let challenge_ttl: std::time::Duration = config.challenge_ttl.try_into().map_err(|e| {
+            framework::Error::BusinessPanic(anyhow::anyhow!("Invalid challenge ttl: {e}"))
+        })?;
+        let nonce: [u8; 16] = rand::random();
+        let issued_at = framework::now_time().assume_utc();
+        let message = SignInMessage {
+            domain: &config.domain,
+            uri: &config.uri,
+            statement: &config.statement,
+            kind: input.kind,
+            address: &address,
+            nonce: &hex::encode(nonce),
+            issued_at,
+            expires_at: issued_at + config.challenge_ttl,
+        }
+        .to_message()
+        .map_err(|e| framework::Error::BusinessPanic(e.into()))?;
+        let challenge = WalletLoginChallenge {
+            nonce: WalletLoginChallengeKey(nonce),
+            kind: input.kind,
+            address,
+            message: message.clone(),
+        };
+        let mut redis = self.redis.clone();
+        challenge.write_with_ttl(&mut redis, challenge_ttl).await?;
+        Ok(Some(WalletLoginMessage { nonce, message }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WalletLogin {
+    pub nonce: [u8; 16],
+    /// `r || s || v` over the message from [StartWalletLogin]
+    pub signature: Vec<u8>,
+    pub client: SessionClient,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalletLoginResult {
+    /// Also returned when a new account was just created for the wallet
+    LoggedIn(SessionId),
+    RequiredMfa([u8; 32]),
+    Suspended(UserSuspension),
+    InvalidSignature,
+    Expired,
+}
+
+impl Processor<WalletLogin> for WalletLoginService {
+    type Output = WalletLoginResult;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: WalletLogin) -> Result<WalletLoginResult, framework::Error> {
+        if !self.auth_config.load().wallet_login.enabled {
+            return Ok(WalletLoginResult::Expired);
+        }
+        let mut redis = self.redis.clone();
+        let Some(challenge) =
+            WalletLoginChallenge::take(&mut redis, WalletLoginChallengeKey(input.nonce)).await?
+        else {
+            return Ok(WalletLoginResult::Expired);
+        };
+        let signer = recover_signer(challenge.kind, &challenge.message, &input.signature);
+        if signer != Some(challenge.address) {
+            return Ok(WalletLoginResult::InvalidSignature);
+        }
+        let Some(chain) = challenge.kind.blockchain() else {
+            return Ok(WalletLoginResult::InvalidSignature);
+        };
+        let address = challenge.kind.format_address(&challenge.address);
+
+        let user_id = self
+            .db
+            .process(FindOrRegisterWalletUser { chain, address })
+            .await?;
+
+        if let Some(suspension) = self.db.process(FindUserSuspension { user_id }).await? {
+            return Ok(WalletLoginResult::Suspended(suspension));
+        }
+        let mfa_enabled = self
+            .mfa_service
+            .process(CheckMfaEnabled { user_id })
+            .await?;
+        if mfa_enabled {
+            let mfa_token = self
+                .mfa_service
+                .process(CreateLoginMfaSession {
+                    user_id,
+                    login_method: LoginMethod::Wallet,
+                })
+                .await?;
+            return Ok(WalletLoginResult::RequiredMfa(mfa_token.token));
+        }
+        let session_id = self
+            .session_service
+            .process(CreateSession {
+                user_id,
+                login_method: LoginMethod::Wallet,
+                client: input.client,
+            })
+            .await?;
+        Ok(WalletLoginResult::LoggedIn(session_id))
+    }
+}
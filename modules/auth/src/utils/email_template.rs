@@ -0,0 +1,292 @@
+use crate::entities::db::email_otp::EmailOtpUsage;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+/// Languages the emails are written in.
+pub enum EmailLocale {
+    #[default]
+    En,
+    Ja,
+    Zh,
+}
+
+impl EmailLocale {
+    /// The supported locale the client prefers most, from an `Accept-Language` header.
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut best: Option<(Self, f32)> = None;
+        for item in header.split(',') {
+            let mut parts = item.split(';');
+            let Some(locale) = parts.next().and_then(Self::from_language_tag) else {
+                continue;
+            };
+            let quality = match parts.find_map(|p| p.trim().strip_prefix("q=")) {
+                Some(q) => q.parse().unwrap_or(0.0),
+                None => 1.0,
+            };
+            if quality > 0.0 && best.is_none_or(|(_, best_quality)| quality > best_quality) {
+                best = Some((locale, quality));
+            }
+        }
+        best.map(|(locale, _)| locale)
+    }
+
+    /// The locale of a language tag such as `ja-JP`, if supported.
+    pub fn from_language_tag(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?;
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Some(EmailLocale::En),
+            "ja" => Some(EmailLocale::Ja),
+            "zh" => Some(EmailLocale::Zh),
+            _ => None,
+        }
+    }
+
+    /// The primary language subtag, as used in `<html lang>`.
+    pub fn html_lang(&self) -> &'static str {
+        match self {
+            EmailLocale::En => "en",
+            EmailLocale::Ja => "ja",
+            EmailLocale::Zh => "zh",
+        }
+    }
+}
+
+/// An email ready to be sent as `multipart/alternative`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+/// Translated strings. `{site}` and `{minutes}` are filled in when rendering.
+struct OtpEmailStrings {
+    subject: &'static str,
+    intro: &'static str,
+    expiry: &'static str,
+    ignore: &'static str,
+}
+
+fn otp_email_strings(locale: EmailLocale, usage: EmailOtpUsage) -> OtpEmailStrings {
+    let (subject, intro) = match (locale, usage) {
+        (EmailLocale::En, EmailOtpUsage::Login) => (
+            "Your sign-in code for {site}",
+            "Use this code to sign in to {site}.",
+        ),
+        (EmailLocale::En, EmailOtpUsage::PasswordReset) => (
+            "Reset your {site} password",
+            "Use this code to reset your {site} password.",
+        ),
+        (EmailLocale::En, EmailOtpUsage::ChangeEmailAddress) => (
+            "Confirm your new email address",
+            "Use this code to confirm this email address for your {site} account.",
+        ),
+        (EmailLocale::En, EmailOtpUsage::SudoMode) => (
+            "Confirm a sensitive action",
+            "Use this code to confirm a sensitive change to your {site} account.",
+        ),
+        (EmailLocale::Ja, EmailOtpUsage::Login) => (
+            "{site} のログインコード",
+            "{site} にログインするには、次のコードを入力してください。",
+        ),
+        (EmailLocale::Ja, EmailOtpUsage::PasswordReset) => (
+            "{site} のパスワード再設定",
+            "{site} のパスワードを再設定するには、次のコードを入力してください。",
+        ),
+        (EmailLocale::Ja, EmailOtpUsage::ChangeEmailAddress) => (
+            "新しいメールアドレスの確認",
+            "{site} アカウントのメールアドレスを確認するには、次のコードを入力してください。",
+        ),
+        (EmailLocale::Ja, EmailOtpUsage::SudoMode) => (
+            "重要な操作の確認",
+            "{site} アカウントの重要な操作を確認するには、次のコードを入力してください。",
+        ),
+        (EmailLocale::Zh, EmailOtpUsage::Login) => {
+            ("{site} 登录验证码", "请使用以下验证码登录 {site}。")
+        }
+        (EmailLocale::Zh, EmailOtpUsage::PasswordReset) => {
+            ("重置 {site} 密码", "请使用以下验证码重置您的 {site} 密码。")
+        }
+        (EmailLocale::Zh, EmailOtpUsage::ChangeEmailAddress) => (
+            "确认新邮箱地址",
+            "请使用以下验证码确认您 {site} 账户的邮箱地址。",
+        ),
+        (EmailLocale::Zh, EmailOtpUsage::SudoMode) => (
+            "确认敏感操作",
+            "请使用以下验证码确认您 {site} 账户中的敏感操作。",
+        ),
+    };
+    let (expiry, ignore) = match locale {
+        EmailLocale::En => (
+            "This code expires in {minutes} minutes.",
+            "If you did not request this code, you can ignore this email.",
+        ),
+        EmailLocale::Ja => (
+            "このコードの有効期限は {minutes} 分です。",
+            "このメールに心当たりがない場合は、破棄してください。",
+        ),
+        EmailLocale::Zh => (
+            "验证码将在 {minutes} 分钟后失效。",
+            "如果这不是您本人的操作，请忽略此邮件。",
+        ),
+    };
+    OtpEmailStrings {
+        subject,
+        intro,
+        expiry,
+        ignore,
+    }
+}
+
+const OTP_EMAIL_HTML: &str = r#"<!DOCTYPE html>
+<html lang="{{lang}}">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{subject}}</title>
+</head>
+<body style="margin:0;padding:24px;background:#f4f4f5;font-family:-apple-system,'Segoe UI',Roboto,'Hiragino Sans','Microsoft YaHei',sans-serif;color:#18181b;">
+<table role="presentation" width="100%" cellspacing="0" cellpadding="0" style="max-width:480px;margin:0 auto;background:#ffffff;border-radius:8px;">
+<tr><td style="padding:32px;">
+<p style="margin:0 0 24px;font-size:16px;line-height:1.5;">{{intro}}</p>
+<p style="margin:0 0 24px;font-size:32px;font-weight:600;letter-spacing:8px;text-align:center;font-family:ui-monospace,Menlo,Consolas,monospace;">{{code}}</p>
+<p style="margin:0 0 8px;font-size:14px;line-height:1.5;color:#52525b;">{{expiry}}</p>
+<p style="margin:0;font-size:14px;line-height:1.5;color:#52525b;">{{ignore}}</p>
+</td></tr>
+</table>
+<p style="margin:16px 0 0;font-size:12px;text-align:center;color:#a1a1aa;">{{site}}</p>
+</body>
+</html>
+"#;
+
+/// Escape text to be placed in HTML content or a quoted attribute.
+pub fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Replace every `{{name}}` in one pass, so that values are never expanded again.
+pub fn fill_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let value = after.find("}}").and_then(|end| {
+            let name = &after[..end];
+            values
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| (*value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                out.push_str(value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The email carrying an OTP code for the given purpose.
+pub fn render_otp_email(
+    locale: EmailLocale,
+    usage: EmailOtpUsage,
+    site_name: &str,
+    code: &str,
+    expire_after: std::time::Duration,
+) -> RenderedEmail {
+    let strings = otp_email_strings(locale, usage);
+    let minutes = expire_after.as_secs().div_ceil(60).max(1).to_string();
+    let fill = |s: &str| {
+        s.replace("{site}", site_name)
+            .replace("{minutes}", &minutes)
+    };
+    let subject = fill(strings.subject);
+    let intro = fill(strings.intro);
+    let expiry = fill(strings.expiry);
+    let ignore = fill(strings.ignore);
+
+    let text = format!("{intro}\n\n    {code}\n\n{expiry}\n{ignore}\n\n-- \n{site_name}\n");
+    let html = fill_template(
+        OTP_EMAIL_HTML,
+        &[
+            ("lang", locale.html_lang()),
+            ("subject", &escape_html(&subject)),
+            ("intro", &escape_html(&intro)),
+            ("code", &escape_html(code)),
+            ("expiry", &escape_html(&expiry)),
+            ("ignore", &escape_html(&ignore)),
+            ("site", &escape_html(site_name)),
+        ],
+    );
+    RenderedEmail {
+        subject,
+        html,
+        text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locale_from_accept_language() {
+        assert_eq!(
+            EmailLocale::from_accept_language("ja-JP,ja;q=0.9,en;q=0.8"),
+            Some(EmailLocale::Ja)
+        );
+        assert_eq!(
+            EmailLocale::from_accept_language("fr-FR, en;q=0.5, zh-CN;q=0.7"),
+            Some(EmailLocale::Zh)
+        );
+        assert_eq!(EmailLocale::from_accept_language("zh;q=0, fr"), None);
+        assert_eq!(EmailLocale::from_accept_language(""), None);
+    }
+
+    #[test]
+    fn otp_email_renders() {
+        let email = render_otp_email(
+            EmailLocale::En,
+            EmailOtpUsage::PasswordReset,
+            "<Phantom> {{code}}",
+            "012345",
+            std::time::Duration::from_secs(600),
+        );
+        assert_eq!(email.subject, "Reset your <Phantom> {{code}} password");
+        assert!(email.text.contains("    012345\n"));
+        assert!(email.text.contains("expires in 10 minutes"));
+        assert!(email.html.contains("&lt;Phantom&gt; {{code}}"));
+        assert!(!email.html.contains("<Phantom>"));
+        assert_eq!(email.html.matches("012345").count(), 1);
+    }
+}
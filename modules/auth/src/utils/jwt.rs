@@ -1,4 +1,6 @@
 use compact_str::CompactString;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 /// Access JWT token string
@@ -19,6 +21,31 @@ impl AccessToken {
     pub fn new(inner: impl AsRef<str>) -> Self {
         Self(inner.as_ref().to_owned())
     }
+
+    /// Sign the claims with HS256.
+    pub fn sign(claims: &TokenClaims, key: &[u8]) -> Result<Self, jsonwebtoken::errors::Error> {
+        jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            claims,
+            &EncodingKey::from_secret(key),
+        )
+        .map(Self)
+    }
+
+    /// Check the signature, expiry, issuer and audience of the token.
+    pub fn verify(
+        &self,
+        key: &[u8],
+        issuer: &str,
+        audience: &str,
+    ) -> Result<TokenClaims, jsonwebtoken::errors::Error> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        validation.set_issuer(&[issuer]);
+        validation.set_audience(&[audience]);
+        jsonwebtoken::decode::<TokenClaims>(&self.0, &DecodingKey::from_secret(key), &validation)
+            .map(|data| data.claims)
+    }
 }
 
 impl std::fmt::Debug for AccessToken {
@@ -27,7 +54,9 @@ impl std::fmt::Debug for AccessToken {
     }
 }
 
-/// Refresh JWT token string
+/// Opaque refresh token string, `<session id>.<hex encoded secret>`.
+///
+/// Only the SHA-256 of the secret is stored, see `RefreshTokenFamily`.
 #[derive(Clone, PartialEq, Eq)]
 pub struct RefreshToken(String);
 
@@ -45,6 +74,21 @@ impl RefreshToken {
     pub fn new(inner: impl AsRef<str>) -> Self {
         Self(inner.as_ref().to_owned())
     }
+
+    /// Generate a token for the session. Returns the token and the hash to store.
+    pub fn generate(session_id: Uuid) -> (Self, [u8; 32]) {
+        let secret: [u8; 32] = rand::random();
+        let token = Self(format!("{session_id}.{}", hex::encode(secret)));
+        (token, Sha256::digest(secret).into())
+    }
+
+    /// The session id and the hash of the secret. `None` if the token is malformed.
+    pub fn parse(&self) -> Option<(Uuid, [u8; 32])> {
+        let (session_id, secret) = self.0.split_once('.')?;
+        let session_id = Uuid::parse_str(session_id).ok()?;
+        let secret: [u8; 32] = hex::decode(secret).ok()?.try_into().ok()?;
+        Some((session_id, Sha256::digest(secret).into()))
+    }
 }
 
 impl std::fmt::Debug for RefreshToken {
@@ -64,3 +108,65 @@ pub struct TokenClaims {
     pub iss: CompactString,
     pub aud: CompactString,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn claims(exp: usize) -> TokenClaims {
+        TokenClaims {
+            sub: Uuid::new_v4(),
+            sid: Uuid::new_v4(),
+            exp,
+            iss: "phantom-store".into(),
+            aud: "phantom-store".into(),
+        }
+    }
+
+    fn now() -> usize {
+        time::OffsetDateTime::now_utc().unix_timestamp() as usize
+    }
+
+    #[test]
+    fn access_token_round_trip() -> Result<(), jsonwebtoken::errors::Error> {
+        let claims = claims(now() + 60);
+        let verified =
+            AccessToken::sign(&claims, KEY)?.verify(KEY, "phantom-store", "phantom-store")?;
+        assert_eq!((verified.sub, verified.sid), (claims.sub, claims.sid));
+        Ok(())
+    }
+
+    #[test]
+    fn access_token_rejects_wrong_key_audience_and_expiry()
+    -> Result<(), jsonwebtoken::errors::Error> {
+        let token = AccessToken::sign(&claims(now() + 60), KEY)?;
+        let other_key = b"another key, also 32 bytes long!";
+        assert!(
+            token
+                .verify(other_key, "phantom-store", "phantom-store")
+                .is_err()
+        );
+        assert!(token.verify(KEY, "phantom-store", "elsewhere").is_err());
+        let expired = AccessToken::sign(&claims(now() - 120), KEY)?;
+        assert!(
+            expired
+                .verify(KEY, "phantom-store", "phantom-store")
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn refresh_token_parses_to_its_hash() {
+        let session_id = Uuid::new_v4();
+        let (token, hash) = RefreshToken::generate(session_id);
+        assert_eq!(token.parse(), Some((session_id, hash)));
+        assert_eq!(RefreshToken::new("not a token").parse(), None);
+        assert_eq!(
+            RefreshToken::new(format!("{session_id}.abcd")).parse(),
+            None
+        );
+    }
+}
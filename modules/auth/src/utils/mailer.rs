@@ -0,0 +1,125 @@
+use crate::config::{EmailTransportConfig, SmtpSecurity};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::response::Category;
+use lettre::{AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::instrument;
+
+enum Transport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    File(AsyncFileTransport<Tokio1Executor>, PathBuf),
+}
+
+fn build_transport(config: &EmailTransportConfig) -> Result<Transport, framework::Error> {
+    match config {
+        EmailTransportConfig::Smtp {
+            host,
+            port,
+            security,
+            credentials,
+            timeout,
+        } => {
+            let mut builder = match security {
+                SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+                SmtpSecurity::StartTls => {
+                    AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                }
+                SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                    host.as_str(),
+                )),
+            }
+            .map_err(|e| framework::Error::BusinessPanic(e.into()))?;
+            if let Some(port) = port {
+                builder = builder.port(*port);
+            }
+            if let Some(credentials) = credentials {
+                builder = builder.credentials(Credentials::new(
+                    credentials.username.to_string(),
+                    credentials.password.expose().to_owned(),
+                ));
+            }
+            let timeout = (*timeout).try_into().map_err(|e| {
+                framework::Error::BusinessPanic(anyhow::anyhow!("Invalid SMTP timeout: {e}"))
+            })?;
+            Ok(Transport::Smtp(builder.timeout(Some(timeout)).build()))
+        }
+        EmailTransportConfig::File { directory } => Ok(Transport::File(
+            AsyncFileTransport::new(directory),
+            directory.clone(),
+        )),
+    }
+}
+
+fn smtp_error(e: lettre::transport::smtp::Error) -> framework::Error {
+    match e.status() {
+        // 55x: the mailbox does not exist or refuses the mail, retrying will not help
+        Some(code) if e.is_permanent() && code.category == Category::MailSystem => {
+            tracing::warn!(error = %e, "Email bounced");
+            framework::Error::InvalidInput
+        }
+        // Such as rejected credentials, which need the config fixed
+        Some(_) if e.is_permanent() => framework::Error::BusinessPanic(e.into()),
+        // 4xx, timeouts and connection failures
+        _ => framework::Error::Io(e.into()),
+    }
+}
+
+struct CachedTransport {
+    config: EmailTransportConfig,
+    transport: Arc<Transport>,
+}
+
+/// Sends emails with the configured transport.
+///
+/// The transport, with its SMTP connection pool, is kept until the transport config changes.
+#[derive(Clone, Default)]
+pub struct Mailer {
+    cached: Arc<Mutex<Option<CachedTransport>>>,
+}
+
+impl Mailer {
+    fn transport(&self, config: &EmailTransportConfig) -> Result<Arc<Transport>, framework::Error> {
+        let mut cached = self.cached.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(cached) = cached.as_ref()
+            && cached.config == *config
+        {
+            return Ok(cached.transport.clone());
+        }
+        let transport = Arc::new(build_transport(config)?);
+        *cached = Some(CachedTransport {
+            config: config.clone(),
+            transport: transport.clone(),
+        });
+        Ok(transport)
+    }
+
+    /// Send the email.
+    ///
+    /// A bounce is [InvalidInput](framework::Error::InvalidInput), failures worth retrying are
+    /// [Io](framework::Error::Io) and a broken setup is
+    /// [BusinessPanic](framework::Error::BusinessPanic), so that message consumers ack or requeue
+    /// accordingly.
+    #[instrument(skip_all, err)]
+    pub async fn send(
+        &self,
+        config: &EmailTransportConfig,
+        message: Message,
+    ) -> Result<(), framework::Error> {
+        match &*self.transport(config)? {
+            Transport::Smtp(transport) => {
+                transport.send(message).await.map_err(smtp_error)?;
+            }
+            Transport::File(transport, directory) => {
+                tokio::fs::create_dir_all(directory)
+                    .await
+                    .map_err(|e| framework::Error::Io(e.into()))?;
+                transport
+                    .send(message)
+                    .await
+                    .map_err(|e| framework::Error::Io(e.into()))?;
+            }
+        }
+        Ok(())
+    }
+}
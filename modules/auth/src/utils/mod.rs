@@ -1,3 +1,8 @@
+pub mod email_template;
 pub mod jwt;
+pub mod mailer;
 pub mod oauth;
 pub(crate) mod password;
+pub mod recovery_code;
+pub mod wallet;
+pub mod webauthn;
@@ -1,4 +1,5 @@
 pub mod client_config;
+pub mod oidc;
 pub mod providers;
 
 use compact_str::CompactString;
@@ -3,18 +3,20 @@ use crate::utils::oauth::providers::{
 };
 use crate::utils::oauth::{OAuthUserInfo, OAuthUserInfoError};
 use compact_str::{CompactString, format_compact};
+use std::borrow::Cow;
 
 #[derive(Debug, Clone, Copy)]
 pub struct DiscordProvider;
 
 pub const DISCORD_CONSTANTS: OAuthProviderConstants = OAuthProviderConstants {
     name: super::OAuthProviderName::Discord,
-    authorization_url: "https://discord.com/api/oauth2/authorize",
-    token_url: "https://discord.com/api/oauth2/token",
-    user_info_url: Some("https://discord.com/api/users/@me"),
-    scopes: &["identify", "email"],
+    authorization_url: Cow::Borrowed("https://discord.com/api/oauth2/authorize"),
+    token_url: Cow::Borrowed("https://discord.com/api/oauth2/token"),
+    user_info_url: Some(Cow::Borrowed("https://discord.com/api/users/@me")),
+    scopes: Cow::Borrowed(&[Cow::Borrowed("identify"), Cow::Borrowed("email")]),
     credential_position: ClientCredentialPosition::InUrl,
     use_pkce: false,
+    use_nonce: false,
 };
 
 impl OAuthDataFetch for DiscordProvider {
@@ -28,7 +30,7 @@ impl OAuthDataFetch for DiscordProvider {
         };
         let client = &*REQWEST_CLIENT;
         let response = client
-            .get(user_info_url)
+            .get(user_info_url.as_ref())
             .bearer_auth(access_token)
             .send()
             .await?;
@@ -3,18 +3,20 @@ use crate::utils::oauth::providers::{
 };
 use crate::utils::oauth::{OAuthUserInfo, OAuthUserInfoError};
 use compact_str::CompactString;
+use std::borrow::Cow;
 
 #[derive(Debug, Clone, Copy)]
 pub struct GithubProvider;
 
 pub const GITHUB_CONSTANTS: OAuthProviderConstants = OAuthProviderConstants {
     name: super::OAuthProviderName::Github,
-    authorization_url: "https://github.com/login/oauth/authorize",
-    token_url: "https://github.com/login/oauth/access_token",
-    user_info_url: Some("https://api.github.com/user"),
-    scopes: &["read:user", "user:email"],
+    authorization_url: Cow::Borrowed("https://github.com/login/oauth/authorize"),
+    token_url: Cow::Borrowed("https://github.com/login/oauth/access_token"),
+    user_info_url: Some(Cow::Borrowed("https://api.github.com/user")),
+    scopes: Cow::Borrowed(&[Cow::Borrowed("read:user"), Cow::Borrowed("user:email")]),
     credential_position: ClientCredentialPosition::InUrl,
     use_pkce: false,
+    use_nonce: false,
 };
 
 impl OAuthDataFetch for GithubProvider {
@@ -28,7 +30,7 @@ impl OAuthDataFetch for GithubProvider {
         };
         let client = &*REQWEST_CLIENT;
         let response = client
-            .get(user_info_url)
+            .get(user_info_url.as_ref())
             .bearer_auth(access_token)
             .send()
             .await?;
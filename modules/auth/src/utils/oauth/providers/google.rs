@@ -3,22 +3,26 @@ use crate::utils::oauth::providers::{
 };
 use crate::utils::oauth::{OAuthUserInfo, OAuthUserInfoError};
 use compact_str::CompactString;
+use std::borrow::Cow;
 
 #[derive(Debug, Clone, Copy)]
 pub struct GoogleProvider;
 
 pub const GOOGLE_CONSTANTS: OAuthProviderConstants = OAuthProviderConstants {
     name: super::OAuthProviderName::Google,
-    authorization_url: "https://accounts.google.com/o/oauth2/v2/auth",
-    token_url: "https://www.googleapis.com/oauth2/v3/token",
-    user_info_url: Some("https://openidconnect.googleapis.com/v1/userinfo"),
-    scopes: &[
-        "openid",
-        "https://www.googleapis.com/auth/userinfo.email",
-        "https://www.googleapis.com/auth/userinfo.profile",
-    ],
+    authorization_url: Cow::Borrowed("https://accounts.google.com/o/oauth2/v2/auth"),
+    token_url: Cow::Borrowed("https://www.googleapis.com/oauth2/v3/token"),
+    user_info_url: Some(Cow::Borrowed(
+        "https://openidconnect.googleapis.com/v1/userinfo",
+    )),
+    scopes: Cow::Borrowed(&[
+        Cow::Borrowed("openid"),
+        Cow::Borrowed("https://www.googleapis.com/auth/userinfo.email"),
+        Cow::Borrowed("https://www.googleapis.com/auth/userinfo.profile"),
+    ]),
     credential_position: ClientCredentialPosition::InUrl,
     use_pkce: true,
+    use_nonce: false,
 };
 
 impl OAuthDataFetch for GoogleProvider {
@@ -32,7 +36,7 @@ impl OAuthDataFetch for GoogleProvider {
         };
         let client = &*REQWEST_CLIENT;
         let response = client
-            .get(user_info_url)
+            .get(user_info_url.as_ref())
             .bearer_auth(access_token)
             .send()
             .await?;
@@ -3,18 +3,27 @@ use crate::utils::oauth::providers::{
 };
 use crate::utils::oauth::{OAuthUserInfo, OAuthUserInfoError};
 use compact_str::CompactString;
+use std::borrow::Cow;
 
 #[derive(Debug, Clone, Copy)]
 pub struct MicrosoftProvider;
 
 pub const MICROSOFT_CONSTANTS: OAuthProviderConstants = OAuthProviderConstants {
     name: super::OAuthProviderName::Microsoft,
-    authorization_url: "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
-    token_url: "https://login.microsoftonline.com/common/oauth2/v2.0/token",
-    user_info_url: Some("https://graph.microsoft.com/oidc/userinfo"),
-    scopes: &["openid", "email", "profile", "offline_access"],
+    authorization_url: Cow::Borrowed(
+        "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+    ),
+    token_url: Cow::Borrowed("https://login.microsoftonline.com/common/oauth2/v2.0/token"),
+    user_info_url: Some(Cow::Borrowed("https://graph.microsoft.com/oidc/userinfo")),
+    scopes: Cow::Borrowed(&[
+        Cow::Borrowed("openid"),
+        Cow::Borrowed("email"),
+        Cow::Borrowed("profile"),
+        Cow::Borrowed("offline_access"),
+    ]),
     credential_position: ClientCredentialPosition::InBody,
     use_pkce: true,
+    use_nonce: false,
 };
 
 impl OAuthDataFetch for MicrosoftProvider {
@@ -28,7 +37,7 @@ impl OAuthDataFetch for MicrosoftProvider {
         };
         let client = &*REQWEST_CLIENT;
         let response = client
-            .get(user_info_url)
+            .get(user_info_url.as_ref())
             .bearer_auth(access_token)
             .send()
             .await?;
@@ -1,42 +1,36 @@
 use crate::entities::redis::oauth_challenge::OAuthChallengeKey;
 use crate::utils::oauth::client_config::OAuthProviderClientConfig;
+use crate::utils::oauth::oidc::{OidcDiscovery, OidcProviderId};
 use crate::utils::oauth::{OAuthUserInfo, OAuthUserInfoError};
+use std::borrow::Cow;
 
 pub mod discord;
 pub mod github;
 pub mod google;
 pub mod microsoft;
 
-#[derive(
-    Debug,
-    Clone,
-    Copy,
-    PartialEq,
-    Eq,
-    serde::Serialize,
-    serde::Deserialize,
-    rkyv::Archive,
-    rkyv::Serialize,
-    rkyv::Deserialize,
-    sqlx::Type,
-)]
-#[serde(rename_all = "lowercase")]
-#[sqlx(type_name = "text", rename_all = "lowercase")]
-/// All providers supported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+/// All providers supported. Stored and configured as the lowercase name, `oidc:<id>` for
+/// generic OpenID Connect providers.
 pub enum OAuthProviderName {
     Google,
     Microsoft,
     Github,
     Discord,
+    /// A generic OpenID Connect provider, configured with its issuer
+    Oidc(OidcProviderId),
 }
 
 impl OAuthProviderName {
-    pub fn oauth_constants(&self) -> OAuthProviderConstants {
+    /// The endpoints of a built-in provider. Those of OpenID Connect providers are discovered,
+    /// see [OAuthProviderConstants::oidc].
+    pub fn oauth_constants(&self) -> Option<OAuthProviderConstants> {
         match self {
-            OAuthProviderName::Google => google::GOOGLE_CONSTANTS,
-            OAuthProviderName::Microsoft => microsoft::MICROSOFT_CONSTANTS,
-            OAuthProviderName::Github => github::GITHUB_CONSTANTS,
-            OAuthProviderName::Discord => discord::DISCORD_CONSTANTS,
+            OAuthProviderName::Google => Some(google::GOOGLE_CONSTANTS),
+            OAuthProviderName::Microsoft => Some(microsoft::MICROSOFT_CONSTANTS),
+            OAuthProviderName::Github => Some(github::GITHUB_CONSTANTS),
+            OAuthProviderName::Discord => Some(discord::DISCORD_CONSTANTS),
+            OAuthProviderName::Oidc(_) => None,
         }
     }
 }
@@ -57,6 +51,8 @@ impl OAuthDataFetch for OAuthProviderName {
             OAuthProviderName::Discord => {
                 discord::DiscordProvider.fetch_user_info(access_token).await
             }
+            // Identified by the ID token instead
+            OAuthProviderName::Oidc(_) => Err(OAuthUserInfoError::NoUserInfoUrl),
         }
     }
 }
@@ -68,6 +64,7 @@ impl std::fmt::Display for OAuthProviderName {
             OAuthProviderName::Microsoft => write!(f, "microsoft"),
             OAuthProviderName::Github => write!(f, "github"),
             OAuthProviderName::Discord => write!(f, "discord"),
+            OAuthProviderName::Oidc(id) => write!(f, "oidc:{id}"),
         }
     }
 }
@@ -80,11 +77,55 @@ impl std::str::FromStr for OAuthProviderName {
             "microsoft" => Ok(OAuthProviderName::Microsoft),
             "github" => Ok(OAuthProviderName::Github),
             "discord" => Ok(OAuthProviderName::Discord),
-            _ => Err(framework::Error::InvalidInput),
+            _ => s
+                .strip_prefix("oidc:")
+                .and_then(OidcProviderId::new)
+                .map(OAuthProviderName::Oidc)
+                .ok_or(framework::Error::InvalidInput),
         }
     }
 }
 
+impl serde::Serialize for OAuthProviderName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for OAuthProviderName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = compact_str::CompactString::deserialize(deserializer)?;
+        name.parse()
+            .map_err(|_| serde::de::Error::custom(format!("unknown OAuth provider `{name}`")))
+    }
+}
+
+impl sqlx::Type<sqlx::Postgres> for OAuthProviderName {
+    fn type_info() -> sqlx::postgres::PgTypeInfo {
+        <str as sqlx::Type<sqlx::Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &sqlx::postgres::PgTypeInfo) -> bool {
+        <str as sqlx::Type<sqlx::Postgres>>::compatible(ty)
+    }
+}
+
+impl sqlx::Encode<'_, sqlx::Postgres> for OAuthProviderName {
+    fn encode_by_ref(
+        &self,
+        buf: &mut sqlx::postgres::PgArgumentBuffer,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        <String as sqlx::Encode<sqlx::Postgres>>::encode(self.to_string(), buf)
+    }
+}
+
+impl<'r> sqlx::Decode<'r, sqlx::Postgres> for OAuthProviderName {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let name = <&str as sqlx::Decode<sqlx::Postgres>>::decode(value)?;
+        Ok(name.parse()?)
+    }
+}
+
 pub(crate) trait OAuthDataFetch {
     async fn fetch_user_info(
         &self,
@@ -92,10 +133,19 @@ pub(crate) trait OAuthDataFetch {
     ) -> Result<OAuthUserInfo, OAuthUserInfoError>;
 }
 
+/// The ID token OpenID Connect providers return along with the access token
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct IdTokenFields {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+impl oauth2::ExtraTokenFields for IdTokenFields {}
+
 #[allow(clippy::type_complexity)]
 type OAuthClient = oauth2::Client<
     oauth2::StandardErrorResponse<oauth2::basic::BasicErrorResponseType>,
-    oauth2::StandardTokenResponse<oauth2::EmptyExtraTokenFields, oauth2::basic::BasicTokenType>,
+    oauth2::StandardTokenResponse<IdTokenFields, oauth2::basic::BasicTokenType>,
     oauth2::StandardTokenIntrospectionResponse<
         oauth2::EmptyExtraTokenFields,
         oauth2::basic::BasicTokenType,
@@ -110,7 +160,7 @@ type OAuthClient = oauth2::Client<
 >;
 
 lazy_static::lazy_static! {
-    static ref REQWEST_CLIENT: reqwest::Client = {
+    pub(crate) static ref REQWEST_CLIENT: reqwest::Client = {
         // SAFETY: it will never fail
         #[allow(clippy::expect_used)]
         reqwest::Client::builder()
@@ -128,7 +178,7 @@ lazy_static::lazy_static! {
 pub(crate) const USER_AGENT: &str = "helium-microservices/0.1.0";
 
 type OAuthCodeExchangeResponse =
-    oauth2::StandardTokenResponse<oauth2::EmptyExtraTokenFields, oauth2::basic::BasicTokenType>;
+    oauth2::StandardTokenResponse<IdTokenFields, oauth2::basic::BasicTokenType>;
 type OAuthCodeExchangeError = oauth2::RequestTokenError<
     oauth2::HttpClientError<reqwest::Error>,
     oauth2::StandardErrorResponse<oauth2::basic::BasicErrorResponseType>,
@@ -139,6 +189,7 @@ pub struct AuthorizeUrlEssentials {
     pub state: OAuthChallengeKey,
     pub authorization_url: url::Url,
     pub pkce_verifier: Option<String>,
+    pub nonce: Option<String>,
 }
 
 impl OAuthProviderConstants {
@@ -150,11 +201,11 @@ impl OAuthProviderConstants {
         redirect_uri: &str,
     ) -> Result<OAuthClient, url::ParseError> {
         let client_id = oauth2::ClientId::new(config.client_id.to_string());
-        let client_secret = oauth2::ClientSecret::new(config.client_secret.to_string());
-        let auth_url = oauth2::AuthUrl::new(self.authorization_url.to_owned())?;
-        let token_url = oauth2::TokenUrl::new(self.token_url.to_owned())?;
+        let client_secret = oauth2::ClientSecret::new(config.client_secret.expose().to_owned());
+        let auth_url = oauth2::AuthUrl::new(self.authorization_url.to_string())?;
+        let token_url = oauth2::TokenUrl::new(self.token_url.to_string())?;
         let redirect_uri = oauth2::RedirectUrl::new(redirect_uri.to_owned())?;
-        let client: OAuthClient = oauth2::basic::BasicClient::new(client_id)
+        let client: OAuthClient = oauth2::Client::new(client_id)
             .set_client_secret(client_secret)
             .set_auth_type(match self.credential_position {
                 ClientCredentialPosition::InBody => oauth2::AuthType::RequestBody,
@@ -174,9 +225,15 @@ impl OAuthProviderConstants {
         } else {
             None
         };
+        let nonce = self
+            .use_nonce
+            .then(|| hex::encode(rand::random::<[u8; 16]>()));
         let mut req = client.authorize_url(|| oauth2::CsrfToken::new(new_state.to_string()));
-        for scope in self.scopes {
-            req = req.add_scope(oauth2::Scope::new((*scope).to_owned()));
+        for scope in self.scopes.iter() {
+            req = req.add_scope(oauth2::Scope::new(scope.to_string()));
+        }
+        if let Some(nonce) = &nonce {
+            req = req.add_extra_param("nonce", nonce.clone());
         }
         if let Some((pkce_challenge, pkce_verifier)) = pkce_verifier {
             req = req.set_pkce_challenge(pkce_challenge);
@@ -185,6 +242,7 @@ impl OAuthProviderConstants {
                 authorization_url: url,
                 state: new_state,
                 pkce_verifier: Some(pkce_verifier.into_secret()),
+                nonce,
             }
         } else {
             let (url, _) = req.url();
@@ -192,6 +250,7 @@ impl OAuthProviderConstants {
                 authorization_url: url,
                 state: new_state,
                 pkce_verifier: None,
+                nonce,
             }
         }
     }
@@ -214,12 +273,49 @@ impl OAuthProviderConstants {
 #[derive(Debug, Clone)]
 pub struct OAuthProviderConstants {
     name: OAuthProviderName,
-    authorization_url: &'static str,
-    token_url: &'static str,
-    user_info_url: Option<&'static str>,
-    scopes: &'static [&'static str],
+    authorization_url: Cow<'static, str>,
+    token_url: Cow<'static, str>,
+    user_info_url: Option<Cow<'static, str>>,
+    scopes: Cow<'static, [Cow<'static, str>]>,
     credential_position: ClientCredentialPosition,
     use_pkce: bool,
+    /// Send a nonce that the ID token has to carry
+    use_nonce: bool,
+}
+
+impl OAuthProviderConstants {
+    /// The endpoints of an OpenID Connect provider from its discovery document. `openid` is
+    /// always requested in addition to `scopes`.
+    pub fn oidc(id: OidcProviderId, discovery: OidcDiscovery, scopes: &[String]) -> Self {
+        let scopes = std::iter::once(Cow::Borrowed("openid"))
+            .chain(
+                scopes
+                    .iter()
+                    .filter(|scope| *scope != "openid")
+                    .map(|scope| Cow::Owned(scope.clone())),
+            )
+            .collect::<Vec<_>>();
+        // `client_secret_basic` is the default when the provider does not say
+        let credential_position = match &discovery.token_endpoint_auth_methods_supported {
+            Some(methods)
+                if !methods.iter().any(|m| m == "client_secret_basic")
+                    && methods.iter().any(|m| m == "client_secret_post") =>
+            {
+                ClientCredentialPosition::InBody
+            }
+            _ => ClientCredentialPosition::InUrl,
+        };
+        Self {
+            name: OAuthProviderName::Oidc(id),
+            authorization_url: Cow::Owned(discovery.authorization_endpoint),
+            token_url: Cow::Owned(discovery.token_endpoint),
+            user_info_url: None,
+            scopes: Cow::Owned(scopes),
+            credential_position,
+            use_pkce: true,
+            use_nonce: true,
+        }
+    }
 }
 
 #[derive(
@@ -0,0 +1,70 @@
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+/// How many recovery codes a user gets at once.
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Lowercase letters and digits without the easily confused `0`, `1`, `i`, `l` and `o`.
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+const RECOVERY_CODE_LENGTH: usize = 16;
+const RECOVERY_CODE_GROUP: usize = 4;
+
+/// A fresh recovery code, e.g. `k7fw-q2mx-8hdr-taz3`. With about 79 bits of entropy a plain
+/// SHA-256 is enough to store it.
+pub fn generate_recovery_code() -> String {
+    let mut rng = rand::rng();
+    let mut code = String::with_capacity(RECOVERY_CODE_LENGTH + RECOVERY_CODE_LENGTH / 4);
+    for i in 0..RECOVERY_CODE_LENGTH {
+        if i > 0 && i % RECOVERY_CODE_GROUP == 0 {
+            code.push('-');
+        }
+        let index = rng.random_range(0..RECOVERY_CODE_ALPHABET.len());
+        code.push(RECOVERY_CODE_ALPHABET[index] as char);
+    }
+    code
+}
+
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect()
+}
+
+/// Hash of the code as typed by the user. Case, separators and surrounding whitespace are
+/// ignored.
+pub fn hash_recovery_code(code: &str) -> [u8; 32] {
+    let normalized: String = code
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    Sha256::digest(normalized.trim().as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_code_format() {
+        let code = generate_recovery_code();
+        assert_eq!(code.len(), 19);
+        assert!(
+            code.split('-')
+                .all(|group| group.len() == RECOVERY_CODE_GROUP)
+        );
+        assert!(
+            code.bytes()
+                .all(|c| c == b'-' || RECOVERY_CODE_ALPHABET.contains(&c))
+        );
+    }
+
+    #[test]
+    fn hash_ignores_formatting() {
+        let code = "k7fw-q2mx-8hdr-taz3";
+        let hash = hash_recovery_code(code);
+        assert_eq!(hash_recovery_code("K7FW Q2MX 8HDR TAZ3"), hash);
+        assert_eq!(hash_recovery_code(" k7fwq2mx8hdrtaz3\n"), hash);
+        assert_ne!(hash_recovery_code("k7fw-q2mx-8hdr-taz4"), hash);
+    }
+}
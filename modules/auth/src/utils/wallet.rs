@@ -0,0 +1,265 @@
+use blockchain_sync::utils::supported_tokens::FlattenSupportedBlockchains;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha3::{Digest, Keccak256};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Accounts created by a wallet login have no email address, so they get a unique placeholder
+/// under this reserved domain. Mail to it can never be delivered.
+pub const WALLET_EMAIL_DOMAIN: &str = "wallet.invalid";
+
+/// Whether the email address is under [`WALLET_EMAIL_DOMAIN`]. No one may register or sign in
+/// with such an address.
+pub fn is_wallet_email(email: &str) -> bool {
+    email
+        .rsplit_once('@')
+        .is_some_and(|(_, domain)| domain.eq_ignore_ascii_case(WALLET_EMAIL_DOMAIN))
+}
+
+/// TRON addresses are the Ethereum-style address behind this version byte.
+const TRON_ADDRESS_PREFIX: u8 = 0x41;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+/// The kind of wallet a login message is signed with.
+pub enum WalletKind {
+    /// An EVM account. The chain only decides which chain the address is recorded for.
+    Ethereum {
+        chain_id: u64,
+    },
+    Tron,
+}
+
+impl WalletKind {
+    /// The chain the address is linked for, `None` if the EVM chain is not supported.
+    pub fn blockchain(&self) -> Option<FlattenSupportedBlockchains> {
+        match self {
+            WalletKind::Ethereum { chain_id } => match chain_id {
+                1 => Some(FlattenSupportedBlockchains::Ethereum),
+                10 => Some(FlattenSupportedBlockchains::Optimism),
+                137 => Some(FlattenSupportedBlockchains::Polygon),
+                8453 => Some(FlattenSupportedBlockchains::Base),
+                42161 => Some(FlattenSupportedBlockchains::ArbitrumOne),
+                43114 => Some(FlattenSupportedBlockchains::AvalancheC),
+                59144 => Some(FlattenSupportedBlockchains::Linea),
+                _ => None,
+            },
+            WalletKind::Tron => Some(FlattenSupportedBlockchains::Tron),
+        }
+    }
+
+    /// Parse an address as typed by the user or reported by the wallet.
+    pub fn parse_address(&self, address: &str) -> Option<[u8; 20]> {
+        match self {
+            WalletKind::Ethereum { .. } => parse_evm_address(address),
+            WalletKind::Tron => parse_tron_address(address),
+        }
+    }
+
+    /// The canonical form the address is stored in: lowercase hex for EVM accounts and base58
+    /// for TRON.
+    pub fn format_address(&self, address: &[u8; 20]) -> String {
+        match self {
+            WalletKind::Ethereum { .. } => format!("0x{}", hex::encode(address)),
+            WalletKind::Tron => tron_address(address),
+        }
+    }
+
+    /// The prefix of the personal message hash, see EIP-191 and TRON `signMessageV2`.
+    fn message_prefix(&self) -> &'static str {
+        match self {
+            WalletKind::Ethereum { .. } => "\x19Ethereum Signed Message:\n",
+            WalletKind::Tron => "\x19TRON Signed Message:\n",
+        }
+    }
+}
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+/// `0x`-prefixed hex. Mixed-case input must carry a valid EIP-55 checksum.
+fn parse_evm_address(address: &str) -> Option<[u8; 20]> {
+    let digits = address.strip_prefix("0x")?;
+    let bytes: [u8; 20] = hex::decode(digits).ok()?.try_into().ok()?;
+    let mixed_case = digits.bytes().any(|c| c.is_ascii_lowercase())
+        && digits.bytes().any(|c| c.is_ascii_uppercase());
+    if mixed_case && checksum_address(&bytes) != address {
+        return None;
+    }
+    Some(bytes)
+}
+
+fn parse_tron_address(address: &str) -> Option<[u8; 20]> {
+    let decoded = bs58::decode(address).with_check(None).into_vec().ok()?;
+    let (&prefix, bytes) = decoded.split_first()?;
+    if prefix != TRON_ADDRESS_PREFIX {
+        return None;
+    }
+    bytes.try_into().ok()
+}
+
+fn tron_address(address: &[u8; 20]) -> String {
+    let mut bytes = Vec::with_capacity(21);
+    bytes.push(TRON_ADDRESS_PREFIX);
+    bytes.extend_from_slice(address);
+    bs58::encode(bytes).with_check().into_string()
+}
+
+/// EIP-55 mixed-case checksum encoding, which EIP-4361 requires in the message.
+pub fn checksum_address(address: &[u8; 20]) -> String {
+    let lower = hex::encode(address);
+    let hash = keccak256(lower.as_bytes());
+    let mut out = String::with_capacity(42);
+    out.push_str("0x");
+    for (i, c) in lower.chars().enumerate() {
+        let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+        if nibble >= 8 {
+            out.push(c.to_ascii_uppercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+pub struct SignInMessage<'a> {
+    pub domain: &'a str,
+    pub uri: &'a str,
+    pub statement: &'a str,
+    pub kind: WalletKind,
+    pub address: &'a [u8; 20],
+    pub nonce: &'a str,
+    pub issued_at: OffsetDateTime,
+    pub expires_at: OffsetDateTime,
+}
+
+impl SignInMessage<'_> {
+    /// The EIP-4361 message. TRON has no chain ID, so the same layout is used without it.
+    pub fn to_message(&self) -> Result<String, time::error::Format> {
+        let (account, address, chain_id) = match self.kind {
+            WalletKind::Ethereum { chain_id } => {
+                ("Ethereum", checksum_address(self.address), Some(chain_id))
+            }
+            WalletKind::Tron => ("TRON", tron_address(self.address), None),
+        };
+        let mut message = format!(
+            "{} wants you to sign in with your {account} account:\n{address}\n\n{}\n\nURI: {}\nVersion: 1\n",
+            self.domain, self.statement, self.uri
+        );
+        if let Some(chain_id) = chain_id {
+            message.push_str(&format!("Chain ID: {chain_id}\n"));
+        }
+        message.push_str(&format!(
+            "Nonce: {}\nIssued At: {}\nExpiration Time: {}",
+            self.nonce,
+            self.issued_at.format(&Rfc3339)?,
+            self.expires_at.format(&Rfc3339)?
+        ));
+        Ok(message)
+    }
+}
+
+/// Hash of a personal message as the wallet signs it.
+fn personal_message_hash(kind: WalletKind, message: &str) -> [u8; 32] {
+    let mut data = Vec::with_capacity(message.len() + 32);
+    data.extend_from_slice(kind.message_prefix().as_bytes());
+    data.extend_from_slice(message.len().to_string().as_bytes());
+    data.extend_from_slice(message.as_bytes());
+    keccak256(&data)
+}
+
+/// The address whose key produced `signature` (65 bytes, `r || s || v`) over the personal
+/// message, `None` if the signature is malformed.
+pub fn recover_signer(kind: WalletKind, message: &str, signature: &[u8]) -> Option<[u8; 20]> {
+    let (rs, &[v]) = signature.split_at_checked(64)? else {
+        return None;
+    };
+    let mut signature = Signature::from_slice(rs).ok()?;
+    // Wallets report the recovery ID as 27 or 28
+    let mut recovery_id = RecoveryId::from_byte(if v >= 27 { v - 27 } else { v })?;
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+        recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+    }
+    let hash = personal_message_hash(kind, message);
+    let key = VerifyingKey::recover_from_prehash(&hash, &signature, recovery_id).ok()?;
+    let point = key.to_encoded_point(false);
+    let hash = keccak256(point.as_bytes().get(1..)?);
+    hash[12..].try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+
+    const ETHEREUM: WalletKind = WalletKind::Ethereum { chain_id: 1 };
+
+    fn sign(key: &SigningKey, kind: WalletKind, message: &str) -> anyhow::Result<Vec<u8>> {
+        let (signature, recovery_id) =
+            key.sign_prehash_recoverable(&personal_message_hash(kind, message))?;
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(27 + recovery_id.to_byte());
+        Ok(bytes)
+    }
+
+    #[test]
+    fn address_encoding() -> anyhow::Result<()> {
+        // Address of the private key 1
+        let address = parse_evm_address("0x7e5f4552091a69125d5dfcb7b8c2659029395bdf")
+            .ok_or_else(|| anyhow::anyhow!("lowercase address rejected"))?;
+        assert_eq!(
+            checksum_address(&address),
+            "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf"
+        );
+        assert!(parse_evm_address("0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf").is_some());
+        assert!(parse_evm_address("0x7E5F4552091A69125d5DfCb7b8C2659029395BdF").is_none());
+        let tron = tron_address(&address);
+        assert_eq!(tron, "TMVQGm1qAQYVdetCeGRRkTWYYrLXuHK2HC");
+        assert_eq!(parse_tron_address(&tron), Some(address));
+        Ok(())
+    }
+
+    #[test]
+    fn signers_are_recovered() -> anyhow::Result<()> {
+        let key = SigningKey::from_slice(&[7; 32])?;
+        let point = key.verifying_key().to_encoded_point(false);
+        let expected: [u8; 20] = keccak256(&point.as_bytes()[1..])[12..].try_into()?;
+        let issued_at = OffsetDateTime::from_unix_timestamp(1_700_000_000)?;
+        for kind in [ETHEREUM, WalletKind::Tron] {
+            let message = SignInMessage {
+                domain: "shop.example.com",
+                uri: "https://shop.example.com",
+                statement: "Sign in to Phantom Store",
+                kind,
+                address: &expected,
+                nonce: "0123456789abcdef",
+                issued_at,
+                expires_at: issued_at + time::Duration::minutes(5),
+            }
+            .to_message()?;
+            let signature = sign(&key, kind, &message)?;
+            assert_eq!(recover_signer(kind, &message, &signature), Some(expected));
+            assert_ne!(
+                recover_signer(kind, &format!("{message}\n"), &signature),
+                Some(expected)
+            );
+        }
+        // A signature for one chain family does not log in on the other
+        let signature = sign(&key, ETHEREUM, "hello")?;
+        assert_ne!(
+            recover_signer(WalletKind::Tron, "hello", &signature),
+            Some(expected)
+        );
+        assert_eq!(recover_signer(ETHEREUM, "hello", &signature[..64]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn wallet_emails_are_recognized() {
+        assert!(is_wallet_email(&format!("0xabc@{WALLET_EMAIL_DOMAIN}")));
+        assert!(is_wallet_email("0xabc@Wallet.Invalid"));
+        assert!(!is_wallet_email("user@example.com"));
+        assert!(!is_wallet_email("wallet.invalid"));
+    }
+}
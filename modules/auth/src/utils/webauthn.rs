@@ -0,0 +1,231 @@
+use crate::config::WebauthnConfig;
+use crate::entities::redis::webauthn_challenge::PasskeyChallengePurpose;
+use uuid::Uuid;
+use webauthn_rs::prelude::{
+    AuthenticationResult, CreationChallengeResponse, DiscoverableAuthentication, DiscoverableKey,
+    Passkey, PasskeyAuthentication, PasskeyRegistration, PublicKeyCredential,
+    RegisterPublicKeyCredential, RequestChallengeResponse, Url, WebauthnError,
+};
+use webauthn_rs::{Webauthn, WebauthnBuilder};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PasskeyError {
+    #[error("WebAuthn: {0}")]
+    Webauthn(#[from] WebauthnError),
+    #[error("Malformed challenge state: {0}")]
+    State(#[from] serde_json::Error),
+}
+
+impl From<PasskeyError> for framework::Error {
+    fn from(value: PasskeyError) -> Self {
+        framework::Error::BusinessPanic(value.into())
+    }
+}
+
+pub fn build_webauthn(config: &WebauthnConfig) -> Result<Webauthn, WebauthnError> {
+    let mut origins = config
+        .rp_origins
+        .iter()
+        .map(|origin| Url::parse(origin).map_err(|_| WebauthnError::Configuration));
+    let main_origin = origins.next().ok_or(WebauthnError::Configuration)??;
+    let mut builder = WebauthnBuilder::new(&config.rp_id, &main_origin)?.rp_name(&config.rp_name);
+    for origin in origins {
+        builder = builder.append_allowed_origin(&origin?);
+    }
+    builder.build()
+}
+
+/// Start registering a passkey. Credentials the user already has are excluded, so the same
+/// authenticator cannot be registered twice.
+pub fn start_registration(
+    webauthn: &Webauthn,
+    user_id: Uuid,
+    user_name: &str,
+    existing: &[Passkey],
+) -> Result<(CreationChallengeResponse, String), PasskeyError> {
+    let exclude = existing
+        .iter()
+        .map(|passkey| passkey.cred_id().clone())
+        .collect();
+    let (options, state) =
+        webauthn.start_passkey_registration(user_id, user_name, user_name, Some(exclude))?;
+    Ok((options, serde_json::to_string(&state)?))
+}
+
+pub fn finish_registration(
+    webauthn: &Webauthn,
+    state: &str,
+    credential: &RegisterPublicKeyCredential,
+) -> Result<Passkey, PasskeyError> {
+    let state: PasskeyRegistration = serde_json::from_str(state)?;
+    Ok(webauthn.finish_passkey_registration(credential, &state)?)
+}
+
+/// Start an assertion. Passwordless logins ask for any discoverable credential of this site,
+/// everything else for one of the `passkeys` of the known user.
+pub fn start_assertion(
+    webauthn: &Webauthn,
+    purpose: PasskeyChallengePurpose,
+    passkeys: &[Passkey],
+) -> Result<(RequestChallengeResponse, String), PasskeyError> {
+    match purpose {
+        PasskeyChallengePurpose::Login => {
+            let (options, state) = webauthn.start_discoverable_authentication()?;
+            Ok((options, serde_json::to_string(&state)?))
+        }
+        PasskeyChallengePurpose::Mfa(_) | PasskeyChallengePurpose::Sudo(_) => {
+            let (options, state) = webauthn.start_passkey_authentication(passkeys)?;
+            Ok((options, serde_json::to_string(&state)?))
+        }
+    }
+}
+
+/// The user a discoverable credential claims to belong to. Only trustworthy once the assertion
+/// was verified against the passkeys of that user.
+pub fn claimed_user_id(webauthn: &Webauthn, credential: &PublicKeyCredential) -> Option<Uuid> {
+    webauthn
+        .identify_discoverable_authentication(credential)
+        .ok()
+        .map(|(user_id, _)| user_id)
+}
+
+pub fn finish_assertion(
+    webauthn: &Webauthn,
+    purpose: PasskeyChallengePurpose,
+    state: &str,
+    credential: &PublicKeyCredential,
+    passkeys: &[Passkey],
+) -> Result<AuthenticationResult, PasskeyError> {
+    match purpose {
+        PasskeyChallengePurpose::Login => {
+            let state: DiscoverableAuthentication = serde_json::from_str(state)?;
+            let keys: Vec<DiscoverableKey> = passkeys.iter().map(Into::into).collect();
+            Ok(webauthn.finish_discoverable_authentication(credential, state, &keys)?)
+        }
+        PasskeyChallengePurpose::Mfa(_) | PasskeyChallengePurpose::Sudo(_) => {
+            let state: PasskeyAuthentication = serde_json::from_str(state)?;
+            Ok(webauthn.finish_passkey_authentication(credential, &state)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use webauthn_authenticator_rs::WebauthnAuthenticator;
+    use webauthn_authenticator_rs::softpasskey::SoftPasskey;
+
+    type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+    fn config() -> WebauthnConfig {
+        WebauthnConfig {
+            rp_origins: Box::new([
+                "http://localhost:8080".into(),
+                "http://localhost:3000".into(),
+            ]),
+            ..WebauthnConfig::default()
+        }
+    }
+
+    /// Register a software passkey. It claims user verification, which the passkey flows require.
+    fn register(
+        webauthn: &Webauthn,
+        authenticator: &mut WebauthnAuthenticator<SoftPasskey>,
+        user_id: Uuid,
+    ) -> Result<Passkey, Box<dyn std::error::Error>> {
+        let (options, state) = start_registration(webauthn, user_id, "alice@example.com", &[])?;
+        let origin = Url::parse("http://localhost:8080")?;
+        let credential = authenticator
+            .do_registration(origin, options)
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(finish_registration(webauthn, &state, &credential)?)
+    }
+
+    /// The software authenticator has no resident credentials to pick from, so offer it the
+    /// one a browser would have picked. The allow list is not covered by the signature.
+    fn allow(
+        webauthn: &Webauthn,
+        mut options: RequestChallengeResponse,
+        passkey: &Passkey,
+    ) -> Result<RequestChallengeResponse, WebauthnError> {
+        let (with_passkey, _) =
+            webauthn.start_passkey_authentication(std::slice::from_ref(passkey))?;
+        options.public_key.allow_credentials = with_passkey.public_key.allow_credentials;
+        Ok(options)
+    }
+
+    #[test]
+    fn register_and_verify_for_mfa() -> TestResult {
+        let webauthn = build_webauthn(&config())?;
+        let mut authenticator = WebauthnAuthenticator::new(SoftPasskey::new(true));
+        let user_id = Uuid::new_v4();
+        let passkey = register(&webauthn, &mut authenticator, user_id)?;
+
+        let purpose = PasskeyChallengePurpose::Mfa(user_id);
+        let (options, state) = start_assertion(&webauthn, purpose, std::slice::from_ref(&passkey))?;
+        let credential = authenticator
+            .do_authentication(Url::parse("http://localhost:3000")?, options)
+            .map_err(|e| format!("{e:?}"))?;
+        let result = finish_assertion(&webauthn, purpose, &state, &credential, &[passkey])?;
+        assert!(result.user_verified());
+        Ok(())
+    }
+
+    #[test]
+    fn discoverable_login_identifies_user() -> TestResult {
+        let webauthn = build_webauthn(&config())?;
+        let mut authenticator = WebauthnAuthenticator::new(SoftPasskey::new(true));
+        let user_id = Uuid::new_v4();
+        let passkey = register(&webauthn, &mut authenticator, user_id)?;
+
+        let purpose = PasskeyChallengePurpose::Login;
+        let (options, state) = start_assertion(&webauthn, purpose, &[])?;
+        let mut credential = authenticator
+            .do_authentication(
+                Url::parse("http://localhost:8080")?,
+                allow(&webauthn, options, &passkey)?,
+            )
+            .map_err(|e| format!("{e:?}"))?;
+        // The software authenticator does not return the user handle that a real discoverable
+        // credential would. It is not covered by the signature, so it can be filled in here.
+        credential.response.user_handle = Some(user_id.as_bytes().to_vec().into());
+
+        assert_eq!(claimed_user_id(&webauthn, &credential), Some(user_id));
+        finish_assertion(&webauthn, purpose, &state, &credential, &[passkey])?;
+        Ok(())
+    }
+
+    #[test]
+    fn assertion_rejects_other_credentials() -> TestResult {
+        let webauthn = build_webauthn(&config())?;
+        let mut authenticator = WebauthnAuthenticator::new(SoftPasskey::new(true));
+        let mut other_authenticator = WebauthnAuthenticator::new(SoftPasskey::new(true));
+        let user_id = Uuid::new_v4();
+        let passkey = register(&webauthn, &mut authenticator, user_id)?;
+        let other = register(&webauthn, &mut other_authenticator, Uuid::new_v4())?;
+
+        let purpose = PasskeyChallengePurpose::Login;
+        let (options, state) = start_assertion(&webauthn, purpose, &[])?;
+        let mut credential = other_authenticator
+            .do_authentication(
+                Url::parse("http://localhost:8080")?,
+                allow(&webauthn, options, &other)?,
+            )
+            .map_err(|e| format!("{e:?}"))?;
+        credential.response.user_handle = Some(user_id.as_bytes().to_vec().into());
+
+        // Claiming to be another user does not help without one of their passkeys
+        assert!(finish_assertion(&webauthn, purpose, &state, &credential, &[passkey]).is_err());
+        assert!(finish_assertion(&webauthn, purpose, &state, &credential, &[other]).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_origin_is_rejected() {
+        let mut config = config();
+        config.rp_origins = Box::new(["https://evil.example".into()]);
+        assert!(build_webauthn(&config).is_err());
+        config.rp_origins = Box::new([]);
+        assert!(build_webauthn(&config).is_err());
+    }
+}
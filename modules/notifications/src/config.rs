@@ -0,0 +1,161 @@
+use admin::utils::config_secret::SecretString;
+
+/// Delivery to the webhooks registered by users, off until enabled by the operator since the
+/// shop then sends requests to URLs chosen by users.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WebhookChannelConfig {
+    pub enabled: bool,
+    pub timeout: time::Duration,
+}
+
+impl Default for WebhookChannelConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout: time::Duration::seconds(10),
+        }
+    }
+}
+
+/// Delivery through the shop's Telegram bot, to users who opened it through their link.
+///
+/// Chats are linked by polling the bot for updates, which Telegram refuses while a webhook is
+/// set for the bot.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TelegramChannelConfig {
+    /// The channel is disabled while empty
+    #[serde(default)]
+    pub bot_token: SecretString,
+    /// Username of the bot, without the `@`, which the links to open it point to
+    #[serde(default)]
+    pub bot_username: String,
+    #[serde(default = "default_telegram_api_base_url")]
+    pub api_base_url: String,
+    pub timeout: time::Duration,
+}
+
+fn default_telegram_api_base_url() -> String {
+    "https://api.telegram.org".to_owned()
+}
+
+impl Default for TelegramChannelConfig {
+    fn default() -> Self {
+        Self {
+            bot_token: SecretString::default(),
+            bot_username: String::new(),
+            api_base_url: default_telegram_api_base_url(),
+            timeout: time::Duration::seconds(10),
+        }
+    }
+}
+
+impl TelegramChannelConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.bot_token.expose().is_empty()
+    }
+
+    /// Whether users can link their chat, which needs the username of the bot as well.
+    pub fn can_link(&self) -> bool {
+        self.is_enabled() && !self.bot_username.is_empty()
+    }
+}
+
+/// Delivery of events to the webhook endpoints of the merchant.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MerchantWebhookConfig {
+    pub timeout: time::Duration,
+    /// Attempts of a delivery before giving up
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on every following one
+    pub initial_backoff: time::Duration,
+    pub max_backoff: time::Duration,
+    /// Failed attempts in a row after which an endpoint is disabled
+    pub disable_after_failures: u32,
+}
+
+impl Default for MerchantWebhookConfig {
+    fn default() -> Self {
+        Self {
+            timeout: time::Duration::seconds(10),
+            max_attempts: 8,
+            initial_backoff: time::Duration::seconds(30),
+            max_backoff: time::Duration::hours(6),
+            disable_after_failures: 50,
+        }
+    }
+}
+
+impl MerchantWebhookConfig {
+    /// Delay before retrying a delivery that failed `attempts` times.
+    pub fn backoff(&self, attempts: u32) -> time::Duration {
+        let factor = 2_i32.saturating_pow(attempts.saturating_sub(1));
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+/// Emails are sent with the sender and transport of
+/// [`AuthConfig::email_delivery`](auth::config::AuthConfig::email_delivery).
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct NotificationConfig {
+    #[serde(default)]
+    pub webhook: WebhookChannelConfig,
+    #[serde(default)]
+    pub telegram: TelegramChannelConfig,
+    #[serde(default)]
+    pub merchant_webhook: MerchantWebhookConfig,
+}
+
+impl admin::utils::config_provider::ConfigJson for NotificationConfig {
+    const KEY: &'static str = "notification_config";
+
+    fn validate(&self) -> Result<(), &'static str> {
+        if !self.webhook.timeout.is_positive() || !self.telegram.timeout.is_positive() {
+            return Err("notification timeouts must be positive");
+        }
+        let api_base_url_is_http = url::Url::parse(&self.telegram.api_base_url)
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+        if !api_base_url_is_http {
+            return Err("Telegram API base URL must be an http(s) URL");
+        }
+        let bot_username_is_valid = self
+            .telegram
+            .bot_username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !bot_username_is_valid {
+            return Err("Telegram bot username must only contain letters, digits and `_`");
+        }
+        let merchant_webhook = &self.merchant_webhook;
+        if !merchant_webhook.timeout.is_positive()
+            || !merchant_webhook.initial_backoff.is_positive()
+            || merchant_webhook.initial_backoff > merchant_webhook.max_backoff
+        {
+            return Err("merchant webhook timeout and backoffs must be positive and ordered");
+        }
+        if merchant_webhook.max_attempts == 0 || merchant_webhook.disable_after_failures == 0 {
+            return Err("merchant webhook attempt limits must be positive");
+        }
+        Ok(())
+    }
+}
+
+/// Register the config types of this module.
+pub fn register_configs(registry: &mut admin::utils::config_provider::ConfigRegistry) {
+    registry.register::<NotificationConfig>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merchant_webhook_backoff() {
+        let config = MerchantWebhookConfig::default();
+        assert_eq!(config.backoff(1), time::Duration::seconds(30));
+        assert_eq!(config.backoff(3), time::Duration::minutes(2));
+        assert_eq!(config.backoff(11), time::Duration::hours(6));
+        assert_eq!(config.backoff(u32::MAX), time::Duration::hours(6));
+    }
+}
@@ -0,0 +1,640 @@
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use time::PrimitiveDateTime;
+use tracing::{Instrument, info_span, instrument};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, serde::Serialize)]
+#[sqlx(
+    type_name = "notification.merchant_webhook_event",
+    rename_all = "snake_case"
+)]
+#[serde(rename_all = "snake_case")]
+pub enum MerchantWebhookEvent {
+    OrderPaid,
+    PaymentCallback,
+    OrderStatusChanged,
+}
+
+impl MerchantWebhookEvent {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            MerchantWebhookEvent::OrderPaid => "order_paid",
+            MerchantWebhookEvent::PaymentCallback => "payment_callback",
+            MerchantWebhookEvent::OrderStatusChanged => "order_status_changed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(
+    type_name = "notification.merchant_webhook_delivery_status",
+    rename_all = "snake_case"
+)]
+pub enum MerchantWebhookDeliveryStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Clone, Eq, PartialEq, sqlx::FromRow)]
+pub struct MerchantWebhookEndpoint {
+    pub id: i32,
+    pub url: String,
+    pub description: String,
+    /// Sealed with the config keyring, see [`open_secret`](crate::utils::webhook_signature::open_secret)
+    pub secret: String,
+    pub events: Vec<MerchantWebhookEvent>,
+    pub is_enabled: bool,
+    pub consecutive_failures: i32,
+    pub auto_disabled_at: Option<PrimitiveDateTime>,
+    pub created_at: PrimitiveDateTime,
+    pub updated_at: PrimitiveDateTime,
+}
+
+impl std::fmt::Debug for MerchantWebhookEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MerchantWebhookEndpoint")
+            .field("id", &self.id)
+            .field("url", &self.url)
+            .field("description", &self.description)
+            .field("secret", &"[REDACTED]")
+            .field("events", &self.events)
+            .field("is_enabled", &self.is_enabled)
+            .field("consecutive_failures", &self.consecutive_failures)
+            .field("auto_disabled_at", &self.auto_disabled_at)
+            .field("created_at", &self.created_at)
+            .field("updated_at", &self.updated_at)
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+pub struct CreateMerchantWebhookEndpoint {
+    pub url: String,
+    pub description: String,
+    pub secret: String,
+    pub events: Vec<MerchantWebhookEvent>,
+}
+
+impl Processor<CreateMerchantWebhookEndpoint> for DatabaseProcessor {
+    type Output = MerchantWebhookEndpoint;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:CreateMerchantWebhookEndpoint", err)]
+    async fn process(
+        &self,
+        input: CreateMerchantWebhookEndpoint,
+    ) -> Result<MerchantWebhookEndpoint, sqlx::Error> {
+        sqlx::query_as!(
+            MerchantWebhookEndpoint,
+            r#"
+            INSERT INTO "notification"."merchant_webhook_endpoint" (url, description, secret, events)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, url, description, secret,
+            events as "events: Vec<MerchantWebhookEvent>",
+            is_enabled, consecutive_failures, auto_disabled_at, created_at, updated_at
+            "#,
+            input.url,
+            input.description,
+            input.secret,
+            input.events as Vec<MerchantWebhookEvent>
+        )
+        .fetch_one(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Enabling an endpoint also resets its failure count.
+pub struct UpdateMerchantWebhookEndpoint {
+    pub id: i32,
+    pub url: String,
+    pub description: String,
+    pub events: Vec<MerchantWebhookEvent>,
+    pub is_enabled: bool,
+}
+
+impl Processor<UpdateMerchantWebhookEndpoint> for DatabaseProcessor {
+    type Output = Option<MerchantWebhookEndpoint>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:UpdateMerchantWebhookEndpoint", err)]
+    async fn process(
+        &self,
+        input: UpdateMerchantWebhookEndpoint,
+    ) -> Result<Option<MerchantWebhookEndpoint>, sqlx::Error> {
+        sqlx::query_as!(
+            MerchantWebhookEndpoint,
+            r#"
+            UPDATE "notification"."merchant_webhook_endpoint"
+            SET url = $2,
+                description = $3,
+                events = $4,
+                consecutive_failures = CASE
+                    WHEN $5 AND NOT is_enabled THEN 0
+                    ELSE consecutive_failures
+                END,
+                auto_disabled_at = CASE WHEN $5 THEN NULL ELSE auto_disabled_at END,
+                is_enabled = $5,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, url, description, secret,
+            events as "events: Vec<MerchantWebhookEvent>",
+            is_enabled, consecutive_failures, auto_disabled_at, created_at, updated_at
+            "#,
+            input.id,
+            input.url,
+            input.description,
+            input.events as Vec<MerchantWebhookEvent>,
+            input.is_enabled
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Clone)]
+pub struct SetMerchantWebhookEndpointSecret {
+    pub id: i32,
+    pub secret: String,
+}
+
+impl Processor<SetMerchantWebhookEndpointSecret> for DatabaseProcessor {
+    type Output = Option<MerchantWebhookEndpoint>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:SetMerchantWebhookEndpointSecret", err)]
+    async fn process(
+        &self,
+        input: SetMerchantWebhookEndpointSecret,
+    ) -> Result<Option<MerchantWebhookEndpoint>, sqlx::Error> {
+        sqlx::query_as!(
+            MerchantWebhookEndpoint,
+            r#"
+            UPDATE "notification"."merchant_webhook_endpoint"
+            SET secret = $2, updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, url, description, secret,
+            events as "events: Vec<MerchantWebhookEvent>",
+            is_enabled, consecutive_failures, auto_disabled_at, created_at, updated_at
+            "#,
+            input.id,
+            input.secret
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Clone)]
+pub struct StoredMerchantWebhookSecret {
+    pub id: i32,
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// The stored secrets of every endpoint, to seal them again.
+pub struct ListMerchantWebhookSecrets;
+
+impl Processor<ListMerchantWebhookSecrets> for DatabaseProcessor {
+    type Output = Vec<StoredMerchantWebhookSecret>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListMerchantWebhookSecrets", err)]
+    async fn process(
+        &self,
+        _input: ListMerchantWebhookSecrets,
+    ) -> Result<Vec<StoredMerchantWebhookSecret>, sqlx::Error> {
+        sqlx::query_as!(
+            StoredMerchantWebhookSecret,
+            r#"
+            SELECT id, secret
+            FROM "notification"."merchant_webhook_endpoint"
+            ORDER BY id
+            "#
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Clone)]
+/// Store the same secret sealed again, unless it was rotated meanwhile.
+pub struct ResealMerchantWebhookSecret {
+    pub id: i32,
+    pub previous: String,
+    pub secret: String,
+}
+
+impl Processor<ResealMerchantWebhookSecret> for DatabaseProcessor {
+    type Output = bool;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ResealMerchantWebhookSecret", err)]
+    async fn process(&self, input: ResealMerchantWebhookSecret) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE "notification"."merchant_webhook_endpoint"
+            SET secret = $3
+            WHERE id = $1 AND secret = $2
+            "#,
+            input.id,
+            input.previous,
+            input.secret
+        )
+        .execute(self.db())
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Delete an endpoint along with its delivery log.
+pub struct DeleteMerchantWebhookEndpoint {
+    pub id: i32,
+}
+
+impl Processor<DeleteMerchantWebhookEndpoint> for DatabaseProcessor {
+    type Output = bool;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:DeleteMerchantWebhookEndpoint", err)]
+    async fn process(&self, input: DeleteMerchantWebhookEndpoint) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM "notification"."merchant_webhook_endpoint"
+            WHERE id = $1
+            "#,
+            input.id
+        )
+        .execute(self.db())
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListMerchantWebhookEndpoints;
+
+impl Processor<ListMerchantWebhookEndpoints> for DatabaseProcessor {
+    type Output = Vec<MerchantWebhookEndpoint>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListMerchantWebhookEndpoints", err)]
+    async fn process(
+        &self,
+        _input: ListMerchantWebhookEndpoints,
+    ) -> Result<Vec<MerchantWebhookEndpoint>, sqlx::Error> {
+        sqlx::query_as!(
+            MerchantWebhookEndpoint,
+            r#"
+            SELECT id, url, description, secret,
+            events as "events: Vec<MerchantWebhookEvent>",
+            is_enabled, consecutive_failures, auto_disabled_at, created_at, updated_at
+            FROM "notification"."merchant_webhook_endpoint"
+            ORDER BY id
+            "#
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct MerchantWebhookDelivery {
+    pub id: i64,
+    pub endpoint_id: i32,
+    pub event_id: Uuid,
+    pub event: MerchantWebhookEvent,
+    pub payload: serde_json::Value,
+    pub status: MerchantWebhookDeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: Option<PrimitiveDateTime>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: PrimitiveDateTime,
+    pub delivered_at: Option<PrimitiveDateTime>,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// List the deliveries to an endpoint, newest first, with keyset pagination on `id`.
+pub struct ListMerchantWebhookDeliveries {
+    pub endpoint_id: i32,
+    pub before_id: Option<i64>,
+    pub limit: i64,
+}
+
+impl Processor<ListMerchantWebhookDeliveries> for DatabaseProcessor {
+    type Output = Vec<MerchantWebhookDelivery>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListMerchantWebhookDeliveries", err)]
+    async fn process(
+        &self,
+        input: ListMerchantWebhookDeliveries,
+    ) -> Result<Vec<MerchantWebhookDelivery>, sqlx::Error> {
+        sqlx::query_as!(
+            MerchantWebhookDelivery,
+            r#"
+            SELECT id, endpoint_id, event_id, event as "event: MerchantWebhookEvent", payload,
+            status as "status: MerchantWebhookDeliveryStatus", attempts, next_attempt_at,
+            last_status_code, last_error, created_at, delivered_at
+            FROM "notification"."merchant_webhook_delivery"
+            WHERE endpoint_id = $1 AND ($2::BIGINT IS NULL OR id < $2)
+            ORDER BY id DESC
+            LIMIT $3
+            "#,
+            input.endpoint_id,
+            input.before_id,
+            input.limit
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+/// A delivery about to be attempted, with the endpoint it goes to.
+#[derive(Clone, PartialEq, sqlx::FromRow)]
+pub struct DueMerchantWebhookDelivery {
+    pub id: i64,
+    pub endpoint_id: i32,
+    pub event_id: Uuid,
+    pub event: MerchantWebhookEvent,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub url: String,
+    /// Sealed, as [`MerchantWebhookEndpoint::secret`]
+    pub secret: String,
+}
+
+impl std::fmt::Debug for DueMerchantWebhookDelivery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DueMerchantWebhookDelivery")
+            .field("id", &self.id)
+            .field("endpoint_id", &self.endpoint_id)
+            .field("event_id", &self.event_id)
+            .field("event", &self.event)
+            .field("attempts", &self.attempts)
+            .field("url", &self.url)
+            .field("secret", &"[REDACTED]")
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Queue an event for every enabled endpoint subscribed to it.
+///
+/// The deliveries are leased until `lease_until`, after which they are retried if the first
+/// attempt was never recorded.
+pub struct CreateMerchantWebhookDeliveries {
+    pub event: MerchantWebhookEvent,
+    pub event_id: Uuid,
+    pub payload: serde_json::Value,
+    pub lease_until: PrimitiveDateTime,
+}
+
+impl Processor<CreateMerchantWebhookDeliveries> for DatabaseProcessor {
+    type Output = Vec<DueMerchantWebhookDelivery>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:CreateMerchantWebhookDeliveries", err)]
+    async fn process(
+        &self,
+        input: CreateMerchantWebhookDeliveries,
+    ) -> Result<Vec<DueMerchantWebhookDelivery>, sqlx::Error> {
+        sqlx::query_as!(
+            DueMerchantWebhookDelivery,
+            r#"
+            WITH created AS (
+                INSERT INTO "notification"."merchant_webhook_delivery"
+                (endpoint_id, event_id, event, payload, next_attempt_at)
+                SELECT id, $1, $2, $3, $4
+                FROM "notification"."merchant_webhook_endpoint"
+                WHERE is_enabled AND $2 = ANY(events)
+                RETURNING id, endpoint_id, event_id, event, payload, attempts
+            )
+            SELECT c.id, c.endpoint_id, c.event_id, c.event as "event!: MerchantWebhookEvent",
+            c.payload, c.attempts, e.url, e.secret
+            FROM created c
+            JOIN "notification"."merchant_webhook_endpoint" e ON e.id = c.endpoint_id
+            "#,
+            input.event_id,
+            input.event as MerchantWebhookEvent,
+            input.payload,
+            input.lease_until
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Queue a copy of a past delivery, with the same event ID, to the same endpoint.
+pub struct RedeliverMerchantWebhook {
+    pub delivery_id: i64,
+    pub lease_until: PrimitiveDateTime,
+}
+
+impl Processor<RedeliverMerchantWebhook> for DatabaseProcessor {
+    type Output = Option<DueMerchantWebhookDelivery>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:RedeliverMerchantWebhook", err)]
+    async fn process(
+        &self,
+        input: RedeliverMerchantWebhook,
+    ) -> Result<Option<DueMerchantWebhookDelivery>, sqlx::Error> {
+        sqlx::query_as!(
+            DueMerchantWebhookDelivery,
+            r#"
+            WITH created AS (
+                INSERT INTO "notification"."merchant_webhook_delivery"
+                (endpoint_id, event_id, event, payload, next_attempt_at)
+                SELECT endpoint_id, event_id, event, payload, $2
+                FROM "notification"."merchant_webhook_delivery"
+                WHERE id = $1
+                RETURNING id, endpoint_id, event_id, event, payload, attempts
+            )
+            SELECT c.id, c.endpoint_id, c.event_id, c.event as "event!: MerchantWebhookEvent",
+            c.payload, c.attempts, e.url, e.secret
+            FROM created c
+            JOIN "notification"."merchant_webhook_endpoint" e ON e.id = c.endpoint_id
+            "#,
+            input.delivery_id,
+            input.lease_until
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Lease the pending deliveries of enabled endpoints whose next attempt is due.
+pub struct ClaimDueMerchantWebhookDeliveries {
+    pub limit: i64,
+    pub lease_until: PrimitiveDateTime,
+}
+
+impl Processor<ClaimDueMerchantWebhookDeliveries> for DatabaseProcessor {
+    type Output = Vec<DueMerchantWebhookDelivery>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ClaimDueMerchantWebhookDeliveries", err)]
+    async fn process(
+        &self,
+        input: ClaimDueMerchantWebhookDeliveries,
+    ) -> Result<Vec<DueMerchantWebhookDelivery>, sqlx::Error> {
+        sqlx::query_as!(
+            DueMerchantWebhookDelivery,
+            r#"
+            WITH claimed AS (
+                UPDATE "notification"."merchant_webhook_delivery"
+                SET next_attempt_at = $2
+                WHERE id IN (
+                    SELECT d.id
+                    FROM "notification"."merchant_webhook_delivery" d
+                    JOIN "notification"."merchant_webhook_endpoint" e ON e.id = d.endpoint_id
+                    WHERE d.status = 'pending' AND d.next_attempt_at <= NOW() AND e.is_enabled
+                    ORDER BY d.next_attempt_at
+                    LIMIT $1
+                    FOR UPDATE OF d SKIP LOCKED
+                )
+                RETURNING id, endpoint_id, event_id, event, payload, attempts
+            )
+            SELECT c.id, c.endpoint_id, c.event_id, c.event as "event!: MerchantWebhookEvent",
+            c.payload, c.attempts, e.url, e.secret
+            FROM claimed c
+            JOIN "notification"."merchant_webhook_endpoint" e ON e.id = c.endpoint_id
+            "#,
+            input.limit,
+            input.lease_until
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MerchantWebhookAttemptOutcome {
+    Succeeded {
+        status_code: i32,
+    },
+    Failed {
+        /// Absent when no response was received
+        status_code: Option<i32>,
+        error: String,
+        /// `None` to give up
+        retry_at: Option<PrimitiveDateTime>,
+    },
+}
+
+#[derive(Debug, Clone)]
+/// Record an attempt on the delivery and the failure count of its endpoint.
+///
+/// An endpoint reaching `disable_after_failures` failed attempts in a row is disabled and its
+/// pending deliveries are given up.
+pub struct RecordMerchantWebhookAttempt {
+    pub delivery_id: i64,
+    pub endpoint_id: i32,
+    pub outcome: MerchantWebhookAttemptOutcome,
+    pub disable_after_failures: i32,
+}
+
+impl Processor<RecordMerchantWebhookAttempt> for DatabaseProcessor {
+    /// Whether the endpoint has been disabled
+    type Output = bool;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL-Transaction:RecordMerchantWebhookAttempt", err)]
+    async fn process(&self, input: RecordMerchantWebhookAttempt) -> Result<bool, sqlx::Error> {
+        let (status, status_code, error, next_attempt_at) = match input.outcome {
+            MerchantWebhookAttemptOutcome::Succeeded { status_code } => (
+                MerchantWebhookDeliveryStatus::Succeeded,
+                Some(status_code),
+                None,
+                None,
+            ),
+            MerchantWebhookAttemptOutcome::Failed {
+                status_code,
+                error,
+                retry_at: Some(retry_at),
+            } => (
+                MerchantWebhookDeliveryStatus::Pending,
+                status_code,
+                Some(error),
+                Some(retry_at),
+            ),
+            MerchantWebhookAttemptOutcome::Failed {
+                status_code,
+                error,
+                retry_at: None,
+            } => (
+                MerchantWebhookDeliveryStatus::Failed,
+                status_code,
+                Some(error),
+                None,
+            ),
+        };
+        let mut tx = self
+            .db()
+            .begin()
+            .instrument(info_span!("<Transaction Begin>"))
+            .await?;
+        sqlx::query!(
+            r#"
+            UPDATE "notification"."merchant_webhook_delivery"
+            SET status = $2,
+                attempts = attempts + 1,
+                last_status_code = $3,
+                last_error = $4,
+                next_attempt_at = $5,
+                delivered_at = CASE WHEN $6 THEN NOW() ELSE delivered_at END
+            WHERE id = $1
+            "#,
+            input.delivery_id,
+            status as MerchantWebhookDeliveryStatus,
+            status_code,
+            error,
+            next_attempt_at,
+            status == MerchantWebhookDeliveryStatus::Succeeded
+        )
+        .execute(&mut *tx)
+        .await?;
+        if status == MerchantWebhookDeliveryStatus::Succeeded {
+            sqlx::query!(
+                r#"
+                UPDATE "notification"."merchant_webhook_endpoint"
+                SET consecutive_failures = 0
+                WHERE id = $1
+                "#,
+                input.endpoint_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            return Ok(false);
+        }
+        let disabled = sqlx::query!(
+            r#"
+            UPDATE "notification"."merchant_webhook_endpoint"
+            SET consecutive_failures = consecutive_failures + 1,
+                is_enabled = is_enabled AND consecutive_failures + 1 < $2,
+                auto_disabled_at = CASE
+                    WHEN is_enabled AND consecutive_failures + 1 >= $2 THEN NOW()
+                    ELSE auto_disabled_at
+                END
+            WHERE id = $1
+            -- NOW() is the start of the transaction, so only an endpoint disabled just now matches
+            RETURNING (NOT is_enabled AND auto_disabled_at IS NOT DISTINCT FROM NOW()) as "disabled!"
+            "#,
+            input.endpoint_id,
+            input.disable_after_failures
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .is_some_and(|row| row.disabled);
+        if disabled {
+            sqlx::query!(
+                r#"
+                UPDATE "notification"."merchant_webhook_delivery"
+                SET status = 'failed', next_attempt_at = NULL, last_error = 'Endpoint disabled'
+                WHERE endpoint_id = $1 AND status = 'pending'
+                "#,
+                input.endpoint_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(disabled)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod merchant_webhook;
+pub mod notification_preference;
+pub mod telegram_link;
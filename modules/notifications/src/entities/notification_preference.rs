@@ -0,0 +1,140 @@
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Eq, PartialEq, sqlx::FromRow)]
+pub struct NotificationPreference {
+    pub user_id: Uuid,
+    pub email_enabled: bool,
+    pub webhook_url: Option<String>,
+    pub telegram_chat_id: Option<i64>,
+    pub order_updates: bool,
+    pub account_updates: bool,
+    /// Language tag, the configured default locale applies when absent
+    pub locale: Option<String>,
+    pub updated_at: time::PrimitiveDateTime,
+}
+
+impl NotificationPreference {
+    /// The preference of a user who never changed it, matching the column defaults.
+    pub fn default_for(user_id: Uuid) -> Self {
+        Self {
+            user_id,
+            email_enabled: true,
+            webhook_url: None,
+            telegram_chat_id: None,
+            order_updates: true,
+            account_updates: true,
+            locale: None,
+            updated_at: framework::now_time(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FindNotificationPreference {
+    pub user_id: Uuid,
+}
+
+impl Processor<FindNotificationPreference> for DatabaseProcessor {
+    type Output = Option<NotificationPreference>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:FindNotificationPreference", err)]
+    async fn process(
+        &self,
+        input: FindNotificationPreference,
+    ) -> Result<Option<NotificationPreference>, sqlx::Error> {
+        sqlx::query_as!(
+            NotificationPreference,
+            r#"
+            SELECT user_id, email_enabled, webhook_url, telegram_chat_id,
+            order_updates, account_updates, locale, updated_at
+            FROM "notification"."user_preference"
+            WHERE user_id = $1
+            "#,
+            input.user_id
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Create or replace the preference of a user, except for the Telegram chat which is only set
+/// through [`SetTelegramChat`].
+pub struct SaveNotificationPreference {
+    pub user_id: Uuid,
+    pub email_enabled: bool,
+    pub webhook_url: Option<String>,
+    pub order_updates: bool,
+    pub account_updates: bool,
+    pub locale: Option<String>,
+}
+
+impl Processor<SaveNotificationPreference> for DatabaseProcessor {
+    type Output = NotificationPreference;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:SaveNotificationPreference", err)]
+    async fn process(
+        &self,
+        input: SaveNotificationPreference,
+    ) -> Result<NotificationPreference, sqlx::Error> {
+        sqlx::query_as!(
+            NotificationPreference,
+            r#"
+            INSERT INTO "notification"."user_preference"
+            (user_id, email_enabled, webhook_url, order_updates, account_updates, locale)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (user_id) DO UPDATE
+            SET email_enabled = EXCLUDED.email_enabled,
+                webhook_url = EXCLUDED.webhook_url,
+                order_updates = EXCLUDED.order_updates,
+                account_updates = EXCLUDED.account_updates,
+                locale = EXCLUDED.locale,
+                updated_at = NOW()
+            RETURNING user_id, email_enabled, webhook_url, telegram_chat_id,
+            order_updates, account_updates, locale, updated_at
+            "#,
+            input.user_id,
+            input.email_enabled,
+            input.webhook_url,
+            input.order_updates,
+            input.account_updates,
+            input.locale
+        )
+        .fetch_one(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Link the user to the private chat they started with the bot, or unlink it.
+pub struct SetTelegramChat {
+    pub user_id: Uuid,
+    pub chat_id: Option<i64>,
+}
+
+impl Processor<SetTelegramChat> for DatabaseProcessor {
+    type Output = NotificationPreference;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:SetTelegramChat", err)]
+    async fn process(&self, input: SetTelegramChat) -> Result<NotificationPreference, sqlx::Error> {
+        sqlx::query_as!(
+            NotificationPreference,
+            r#"
+            INSERT INTO "notification"."user_preference" (user_id, telegram_chat_id)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET telegram_chat_id = EXCLUDED.telegram_chat_id,
+                updated_at = NOW()
+            RETURNING user_id, email_enabled, webhook_url, telegram_chat_id,
+            order_updates, account_updates, locale, updated_at
+            "#,
+            input.user_id,
+            input.chat_id
+        )
+        .fetch_one(self.db())
+        .await
+    }
+}
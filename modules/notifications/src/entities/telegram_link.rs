@@ -0,0 +1,84 @@
+use framework::redis::RedisKey;
+use uuid::Uuid;
+
+/// Key of a pending Telegram link, by the token sent in the `/start` deep link of the bot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TelegramLinkTokenKey(pub [u8; 16]);
+
+impl redis::ToSingleRedisArg for TelegramLinkTokenKey {}
+
+impl redis::ToRedisArgs for TelegramLinkTokenKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        let key = RedisKey::from(format!("telegram_link:{}", hex::encode(self.0)));
+        key.write_redis_args(out);
+    }
+}
+
+/// A user waiting to open the bot through their deep link, so that the chat it is opened from
+/// is known to be theirs.
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    kanau::RkyvMessageDe,
+    kanau::RkyvMessageSer,
+)]
+pub struct TelegramLinkToken {
+    pub token: [u8; 16],
+    pub user_id: Uuid,
+}
+
+impl core::fmt::Debug for TelegramLinkToken {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TelegramLinkToken")
+            .field("token", &"[redacted]")
+            .field("user_id", &self.user_id)
+            .finish()
+    }
+}
+
+impl framework::redis::KeyValue for TelegramLinkToken {
+    type Key = TelegramLinkTokenKey;
+    type Value = Self;
+
+    fn key(&self) -> Self::Key {
+        TelegramLinkTokenKey(self.token)
+    }
+
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+
+    fn into_value(self) -> Self::Value {
+        self
+    }
+
+    fn new(key: Self::Key, mut value: Self::Value) -> Self {
+        value.token = key.0;
+        value
+    }
+}
+
+impl framework::redis::KeyValueRead for TelegramLinkToken {}
+impl framework::redis::KeyValueWrite for TelegramLinkToken {}
+
+/// Key of the id of the next bot update to fetch, shared by the instances polling for updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TelegramUpdateOffsetKey;
+
+impl redis::ToSingleRedisArg for TelegramUpdateOffsetKey {}
+
+impl redis::ToRedisArgs for TelegramUpdateOffsetKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        RedisKey::from("telegram_update_offset").write_redis_args(out);
+    }
+}
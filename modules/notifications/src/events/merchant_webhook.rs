@@ -0,0 +1,47 @@
+/// How often the deliveries due for a retry are looked for.
+const RETRY_INTERVAL: time::Duration = time::Duration::seconds(30);
+
+/// Cron signal to retry the merchant webhook deliveries whose backoff elapsed.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    kanau::RkyvMessageSer,
+    kanau::RkyvMessageDe,
+)]
+pub struct MerchantWebhookRetryTick {
+    /// Unix timestamp in seconds
+    pub ticked_at: i64,
+}
+
+impl framework::rabbitmq::AmqpRouting for MerchantWebhookRetryTick {
+    const EXCHANGE: &'static str = "notifications";
+    const EXCHANGE_TYPE: framework::rabbitmq::AmqpExchangeType =
+        framework::rabbitmq::AmqpExchangeType::Direct;
+    const ROUTING_KEY: &'static str = "merchant_webhook_retry";
+}
+
+impl framework::rabbitmq::AmqpMessageSend for MerchantWebhookRetryTick {}
+
+impl framework::cron::CronJobExecutionSignal for MerchantWebhookRetryTick {
+    fn tick(now: time::OffsetDateTime) -> Self {
+        Self {
+            ticked_at: now.unix_timestamp(),
+        }
+    }
+
+    fn time_pool(
+        now: time::OffsetDateTime,
+        last_time: time::OffsetDateTime,
+    ) -> std::task::Poll<Self> {
+        if now - last_time >= RETRY_INTERVAL {
+            std::task::Poll::Ready(Self::tick(now))
+        } else {
+            std::task::Poll::Pending
+        }
+    }
+}
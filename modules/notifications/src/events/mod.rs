@@ -0,0 +1,2 @@
+pub mod merchant_webhook;
+pub mod telegram_link;
@@ -0,0 +1,47 @@
+/// How often the bot is asked for the messages users sent it.
+const POLL_INTERVAL: time::Duration = time::Duration::seconds(5);
+
+/// Cron signal to link the Telegram chats users opened the bot from.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    kanau::RkyvMessageSer,
+    kanau::RkyvMessageDe,
+)]
+pub struct TelegramUpdatesTick {
+    /// Unix timestamp in seconds
+    pub ticked_at: i64,
+}
+
+impl framework::rabbitmq::AmqpRouting for TelegramUpdatesTick {
+    const EXCHANGE: &'static str = "notifications";
+    const EXCHANGE_TYPE: framework::rabbitmq::AmqpExchangeType =
+        framework::rabbitmq::AmqpExchangeType::Direct;
+    const ROUTING_KEY: &'static str = "telegram_updates";
+}
+
+impl framework::rabbitmq::AmqpMessageSend for TelegramUpdatesTick {}
+
+impl framework::cron::CronJobExecutionSignal for TelegramUpdatesTick {
+    fn tick(now: time::OffsetDateTime) -> Self {
+        Self {
+            ticked_at: now.unix_timestamp(),
+        }
+    }
+
+    fn time_pool(
+        now: time::OffsetDateTime,
+        last_time: time::OffsetDateTime,
+    ) -> std::task::Poll<Self> {
+        if now - last_time >= POLL_INTERVAL {
+            std::task::Poll::Ready(Self::tick(now))
+        } else {
+            std::task::Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::entities::merchant_webhook::MerchantWebhookEvent;
+use crate::events::merchant_webhook::MerchantWebhookRetryTick;
+use crate::services::merchant_webhook::MerchantWebhookDispatcher;
+use crate::utils::http_client::HttpClient;
+use framework::rabbitmq::AmqpMessageProcessor;
+use kanau::processor::Processor;
+use ordering::entities::order::{
+    FindUserOrderById, OrderStatus, PaymentMethod, PaymentMethodInfo, UserOrder,
+};
+use ordering::events::order::{OrderPaidEvent, OrderStatusChangedEvent};
+use ordering::events::payment::PaymentCallbackEvent;
+use rust_decimal::Decimal;
+use time::PrimitiveDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Forward ordering and payment events to the webhook endpoints of the merchant.
+#[derive(Clone)]
+pub struct MerchantWebhookHook<H = reqwest::Client> {
+    pub dispatcher: MerchantWebhookDispatcher<H>,
+}
+
+/// The order as sent in webhook payloads, with timestamps in Unix seconds.
+#[derive(Debug, Clone, serde::Serialize)]
+struct OrderSnapshot {
+    id: Uuid,
+    user_id: Uuid,
+    production_id: Uuid,
+    total_amount: Decimal,
+    coupon_used: Option<i32>,
+    status: OrderStatus,
+    created_at: i64,
+    paid_at: Option<i64>,
+    payment_method: Option<PaymentMethod>,
+    payment_method_info: Option<PaymentMethodInfo>,
+    tracking_number: Option<String>,
+}
+
+fn unix_timestamp(time: PrimitiveDateTime) -> i64 {
+    time.assume_utc().unix_timestamp()
+}
+
+impl From<UserOrder> for OrderSnapshot {
+    fn from(order: UserOrder) -> Self {
+        Self {
+            id: order.id,
+            user_id: order.user,
+            production_id: order.production,
+            total_amount: order.total_amount,
+            coupon_used: order.coupon_used,
+            status: order.order_status,
+            created_at: unix_timestamp(order.created_at),
+            paid_at: order.paid_at.map(unix_timestamp),
+            payment_method: order.payment_method,
+            payment_method_info: order.payment_method_info.0,
+            tracking_number: order.tracking_number,
+        }
+    }
+}
+
+impl<H: HttpClient> MerchantWebhookHook<H> {
+    async fn find_order(&self, order_id: Uuid) -> Result<OrderSnapshot, framework::Error> {
+        self.dispatcher
+            .db
+            .process(FindUserOrderById { id: order_id })
+            .await?
+            .map(OrderSnapshot::from)
+            .ok_or(framework::Error::NotFound)
+    }
+
+    async fn publish(
+        &self,
+        event: MerchantWebhookEvent,
+        data: serde_json::Value,
+    ) -> Result<(), framework::Error> {
+        self.dispatcher.publish(event, data).await
+    }
+}
+
+impl<H: HttpClient> Processor<OrderPaidEvent> for MerchantWebhookHook<H> {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: OrderPaidEvent) -> Result<(), framework::Error> {
+        let order = self.find_order(input.order_id).await?;
+        self.publish(
+            MerchantWebhookEvent::OrderPaid,
+            serde_json::json!({
+                "order": order,
+                "paid_at": input.paid_at,
+            }),
+        )
+        .await
+    }
+}
+
+impl<H: HttpClient> AmqpMessageProcessor<OrderPaidEvent> for MerchantWebhookHook<H> {
+    const QUEUE: &'static str = "notifications.merchant_webhook.order_paid";
+}
+
+impl<H: HttpClient> Processor<PaymentCallbackEvent> for MerchantWebhookHook<H> {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: PaymentCallbackEvent) -> Result<(), framework::Error> {
+        self.publish(
+            MerchantWebhookEvent::PaymentCallback,
+            serde_json::json!({
+                "order_id": input.order_id,
+                "payment_method_info": input.payment_method_info,
+                "created_at": input.created_at,
+            }),
+        )
+        .await
+    }
+}
+
+impl<H: HttpClient> AmqpMessageProcessor<PaymentCallbackEvent> for MerchantWebhookHook<H> {
+    const QUEUE: &'static str = "notifications.merchant_webhook.payment_callback";
+}
+
+impl<H: HttpClient> Processor<OrderStatusChangedEvent> for MerchantWebhookHook<H> {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: OrderStatusChangedEvent) -> Result<(), framework::Error> {
+        let order = self.find_order(input.order_id).await?;
+        self.publish(
+            MerchantWebhookEvent::OrderStatusChanged,
+            serde_json::json!({
+                "order": order,
+                "new_status": input.new_status,
+                "changed_at": input.changed_at,
+            }),
+        )
+        .await
+    }
+}
+
+impl<H: HttpClient> AmqpMessageProcessor<OrderStatusChangedEvent> for MerchantWebhookHook<H> {
+    const QUEUE: &'static str = "notifications.merchant_webhook.order_status_changed";
+}
+
+impl<H: HttpClient> Processor<MerchantWebhookRetryTick> for MerchantWebhookHook<H> {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, _input: MerchantWebhookRetryTick) -> Result<(), framework::Error> {
+        let attempted = self.dispatcher.retry_due().await?;
+        if attempted > 0 {
+            tracing::info!(attempted, "Retried merchant webhook deliveries");
+        }
+        Ok(())
+    }
+}
+
+impl<H: HttpClient> AmqpMessageProcessor<MerchantWebhookRetryTick> for MerchantWebhookHook<H> {
+    const QUEUE: &'static str = "notifications.merchant_webhook.retry";
+}
@@ -0,0 +1,3 @@
+pub mod merchant_webhook;
+pub mod notification;
+pub mod telegram_link;
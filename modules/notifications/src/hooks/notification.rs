@@ -0,0 +1,155 @@
+use crate::services::notification::{NotificationService, Notify};
+use crate::utils::http_client::HttpClient;
+use crate::utils::template::Notification;
+use auth::events::account::UserRegisterEvent;
+use auth::events::security::{SecurityNotificationEvent, SecurityNotificationKind};
+use framework::rabbitmq::AmqpMessageProcessor;
+use kanau::processor::Processor;
+use ordering::entities::order::{FindUserOrderById, OrderStatus, UserOrder};
+use ordering::events::delivery::DeliveryUpdate;
+use ordering::events::order::{OrderPaidEvent, OrderStatusChangedEvent};
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Notify users about their account and orders.
+#[derive(Clone)]
+pub struct NotificationHook<H = reqwest::Client> {
+    pub service: NotificationService<H>,
+}
+
+impl<H: HttpClient> NotificationHook<H> {
+    async fn find_order(&self, order_id: Uuid) -> Result<UserOrder, framework::Error> {
+        self.service
+            .db
+            .process(FindUserOrderById { id: order_id })
+            .await?
+            .ok_or(framework::Error::NotFound)
+    }
+}
+
+impl<H: HttpClient> Processor<UserRegisterEvent> for NotificationHook<H> {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: UserRegisterEvent) -> Result<(), framework::Error> {
+        self.service
+            .process(Notify {
+                user_id: input.user_id,
+                occurred_at: input.registered_at as i64,
+                notification: Notification::Welcome,
+            })
+            .await
+    }
+}
+
+impl<H: HttpClient> AmqpMessageProcessor<UserRegisterEvent> for NotificationHook<H> {
+    const QUEUE: &'static str = "notifications.user_register";
+}
+
+impl<H: HttpClient> Processor<SecurityNotificationEvent> for NotificationHook<H> {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: SecurityNotificationEvent) -> Result<(), framework::Error> {
+        let notification = match input.kind {
+            SecurityNotificationKind::RecoveryCodeUsed { remaining_codes } => {
+                Notification::RecoveryCodeUsed {
+                    remaining_codes,
+                    ip: input.ip,
+                    user_agent: input.user_agent,
+                }
+            }
+        };
+        self.service
+            .process(Notify {
+                user_id: input.user_id,
+                occurred_at: input.occurred_at as i64,
+                notification,
+            })
+            .await
+    }
+}
+
+impl<H: HttpClient> AmqpMessageProcessor<SecurityNotificationEvent> for NotificationHook<H> {
+    const QUEUE: &'static str = "notifications.security_notification";
+}
+
+impl<H: HttpClient> Processor<OrderPaidEvent> for NotificationHook<H> {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: OrderPaidEvent) -> Result<(), framework::Error> {
+        let order = self.find_order(input.order_id).await?;
+        self.service
+            .process(Notify {
+                user_id: order.user,
+                occurred_at: input.paid_at,
+                notification: Notification::OrderPaid {
+                    order_id: order.id,
+                    total_amount: order.total_amount,
+                },
+            })
+            .await
+    }
+}
+
+impl<H: HttpClient> AmqpMessageProcessor<OrderPaidEvent> for NotificationHook<H> {
+    const QUEUE: &'static str = "notifications.order_paid";
+}
+
+impl<H: HttpClient> Processor<OrderStatusChangedEvent> for NotificationHook<H> {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: OrderStatusChangedEvent) -> Result<(), framework::Error> {
+        // payments have a notification of their own, sent on `OrderPaidEvent`
+        if matches!(input.new_status, OrderStatus::Unpaid | OrderStatus::Paid) {
+            return Ok(());
+        }
+        let order = self.find_order(input.order_id).await?;
+        self.service
+            .process(Notify {
+                user_id: order.user,
+                occurred_at: input.changed_at,
+                notification: Notification::OrderStatusChanged {
+                    order_id: order.id,
+                    status: input.new_status,
+                },
+            })
+            .await
+    }
+}
+
+impl<H: HttpClient> AmqpMessageProcessor<OrderStatusChangedEvent> for NotificationHook<H> {
+    const QUEUE: &'static str = "notifications.order_status_changed";
+}
+
+impl<H: HttpClient> Processor<DeliveryUpdate> for NotificationHook<H> {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: DeliveryUpdate) -> Result<(), framework::Error> {
+        // only the latest item is news to the user
+        let Some(latest) = input.items.into_iter().max_by_key(|item| item.created_at) else {
+            return Ok(());
+        };
+        let order = self.find_order(input.order_id).await?;
+        self.service
+            .process(Notify {
+                user_id: order.user,
+                occurred_at: latest.created_at,
+                notification: Notification::DeliveryUpdated {
+                    order_id: order.id,
+                    tracking_number: input.tracking_number.or(order.tracking_number),
+                    status: latest.status,
+                    location: latest.location,
+                    description: latest.description,
+                },
+            })
+            .await
+    }
+}
+
+impl<H: HttpClient> AmqpMessageProcessor<DeliveryUpdate> for NotificationHook<H> {
+    const QUEUE: &'static str = "notifications.delivery_update";
+}
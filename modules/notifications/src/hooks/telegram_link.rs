@@ -0,0 +1,29 @@
+use crate::events::telegram_link::TelegramUpdatesTick;
+use crate::services::telegram_link::{PollTelegramUpdates, TelegramLinkService};
+use crate::utils::http_client::HttpClient;
+use framework::rabbitmq::AmqpMessageProcessor;
+use kanau::processor::Processor;
+use tracing::instrument;
+
+/// Link the Telegram chats users opened the bot from through their link.
+#[derive(Clone)]
+pub struct TelegramLinkHook<H = reqwest::Client> {
+    pub service: TelegramLinkService<H>,
+}
+
+impl<H: HttpClient> Processor<TelegramUpdatesTick> for TelegramLinkHook<H> {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, _input: TelegramUpdatesTick) -> Result<(), framework::Error> {
+        let linked = self.service.process(PollTelegramUpdates).await?;
+        if linked > 0 {
+            tracing::info!(linked, "Linked Telegram chats");
+        }
+        Ok(())
+    }
+}
+
+impl<H: HttpClient> AmqpMessageProcessor<TelegramUpdatesTick> for TelegramLinkHook<H> {
+    const QUEUE: &'static str = "notifications.telegram_link.updates";
+}
@@ -0,0 +1,12 @@
+#![forbid(clippy::unwrap_used)]
+#![forbid(unsafe_code)]
+#![forbid(clippy::expect_used)]
+#![forbid(clippy::panic)]
+
+pub mod config;
+pub mod entities;
+pub mod events;
+pub mod hooks;
+pub mod rpc;
+pub mod services;
+pub mod utils;
@@ -0,0 +1,319 @@
+use crate::entities::merchant_webhook::{
+    MerchantWebhookAttemptOutcome, MerchantWebhookDelivery, MerchantWebhookDeliveryStatus,
+    MerchantWebhookEndpoint, MerchantWebhookEvent,
+};
+use crate::services::merchant_webhook::{
+    DeleteWebhookEndpoint, ListWebhookDeliveries, ListWebhookEndpoints,
+    MerchantWebhookManageService, RedeliverWebhook, RegisterWebhookEndpoint, ResealWebhookSecrets,
+    RotateWebhookSecret, UpdateWebhookEndpoint,
+};
+use admin::rpc::middleware::AdminId;
+use admin::utils::rbac::{AuthenticatedAdminOperation, AuthorizationAdapter, AuthorizationLayer};
+use phantom_shop_proto::v1::notifications::admin::{
+    DeleteWebhookEndpointRequest, DeleteWebhookEndpointResponse, ListWebhookDeliveriesRequest,
+    ListWebhookDeliveriesResponse, ListWebhookEndpointsRequest, ListWebhookEndpointsResponse,
+    RedeliverWebhookRequest, RedeliverWebhookResponse, RegisterWebhookEndpointRequest,
+    ResealWebhookSecretsRequest, ResealWebhookSecretsResponse, RotateWebhookSecretRequest,
+    UpdateWebhookEndpointRequest, WebhookDelivery as ProtoWebhookDelivery,
+    WebhookDeliveryStatus as ProtoWebhookDeliveryStatus, WebhookEndpoint as ProtoWebhookEndpoint,
+    WebhookEvent as ProtoWebhookEvent,
+};
+use tonic::{Request, Response, Status};
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+pub struct MerchantWebhookManageServiceImpl {
+    pub inner: MerchantWebhookManageService,
+    pub authorization: AuthorizationAdapter,
+}
+
+impl MerchantWebhookManageServiceImpl {
+    pub fn new(inner: MerchantWebhookManageService, authorization: AuthorizationLayer) -> Self {
+        Self {
+            inner,
+            authorization: authorization.into_adapter(),
+        }
+    }
+}
+
+impl From<MerchantWebhookEvent> for ProtoWebhookEvent {
+    fn from(value: MerchantWebhookEvent) -> Self {
+        match value {
+            MerchantWebhookEvent::OrderPaid => Self::OrderPaid,
+            MerchantWebhookEvent::PaymentCallback => Self::PaymentCallback,
+            MerchantWebhookEvent::OrderStatusChanged => Self::OrderStatusChanged,
+        }
+    }
+}
+
+impl From<MerchantWebhookDeliveryStatus> for ProtoWebhookDeliveryStatus {
+    fn from(value: MerchantWebhookDeliveryStatus) -> Self {
+        match value {
+            MerchantWebhookDeliveryStatus::Pending => Self::Pending,
+            MerchantWebhookDeliveryStatus::Succeeded => Self::Succeeded,
+            MerchantWebhookDeliveryStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+fn parse_events(events: Vec<i32>) -> Result<Vec<MerchantWebhookEvent>, Status> {
+    events
+        .into_iter()
+        .map(|event| match ProtoWebhookEvent::try_from(event) {
+            Ok(ProtoWebhookEvent::OrderPaid) => Ok(MerchantWebhookEvent::OrderPaid),
+            Ok(ProtoWebhookEvent::PaymentCallback) => Ok(MerchantWebhookEvent::PaymentCallback),
+            Ok(ProtoWebhookEvent::OrderStatusChanged) => {
+                Ok(MerchantWebhookEvent::OrderStatusChanged)
+            }
+            Ok(ProtoWebhookEvent::Unspecified) | Err(_) => {
+                Err(Status::invalid_argument("Invalid webhook event"))
+            }
+        })
+        .collect()
+}
+
+/// The secret is left out, to be set only where the admin needs to see it.
+fn endpoint_without_secret(endpoint: MerchantWebhookEndpoint) -> ProtoWebhookEndpoint {
+    ProtoWebhookEndpoint {
+        id: endpoint.id,
+        url: endpoint.url,
+        description: endpoint.description,
+        events: endpoint
+            .events
+            .into_iter()
+            .map(|event| ProtoWebhookEvent::from(event).into())
+            .collect(),
+        is_enabled: endpoint.is_enabled,
+        consecutive_failures: endpoint.consecutive_failures,
+        auto_disabled_at: endpoint.auto_disabled_at.map(Into::into),
+        created_at: Some(endpoint.created_at.into()),
+        updated_at: Some(endpoint.updated_at.into()),
+        secret: None,
+    }
+}
+
+fn endpoint_with_secret(endpoint: MerchantWebhookEndpoint) -> ProtoWebhookEndpoint {
+    let secret = endpoint.secret.clone();
+    ProtoWebhookEndpoint {
+        secret: Some(secret),
+        ..endpoint_without_secret(endpoint)
+    }
+}
+
+impl From<MerchantWebhookDelivery> for ProtoWebhookDelivery {
+    fn from(value: MerchantWebhookDelivery) -> Self {
+        Self {
+            id: value.id,
+            endpoint_id: value.endpoint_id,
+            event_id: value.event_id.to_string(),
+            event: ProtoWebhookEvent::from(value.event).into(),
+            payload: value.payload.to_string(),
+            status: ProtoWebhookDeliveryStatus::from(value.status).into(),
+            attempts: value.attempts,
+            next_attempt_at: value.next_attempt_at.map(Into::into),
+            last_status_code: value.last_status_code,
+            last_error: value.last_error,
+            created_at: Some(value.created_at.into()),
+            delivered_at: value.delivered_at.map(Into::into),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl phantom_shop_proto::v1::notifications::admin::merchant_webhook_manage_service_server::MerchantWebhookManageService
+    for MerchantWebhookManageServiceImpl
+{
+    async fn register_webhook_endpoint(
+        &self,
+        request: Request<RegisterWebhookEndpointRequest>,
+    ) -> Result<Response<ProtoWebhookEndpoint>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = RegisterWebhookEndpoint {
+            url: req.url,
+            description: req.description,
+            events: parse_events(req.events)?,
+        };
+        let endpoint = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(endpoint_with_secret(endpoint)))
+    }
+
+    async fn update_webhook_endpoint(
+        &self,
+        request: Request<UpdateWebhookEndpointRequest>,
+    ) -> Result<Response<ProtoWebhookEndpoint>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let operation = UpdateWebhookEndpoint {
+            id: req.id,
+            url: req.url,
+            description: req.description,
+            events: parse_events(req.events)?,
+            is_enabled: req.is_enabled,
+        };
+        let endpoint = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(endpoint_without_secret(endpoint)))
+    }
+
+    async fn rotate_webhook_secret(
+        &self,
+        request: Request<RotateWebhookSecretRequest>,
+    ) -> Result<Response<ProtoWebhookEndpoint>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let endpoint = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: RotateWebhookSecret { id: req.id },
+                },
+            )
+            .await?;
+        Ok(Response::new(endpoint_with_secret(endpoint)))
+    }
+
+    async fn delete_webhook_endpoint(
+        &self,
+        request: Request<DeleteWebhookEndpointRequest>,
+    ) -> Result<Response<DeleteWebhookEndpointResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        self.authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: DeleteWebhookEndpoint { id: req.id },
+                },
+            )
+            .await?;
+        Ok(Response::new(DeleteWebhookEndpointResponse {}))
+    }
+
+    async fn list_webhook_endpoints(
+        &self,
+        request: Request<ListWebhookEndpointsRequest>,
+    ) -> Result<Response<ListWebhookEndpointsResponse>, Status> {
+        let (admin_id, _) = AdminId::from_request(request)?;
+        let endpoints = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: ListWebhookEndpoints,
+                },
+            )
+            .await?;
+        Ok(Response::new(ListWebhookEndpointsResponse {
+            endpoints: endpoints.into_iter().map(endpoint_without_secret).collect(),
+        }))
+    }
+
+    async fn list_webhook_deliveries(
+        &self,
+        request: Request<ListWebhookDeliveriesRequest>,
+    ) -> Result<Response<ListWebhookDeliveriesResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let limit = match req.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            limit => limit.min(MAX_PAGE_SIZE),
+        };
+        let deliveries = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: ListWebhookDeliveries {
+                        endpoint_id: req.endpoint_id,
+                        before_id: req.before_id,
+                        limit: limit.into(),
+                    },
+                },
+            )
+            .await?;
+        Ok(Response::new(ListWebhookDeliveriesResponse {
+            deliveries: deliveries.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn redeliver_webhook(
+        &self,
+        request: Request<RedeliverWebhookRequest>,
+    ) -> Result<Response<RedeliverWebhookResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let redelivery = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: RedeliverWebhook {
+                        delivery_id: req.delivery_id,
+                    },
+                },
+            )
+            .await?;
+        let response = match redelivery.outcome {
+            MerchantWebhookAttemptOutcome::Succeeded { status_code } => RedeliverWebhookResponse {
+                delivery_id: redelivery.delivery_id,
+                succeeded: true,
+                status_code: Some(status_code),
+                error: None,
+            },
+            MerchantWebhookAttemptOutcome::Failed {
+                status_code, error, ..
+            } => RedeliverWebhookResponse {
+                delivery_id: redelivery.delivery_id,
+                succeeded: false,
+                status_code,
+                error: Some(error),
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn reseal_webhook_secrets(
+        &self,
+        request: Request<ResealWebhookSecretsRequest>,
+    ) -> Result<Response<ResealWebhookSecretsResponse>, Status> {
+        let (admin_id, _) = AdminId::from_request(request)?;
+        let resealed_count = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: ResealWebhookSecrets,
+                },
+            )
+            .await?;
+        Ok(Response::new(ResealWebhookSecretsResponse { resealed_count }))
+    }
+}
@@ -0,0 +1,2 @@
+pub mod admin_merchant_webhook;
+pub mod user_preference;
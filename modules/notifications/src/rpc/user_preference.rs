@@ -0,0 +1,106 @@
+use crate::entities::notification_preference::NotificationPreference;
+use crate::services::preference::{
+    GetNotificationPreference, NotificationPreferenceService, UpdateNotificationPreference,
+};
+use crate::services::telegram_link::{StartTelegramLink, TelegramLinkService, UnlinkTelegram};
+use auth::rpc::middleware::UserId;
+use kanau::processor::Processor;
+use phantom_shop_proto::v1::notifications::user::{
+    GetNotificationPreferenceRequest, NotificationPreference as ProtoNotificationPreference,
+    StartTelegramLinkRequest, StartTelegramLinkResponse, UnlinkTelegramRequest,
+};
+use tonic::{Request, Response, Status};
+
+pub struct UserNotificationPreferenceServiceImpl {
+    pub inner: NotificationPreferenceService,
+    pub telegram_link: TelegramLinkService,
+}
+
+impl UserNotificationPreferenceServiceImpl {
+    pub fn new(inner: NotificationPreferenceService, telegram_link: TelegramLinkService) -> Self {
+        Self {
+            inner,
+            telegram_link,
+        }
+    }
+}
+
+impl From<NotificationPreference> for ProtoNotificationPreference {
+    fn from(value: NotificationPreference) -> Self {
+        Self {
+            email_enabled: value.email_enabled,
+            webhook_url: value.webhook_url,
+            telegram_chat_id: value.telegram_chat_id,
+            order_updates: value.order_updates,
+            account_updates: value.account_updates,
+            locale: value.locale,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl phantom_shop_proto::v1::notifications::user::user_notification_preference_service_server::UserNotificationPreferenceService
+    for UserNotificationPreferenceServiceImpl
+{
+    async fn get_notification_preference(
+        &self,
+        request: Request<GetNotificationPreferenceRequest>,
+    ) -> Result<Response<ProtoNotificationPreference>, Status> {
+        let (user_id, _) = UserId::from_request(request)?;
+        let preference = self
+            .inner
+            .process(GetNotificationPreference {
+                user_id: user_id.into_inner(),
+            })
+            .await?;
+        Ok(Response::new(preference.into()))
+    }
+
+    async fn update_notification_preference(
+        &self,
+        request: Request<ProtoNotificationPreference>,
+    ) -> Result<Response<ProtoNotificationPreference>, Status> {
+        let (user_id, req) = UserId::from_request(request)?;
+        let preference = self
+            .inner
+            .process(UpdateNotificationPreference {
+                user_id: user_id.into_inner(),
+                email_enabled: req.email_enabled,
+                webhook_url: req.webhook_url,
+                order_updates: req.order_updates,
+                account_updates: req.account_updates,
+                locale: req.locale,
+            })
+            .await?;
+        Ok(Response::new(preference.into()))
+    }
+
+    async fn start_telegram_link(
+        &self,
+        request: Request<StartTelegramLinkRequest>,
+    ) -> Result<Response<StartTelegramLinkResponse>, Status> {
+        let (user_id, _) = UserId::from_request(request)?;
+        let link = self
+            .telegram_link
+            .process(StartTelegramLink {
+                user_id: user_id.into_inner(),
+            })
+            .await?
+            .ok_or_else(|| Status::failed_precondition("Telegram notifications are unavailable"))?;
+        Ok(Response::new(StartTelegramLinkResponse { link }))
+    }
+
+    async fn unlink_telegram(
+        &self,
+        request: Request<UnlinkTelegramRequest>,
+    ) -> Result<Response<ProtoNotificationPreference>, Status> {
+        let (user_id, _) = UserId::from_request(request)?;
+        let preference = self
+            .telegram_link
+            .process(UnlinkTelegram {
+                user_id: user_id.into_inner(),
+            })
+            .await?;
+        Ok(Response::new(preference.into()))
+    }
+}
@@ -0,0 +1,51 @@
+use super::{NotificationChannel, OutgoingNotification, Recipient};
+use admin::utils::config_provider::LiveConfig;
+use auth::config::AuthConfig;
+use auth::utils::mailer::Mailer;
+use lettre::Message;
+use lettre::message::{Mailbox, MultiPart};
+
+/// Sends emails with the sender and transport shared with the OTP emails.
+#[derive(Clone)]
+pub struct EmailChannel {
+    pub auth_config: LiveConfig<AuthConfig>,
+    pub mailer: Mailer,
+}
+
+impl NotificationChannel for EmailChannel {
+    const NAME: &'static str = "email";
+
+    fn reaches(&self, recipient: &Recipient) -> bool {
+        recipient.email.is_some()
+    }
+
+    async fn deliver(
+        &self,
+        recipient: &Recipient,
+        notification: &OutgoingNotification<'_>,
+    ) -> Result<(), framework::Error> {
+        let Some(email_address) = &recipient.email else {
+            return Ok(());
+        };
+        let config = self.auth_config.load();
+        let delivery = &config.email_delivery;
+        let from: Mailbox = delivery
+            .from
+            .parse()
+            .map_err(|e| framework::Error::BusinessPanic(anyhow::anyhow!("Invalid sender: {e}")))?;
+        let to: Mailbox = email_address
+            .parse()
+            .map_err(|_| framework::Error::InvalidInput)?;
+        let rendered = &notification.rendered;
+        let message = Message::builder()
+            .from(from)
+            .to(to)
+            .subject(rendered.subject.as_str())
+            .multipart(MultiPart::alternative_plain_html(
+                rendered.text.clone(),
+                rendered.html.clone(),
+            ))
+            .map_err(|e| framework::Error::BusinessPanic(e.into()))?;
+        self.mailer.send(&delivery.transport, message).await
+    }
+}
@@ -0,0 +1,41 @@
+use crate::utils::template::Notification;
+use auth::utils::email_template::RenderedEmail;
+use uuid::Uuid;
+
+pub mod email;
+pub mod telegram;
+pub mod webhook;
+
+/// Where a user can be reached, as allowed by their preference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recipient {
+    pub user_id: Uuid,
+    pub email: Option<String>,
+    pub webhook_url: Option<String>,
+    pub telegram_chat_id: Option<i64>,
+}
+
+/// A notification rendered in the language of its recipient.
+#[derive(Debug, Clone)]
+pub struct OutgoingNotification<'a> {
+    pub notification: &'a Notification,
+    /// Unix timestamp in seconds
+    pub occurred_at: i64,
+    pub rendered: RenderedEmail,
+}
+
+/// A way to deliver notifications.
+pub trait NotificationChannel: Send + Sync {
+    const NAME: &'static str;
+
+    /// Whether the channel is enabled and the recipient has an address on it.
+    fn reaches(&self, recipient: &Recipient) -> bool;
+
+    /// Deliver the notification, with errors classified like those of
+    /// [Mailer::send](auth::utils::mailer::Mailer::send).
+    fn deliver(
+        &self,
+        recipient: &Recipient,
+        notification: &OutgoingNotification<'_>,
+    ) -> impl Future<Output = Result<(), framework::Error>> + Send;
+}
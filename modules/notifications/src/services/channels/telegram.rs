@@ -0,0 +1,53 @@
+use super::{NotificationChannel, OutgoingNotification, Recipient};
+use crate::config::NotificationConfig;
+use crate::utils::http_client::HttpClient;
+use admin::utils::config_provider::LiveConfig;
+
+/// Sends notifications as messages of the shop's Telegram bot.
+#[derive(Clone)]
+pub struct TelegramChannel<H> {
+    pub config: LiveConfig<NotificationConfig>,
+    pub http: H,
+}
+
+impl<H: HttpClient> NotificationChannel for TelegramChannel<H> {
+    const NAME: &'static str = "telegram";
+
+    fn reaches(&self, recipient: &Recipient) -> bool {
+        recipient.telegram_chat_id.is_some() && self.config.load().telegram.is_enabled()
+    }
+
+    async fn deliver(
+        &self,
+        recipient: &Recipient,
+        notification: &OutgoingNotification<'_>,
+    ) -> Result<(), framework::Error> {
+        let Some(chat_id) = recipient.telegram_chat_id else {
+            return Ok(());
+        };
+        let config = self.config.load();
+        let telegram = &config.telegram;
+        // https://core.telegram.org/bots/api#sendmessage
+        let url = format!(
+            "{}/bot{}/sendMessage",
+            telegram.api_base_url.trim_end_matches('/'),
+            telegram.bot_token.expose()
+        );
+        let rendered = &notification.rendered;
+        let body = serde_json::to_vec(&serde_json::json!({
+            "chat_id": chat_id,
+            "text": format!("{}\n\n{}", rendered.subject, rendered.text),
+            "link_preview_options": { "is_disabled": true },
+        }))
+        .map_err(|e| framework::Error::BusinessPanic(e.into()))?;
+        let timeout = telegram.timeout.try_into().map_err(|e| {
+            framework::Error::BusinessPanic(anyhow::anyhow!("Invalid timeout: {e}"))
+        })?;
+        // A 403 means that the user blocked the bot, which is not worth retrying
+        self.http
+            .post_json(&url, &[], body, timeout)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use super::{NotificationChannel, OutgoingNotification, Recipient};
+use crate::config::NotificationConfig;
+use crate::utils::http_client::HttpClient;
+use crate::utils::template::Notification;
+use admin::utils::config_provider::LiveConfig;
+use uuid::Uuid;
+
+/// Body POSTed to the webhook of a user.
+#[derive(Debug, serde::Serialize)]
+struct WebhookPayload<'a> {
+    user_id: Uuid,
+    occurred_at: i64,
+    subject: &'a str,
+    text: &'a str,
+    notification: &'a Notification,
+}
+
+/// POSTs notifications as JSON to the webhook URL a user registered.
+#[derive(Clone)]
+pub struct WebhookChannel<H> {
+    pub config: LiveConfig<NotificationConfig>,
+    pub http: H,
+}
+
+impl<H: HttpClient> NotificationChannel for WebhookChannel<H> {
+    const NAME: &'static str = "webhook";
+
+    fn reaches(&self, recipient: &Recipient) -> bool {
+        recipient.webhook_url.is_some() && self.config.load().webhook.enabled
+    }
+
+    async fn deliver(
+        &self,
+        recipient: &Recipient,
+        notification: &OutgoingNotification<'_>,
+    ) -> Result<(), framework::Error> {
+        let Some(url) = &recipient.webhook_url else {
+            return Ok(());
+        };
+        let body = serde_json::to_vec(&WebhookPayload {
+            user_id: recipient.user_id,
+            occurred_at: notification.occurred_at,
+            subject: &notification.rendered.subject,
+            text: &notification.rendered.text,
+            notification: notification.notification,
+        })
+        .map_err(|e| framework::Error::BusinessPanic(e.into()))?;
+        let timeout = self.config.load().webhook.timeout.try_into().map_err(|e| {
+            framework::Error::BusinessPanic(anyhow::anyhow!("Invalid timeout: {e}"))
+        })?;
+        self.http
+            .post_json_to_public_host(url, &[], body, timeout)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,436 @@
+use crate::config::NotificationConfig;
+use crate::entities::merchant_webhook::{
+    ClaimDueMerchantWebhookDeliveries, CreateMerchantWebhookDeliveries,
+    CreateMerchantWebhookEndpoint, DeleteMerchantWebhookEndpoint, DueMerchantWebhookDelivery,
+    ListMerchantWebhookDeliveries, ListMerchantWebhookEndpoints, ListMerchantWebhookSecrets,
+    MerchantWebhookAttemptOutcome, MerchantWebhookDelivery, MerchantWebhookEndpoint,
+    MerchantWebhookEvent, RecordMerchantWebhookAttempt, RedeliverMerchantWebhook,
+    ResealMerchantWebhookSecret, SetMerchantWebhookEndpointSecret, UpdateMerchantWebhookEndpoint,
+};
+use crate::utils::http_client::HttpClient;
+use crate::utils::webhook_signature::{
+    WEBHOOK_EVENT_HEADER, WEBHOOK_ID_HEADER, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER,
+    generate_secret, is_plaintext_secret, open_secret, seal_secret, sign,
+};
+use admin::entities::admin_account::AdminRole;
+use admin::rbac;
+use admin::utils::config_provider::LiveConfig;
+use admin::utils::config_secret::{ConfigKeyring, rewrap_stored_secret};
+use admin::utils::rbac::Permission;
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Deliveries retried per tick at most.
+const RETRY_BATCH_SIZE: i64 = 100;
+/// Time left to record an attempt before its delivery may be claimed again.
+const LEASE_MARGIN: time::Duration = time::Duration::seconds(30);
+/// Longer response bodies are cut off in the delivery log.
+const MAX_LOGGED_BODY_CHARS: usize = 512;
+const MAX_DESCRIPTION_LENGTH: usize = 256;
+const MAX_DELIVERY_PAGE_SIZE: i64 = 100;
+
+/// Signs events and sends them to the webhook endpoints of the merchant.
+#[derive(Clone)]
+pub struct MerchantWebhookDispatcher<H = reqwest::Client> {
+    pub db: DatabaseProcessor,
+    pub config: LiveConfig<NotificationConfig>,
+    pub http: H,
+}
+
+impl<H: HttpClient> MerchantWebhookDispatcher<H> {
+    fn lease_until(&self) -> time::PrimitiveDateTime {
+        framework::now_time() + self.config.load().merchant_webhook.timeout + LEASE_MARGIN
+    }
+
+    /// Queue the event for the subscribed endpoints and attempt the deliveries right away.
+    ///
+    /// Failed attempts are left to [retry_due](Self::retry_due), so that the event is never
+    /// queued twice.
+    #[instrument(skip(self, data), err)]
+    pub async fn publish(
+        &self,
+        event: MerchantWebhookEvent,
+        data: serde_json::Value,
+    ) -> Result<(), framework::Error> {
+        let event_id = Uuid::new_v4();
+        let payload = serde_json::json!({
+            "id": event_id,
+            "type": event,
+            "created_at": framework::now_time().assume_utc().unix_timestamp(),
+            "data": data,
+        });
+        let deliveries = self
+            .db
+            .process(CreateMerchantWebhookDeliveries {
+                event,
+                event_id,
+                payload,
+                lease_until: self.lease_until(),
+            })
+            .await?;
+        for delivery in deliveries {
+            if let Err(e) = self.attempt(delivery).await {
+                tracing::warn!(error = %e, "Failed to record a merchant webhook attempt");
+            }
+        }
+        Ok(())
+    }
+
+    /// Attempt the leased deliveries whose backoff elapsed.
+    ///
+    /// Returns the number of deliveries attempted.
+    #[instrument(skip_all, err)]
+    pub async fn retry_due(&self) -> Result<usize, framework::Error> {
+        let deliveries = self
+            .db
+            .process(ClaimDueMerchantWebhookDeliveries {
+                limit: RETRY_BATCH_SIZE,
+                lease_until: self.lease_until(),
+            })
+            .await?;
+        let count = deliveries.len();
+        for delivery in deliveries {
+            self.attempt(delivery).await?;
+        }
+        Ok(count)
+    }
+
+    /// Sign and send a delivery, then record the outcome.
+    #[instrument(skip_all, fields(delivery_id = delivery.id, endpoint_id = delivery.endpoint_id), err)]
+    pub async fn attempt(
+        &self,
+        delivery: DueMerchantWebhookDelivery,
+    ) -> Result<MerchantWebhookAttemptOutcome, framework::Error> {
+        let config = self.config.load();
+        let config = &config.merchant_webhook;
+        let body = serde_json::to_vec(&delivery.payload)
+            .map_err(|e| framework::Error::BusinessPanic(e.into()))?;
+        let now = framework::now_time();
+        let timestamp = now.assume_utc().unix_timestamp();
+        let signature = sign(&open_secret(&delivery.secret)?, timestamp, &body);
+        let event_id = delivery.event_id.to_string();
+        let timestamp = timestamp.to_string();
+        let headers = [
+            (WEBHOOK_ID_HEADER, event_id.as_str()),
+            (WEBHOOK_EVENT_HEADER, delivery.event.as_str()),
+            (WEBHOOK_TIMESTAMP_HEADER, timestamp.as_str()),
+            (WEBHOOK_SIGNATURE_HEADER, signature.as_str()),
+        ];
+        let timeout = config.timeout.try_into().map_err(|e| {
+            framework::Error::BusinessPanic(anyhow::anyhow!("Invalid timeout: {e}"))
+        })?;
+        let result = self
+            .http
+            .post_json(&delivery.url, &headers, body, timeout)
+            .await;
+
+        let attempts = u32::try_from(delivery.attempts).unwrap_or_default() + 1;
+        let retry_at = (attempts < config.max_attempts).then(|| now + config.backoff(attempts));
+        let outcome = match result {
+            Ok(response) if (200..300).contains(&response.status) => {
+                MerchantWebhookAttemptOutcome::Succeeded {
+                    status_code: response.status.into(),
+                }
+            }
+            Ok(response) => MerchantWebhookAttemptOutcome::Failed {
+                status_code: Some(response.status.into()),
+                error: format!(
+                    "HTTP status {}: {}",
+                    response.status,
+                    response
+                        .body
+                        .chars()
+                        .take(MAX_LOGGED_BODY_CHARS)
+                        .collect::<String>()
+                ),
+                retry_at,
+            },
+            Err(e) => MerchantWebhookAttemptOutcome::Failed {
+                status_code: None,
+                error: e.to_string(),
+                retry_at,
+            },
+        };
+        let disabled = self
+            .db
+            .process(RecordMerchantWebhookAttempt {
+                delivery_id: delivery.id,
+                endpoint_id: delivery.endpoint_id,
+                outcome: outcome.clone(),
+                disable_after_failures: i32::try_from(config.disable_after_failures)
+                    .unwrap_or(i32::MAX),
+            })
+            .await?;
+        if disabled {
+            tracing::warn!(
+                endpoint_id = delivery.endpoint_id,
+                "Disabled a merchant webhook endpoint failing too many times"
+            );
+        }
+        Ok(outcome)
+    }
+}
+
+/// Admin management of the webhook endpoints and their deliveries.
+#[derive(Clone)]
+pub struct MerchantWebhookManageService {
+    pub dispatcher: MerchantWebhookDispatcher,
+}
+
+impl MerchantWebhookManageService {
+    fn db(&self) -> &DatabaseProcessor {
+        &self.dispatcher.db
+    }
+}
+
+/// Endpoints may be plain http, such as fulfilment systems on the internal network.
+fn check_endpoint(
+    url: &str,
+    description: &str,
+    events: &[MerchantWebhookEvent],
+) -> Result<(), framework::Error> {
+    let url_is_http = url::Url::parse(url)
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some());
+    if !url_is_http || description.len() > MAX_DESCRIPTION_LENGTH || events.is_empty() {
+        return Err(framework::Error::InvalidInput);
+    }
+    Ok(())
+}
+
+fn dedup_events(mut events: Vec<MerchantWebhookEvent>) -> Vec<MerchantWebhookEvent> {
+    events.sort_by_key(|event| event.as_str());
+    events.dedup();
+    events
+}
+
+#[derive(Debug, Clone)]
+pub struct RegisterWebhookEndpoint {
+    pub url: String,
+    pub description: String,
+    pub events: Vec<MerchantWebhookEvent>,
+}
+
+impl Processor<RegisterWebhookEndpoint> for MerchantWebhookManageService {
+    type Output = MerchantWebhookEndpoint;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: RegisterWebhookEndpoint,
+    ) -> Result<MerchantWebhookEndpoint, framework::Error> {
+        check_endpoint(&input.url, &input.description, &input.events)?;
+        let secret = generate_secret();
+        let endpoint = self
+            .db()
+            .process(CreateMerchantWebhookEndpoint {
+                url: input.url,
+                description: input.description,
+                secret: seal_secret(&secret)?,
+                events: dedup_events(input.events),
+            })
+            .await?;
+        Ok(MerchantWebhookEndpoint { secret, ..endpoint })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateWebhookEndpoint {
+    pub id: i32,
+    pub url: String,
+    pub description: String,
+    pub events: Vec<MerchantWebhookEvent>,
+    pub is_enabled: bool,
+}
+
+impl Processor<UpdateWebhookEndpoint> for MerchantWebhookManageService {
+    type Output = MerchantWebhookEndpoint;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: UpdateWebhookEndpoint,
+    ) -> Result<MerchantWebhookEndpoint, framework::Error> {
+        check_endpoint(&input.url, &input.description, &input.events)?;
+        self.db()
+            .process(UpdateMerchantWebhookEndpoint {
+                id: input.id,
+                url: input.url,
+                description: input.description,
+                events: dedup_events(input.events),
+                is_enabled: input.is_enabled,
+            })
+            .await?
+            .ok_or(framework::Error::NotFound)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Replace the signing secret. Receivers must switch to the new one at once.
+pub struct RotateWebhookSecret {
+    pub id: i32,
+}
+
+impl Processor<RotateWebhookSecret> for MerchantWebhookManageService {
+    type Output = MerchantWebhookEndpoint;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: RotateWebhookSecret,
+    ) -> Result<MerchantWebhookEndpoint, framework::Error> {
+        let secret = generate_secret();
+        let endpoint = self
+            .db()
+            .process(SetMerchantWebhookEndpointSecret {
+                id: input.id,
+                secret: seal_secret(&secret)?,
+            })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        Ok(MerchantWebhookEndpoint { secret, ..endpoint })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DeleteWebhookEndpoint {
+    pub id: i32,
+}
+
+impl Processor<DeleteWebhookEndpoint> for MerchantWebhookManageService {
+    type Output = ();
+    type Error = framework::Error;
+    async fn process(&self, input: DeleteWebhookEndpoint) -> Result<(), framework::Error> {
+        self.db()
+            .process(DeleteMerchantWebhookEndpoint { id: input.id })
+            .await?
+            .then_some(())
+            .ok_or(framework::Error::NotFound)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListWebhookEndpoints;
+
+impl Processor<ListWebhookEndpoints> for MerchantWebhookManageService {
+    type Output = Vec<MerchantWebhookEndpoint>;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        _input: ListWebhookEndpoints,
+    ) -> Result<Vec<MerchantWebhookEndpoint>, framework::Error> {
+        Ok(self.db().process(ListMerchantWebhookEndpoints).await?)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListWebhookDeliveries {
+    pub endpoint_id: i32,
+    pub before_id: Option<i64>,
+    pub limit: i64,
+}
+
+impl Processor<ListWebhookDeliveries> for MerchantWebhookManageService {
+    type Output = Vec<MerchantWebhookDelivery>;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: ListWebhookDeliveries,
+    ) -> Result<Vec<MerchantWebhookDelivery>, framework::Error> {
+        Ok(self
+            .db()
+            .process(ListMerchantWebhookDeliveries {
+                endpoint_id: input.endpoint_id,
+                before_id: input.before_id,
+                limit: input.limit.clamp(1, MAX_DELIVERY_PAGE_SIZE),
+            })
+            .await?)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Send a past delivery again, as a new delivery with the same event ID.
+///
+/// Also allowed for disabled endpoints, to check them before enabling them again. Their failed
+/// redeliveries are not retried.
+pub struct RedeliverWebhook {
+    pub delivery_id: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Redelivery {
+    pub delivery_id: i64,
+    pub outcome: MerchantWebhookAttemptOutcome,
+}
+
+impl Processor<RedeliverWebhook> for MerchantWebhookManageService {
+    type Output = Redelivery;
+    type Error = framework::Error;
+    async fn process(&self, input: RedeliverWebhook) -> Result<Redelivery, framework::Error> {
+        let delivery = self
+            .db()
+            .process(RedeliverMerchantWebhook {
+                delivery_id: input.delivery_id,
+                lease_until: self.dispatcher.lease_until(),
+            })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        let delivery_id = delivery.id;
+        let outcome = self.dispatcher.attempt(delivery).await?;
+        Ok(Redelivery {
+            delivery_id,
+            outcome,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Seal the signing secrets stored in plaintext, and rewrap those sealed with a key other than
+/// the current one. Run along with [`RotateConfigSecrets`](admin::services::config_history::RotateConfigSecrets)
+/// after a key rotation, before removing the old key.
+pub struct ResealWebhookSecrets;
+
+impl Processor<ResealWebhookSecrets> for MerchantWebhookManageService {
+    /// Number of resealed secrets
+    type Output = u32;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, _input: ResealWebhookSecrets) -> Result<u32, framework::Error> {
+        let keyring = ConfigKeyring::global()?;
+        let mut resealed = 0;
+        for stored in self.db().process(ListMerchantWebhookSecrets).await? {
+            let secret = if is_plaintext_secret(&stored.secret) {
+                seal_secret(&stored.secret)?
+            } else if let Some(rewrapped) = rewrap_stored_secret(&stored.secret, keyring)? {
+                rewrapped
+            } else {
+                continue;
+            };
+            // Not replaced if rotated meanwhile, the new secret being sealed with the current key
+            let replaced = self
+                .db()
+                .process(ResealMerchantWebhookSecret {
+                    id: stored.id,
+                    previous: stored.secret,
+                    secret,
+                })
+                .await?;
+            if replaced {
+                resealed += 1;
+            }
+        }
+        tracing::info!(
+            resealed,
+            key_id = keyring.current_key_id(),
+            "Resealed merchant webhook secrets"
+        );
+        Ok(resealed)
+    }
+}
+
+rbac! { MerchantWebhookManageService : RegisterWebhookEndpoint => MerchantWebhookEndpoint | [AdminRole::Owner], Permission::ConfigWrite }
+rbac! { MerchantWebhookManageService : UpdateWebhookEndpoint => MerchantWebhookEndpoint | [AdminRole::Owner], Permission::ConfigWrite }
+rbac! { MerchantWebhookManageService : RotateWebhookSecret => MerchantWebhookEndpoint | [AdminRole::Owner], Permission::ConfigWrite }
+rbac! { MerchantWebhookManageService : DeleteWebhookEndpoint => () | [AdminRole::Owner], Permission::ConfigWrite }
+rbac! { MerchantWebhookManageService : ListWebhookEndpoints => Vec<MerchantWebhookEndpoint> | [AdminRole::Owner], Permission::ConfigRead }
+rbac! { MerchantWebhookManageService : ListWebhookDeliveries => Vec<MerchantWebhookDelivery> | [AdminRole::Owner], Permission::ConfigRead }
+rbac! { MerchantWebhookManageService : RedeliverWebhook => Redelivery | [AdminRole::Owner], Permission::ConfigWrite }
+rbac! { MerchantWebhookManageService : ResealWebhookSecrets => u32 | [AdminRole::Owner] }
@@ -0,0 +1,5 @@
+pub mod channels;
+pub mod merchant_webhook;
+pub mod notification;
+pub mod preference;
+pub mod telegram_link;
@@ -0,0 +1,304 @@
+use crate::config::NotificationConfig;
+use crate::entities::notification_preference::{
+    FindNotificationPreference, NotificationPreference,
+};
+use crate::services::channels::email::EmailChannel;
+use crate::services::channels::telegram::TelegramChannel;
+use crate::services::channels::webhook::WebhookChannel;
+use crate::services::channels::{NotificationChannel, OutgoingNotification, Recipient};
+use crate::utils::http_client::HttpClient;
+use crate::utils::template::{Notification, NotificationCategory};
+use admin::utils::config_provider::LiveConfig;
+use auth::config::AuthConfig;
+use auth::entities::db::user_account::FindUserAccountById;
+use auth::utils::email_template::EmailLocale;
+use auth::utils::mailer::Mailer;
+use auth::utils::wallet::is_wallet_email;
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Render notifications for users and deliver them through the channels they chose.
+#[derive(Clone)]
+pub struct NotificationService<H = reqwest::Client> {
+    pub db: DatabaseProcessor,
+    pub auth_config: LiveConfig<AuthConfig>,
+    pub email: EmailChannel,
+    pub webhook: WebhookChannel<H>,
+    pub telegram: TelegramChannel<H>,
+}
+
+impl<H: HttpClient> NotificationService<H> {
+    pub fn new(
+        db: DatabaseProcessor,
+        auth_config: LiveConfig<AuthConfig>,
+        notification_config: LiveConfig<NotificationConfig>,
+        mailer: Mailer,
+        http: H,
+    ) -> Self {
+        Self {
+            db,
+            email: EmailChannel {
+                auth_config: auth_config.clone(),
+                mailer,
+            },
+            webhook: WebhookChannel {
+                config: notification_config.clone(),
+                http: http.clone(),
+            },
+            telegram: TelegramChannel {
+                config: notification_config,
+                http,
+            },
+            auth_config,
+        }
+    }
+
+    /// Deliver through every channel reaching the recipient.
+    ///
+    /// A retryable error is returned only when nothing was delivered, so that redelivering the
+    /// event never duplicates a notification.
+    pub async fn deliver(
+        &self,
+        recipient: &Recipient,
+        notification: &OutgoingNotification<'_>,
+    ) -> Result<(), framework::Error> {
+        let results = tokio::join!(
+            deliver_through(&self.email, recipient, notification),
+            deliver_through(&self.webhook, recipient, notification),
+            deliver_through(&self.telegram, recipient, notification),
+        );
+        let mut retry = None;
+        for result in [results.0, results.1, results.2].into_iter().flatten() {
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e @ framework::Error::Io(_)) => retry = Some(e),
+                Err(_) => {}
+            }
+        }
+        retry.map_or(Ok(()), Err)
+    }
+}
+
+/// `None` when the channel does not reach the recipient.
+async fn deliver_through<C: NotificationChannel>(
+    channel: &C,
+    recipient: &Recipient,
+    notification: &OutgoingNotification<'_>,
+) -> Option<Result<(), framework::Error>> {
+    if !channel.reaches(recipient) {
+        return None;
+    }
+    let result = channel.deliver(recipient, notification).await;
+    if let Err(e) = &result {
+        tracing::warn!(channel = C::NAME, error = %e, "Failed to deliver notification");
+    }
+    Some(result)
+}
+
+#[derive(Debug, Clone)]
+pub struct Notify {
+    pub user_id: Uuid,
+    /// Unix timestamp in seconds
+    pub occurred_at: i64,
+    pub notification: Notification,
+}
+
+impl<H: HttpClient> Processor<Notify> for NotificationService<H> {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, fields(user_id = %input.user_id), err)]
+    async fn process(&self, input: Notify) -> Result<(), framework::Error> {
+        let preference = self
+            .db
+            .process(FindNotificationPreference {
+                user_id: input.user_id,
+            })
+            .await?
+            .unwrap_or_else(|| NotificationPreference::default_for(input.user_id));
+        let wanted = match input.notification.category() {
+            NotificationCategory::Account => preference.account_updates,
+            NotificationCategory::Order => preference.order_updates,
+            NotificationCategory::Security => true,
+        };
+        if !wanted {
+            return Ok(());
+        }
+        let Some(account) = self
+            .db
+            .process(FindUserAccountById { id: input.user_id })
+            .await?
+        else {
+            // deleted since the event was sent
+            return Ok(());
+        };
+        let recipient = Recipient {
+            user_id: input.user_id,
+            email: (preference.email_enabled && !is_wallet_email(&account.email))
+                .then_some(account.email),
+            webhook_url: preference.webhook_url,
+            telegram_chat_id: preference.telegram_chat_id,
+        };
+
+        let config = self.auth_config.load();
+        let delivery = &config.email_delivery;
+        let locale = preference
+            .locale
+            .as_deref()
+            .and_then(EmailLocale::from_language_tag)
+            .unwrap_or(delivery.default_locale);
+        let notification = OutgoingNotification {
+            notification: &input.notification,
+            occurred_at: input.occurred_at,
+            rendered: input.notification.render(locale, &delivery.site_name),
+        };
+        self.deliver(&recipient, &notification).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{TelegramChannelConfig, WebhookChannelConfig};
+    use crate::utils::http_client::HttpResponse;
+    use admin::utils::config_secret::SecretString;
+    use std::sync::{Arc, Mutex, PoisonError};
+
+    #[derive(Debug, Clone)]
+    struct SentRequest {
+        url: String,
+        body: serde_json::Value,
+    }
+
+    /// Records requests and answers them all with the same status.
+    #[derive(Clone)]
+    struct MockHttpClient {
+        status: u16,
+        sent: Arc<Mutex<Vec<SentRequest>>>,
+    }
+
+    impl MockHttpClient {
+        fn new(status: u16) -> Self {
+            Self {
+                status,
+                sent: Arc::default(),
+            }
+        }
+
+        fn sent(&self) -> Vec<SentRequest> {
+            self.sent
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        }
+    }
+
+    impl HttpClient for MockHttpClient {
+        async fn post_json(
+            &self,
+            url: &str,
+            _headers: &[(&str, &str)],
+            body: Vec<u8>,
+            _timeout: std::time::Duration,
+        ) -> Result<HttpResponse, framework::Error> {
+            let body = serde_json::from_slice(&body)
+                .map_err(|e| framework::Error::BusinessPanic(e.into()))?;
+            self.sent
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(SentRequest {
+                    url: url.to_owned(),
+                    body,
+                });
+            Ok(HttpResponse {
+                status: self.status,
+                body: String::new(),
+            })
+        }
+
+        async fn post_json_to_public_host(
+            &self,
+            url: &str,
+            headers: &[(&str, &str)],
+            body: Vec<u8>,
+            timeout: std::time::Duration,
+        ) -> Result<HttpResponse, framework::Error> {
+            self.post_json(url, headers, body, timeout).await
+        }
+    }
+
+    fn service(http: MockHttpClient) -> anyhow::Result<NotificationService<MockHttpClient>> {
+        let db =
+            sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused")?;
+        Ok(NotificationService::new(
+            DatabaseProcessor::new(db),
+            LiveConfig::fixed(AuthConfig::default()),
+            LiveConfig::fixed(NotificationConfig {
+                telegram: TelegramChannelConfig {
+                    bot_token: SecretString::new("123:token"),
+                    ..Default::default()
+                },
+                webhook: WebhookChannelConfig {
+                    enabled: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            Mailer::default(),
+            http,
+        ))
+    }
+
+    #[test]
+    fn notifications_reach_chat_and_webhook() -> anyhow::Result<()> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(deliver_through_chat_and_webhook())
+    }
+
+    async fn deliver_through_chat_and_webhook() -> anyhow::Result<()> {
+        let notification = Notification::Welcome;
+        let outgoing = OutgoingNotification {
+            notification: &notification,
+            occurred_at: 1_700_000_000,
+            rendered: notification.render(EmailLocale::En, "Phantom"),
+        };
+        let recipient = Recipient {
+            user_id: Uuid::nil(),
+            email: None,
+            webhook_url: Some("https://hooks.example.com/phantom".to_owned()),
+            telegram_chat_id: Some(42),
+        };
+
+        let http = MockHttpClient::new(200);
+        service(http.clone())?
+            .deliver(&recipient, &outgoing)
+            .await?;
+        let sent = http.sent();
+        assert_eq!(sent.len(), 2);
+        let webhook = sent
+            .iter()
+            .find(|request| request.url == "https://hooks.example.com/phantom")
+            .ok_or_else(|| anyhow::anyhow!("webhook not called"))?;
+        assert_eq!(webhook.body["notification"]["event"], "welcome");
+        assert_eq!(webhook.body["subject"], "Welcome to Phantom");
+        let telegram = sent
+            .iter()
+            .find(|request| request.url == "https://api.telegram.org/bot123:token/sendMessage")
+            .ok_or_else(|| anyhow::anyhow!("bot API not called"))?;
+        assert_eq!(telegram.body["chat_id"], 42);
+
+        // Retried only while nothing got through
+        let unavailable = service(MockHttpClient::new(503))?
+            .deliver(&recipient, &outgoing)
+            .await;
+        assert!(matches!(unavailable, Err(framework::Error::Io(_))));
+        let blocked = service(MockHttpClient::new(403))?
+            .deliver(&recipient, &outgoing)
+            .await;
+        assert!(blocked.is_ok());
+        Ok(())
+    }
+}
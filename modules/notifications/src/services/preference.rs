@@ -0,0 +1,84 @@
+use crate::entities::notification_preference::{
+    FindNotificationPreference, NotificationPreference, SaveNotificationPreference,
+};
+use auth::utils::email_template::EmailLocale;
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use uuid::Uuid;
+
+const MAX_WEBHOOK_URL_LENGTH: usize = 2048;
+
+#[derive(Clone)]
+pub struct NotificationPreferenceService {
+    pub db: DatabaseProcessor,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GetNotificationPreference {
+    pub user_id: Uuid,
+}
+
+impl Processor<GetNotificationPreference> for NotificationPreferenceService {
+    type Output = NotificationPreference;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: GetNotificationPreference,
+    ) -> Result<NotificationPreference, framework::Error> {
+        Ok(self
+            .db
+            .process(FindNotificationPreference {
+                user_id: input.user_id,
+            })
+            .await?
+            .unwrap_or_else(|| NotificationPreference::default_for(input.user_id)))
+    }
+}
+
+/// The Telegram chat is left as is, see [`TelegramLinkService`](super::telegram_link::TelegramLinkService).
+#[derive(Debug, Clone)]
+pub struct UpdateNotificationPreference {
+    pub user_id: Uuid,
+    pub email_enabled: bool,
+    pub webhook_url: Option<String>,
+    pub order_updates: bool,
+    pub account_updates: bool,
+    pub locale: Option<String>,
+}
+
+impl Processor<UpdateNotificationPreference> for NotificationPreferenceService {
+    type Output = NotificationPreference;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: UpdateNotificationPreference,
+    ) -> Result<NotificationPreference, framework::Error> {
+        if let Some(webhook_url) = &input.webhook_url {
+            let is_https = url::Url::parse(webhook_url)
+                .is_ok_and(|url| url.scheme() == "https" && url.host().is_some());
+            if !is_https || webhook_url.len() > MAX_WEBHOOK_URL_LENGTH {
+                return Err(framework::Error::InvalidInput);
+            }
+        }
+        // stored as the primary subtag, such as `ja` for `ja-JP`
+        let locale = input
+            .locale
+            .map(|tag| {
+                EmailLocale::from_language_tag(&tag)
+                    .map(|locale| locale.html_lang().to_owned())
+                    .ok_or(framework::Error::InvalidInput)
+            })
+            .transpose()?;
+        Ok(self
+            .db
+            .process(SaveNotificationPreference {
+                user_id: input.user_id,
+                email_enabled: input.email_enabled,
+                webhook_url: input.webhook_url,
+                order_updates: input.order_updates,
+                account_updates: input.account_updates,
+                locale,
+            })
+            .await?)
+    }
+}
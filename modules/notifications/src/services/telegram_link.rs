@@ -0,0 +1,255 @@
+use crate::config::NotificationConfig;
+use crate::entities::notification_preference::{NotificationPreference, SetTelegramChat};
+use crate::entities::telegram_link::{
+    TelegramLinkToken, TelegramLinkTokenKey, TelegramUpdateOffsetKey,
+};
+use crate::utils::http_client::HttpClient;
+use admin::utils::config_provider::LiveConfig;
+use framework::redis::{KeyValueWrite, RedisConnection};
+use framework::sqlx::DatabaseProcessor;
+use kanau::message::MessageDe;
+use kanau::processor::Processor;
+use redis::AsyncCommands;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Time a user has to open the bot through their link.
+const LINK_TOKEN_TTL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+/// Updates fetched per poll at most.
+const UPDATE_BATCH_SIZE: u32 = 100;
+const LINKED_MESSAGE: &str = "This chat will now receive your notifications.";
+
+/// Links users to their private chat with the shop's Telegram bot.
+///
+/// A chat id typed in by the user could be the one of someone else, so the user gets a
+/// `/start` link of the bot instead, and the chat it is opened from is linked.
+#[derive(Clone)]
+pub struct TelegramLinkService<H = reqwest::Client> {
+    pub db: DatabaseProcessor,
+    pub redis: RedisConnection,
+    pub config: LiveConfig<NotificationConfig>,
+    pub http: H,
+}
+
+// https://core.telegram.org/bots/api#getupdates
+#[derive(Debug, serde::Deserialize)]
+struct UpdatesResponse {
+    result: Vec<Update>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Chat {
+    id: i64,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+impl Message {
+    /// The token of the deep link the bot was opened through, from a private chat only.
+    fn link_token(&self) -> Option<[u8; 16]> {
+        if self.chat.kind != "private" {
+            return None;
+        }
+        let token = self.text.as_deref()?.strip_prefix("/start ")?.trim();
+        let mut bytes = [0; 16];
+        hex::decode_to_slice(token, &mut bytes).ok()?;
+        Some(bytes)
+    }
+}
+
+impl<H: HttpClient> TelegramLinkService<H> {
+    async fn call_bot_api(
+        &self,
+        method: &str,
+        body: serde_json::Value,
+    ) -> Result<String, framework::Error> {
+        let config = self.config.load();
+        let telegram = &config.telegram;
+        let url = format!(
+            "{}/bot{}/{method}",
+            telegram.api_base_url.trim_end_matches('/'),
+            telegram.bot_token.expose()
+        );
+        let body =
+            serde_json::to_vec(&body).map_err(|e| framework::Error::BusinessPanic(e.into()))?;
+        let timeout = telegram.timeout.try_into().map_err(|e| {
+            framework::Error::BusinessPanic(anyhow::anyhow!("Invalid timeout: {e}"))
+        })?;
+        Ok(self
+            .http
+            .post_json(&url, &[], body, timeout)
+            .await?
+            .error_for_status()?
+            .body)
+    }
+
+    /// Link the chat a user opened the bot from, returning whether the token was still valid.
+    async fn link_chat(&self, token: [u8; 16], chat_id: i64) -> Result<bool, framework::Error> {
+        let mut redis = self.redis.clone();
+        // Taken atomically, so that a link opens a single chat
+        let data: Option<Vec<u8>> = redis.get_del(TelegramLinkTokenKey(token)).await?;
+        let Some(bytes) = data else {
+            return Ok(false);
+        };
+        let link = TelegramLinkToken::from_bytes(&bytes)
+            .map_err(|e| framework::Error::DeserializeError(e.into()))?;
+        self.db
+            .process(SetTelegramChat {
+                user_id: link.user_id,
+                chat_id: Some(chat_id),
+            })
+            .await?;
+        tracing::info!(user_id = %link.user_id, "Linked Telegram chat");
+        if let Err(e) = self
+            .call_bot_api(
+                "sendMessage",
+                serde_json::json!({ "chat_id": chat_id, "text": LINKED_MESSAGE }),
+            )
+            .await
+        {
+            tracing::error!(error = %e, "Failed to confirm the Telegram link");
+        }
+        Ok(true)
+    }
+}
+
+/// Start linking the Telegram chat of a user, returning the link to open the bot with.
+#[derive(Debug, Clone, Copy)]
+pub struct StartTelegramLink {
+    pub user_id: Uuid,
+}
+
+impl<H: HttpClient> Processor<StartTelegramLink> for TelegramLinkService<H> {
+    /// `None` while the bot is not configured
+    type Output = Option<String>;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: StartTelegramLink) -> Result<Option<String>, framework::Error> {
+        let config = self.config.load();
+        if !config.telegram.can_link() {
+            return Ok(None);
+        }
+        let link = TelegramLinkToken {
+            token: rand::random(),
+            user_id: input.user_id,
+        };
+        let mut redis = self.redis.clone();
+        link.write_with_ttl(&mut redis, LINK_TOKEN_TTL).await?;
+        // https://core.telegram.org/bots/features#deep-linking
+        Ok(Some(format!(
+            "https://t.me/{}?start={}",
+            config.telegram.bot_username,
+            hex::encode(link.token)
+        )))
+    }
+}
+
+/// Stop sending notifications to the Telegram chat of a user.
+#[derive(Debug, Clone, Copy)]
+pub struct UnlinkTelegram {
+    pub user_id: Uuid,
+}
+
+impl<H: HttpClient> Processor<UnlinkTelegram> for TelegramLinkService<H> {
+    type Output = NotificationPreference;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(
+        &self,
+        input: UnlinkTelegram,
+    ) -> Result<NotificationPreference, framework::Error> {
+        Ok(self
+            .db
+            .process(SetTelegramChat {
+                user_id: input.user_id,
+                chat_id: None,
+            })
+            .await?)
+    }
+}
+
+/// Fetch the messages sent to the bot and link the chats opened through a link.
+#[derive(Debug, Clone, Copy)]
+pub struct PollTelegramUpdates;
+
+impl<H: HttpClient> Processor<PollTelegramUpdates> for TelegramLinkService<H> {
+    /// Chats linked
+    type Output = usize;
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, _input: PollTelegramUpdates) -> Result<usize, framework::Error> {
+        if !self.config.load().telegram.can_link() {
+            return Ok(0);
+        }
+        let mut redis = self.redis.clone();
+        let offset: Option<i64> = redis.get(TelegramUpdateOffsetKey).await?;
+        let body = self
+            .call_bot_api(
+                "getUpdates",
+                serde_json::json!({
+                    "offset": offset,
+                    "limit": UPDATE_BATCH_SIZE,
+                    "timeout": 0,
+                    "allowed_updates": ["message"],
+                }),
+            )
+            .await?;
+        let updates: UpdatesResponse =
+            serde_json::from_str(&body).map_err(|e| framework::Error::Io(e.into()))?;
+        let mut linked = 0;
+        for update in &updates.result {
+            let Some((token, chat_id)) = update
+                .message
+                .as_ref()
+                .and_then(|message| Some((message.link_token()?, message.chat.id)))
+            else {
+                continue;
+            };
+            if self.link_chat(token, chat_id).await? {
+                linked += 1;
+            }
+        }
+        // Telegram drops the updates before the offset once it is sent
+        if let Some(last) = updates.result.iter().map(|update| update.update_id).max() {
+            let _: () = redis.set(TelegramUpdateOffsetKey, last + 1).await?;
+        }
+        Ok(linked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(kind: &str, text: &str) -> Message {
+        Message {
+            chat: Chat {
+                id: 42,
+                kind: kind.to_owned(),
+            },
+            text: Some(text.to_owned()),
+        }
+    }
+
+    #[test]
+    fn link_token_only_from_private_start() {
+        let token = [7; 16];
+        let start = format!("/start {}", hex::encode(token));
+        assert_eq!(message("private", &start).link_token(), Some(token));
+        assert_eq!(message("group", &start).link_token(), None);
+        assert_eq!(message("private", "/start").link_token(), None);
+        assert_eq!(message("private", "/start not-a-token").link_token(), None);
+    }
+}
@@ -0,0 +1,239 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+/// A response, with the body read as text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl HttpResponse {
+    /// Map failure statuses to errors: `429` and `5xx` are retried, the other rejections are
+    /// not since sending the same request again would be rejected again.
+    pub fn error_for_status(self) -> Result<Self, framework::Error> {
+        match self.status {
+            200..=299 => Ok(self),
+            429 | 500..=599 => Err(framework::Error::Io(anyhow::anyhow!(
+                "HTTP status {}",
+                self.status
+            ))),
+            status => {
+                tracing::warn!(status, body = %self.body, "HTTP request rejected");
+                Err(framework::Error::InvalidInput)
+            }
+        }
+    }
+}
+
+/// The client webhooks and bot API calls are sent with, so that tests can replace it.
+pub trait HttpClient: Clone + Send + Sync + 'static {
+    /// POST a JSON body, which is passed already serialized so that it can be signed.
+    fn post_json(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<HttpResponse, framework::Error>> + Send;
+
+    /// Like [`post_json`](Self::post_json), for URLs given by users: the host must only resolve
+    /// to public addresses, which are then connected to, and redirects are not followed.
+    fn post_json_to_public_host(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<HttpResponse, framework::Error>> + Send;
+}
+
+/// Whether an address can be reached from the internet, so that a user given URL can not be
+/// used to reach the services of the shop or the metadata endpoint of its cloud provider.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_ipv4(mapped);
+            }
+            let segments = ip.segments();
+            // NAT64, which translates to the embedded IPv4 address
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [a, b] = segments[6].to_be_bytes();
+                let [c, d] = segments[7].to_be_bytes();
+                return is_public_ipv4(Ipv4Addr::new(a, b, c, d));
+            }
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()
+                // Documentation
+                || segments[..2] == [0x2001, 0xdb8]
+                // Deprecated IPv4-compatible addresses
+                || (segments[..6] == [0; 6] && ip != Ipv6Addr::UNSPECIFIED))
+        }
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "This network"
+        || a == 0
+        // Shared address space of carrier-grade NAT
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking
+        || (a == 198 && (b == 18 || b == 19))
+        // Reserved
+        || a >= 240)
+}
+
+/// Resolve the host of a user given URL, refusing it unless every address is public.
+async fn resolve_public_host(url: &url::Url) -> Result<Vec<SocketAddr>, framework::Error> {
+    let port = url
+        .port_or_known_default()
+        .ok_or(framework::Error::InvalidInput)?;
+    let addrs: Vec<SocketAddr> = match url.host() {
+        Some(url::Host::Ipv4(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(url::Host::Ipv6(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(url::Host::Domain(domain)) => tokio::net::lookup_host((domain, port))
+            .await
+            .map_err(|e| framework::Error::Io(e.into()))?
+            .collect(),
+        None => return Err(framework::Error::InvalidInput),
+    };
+    if addrs.is_empty() {
+        return Err(framework::Error::Io(anyhow::anyhow!(
+            "Host resolved to no address"
+        )));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        tracing::warn!(host = ?url.host_str(), address = %addr.ip(), "Refused to send to a non-public address");
+        return Err(framework::Error::InvalidInput);
+    }
+    Ok(addrs)
+}
+
+async fn send_json(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: Vec<u8>,
+    timeout: Duration,
+) -> Result<HttpResponse, framework::Error> {
+    let mut request = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .timeout(timeout)
+        .body(body);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    // The URL may carry a token, such as the one of the Telegram bot
+    let io_error = |e: reqwest::Error| framework::Error::Io(e.without_url().into());
+    let response = request.send().await.map_err(io_error)?;
+    let status = response.status().as_u16();
+    let body = response.text().await.map_err(io_error)?;
+    Ok(HttpResponse { status, body })
+}
+
+impl HttpClient for reqwest::Client {
+    async fn post_json(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<HttpResponse, framework::Error> {
+        send_json(self, url, headers, body, timeout).await
+    }
+
+    async fn post_json_to_public_host(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<HttpResponse, framework::Error> {
+        let parsed = url::Url::parse(url).map_err(|_| framework::Error::InvalidInput)?;
+        let addrs = resolve_public_host(&parsed).await?;
+        // Pinned to the checked addresses, so that the host can not resolve elsewhere when
+        // connecting
+        let mut builder = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+        if let Some(url::Host::Domain(domain)) = parsed.host() {
+            builder = builder.resolve_to_addrs(domain, &addrs);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| framework::Error::BusinessPanic(e.into()))?;
+        send_json(&client, url, headers, body, timeout).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_addresses_are_not_public() -> anyhow::Result<()> {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+            "64:ff9b::a00:1",
+        ] {
+            assert!(!is_public_ip(ip.parse()?), "{ip} should not be public");
+        }
+        for ip in ["93.184.216.34", "2606:4700:4700::1111", "::ffff:1.1.1.1"] {
+            assert!(is_public_ip(ip.parse()?), "{ip} should be public");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn urls_of_internal_hosts_are_refused() -> anyhow::Result<()> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(refuse_urls_of_internal_hosts())
+    }
+
+    async fn refuse_urls_of_internal_hosts() -> anyhow::Result<()> {
+        for url in [
+            "http://127.0.0.1:8080/hook",
+            "http://[::1]/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://localhost/hook",
+        ] {
+            let result = reqwest::Client::new()
+                .post_json_to_public_host(url, &[], Vec::new(), Duration::from_secs(1))
+                .await;
+            assert!(
+                matches!(result, Err(framework::Error::InvalidInput)),
+                "{url} should be refused"
+            );
+        }
+        Ok(())
+    }
+}
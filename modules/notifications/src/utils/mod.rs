@@ -0,0 +1,3 @@
+pub mod http_client;
+pub mod template;
+pub mod webhook_signature;
@@ -0,0 +1,362 @@
+use auth::utils::email_template::{EmailLocale, RenderedEmail, escape_html, fill_template};
+use ordering::entities::delivery_tracking::DeliveryStatus;
+use ordering::entities::order::OrderStatus;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+/// Which preference decides whether a notification is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationCategory {
+    Account,
+    Order,
+    /// Always sent, users cannot opt out
+    Security,
+}
+
+/// Something a user is told about. Serialized as the `notification` of webhook payloads.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Notification {
+    Welcome,
+    RecoveryCodeUsed {
+        remaining_codes: u32,
+        ip: Option<String>,
+        user_agent: Option<String>,
+    },
+    OrderPaid {
+        order_id: Uuid,
+        total_amount: Decimal,
+    },
+    OrderStatusChanged {
+        order_id: Uuid,
+        status: OrderStatus,
+    },
+    DeliveryUpdated {
+        order_id: Uuid,
+        tracking_number: Option<String>,
+        status: DeliveryStatus,
+        location: Option<String>,
+        description: String,
+    },
+}
+
+impl Notification {
+    pub fn category(&self) -> NotificationCategory {
+        match self {
+            Notification::Welcome => NotificationCategory::Account,
+            Notification::RecoveryCodeUsed { .. } => NotificationCategory::Security,
+            Notification::OrderPaid { .. }
+            | Notification::OrderStatusChanged { .. }
+            | Notification::DeliveryUpdated { .. } => NotificationCategory::Order,
+        }
+    }
+}
+
+/// Translated strings. `{{name}}` placeholders are filled in when rendering.
+struct Phrases {
+    welcome_subject: &'static str,
+    welcome_ready: &'static str,
+    welcome_settings: &'static str,
+    recovery_subject: &'static str,
+    recovery_used: &'static str,
+    recovery_remaining: &'static str,
+    recovery_device: &'static str,
+    recovery_advice: &'static str,
+    paid_subject: &'static str,
+    paid_received: &'static str,
+    paid_next: &'static str,
+    status_subject: &'static str,
+    status_changed: &'static str,
+    delivery_subject: &'static str,
+    delivery_status: &'static str,
+    delivery_location: &'static str,
+    delivery_tracking: &'static str,
+}
+
+const EN: Phrases = Phrases {
+    welcome_subject: "Welcome to {{site}}",
+    welcome_ready: "Your {{site}} account is ready.",
+    welcome_settings: "We will keep you posted about your orders here. You can choose how you are notified in your account settings.",
+    recovery_subject: "A recovery code was used to sign in",
+    recovery_used: "A recovery code was just used to sign in to your {{site}} account.",
+    recovery_remaining: "Recovery codes left: {{remaining}}",
+    recovery_device: "Device: {{device}}",
+    recovery_advice: "If this was not you, change your password and generate new recovery codes right away.",
+    paid_subject: "Payment received for order {{order}}",
+    paid_received: "We received your payment of {{amount}} for order {{order}}.",
+    paid_next: "We will let you know when it ships.",
+    status_subject: "Order {{order}}: {{status}}",
+    status_changed: "Your order {{order}} is now: {{status}}.",
+    delivery_subject: "Delivery update for order {{order}}",
+    delivery_status: "{{status}}: {{description}}",
+    delivery_location: "Location: {{location}}",
+    delivery_tracking: "Tracking number: {{tracking}}",
+};
+
+const JA: Phrases = Phrases {
+    welcome_subject: "{{site}} へようこそ",
+    welcome_ready: "{{site}} のアカウントが作成されました。",
+    welcome_settings: "ご注文の状況はこちらでお知らせします。通知方法はアカウント設定から変更できます。",
+    recovery_subject: "リカバリーコードでログインがありました",
+    recovery_used: "{{site}} アカウントにリカバリーコードを使用したログインがありました。",
+    recovery_remaining: "残りのリカバリーコード：{{remaining}} 個",
+    recovery_device: "端末：{{device}}",
+    recovery_advice: "心当たりがない場合は、すぐにパスワードを変更し、リカバリーコードを再生成してください。",
+    paid_subject: "ご注文 {{order}} のお支払いを確認しました",
+    paid_received: "ご注文 {{order}} について、{{amount}} のお支払いを確認しました。",
+    paid_next: "発送時に改めてお知らせします。",
+    status_subject: "ご注文 {{order}}：{{status}}",
+    status_changed: "ご注文 {{order}} のステータスが「{{status}}」になりました。",
+    delivery_subject: "ご注文 {{order}} の配送状況",
+    delivery_status: "{{status}}：{{description}}",
+    delivery_location: "現在地：{{location}}",
+    delivery_tracking: "追跡番号：{{tracking}}",
+};
+
+const ZH: Phrases = Phrases {
+    welcome_subject: "欢迎来到 {{site}}",
+    welcome_ready: "您的 {{site}} 账户已创建。",
+    welcome_settings: "我们会在这里通知您订单的最新状态。您可以在账户设置中选择通知方式。",
+    recovery_subject: "有人使用恢复码登录了您的账户",
+    recovery_used: "您的 {{site}} 账户刚刚通过恢复码完成了登录。",
+    recovery_remaining: "剩余恢复码：{{remaining}} 个",
+    recovery_device: "设备：{{device}}",
+    recovery_advice: "如果这不是您本人的操作，请立即修改密码并重新生成恢复码。",
+    paid_subject: "订单 {{order}} 已付款",
+    paid_received: "我们已收到订单 {{order}} 的付款 {{amount}}。",
+    paid_next: "发货后我们会再次通知您。",
+    status_subject: "订单 {{order}}：{{status}}",
+    status_changed: "您的订单 {{order}} 当前状态：{{status}}。",
+    delivery_subject: "订单 {{order}} 物流更新",
+    delivery_status: "{{status}}：{{description}}",
+    delivery_location: "当前位置：{{location}}",
+    delivery_tracking: "运单号：{{tracking}}",
+};
+
+fn phrases(locale: EmailLocale) -> &'static Phrases {
+    match locale {
+        EmailLocale::En => &EN,
+        EmailLocale::Ja => &JA,
+        EmailLocale::Zh => &ZH,
+    }
+}
+
+fn order_status_label(locale: EmailLocale, status: OrderStatus) -> &'static str {
+    match (locale, status) {
+        (EmailLocale::En, OrderStatus::Unpaid) => "awaiting payment",
+        (EmailLocale::En, OrderStatus::Paid) => "paid",
+        (EmailLocale::En, OrderStatus::Delivered) => "shipped",
+        (EmailLocale::En, OrderStatus::Arrived) => "delivered",
+        (EmailLocale::En, OrderStatus::Cancelled) => "cancelled",
+        (EmailLocale::En, OrderStatus::Refunding) => "refund requested",
+        (EmailLocale::En, OrderStatus::Refunded) => "refunded",
+        (EmailLocale::Ja, OrderStatus::Unpaid) => "お支払い待ち",
+        (EmailLocale::Ja, OrderStatus::Paid) => "お支払い済み",
+        (EmailLocale::Ja, OrderStatus::Delivered) => "発送済み",
+        (EmailLocale::Ja, OrderStatus::Arrived) => "配達完了",
+        (EmailLocale::Ja, OrderStatus::Cancelled) => "キャンセル済み",
+        (EmailLocale::Ja, OrderStatus::Refunding) => "返金手続き中",
+        (EmailLocale::Ja, OrderStatus::Refunded) => "返金済み",
+        (EmailLocale::Zh, OrderStatus::Unpaid) => "待付款",
+        (EmailLocale::Zh, OrderStatus::Paid) => "已付款",
+        (EmailLocale::Zh, OrderStatus::Delivered) => "已发货",
+        (EmailLocale::Zh, OrderStatus::Arrived) => "已送达",
+        (EmailLocale::Zh, OrderStatus::Cancelled) => "已取消",
+        (EmailLocale::Zh, OrderStatus::Refunding) => "退款中",
+        (EmailLocale::Zh, OrderStatus::Refunded) => "已退款",
+    }
+}
+
+fn delivery_status_label(locale: EmailLocale, status: DeliveryStatus) -> &'static str {
+    match (locale, status) {
+        (EmailLocale::En, DeliveryStatus::InTransit) => "In transit",
+        (EmailLocale::En, DeliveryStatus::OutForDelivery) => "Out for delivery",
+        (EmailLocale::En, DeliveryStatus::Delivered) => "Delivered",
+        (EmailLocale::En, DeliveryStatus::Cancelled) => "Cancelled",
+        (EmailLocale::En, DeliveryStatus::Returned) => "Returned",
+        (EmailLocale::Ja, DeliveryStatus::InTransit) => "輸送中",
+        (EmailLocale::Ja, DeliveryStatus::OutForDelivery) => "配達中",
+        (EmailLocale::Ja, DeliveryStatus::Delivered) => "配達完了",
+        (EmailLocale::Ja, DeliveryStatus::Cancelled) => "キャンセル",
+        (EmailLocale::Ja, DeliveryStatus::Returned) => "返送",
+        (EmailLocale::Zh, DeliveryStatus::InTransit) => "运输中",
+        (EmailLocale::Zh, DeliveryStatus::OutForDelivery) => "派送中",
+        (EmailLocale::Zh, DeliveryStatus::Delivered) => "已送达",
+        (EmailLocale::Zh, DeliveryStatus::Cancelled) => "已取消",
+        (EmailLocale::Zh, DeliveryStatus::Returned) => "已退回",
+    }
+}
+
+const NOTIFICATION_HTML: &str = r#"<!DOCTYPE html>
+<html lang="{{lang}}">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{subject}}</title>
+</head>
+<body style="margin:0;padding:24px;background:#f4f4f5;font-family:-apple-system,'Segoe UI',Roboto,'Hiragino Sans','Microsoft YaHei',sans-serif;color:#18181b;">
+<table role="presentation" width="100%" cellspacing="0" cellpadding="0" style="max-width:480px;margin:0 auto;background:#ffffff;border-radius:8px;">
+<tr><td style="padding:32px;">
+<h1 style="margin:0 0 24px;font-size:20px;font-weight:600;line-height:1.4;">{{subject}}</h1>
+{{paragraphs}}
+</td></tr>
+</table>
+<p style="margin:16px 0 0;font-size:12px;text-align:center;color:#a1a1aa;">{{site}}</p>
+</body>
+</html>
+"#;
+
+impl Notification {
+    /// The subject and paragraphs in the given language, as an email. Chat and webhook channels
+    /// use the subject and the text part.
+    pub fn render(&self, locale: EmailLocale, site_name: &str) -> RenderedEmail {
+        let phrases = phrases(locale);
+        let (subject, paragraphs) = match self {
+            Notification::Welcome => {
+                let values = [("site", site_name)];
+                (
+                    fill_template(phrases.welcome_subject, &values),
+                    vec![
+                        fill_template(phrases.welcome_ready, &values),
+                        phrases.welcome_settings.to_owned(),
+                    ],
+                )
+            }
+            Notification::RecoveryCodeUsed {
+                remaining_codes,
+                ip,
+                user_agent,
+            } => {
+                let remaining = remaining_codes.to_string();
+                let device = [ip.as_deref(), user_agent.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join(" / ");
+                let values = [
+                    ("site", site_name),
+                    ("remaining", &remaining),
+                    ("device", &device),
+                ];
+                let mut paragraphs = vec![
+                    fill_template(phrases.recovery_used, &values),
+                    fill_template(phrases.recovery_remaining, &values),
+                ];
+                if !device.is_empty() {
+                    paragraphs.push(fill_template(phrases.recovery_device, &values));
+                }
+                paragraphs.push(phrases.recovery_advice.to_owned());
+                (phrases.recovery_subject.to_owned(), paragraphs)
+            }
+            Notification::OrderPaid {
+                order_id,
+                total_amount,
+            } => {
+                let order = order_id.to_string();
+                let amount = total_amount.to_string();
+                let values = [("order", order.as_str()), ("amount", &amount)];
+                (
+                    fill_template(phrases.paid_subject, &values),
+                    vec![
+                        fill_template(phrases.paid_received, &values),
+                        phrases.paid_next.to_owned(),
+                    ],
+                )
+            }
+            Notification::OrderStatusChanged { order_id, status } => {
+                let order = order_id.to_string();
+                let values = [
+                    ("order", order.as_str()),
+                    ("status", order_status_label(locale, *status)),
+                ];
+                (
+                    fill_template(phrases.status_subject, &values),
+                    vec![fill_template(phrases.status_changed, &values)],
+                )
+            }
+            Notification::DeliveryUpdated {
+                order_id,
+                tracking_number,
+                status,
+                location,
+                description,
+            } => {
+                let order = order_id.to_string();
+                let values = [
+                    ("order", order.as_str()),
+                    ("status", delivery_status_label(locale, *status)),
+                    ("description", description),
+                    ("location", location.as_deref().unwrap_or_default()),
+                    ("tracking", tracking_number.as_deref().unwrap_or_default()),
+                ];
+                let mut paragraphs = vec![fill_template(phrases.delivery_status, &values)];
+                if location.is_some() {
+                    paragraphs.push(fill_template(phrases.delivery_location, &values));
+                }
+                if tracking_number.is_some() {
+                    paragraphs.push(fill_template(phrases.delivery_tracking, &values));
+                }
+                (fill_template(phrases.delivery_subject, &values), paragraphs)
+            }
+        };
+
+        let text = format!("{}\n\n-- \n{site_name}\n", paragraphs.join("\n\n"));
+        let paragraphs_html = paragraphs
+            .iter()
+            .map(|paragraph| {
+                format!(
+                    r#"<p style="margin:0 0 16px;font-size:16px;line-height:1.5;">{}</p>"#,
+                    escape_html(paragraph)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let html = fill_template(
+            NOTIFICATION_HTML,
+            &[
+                ("lang", locale.html_lang()),
+                ("subject", &escape_html(&subject)),
+                ("paragraphs", &paragraphs_html),
+                ("site", &escape_html(site_name)),
+            ],
+        );
+        RenderedEmail {
+            subject,
+            html,
+            text,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delivery_update_renders() {
+        let notification = Notification::DeliveryUpdated {
+            order_id: Uuid::nil(),
+            tracking_number: Some("JP123".to_owned()),
+            status: DeliveryStatus::OutForDelivery,
+            location: None,
+            description: "<b>Left</b> the {{site}} depot".to_owned(),
+        };
+        let email = notification.render(EmailLocale::Ja, "Phantom");
+        assert_eq!(
+            email.subject,
+            "ご注文 00000000-0000-0000-0000-000000000000 の配送状況"
+        );
+        assert!(
+            email
+                .text
+                .starts_with("配達中：<b>Left</b> the {{site}} depot\n\n追跡番号：JP123\n")
+        );
+        assert!(!email.text.contains("現在地"));
+        assert!(
+            email
+                .html
+                .contains("&lt;b&gt;Left&lt;/b&gt; the {{site}} depot")
+        );
+        assert!(email.html.contains(r#"<html lang="ja">"#));
+    }
+}
@@ -0,0 +1,70 @@
+use admin::utils::config_secret::{open_stored_secret, seal_stored_secret};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Prefix of the secrets, which tells those stored in plaintext before they were sealed.
+const SECRET_PREFIX: &str = "whsec_";
+
+/// Unique per event, shared by its retries and redeliveries so that receivers can deduplicate.
+pub const WEBHOOK_ID_HEADER: &str = "x-phantom-webhook-id";
+pub const WEBHOOK_EVENT_HEADER: &str = "x-phantom-webhook-event";
+/// Unix timestamp in seconds of the attempt, covered by the signature against replays.
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "x-phantom-webhook-timestamp";
+/// `sha256=<hex>`, see [sign].
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-phantom-webhook-signature";
+
+/// A fresh signing secret, shown to the merchant.
+pub fn generate_secret() -> String {
+    format!("{SECRET_PREFIX}{}", hex::encode(rand::random::<[u8; 32]>()))
+}
+
+/// Seal a secret with the config keyring before storing it.
+pub fn seal_secret(secret: &str) -> Result<String, framework::Error> {
+    Ok(seal_stored_secret(secret)?)
+}
+
+/// Whether a stored secret is still in plaintext, which
+/// [`ResealWebhookSecrets`](crate::services::merchant_webhook::ResealWebhookSecrets) seals.
+pub fn is_plaintext_secret(stored: &str) -> bool {
+    stored.starts_with(SECRET_PREFIX)
+}
+
+/// The secret to sign with, from its stored form.
+pub fn open_secret(stored: &str) -> Result<String, framework::Error> {
+    if is_plaintext_secret(stored) {
+        return Ok(stored.to_owned());
+    }
+    Ok(open_stored_secret(stored)?)
+}
+
+/// The HMAC-SHA256 of `{timestamp}.{body}` keyed with the secret, as the value of
+/// [WEBHOOK_SIGNATURE_HEADER].
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        unreachable!("HMAC accepts keys of any length")
+    };
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_matches_openssl() {
+        // echo -n '1700000000.{"id":1}' | openssl dgst -sha256 -hmac whsec_test
+        assert_eq!(
+            sign("whsec_test", 1_700_000_000, br#"{"id":1}"#),
+            "sha256=2f441ba4b3b2d50d28a9ab9d9fd8880376ecd1eb5d0435401553f5d8d0a5dcf8"
+        );
+    }
+
+    #[test]
+    fn plaintext_secrets_are_recognized() {
+        assert!(is_plaintext_secret(&generate_secret()));
+        assert!(!is_plaintext_secret("v1.current.d3JhcHBlZA.c2VhbGVk"));
+    }
+}
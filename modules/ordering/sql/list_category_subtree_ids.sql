@@ -0,0 +1,14 @@
+-- Recursively fetch the ids of the given category and all its descendants
+WITH RECURSIVE subtree AS (
+    SELECT c.id
+    FROM "shop"."category" c
+    WHERE c.id = $1
+
+    UNION ALL
+
+    SELECT c.id
+    FROM "shop"."category" c
+    INNER JOIN subtree s ON c.parent_id = s.id
+)
+SELECT id AS "id!"
+FROM subtree
@@ -77,6 +77,23 @@ impl Processor<ShowCategoryParentsAndChildren> for DatabaseProcessor {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+/// List the ids of a category and all of its descendants.
+pub struct ListCategorySubtreeIds {
+    pub category_id: i32,
+}
+
+impl Processor<ListCategorySubtreeIds> for DatabaseProcessor {
+    type Output = Vec<i32>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListCategorySubtreeIds", err)]
+    async fn process(&self, input: ListCategorySubtreeIds) -> Result<Vec<i32>, sqlx::Error> {
+        sqlx::query_file_scalar!("sql/list_category_subtree_ids.sql", input.category_id)
+            .fetch_all(self.db())
+            .await
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct FindCategoryById {
     pub id: i32,
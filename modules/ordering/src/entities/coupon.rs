@@ -2,6 +2,7 @@ use framework::sqlx::DatabaseProcessor;
 use kanau::processor::Processor;
 use rust_decimal::Decimal;
 use tracing::instrument;
+use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
 pub struct Coupon {
@@ -14,6 +15,11 @@ pub struct Coupon {
     pub limit_to_category: Option<i32>,
     pub limit_per_user: Option<i32>,
     pub limit_total: Option<i32>,
+    pub limit_to_goods: Option<Vec<i32>>,
+    pub first_order_only: bool,
+    pub new_user_within_days: Option<i32>,
+    /// Whether the coupon can be used together with other stackable coupons
+    pub stackable: bool,
     pub used_count: i32,
     pub created_at: time::PrimitiveDateTime,
     pub updated_at: time::PrimitiveDateTime,
@@ -23,10 +29,14 @@ pub struct Coupon {
 pub enum Discount {
     Rate(RateDiscount),
     Amount(AmountDiscount),
+    BuyXGetY(BuyXGetYDiscount),
 }
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub struct RateDiscount {
     pub rate: Decimal,
+    /// Upper bound of the discounted amount.
+    #[serde(default)]
+    pub max_discount: Option<Decimal>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
@@ -35,6 +45,14 @@ pub struct AmountDiscount {
     pub discount: Decimal,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+/// Every `buy` units of the goods bought, the next `get` units are free.
+pub struct BuyXGetYDiscount {
+    pub goods_id: i32,
+    pub buy: u32,
+    pub get: u32,
+}
+
 #[derive(Debug, Clone)]
 pub struct FindCouponByCode {
     pub code: String,
@@ -52,6 +70,7 @@ impl Processor<FindCouponByCode> for DatabaseProcessor {
             id, code, set_active,
             discount as "discount: sqlx::types::Json<Discount>",
             available_since, available_until, limit_to_category, limit_per_user, limit_total,
+            limit_to_goods, first_order_only, new_user_within_days, stackable,
             used_count, created_at, updated_at
             FROM "shop"."coupon"
             WHERE code = $1
@@ -80,6 +99,7 @@ impl Processor<FindCouponById> for DatabaseProcessor {
             id, code, set_active,
             discount as "discount: sqlx::types::Json<Discount>",
             available_since, available_until, limit_to_category, limit_per_user, limit_total,
+            limit_to_goods, first_order_only, new_user_within_days, stackable,
             used_count, created_at, updated_at
             FROM "shop"."coupon"
             WHERE id = $1
@@ -101,22 +121,29 @@ pub struct CreateNewCoupon {
     pub limit_to_category: Option<i32>,
     pub limit_per_user: Option<i32>,
     pub limit_total: Option<i32>,
+    pub limit_to_goods: Option<Vec<i32>>,
+    pub first_order_only: bool,
+    pub new_user_within_days: Option<i32>,
+    pub stackable: bool,
 }
 
 impl Processor<CreateNewCoupon> for DatabaseProcessor {
-    type Output = Coupon;
+    /// `None` if the code is taken
+    type Output = Option<Coupon>;
     type Error = sqlx::Error;
     #[instrument(skip_all, name = "SQL:CreateNewCoupon", err)]
-    async fn process(&self, input: CreateNewCoupon) -> Result<Coupon, sqlx::Error> {
+    async fn process(&self, input: CreateNewCoupon) -> Result<Option<Coupon>, sqlx::Error> {
         sqlx::query_as!(
             Coupon,
             r#"
-            INSERT INTO "shop"."coupon" (code, set_active, discount, available_since, available_until, limit_to_category, limit_per_user, limit_total)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            INSERT INTO "shop"."coupon" (code, set_active, discount, available_since, available_until, limit_to_category, limit_per_user, limit_total, limit_to_goods, first_order_only, new_user_within_days, stackable)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT (code) DO NOTHING
             RETURNING
             id, code, set_active,
             discount as "discount: sqlx::types::Json<Discount>",
             available_since, available_until, limit_to_category, limit_per_user, limit_total,
+            limit_to_goods, first_order_only, new_user_within_days, stackable,
             used_count, created_at, updated_at
             "#,
             &input.code,
@@ -126,9 +153,13 @@ impl Processor<CreateNewCoupon> for DatabaseProcessor {
             input.available_until,
             input.limit_to_category,
             input.limit_per_user,
-            input.limit_total
+            input.limit_total,
+            input.limit_to_goods.as_deref(),
+            input.first_order_only,
+            input.new_user_within_days,
+            input.stackable
         )
-        .fetch_one(self.db())
+        .fetch_optional(self.db())
         .await
     }
 }
@@ -142,6 +173,10 @@ pub struct UpdateCoupon {
     pub limit_to_category: Option<i32>,
     pub limit_per_user: Option<i32>,
     pub limit_total: Option<i32>,
+    pub limit_to_goods: Option<Vec<i32>>,
+    pub first_order_only: bool,
+    pub new_user_within_days: Option<i32>,
+    pub stackable: bool,
 }
 
 impl Processor<UpdateCoupon> for DatabaseProcessor {
@@ -153,12 +188,14 @@ impl Processor<UpdateCoupon> for DatabaseProcessor {
             Coupon,
             r#"
             UPDATE "shop"."coupon"
-            SET discount = $2, available_since = $3, available_until = $4, limit_to_category = $5, limit_per_user = $6, limit_total = $7
+            SET discount = $2, available_since = $3, available_until = $4, limit_to_category = $5, limit_per_user = $6, limit_total = $7,
+                limit_to_goods = $8, first_order_only = $9, new_user_within_days = $10, stackable = $11
             WHERE id = $1
             RETURNING
             id, code, set_active,
             discount as "discount: sqlx::types::Json<Discount>",
             available_since, available_until, limit_to_category, limit_per_user, limit_total,
+            limit_to_goods, first_order_only, new_user_within_days, stackable,
             used_count, created_at, updated_at
             "#,
             input.id,
@@ -167,7 +204,11 @@ impl Processor<UpdateCoupon> for DatabaseProcessor {
             input.available_until,
             input.limit_to_category,
             input.limit_per_user,
-            input.limit_total
+            input.limit_total,
+            input.limit_to_goods.as_deref(),
+            input.first_order_only,
+            input.new_user_within_days,
+            input.stackable
         )
         .fetch_one(self.db())
         .await
@@ -195,6 +236,7 @@ impl Processor<DisableOrEnableCoupon> for DatabaseProcessor {
             id, code, set_active,
             discount as "discount: sqlx::types::Json<Discount>",
             available_since, available_until, limit_to_category, limit_per_user, limit_total,
+            limit_to_goods, first_order_only, new_user_within_days, stackable,
             used_count, created_at, updated_at
             "#,
             input.id,
@@ -204,3 +246,171 @@ impl Processor<DisableOrEnableCoupon> for DatabaseProcessor {
         .await
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct CouponRedemption {
+    pub id: i64,
+    pub coupon_id: i32,
+    pub user_id: Uuid,
+    pub order_id: Uuid,
+    pub redeemed_at: time::PrimitiveDateTime,
+    /// Set when the order was cancelled
+    pub released_at: Option<time::PrimitiveDateTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+/// Statistics of the redemptions that have not been released.
+pub struct CouponRedemptionStats {
+    pub coupon_id: i32,
+    pub redemption_count: i64,
+    pub unique_users: i64,
+    pub last_redeemed_at: Option<time::PrimitiveDateTime>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListCoupons {
+    pub set_active: Option<bool>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Processor<ListCoupons> for DatabaseProcessor {
+    type Output = Vec<Coupon>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListCoupons", err)]
+    async fn process(&self, input: ListCoupons) -> Result<Vec<Coupon>, sqlx::Error> {
+        sqlx::query_as!(
+            Coupon,
+            r#"
+            SELECT
+            id, code, set_active,
+            discount as "discount: sqlx::types::Json<Discount>",
+            available_since, available_until, limit_to_category, limit_per_user, limit_total,
+            limit_to_goods, first_order_only, new_user_within_days, stackable,
+            used_count, created_at, updated_at
+            FROM "shop"."coupon"
+            WHERE $1::BOOLEAN IS NULL OR set_active = $1
+            ORDER BY id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            input.set_active,
+            input.limit,
+            input.offset
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FindCouponRedemptionStats {
+    pub coupon_ids: Vec<i32>,
+}
+
+impl Processor<FindCouponRedemptionStats> for DatabaseProcessor {
+    type Output = Vec<CouponRedemptionStats>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:FindCouponRedemptionStats", err)]
+    async fn process(
+        &self,
+        input: FindCouponRedemptionStats,
+    ) -> Result<Vec<CouponRedemptionStats>, sqlx::Error> {
+        sqlx::query_as!(
+            CouponRedemptionStats,
+            r#"
+            SELECT
+            coupon_id,
+            COUNT(*) as "redemption_count!",
+            COUNT(DISTINCT user_id) as "unique_users!",
+            MAX(redeemed_at) as last_redeemed_at
+            FROM "shop"."coupon_redemption"
+            WHERE coupon_id = ANY($1) AND released_at IS NULL
+            GROUP BY coupon_id
+            "#,
+            &input.coupon_ids
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Insert a batch of coupons that can be used only once.
+///
+/// The batch is inserted as a whole, and fails with a unique violation if any code already exists.
+pub struct CreateSingleUseCoupons {
+    pub codes: Vec<String>,
+    pub discount: Discount,
+    pub available_since: Option<time::PrimitiveDateTime>,
+    pub available_until: Option<time::PrimitiveDateTime>,
+    pub limit_to_category: Option<i32>,
+    pub limit_to_goods: Option<Vec<i32>>,
+    pub first_order_only: bool,
+    pub new_user_within_days: Option<i32>,
+    pub stackable: bool,
+}
+
+impl Processor<CreateSingleUseCoupons> for DatabaseProcessor {
+    type Output = Vec<Coupon>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:CreateSingleUseCoupons", err)]
+    async fn process(&self, input: CreateSingleUseCoupons) -> Result<Vec<Coupon>, sqlx::Error> {
+        sqlx::query_as!(
+            Coupon,
+            r#"
+            INSERT INTO "shop"."coupon" (code, set_active, discount, available_since, available_until, limit_to_category, limit_per_user, limit_total, limit_to_goods, first_order_only, new_user_within_days, stackable)
+            SELECT code, TRUE, $2, $3, $4, $5, 1, 1, $6, $7, $8, $9
+            FROM UNNEST($1::VARCHAR[]) AS code
+            RETURNING
+            id, code, set_active,
+            discount as "discount: sqlx::types::Json<Discount>",
+            available_since, available_until, limit_to_category, limit_per_user, limit_total,
+            limit_to_goods, first_order_only, new_user_within_days, stackable,
+            used_count, created_at, updated_at
+            "#,
+            &input.codes,
+            sqlx::types::Json(input.discount) as sqlx::types::Json<Discount>,
+            input.available_since,
+            input.available_until,
+            input.limit_to_category,
+            input.limit_to_goods.as_deref(),
+            input.first_order_only,
+            input.new_user_within_days,
+            input.stackable
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// The coupons redeemed by an order, in the order they were applied.
+pub struct ListOrderCoupons {
+    pub order_id: Uuid,
+}
+
+impl Processor<ListOrderCoupons> for DatabaseProcessor {
+    type Output = Vec<Coupon>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListOrderCoupons", err)]
+    async fn process(&self, input: ListOrderCoupons) -> Result<Vec<Coupon>, sqlx::Error> {
+        sqlx::query_as!(
+            Coupon,
+            r#"
+            SELECT
+            c.id, c.code, c.set_active,
+            c.discount as "discount: sqlx::types::Json<Discount>",
+            c.available_since, c.available_until, c.limit_to_category, c.limit_per_user,
+            c.limit_total, c.limit_to_goods, c.first_order_only, c.new_user_within_days,
+            c.stackable, c.used_count, c.created_at, c.updated_at
+            FROM "shop"."coupon_redemption" r
+            JOIN "shop"."coupon" c ON c.id = r.coupon_id
+            WHERE r.order_id = $1
+            ORDER BY r.id
+            "#,
+            input.order_id
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
@@ -1,4 +1,9 @@
+use crate::entities::order::OrderStatus;
+use crate::entities::order_history::record_status_change;
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
 use time::PrimitiveDateTime;
+use tracing::{Instrument, info_span, instrument};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
@@ -32,3 +37,148 @@ pub enum DeliveryStatus {
     Cancelled,
     Returned,
 }
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListDeliveryTrackingByOrder {
+    pub order_id: Uuid,
+}
+
+impl Processor<ListDeliveryTrackingByOrder> for DatabaseProcessor {
+    type Output = Vec<DeliveryTracking>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListDeliveryTrackingByOrder", err)]
+    async fn process(
+        &self,
+        input: ListDeliveryTrackingByOrder,
+    ) -> Result<Vec<DeliveryTracking>, sqlx::Error> {
+        sqlx::query_as!(
+            DeliveryTracking,
+            r#"
+            SELECT id, order_id, status as "status: DeliveryStatus", location, description, created_at
+            FROM "shop"."delivery_tracking"
+            WHERE order_id = $1
+            ORDER BY created_at, id
+            "#,
+            input.order_id
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewDeliveryTrackingItem {
+    pub status: DeliveryStatus,
+    pub location: Option<String>,
+    pub description: String,
+    pub created_at: PrimitiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+/// Insert tracking items of an order and update the order accordingly.
+///
+/// `tracking_number` is only overwritten when provided. `new_status` may only be `Delivered` or
+/// `Arrived`, and the order only moves forward along `Paid -> Delivered -> Arrived`, so
+/// out-of-order updates can not move an order backwards.
+pub struct RecordDeliveryUpdate {
+    pub order_id: Uuid,
+    pub items: Vec<NewDeliveryTrackingItem>,
+    pub tracking_number: Option<String>,
+    pub new_status: Option<OrderStatus>,
+}
+
+impl Processor<RecordDeliveryUpdate> for DatabaseProcessor {
+    /// Whether the order status has been changed
+    type Output = bool;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL-Transaction:RecordDeliveryUpdate", err)]
+    async fn process(&self, input: RecordDeliveryUpdate) -> Result<bool, sqlx::Error> {
+        let mut tx = self
+            .db()
+            .begin()
+            .instrument(info_span!("<Transaction Begin>"))
+            .await?;
+        for item in input.items {
+            sqlx::query!(
+                r#"
+                INSERT INTO "shop"."delivery_tracking" (order_id, status, location, description, created_at)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                input.order_id,
+                item.status as DeliveryStatus,
+                item.location,
+                item.description,
+                item.created_at
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        if let Some(tracking_number) = input.tracking_number {
+            sqlx::query!(
+                r#"
+                UPDATE "shop"."user_order"
+                SET tracking_number = $2
+                WHERE id = $1
+                "#,
+                input.order_id,
+                tracking_number
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        let status_changed = match input.new_status {
+            Some(new_status) => {
+                let old_status = sqlx::query_scalar!(
+                    r#"
+                    UPDATE "shop"."user_order" o
+                    SET order_status = $2::shop.order_status,
+                        delivered_at = COALESCE(o.delivered_at, NOW()),
+                        arrived_at = CASE
+                            WHEN $2::shop.order_status = 'arrived' THEN NOW()
+                            ELSE o.arrived_at
+                        END
+                    FROM (
+                        SELECT id, order_status
+                        FROM "shop"."user_order"
+                        WHERE id = $1
+                        FOR UPDATE
+                    ) old
+                    WHERE o.id = old.id
+                      AND (
+                        ($2::shop.order_status = 'delivered' AND old.order_status = 'paid')
+                        OR (
+                            $2::shop.order_status = 'arrived'
+                            AND old.order_status IN ('paid', 'delivered')
+                        )
+                      )
+                    RETURNING old.order_status as "order_status: OrderStatus"
+                    "#,
+                    input.order_id,
+                    new_status as OrderStatus
+                )
+                .fetch_optional(&mut *tx)
+                .await?;
+                match old_status {
+                    Some(old_status) => {
+                        record_status_change(
+                            &mut tx,
+                            input.order_id,
+                            Some(old_status),
+                            new_status,
+                            None,
+                            None,
+                        )
+                        .await?;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            None => false,
+        };
+        tx.commit()
+            .instrument(info_span!("<Transaction Commit>"))
+            .await?;
+        Ok(status_changed)
+    }
+}
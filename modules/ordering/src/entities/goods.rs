@@ -38,6 +38,30 @@ impl Processor<FindGoodsById> for DatabaseProcessor {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct FindGoodsByIds {
+    pub ids: Vec<i32>,
+}
+
+impl Processor<FindGoodsByIds> for DatabaseProcessor {
+    type Output = Vec<Goods>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:FindGoodsByIds", err)]
+    async fn process(&self, input: FindGoodsByIds) -> Result<Vec<Goods>, sqlx::Error> {
+        sqlx::query_as!(
+            Goods,
+            r#"
+            SELECT id, name, description, pictures, price, category_id, on_sale, stock
+            FROM "shop"."goods"
+            WHERE id = ANY($1)
+            "#,
+            &input.ids
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ListGoodsUnderCategory {
     pub category_id: i32,
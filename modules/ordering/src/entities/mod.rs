@@ -3,4 +3,5 @@ pub mod coupon;
 pub mod delivery_tracking;
 pub mod goods;
 pub mod order;
+pub mod order_history;
 pub mod payment_callback;
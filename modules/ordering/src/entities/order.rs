@@ -1,5 +1,9 @@
+use crate::entities::order_history::record_status_change;
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
 use rust_decimal::Decimal;
 use time::PrimitiveDateTime;
+use tracing::{Instrument, info_span, instrument};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
@@ -20,13 +24,16 @@ pub struct UserOrder {
     pub refunded_at: Option<PrimitiveDateTime>,
 
     pub payment_method: Option<PaymentMethod>,
-    pub payment_method_info: sqlx::types::Json<PaymentMethodInfo>,
+    pub payment_method_info: OrderPaymentMethodInfo,
 
     pub tracking_number: Option<String>,
 
     pub is_soft_deleted: bool,
 }
 
+/// Payment info of an order, `null` until the order is paid.
+pub type OrderPaymentMethodInfo = sqlx::types::Json<Option<PaymentMethodInfo>>;
+
 #[derive(
     Debug,
     Clone,
@@ -51,6 +58,13 @@ pub enum OrderStatus {
     Refunded,
 }
 
+impl OrderStatus {
+    /// Whether the order reached a final state and may be hidden by its owner.
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Arrived | Self::Cancelled | Self::Refunded)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, serde::Serialize)]
 #[sqlx(type_name = "shop.payment_method", rename_all = "snake_case")]
 pub enum PaymentMethod {
@@ -77,3 +91,512 @@ pub enum PaymentMethodInfo {
     StableCoin { txn_hash: String },
     AdminOperation, // reversed for credit card, paypal and others
 }
+
+#[derive(Debug, Clone, Copy)]
+pub struct FindUserOrderById {
+    pub id: Uuid,
+}
+
+impl Processor<FindUserOrderById> for DatabaseProcessor {
+    type Output = Option<UserOrder>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:FindUserOrderById", err)]
+    async fn process(&self, input: FindUserOrderById) -> Result<Option<UserOrder>, sqlx::Error> {
+        sqlx::query_as!(
+            UserOrder,
+            r#"
+            SELECT
+            id, "user", production, total_amount, coupon_used, created_at,
+            order_status as "order_status: OrderStatus",
+            paid_at, delivered_at, arrived_at, cancelled_at, refund_requested_at, refunded_at,
+            payment_method as "payment_method: PaymentMethod",
+            payment_method_info as "payment_method_info: OrderPaymentMethodInfo",
+            tracking_number, is_soft_deleted
+            FROM "shop"."user_order"
+            WHERE id = $1
+            "#,
+            input.id
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// List orders of a user, newest first, with keyset pagination on `(created_at, id)`.
+///
+/// Soft deleted orders are excluded.
+pub struct ListUserOrders {
+    pub user: Uuid,
+    pub status: Option<OrderStatus>,
+    /// Only return orders placed before this `(created_at, id)` cursor.
+    pub before: Option<(PrimitiveDateTime, Uuid)>,
+    pub limit: i64,
+}
+
+impl Processor<ListUserOrders> for DatabaseProcessor {
+    type Output = Vec<UserOrder>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListUserOrders", err)]
+    async fn process(&self, input: ListUserOrders) -> Result<Vec<UserOrder>, sqlx::Error> {
+        let (before_created_at, before_id) = input.before.unzip();
+        sqlx::query_as!(
+            UserOrder,
+            r#"
+            SELECT
+            id, "user", production, total_amount, coupon_used, created_at,
+            order_status as "order_status: OrderStatus",
+            paid_at, delivered_at, arrived_at, cancelled_at, refund_requested_at, refunded_at,
+            payment_method as "payment_method: PaymentMethod",
+            payment_method_info as "payment_method_info: OrderPaymentMethodInfo",
+            tracking_number, is_soft_deleted
+            FROM "shop"."user_order"
+            WHERE "user" = $1
+              AND NOT is_soft_deleted
+              AND ($2::shop.order_status IS NULL OR order_status = $2)
+              AND ($3::timestamp IS NULL OR (created_at, id) < ($3, $4))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $5
+            "#,
+            input.user,
+            input.status as Option<OrderStatus>,
+            before_created_at,
+            before_id,
+            input.limit
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Hide a finished (arrived, cancelled or refunded) order from its owner.
+///
+/// Returns `false` if no such order exists or it is not finished yet.
+pub struct SoftDeleteUserOrder {
+    pub id: Uuid,
+    pub user: Uuid,
+}
+
+impl Processor<SoftDeleteUserOrder> for DatabaseProcessor {
+    type Output = bool;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:SoftDeleteUserOrder", err)]
+    async fn process(&self, input: SoftDeleteUserOrder) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE "shop"."user_order"
+            SET is_soft_deleted = TRUE
+            WHERE id = $1
+              AND "user" = $2
+              AND order_status IN ('arrived', 'cancelled', 'refunded')
+            "#,
+            input.id,
+            input.user
+        )
+        .execute(self.db())
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What coupon eligibility rules need to know about a user.
+pub struct UserOrderingProfile {
+    pub registered_at: PrimitiveDateTime,
+    /// Orders placed before, cancelled ones excluded.
+    pub placed_order_count: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FindUserOrderingProfile {
+    pub user: Uuid,
+}
+
+impl Processor<FindUserOrderingProfile> for DatabaseProcessor {
+    type Output = Option<UserOrderingProfile>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:FindUserOrderingProfile", err)]
+    async fn process(
+        &self,
+        input: FindUserOrderingProfile,
+    ) -> Result<Option<UserOrderingProfile>, sqlx::Error> {
+        sqlx::query_as!(
+            UserOrderingProfile,
+            r#"
+            SELECT
+            u.created_at as registered_at,
+            (
+                SELECT COUNT(*)
+                FROM "shop"."user_order" o
+                WHERE o."user" = u.id AND o.order_status <> 'cancelled'
+            ) as "placed_order_count!"
+            FROM "auth"."user_account" u
+            WHERE u.id = $1
+            "#,
+            input.user
+        )
+        .fetch_optional(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Insert a new unpaid order.
+///
+/// The coupon rows are locked, their availability, `limit_total`, `limit_per_user` and
+/// `first_order_only` are checked, and the redemptions are recorded in the same transaction.
+pub struct PlaceUserOrder {
+    pub user: Uuid,
+    pub production: Uuid,
+    pub total_amount: Decimal,
+    /// Coupons in the order they were applied, the first one is also kept in `coupon_used`
+    pub coupons_used: Vec<i32>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PlaceUserOrderResult {
+    Placed(Box<UserOrder>),
+    CouponNotAvailable,
+    CouponUsageLimitReached,
+    CouponUserLimitReached,
+    NotFirstOrder,
+}
+
+impl Processor<PlaceUserOrder> for DatabaseProcessor {
+    type Output = PlaceUserOrderResult;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL-Transaction:PlaceUserOrder", err)]
+    async fn process(&self, input: PlaceUserOrder) -> Result<PlaceUserOrderResult, sqlx::Error> {
+        let mut tx = self
+            .db()
+            .begin()
+            .instrument(info_span!("<Transaction Begin>"))
+            .await?;
+        // orders of the same user are placed one at a time, so that concurrent orders can not
+        // both pass the first order check
+        sqlx::query!(
+            r#"
+            SELECT id
+            FROM "auth"."user_account"
+            WHERE id = $1
+            FOR NO KEY UPDATE
+            "#,
+            input.user
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let stacked = input.coupons_used.len() > 1;
+        let mut coupon_ids = input.coupons_used.clone();
+        // lock in a fixed order to avoid deadlocks between orders sharing coupons
+        coupon_ids.sort_unstable();
+        let mut first_order_only = false;
+        for coupon_id in coupon_ids {
+            let coupon = sqlx::query!(
+                r#"
+                SELECT set_active, stackable, first_order_only, limit_per_user, limit_total,
+                used_count
+                FROM "shop"."coupon"
+                WHERE id = $1
+                FOR UPDATE
+                "#,
+                coupon_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+            let Some(coupon) = coupon.filter(|c| c.set_active && (c.stackable || !stacked)) else {
+                return Ok(PlaceUserOrderResult::CouponNotAvailable);
+            };
+            first_order_only |= coupon.first_order_only;
+            if coupon
+                .limit_total
+                .is_some_and(|limit| coupon.used_count >= limit)
+            {
+                return Ok(PlaceUserOrderResult::CouponUsageLimitReached);
+            }
+            if let Some(limit) = coupon.limit_per_user {
+                let used_by_user = sqlx::query_scalar!(
+                    r#"
+                    SELECT COUNT(*) as "count!"
+                    FROM "shop"."coupon_redemption"
+                    WHERE coupon_id = $1 AND user_id = $2 AND released_at IS NULL
+                    "#,
+                    coupon_id,
+                    input.user
+                )
+                .fetch_one(&mut *tx)
+                .await?;
+                if used_by_user >= i64::from(limit) {
+                    return Ok(PlaceUserOrderResult::CouponUserLimitReached);
+                }
+            }
+        }
+        if first_order_only {
+            let placed = sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*) as "count!"
+                FROM "shop"."user_order"
+                WHERE "user" = $1 AND order_status <> 'cancelled'
+                "#,
+                input.user
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if placed > 0 {
+                return Ok(PlaceUserOrderResult::NotFirstOrder);
+            }
+        }
+        let order = sqlx::query_as!(
+            UserOrder,
+            r#"
+            INSERT INTO "shop"."user_order" ("user", production, total_amount, coupon_used)
+            VALUES ($1, $2, $3, $4)
+            RETURNING
+            id, "user", production, total_amount, coupon_used, created_at,
+            order_status as "order_status: OrderStatus",
+            paid_at, delivered_at, arrived_at, cancelled_at, refund_requested_at, refunded_at,
+            payment_method as "payment_method: PaymentMethod",
+            payment_method_info as "payment_method_info: OrderPaymentMethodInfo",
+            tracking_number, is_soft_deleted
+            "#,
+            input.user,
+            input.production,
+            input.total_amount,
+            input.coupons_used.first().copied()
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        record_status_change(&mut tx, order.id, None, order.order_status, None, None).await?;
+        for coupon_id in &input.coupons_used {
+            sqlx::query!(
+                r#"
+                INSERT INTO "shop"."coupon_redemption" (coupon_id, user_id, order_id)
+                VALUES ($1, $2, $3)
+                "#,
+                coupon_id,
+                input.user,
+                order.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                UPDATE "shop"."coupon"
+                SET used_count = used_count + 1
+                WHERE id = $1
+                "#,
+                coupon_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit()
+            .instrument(info_span!("<Transaction Commit>"))
+            .await?;
+        Ok(PlaceUserOrderResult::Placed(Box::new(order)))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Search orders for admins, newest first. Every filter is optional and soft deleted orders are
+/// included.
+pub struct SearchUserOrders {
+    pub user: Option<Uuid>,
+    /// Matched case-insensitively against the email of the user.
+    pub email: Option<String>,
+    pub status: Option<OrderStatus>,
+    pub created_since: Option<PrimitiveDateTime>,
+    pub created_until: Option<PrimitiveDateTime>,
+    pub txn_hash: Option<String>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Processor<SearchUserOrders> for DatabaseProcessor {
+    type Output = Vec<UserOrder>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:SearchUserOrders", err)]
+    async fn process(&self, input: SearchUserOrders) -> Result<Vec<UserOrder>, sqlx::Error> {
+        sqlx::query_as!(
+            UserOrder,
+            r#"
+            SELECT
+            o.id, o."user", o.production, o.total_amount, o.coupon_used, o.created_at,
+            o.order_status as "order_status: OrderStatus",
+            o.paid_at, o.delivered_at, o.arrived_at, o.cancelled_at, o.refund_requested_at,
+            o.refunded_at,
+            o.payment_method as "payment_method: PaymentMethod",
+            o.payment_method_info as "payment_method_info: OrderPaymentMethodInfo",
+            o.tracking_number, o.is_soft_deleted
+            FROM "shop"."user_order" o
+            JOIN "auth"."user_account" u ON u.id = o."user"
+            WHERE ($1::uuid IS NULL OR o."user" = $1)
+              AND ($2::text IS NULL OR LOWER(u.email) = LOWER($2))
+              AND ($3::shop.order_status IS NULL OR o.order_status = $3)
+              AND ($4::timestamp IS NULL OR o.created_at >= $4)
+              AND ($5::timestamp IS NULL OR o.created_at < $5)
+              AND ($6::text IS NULL OR o.payment_method_info ->> 'txn_hash' = $6)
+            ORDER BY o.created_at DESC, o.id DESC
+            LIMIT $7 OFFSET $8
+            "#,
+            input.user,
+            input.email,
+            input.status as Option<OrderStatus>,
+            input.created_since,
+            input.created_until,
+            input.txn_hash,
+            input.limit,
+            input.offset
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Mark an unpaid order as paid by an admin, e.g. after a payment made out of band.
+///
+/// Returns `None` if the order is not unpaid.
+pub struct AdminMarkOrderPaid {
+    pub id: Uuid,
+    pub admin_id: Uuid,
+    pub reason: Option<String>,
+}
+
+impl Processor<AdminMarkOrderPaid> for DatabaseProcessor {
+    type Output = Option<UserOrder>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL-Transaction:AdminMarkOrderPaid", err)]
+    async fn process(&self, input: AdminMarkOrderPaid) -> Result<Option<UserOrder>, sqlx::Error> {
+        let mut tx = self
+            .db()
+            .begin()
+            .instrument(info_span!("<Transaction Begin>"))
+            .await?;
+        let payment_method_info: OrderPaymentMethodInfo =
+            sqlx::types::Json(Some(PaymentMethodInfo::AdminOperation));
+        let order = sqlx::query_as!(
+            UserOrder,
+            r#"
+            UPDATE "shop"."user_order"
+            SET order_status = 'paid',
+                paid_at = NOW(),
+                payment_method = 'admin_operation',
+                payment_method_info = $2
+            WHERE id = $1 AND order_status = 'unpaid'
+            RETURNING
+            id, "user", production, total_amount, coupon_used, created_at,
+            order_status as "order_status: OrderStatus",
+            paid_at, delivered_at, arrived_at, cancelled_at, refund_requested_at, refunded_at,
+            payment_method as "payment_method: PaymentMethod",
+            payment_method_info as "payment_method_info: OrderPaymentMethodInfo",
+            tracking_number, is_soft_deleted
+            "#,
+            input.id,
+            payment_method_info as OrderPaymentMethodInfo
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(order) = &order {
+            record_status_change(
+                &mut tx,
+                order.id,
+                Some(OrderStatus::Unpaid),
+                OrderStatus::Paid,
+                Some(input.admin_id),
+                input.reason,
+            )
+            .await?;
+        }
+        tx.commit()
+            .instrument(info_span!("<Transaction Commit>"))
+            .await?;
+        Ok(order)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Cancel an unpaid or paid order by an admin, releasing the coupons it redeemed.
+///
+/// Returns `None` if the order is in any other status.
+pub struct AdminCancelOrder {
+    pub id: Uuid,
+    pub admin_id: Uuid,
+    pub reason: Option<String>,
+}
+
+impl Processor<AdminCancelOrder> for DatabaseProcessor {
+    type Output = Option<UserOrder>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL-Transaction:AdminCancelOrder", err)]
+    async fn process(&self, input: AdminCancelOrder) -> Result<Option<UserOrder>, sqlx::Error> {
+        let mut tx = self
+            .db()
+            .begin()
+            .instrument(info_span!("<Transaction Begin>"))
+            .await?;
+        let old_status = sqlx::query_scalar!(
+            r#"
+            SELECT order_status as "order_status: OrderStatus"
+            FROM "shop"."user_order"
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            input.id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(old_status @ (OrderStatus::Unpaid | OrderStatus::Paid)) = old_status else {
+            return Ok(None);
+        };
+        let order = sqlx::query_as!(
+            UserOrder,
+            r#"
+            UPDATE "shop"."user_order"
+            SET order_status = 'cancelled',
+                cancelled_at = NOW()
+            WHERE id = $1
+            RETURNING
+            id, "user", production, total_amount, coupon_used, created_at,
+            order_status as "order_status: OrderStatus",
+            paid_at, delivered_at, arrived_at, cancelled_at, refund_requested_at, refunded_at,
+            payment_method as "payment_method: PaymentMethod",
+            payment_method_info as "payment_method_info: OrderPaymentMethodInfo",
+            tracking_number, is_soft_deleted
+            "#,
+            input.id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        record_status_change(
+            &mut tx,
+            order.id,
+            Some(old_status),
+            OrderStatus::Cancelled,
+            Some(input.admin_id),
+            input.reason,
+        )
+        .await?;
+        sqlx::query!(
+            r#"
+            WITH released AS (
+                UPDATE "shop"."coupon_redemption"
+                SET released_at = NOW()
+                WHERE order_id = $1 AND released_at IS NULL
+                RETURNING coupon_id
+            )
+            UPDATE "shop"."coupon" c
+            SET used_count = c.used_count - 1
+            FROM released r
+            WHERE c.id = r.coupon_id
+            "#,
+            order.id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit()
+            .instrument(info_span!("<Transaction Commit>"))
+            .await?;
+        Ok(Some(order))
+    }
+}
@@ -0,0 +1,140 @@
+use crate::entities::order::OrderStatus;
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use time::PrimitiveDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct OrderStatusChange {
+    pub id: i64,
+    pub order_id: Uuid,
+    /// `None` for the creation of the order.
+    pub from_status: Option<OrderStatus>,
+    pub to_status: OrderStatus,
+    /// `None` if the change was made by the system.
+    pub changed_by: Option<Uuid>,
+    pub reason: Option<String>,
+    pub changed_at: PrimitiveDateTime,
+}
+
+/// Append a status change to the audit trail of an order.
+///
+/// Must be called in the same transaction that changes the status.
+pub(crate) async fn record_status_change(
+    conn: &mut sqlx::PgConnection,
+    order_id: Uuid,
+    from_status: Option<OrderStatus>,
+    to_status: OrderStatus,
+    changed_by: Option<Uuid>,
+    reason: Option<String>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO "shop"."order_status_history" (order_id, from_status, to_status, changed_by, reason)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        order_id,
+        from_status as Option<OrderStatus>,
+        to_status as OrderStatus,
+        changed_by,
+        reason
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListOrderStatusHistory {
+    pub order_id: Uuid,
+}
+
+impl Processor<ListOrderStatusHistory> for DatabaseProcessor {
+    type Output = Vec<OrderStatusChange>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListOrderStatusHistory", err)]
+    async fn process(
+        &self,
+        input: ListOrderStatusHistory,
+    ) -> Result<Vec<OrderStatusChange>, sqlx::Error> {
+        sqlx::query_as!(
+            OrderStatusChange,
+            r#"
+            SELECT id, order_id,
+            from_status as "from_status: OrderStatus",
+            to_status as "to_status: OrderStatus",
+            changed_by, reason, changed_at
+            FROM "shop"."order_status_history"
+            WHERE order_id = $1
+            ORDER BY changed_at, id
+            "#,
+            input.order_id
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct OrderNote {
+    pub id: i64,
+    pub order_id: Uuid,
+    /// `None` if the admin account has been deleted.
+    pub admin_id: Option<Uuid>,
+    pub content: String,
+    pub created_at: PrimitiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct AddOrderNote {
+    pub order_id: Uuid,
+    pub admin_id: Uuid,
+    pub content: String,
+}
+
+impl Processor<AddOrderNote> for DatabaseProcessor {
+    type Output = OrderNote;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:AddOrderNote", err)]
+    async fn process(&self, input: AddOrderNote) -> Result<OrderNote, sqlx::Error> {
+        sqlx::query_as!(
+            OrderNote,
+            r#"
+            INSERT INTO "shop"."order_note" (order_id, admin_id, content)
+            VALUES ($1, $2, $3)
+            RETURNING id, order_id, admin_id, content, created_at
+            "#,
+            input.order_id,
+            input.admin_id,
+            input.content
+        )
+        .fetch_one(self.db())
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListOrderNotes {
+    pub order_id: Uuid,
+}
+
+impl Processor<ListOrderNotes> for DatabaseProcessor {
+    type Output = Vec<OrderNote>;
+    type Error = sqlx::Error;
+    #[instrument(skip_all, name = "SQL:ListOrderNotes", err)]
+    async fn process(&self, input: ListOrderNotes) -> Result<Vec<OrderNote>, sqlx::Error> {
+        sqlx::query_as!(
+            OrderNote,
+            r#"
+            SELECT id, order_id, admin_id, content, created_at
+            FROM "shop"."order_note"
+            WHERE order_id = $1
+            ORDER BY created_at, id
+            "#,
+            input.order_id
+        )
+        .fetch_all(self.db())
+        .await
+    }
+}
@@ -5,6 +5,7 @@ pub struct DeliveryUpdateItem {
     pub status: DeliveryStatus,
     pub location: Option<String>,
     pub description: String,
+    /// Unix timestamp in seconds
     pub created_at: i64,
 }
 
@@ -21,6 +22,8 @@ pub struct DeliveryUpdateItem {
 )]
 pub struct DeliveryUpdate {
     pub order_id: uuid::Uuid,
+    /// Set when the carrier assigned (or changed) the tracking number.
+    pub tracking_number: Option<String>,
     pub items: Vec<DeliveryUpdateItem>,
 }
 
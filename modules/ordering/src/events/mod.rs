@@ -1,3 +1,3 @@
 pub mod delivery;
-mod order;
-mod payment;
+pub mod order;
+pub mod payment;
@@ -23,3 +23,5 @@ impl framework::rabbitmq::AmqpRouting for PaymentCallbackEvent {
         framework::rabbitmq::AmqpExchangeType::Direct;
     const ROUTING_KEY: &'static str = "payment_callback";
 }
+
+impl framework::rabbitmq::AmqpMessageSend for PaymentCallbackEvent {}
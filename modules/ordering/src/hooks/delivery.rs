@@ -0,0 +1,81 @@
+use crate::entities::delivery_tracking::{
+    DeliveryStatus, NewDeliveryTrackingItem, RecordDeliveryUpdate,
+};
+use crate::entities::order::{FindUserOrderById, OrderStatus};
+use crate::events::delivery::DeliveryUpdate;
+use crate::events::order::OrderStatusChangedEvent;
+use framework::rabbitmq::{AmqpMessageProcessor, AmqpMessageSend, AmqpPool};
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use tracing::instrument;
+
+/// Persist delivery updates from carriers and move orders along `Delivered -> Arrived`.
+#[derive(Clone)]
+pub struct DeliveryUpdateHook {
+    pub db: DatabaseProcessor,
+    pub mq: AmqpPool,
+}
+
+/// The order status implied by a delivery status, if any.
+fn implied_order_status(status: DeliveryStatus) -> Option<OrderStatus> {
+    match status {
+        DeliveryStatus::InTransit | DeliveryStatus::OutForDelivery => Some(OrderStatus::Delivered),
+        DeliveryStatus::Delivered => Some(OrderStatus::Arrived),
+        // cancelled or returned deliveries are handled by admins
+        DeliveryStatus::Cancelled | DeliveryStatus::Returned => None,
+    }
+}
+
+impl Processor<DeliveryUpdate> for DeliveryUpdateHook {
+    type Output = ();
+    type Error = framework::Error;
+    #[instrument(skip_all, err)]
+    async fn process(&self, input: DeliveryUpdate) -> Result<(), framework::Error> {
+        self.db
+            .process(FindUserOrderById { id: input.order_id })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        let items = input
+            .items
+            .into_iter()
+            .map(|item| {
+                let created_at = time::OffsetDateTime::from_unix_timestamp(item.created_at)
+                    .map_err(|_| framework::Error::InvalidInput)?;
+                Ok(NewDeliveryTrackingItem {
+                    status: item.status,
+                    location: item.location,
+                    description: item.description,
+                    created_at: time::PrimitiveDateTime::new(created_at.date(), created_at.time()),
+                })
+            })
+            .collect::<Result<Vec<_>, framework::Error>>()?;
+        // the latest tracking item decides the order status
+        let new_status = items
+            .iter()
+            .max_by_key(|item| item.created_at)
+            .and_then(|item| implied_order_status(item.status));
+        let status_changed = self
+            .db
+            .process(RecordDeliveryUpdate {
+                order_id: input.order_id,
+                items,
+                tracking_number: input.tracking_number,
+                new_status,
+            })
+            .await?;
+        if let (true, Some(new_status)) = (status_changed, new_status) {
+            OrderStatusChangedEvent {
+                order_id: input.order_id,
+                new_status,
+                changed_at: framework::now_time().assume_utc().unix_timestamp(),
+            }
+            .send(&self.mq)
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+impl AmqpMessageProcessor<DeliveryUpdate> for DeliveryUpdateHook {
+    const QUEUE: &'static str = "ordering.delivery_update";
+}
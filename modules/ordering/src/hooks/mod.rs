@@ -1 +1 @@
-
+pub mod delivery;
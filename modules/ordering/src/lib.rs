@@ -6,3 +6,6 @@
 pub mod entities;
 pub mod events;
 pub mod hooks;
+pub mod rpc;
+pub mod services;
+pub mod utils;
@@ -0,0 +1,210 @@
+use crate::rpc::conversions::{parse_coupon_restrictions, parse_optional_timestamp};
+use crate::services::coupon::{
+    CouponManageService, CreateNewCoupon, CreateNewCouponResult, DisableOrEnableCoupon,
+    GenerateSingleUseCoupons, ListCouponsWithStats, UpdateCoupon,
+};
+use admin::rpc::middleware::AdminId;
+use admin::utils::rbac::{AuthenticatedAdminOperation, AuthorizationAdapter, AuthorizationLayer};
+use phantom_shop_proto::v1::ordering::admin::{
+    Coupon as ProtoCoupon, CreateNewCouponRequest, CreateNewCouponResponse,
+    CreateNewCouponResult as ProtoCreateNewCouponResult, DisableOrEnableCouponRequest,
+    GenerateSingleUseCouponsRequest, GenerateSingleUseCouponsResponse, ListCouponsRequest,
+    ListCouponsResponse, UpdateCouponRequest,
+};
+use tonic::{Request, Response, Status};
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+pub struct CouponManageServiceImpl {
+    pub inner: CouponManageService,
+    pub authorization: AuthorizationAdapter,
+}
+
+impl CouponManageServiceImpl {
+    pub fn new(inner: CouponManageService, authorization: AuthorizationLayer) -> Self {
+        Self {
+            inner,
+            authorization: authorization.into_adapter(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl phantom_shop_proto::v1::ordering::admin::coupon_manage_service_server::CouponManageService
+    for CouponManageServiceImpl
+{
+    async fn create_new_coupon(
+        &self,
+        request: Request<CreateNewCouponRequest>,
+    ) -> Result<Response<CreateNewCouponResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let (limit_to_goods, first_order_only, new_user_within_days, stackable) =
+            parse_coupon_restrictions(req.restrictions)?;
+        let operation = CreateNewCoupon {
+            code: req.code,
+            set_active: req.set_active,
+            discount: req
+                .discount
+                .ok_or_else(|| Status::invalid_argument("Missing discount"))?
+                .try_into()?,
+            available_since: parse_optional_timestamp(req.available_since)?,
+            available_until: parse_optional_timestamp(req.available_until)?,
+            limit_to_category: req.limit_to_category,
+            limit_per_user: req.limit_per_user,
+            limit_total: req.limit_total,
+            limit_to_goods,
+            first_order_only,
+            new_user_within_days,
+            stackable,
+        };
+        let result = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        let response = match result {
+            CreateNewCouponResult::Success(coupon) => CreateNewCouponResponse {
+                result: ProtoCreateNewCouponResult::Success.into(),
+                coupon: Some(coupon.into()),
+            },
+            CreateNewCouponResult::DuplicatedCode => CreateNewCouponResponse {
+                result: ProtoCreateNewCouponResult::DuplicatedCode.into(),
+                coupon: None,
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn update_coupon(
+        &self,
+        request: Request<UpdateCouponRequest>,
+    ) -> Result<Response<ProtoCoupon>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let (limit_to_goods, first_order_only, new_user_within_days, stackable) =
+            parse_coupon_restrictions(req.restrictions)?;
+        let operation = UpdateCoupon {
+            id: req.id,
+            discount: req
+                .discount
+                .ok_or_else(|| Status::invalid_argument("Missing discount"))?
+                .try_into()?,
+            available_since: parse_optional_timestamp(req.available_since)?,
+            available_until: parse_optional_timestamp(req.available_until)?,
+            limit_to_category: req.limit_to_category,
+            limit_per_user: req.limit_per_user,
+            limit_total: req.limit_total,
+            limit_to_goods,
+            first_order_only,
+            new_user_within_days,
+            stackable,
+        };
+        let coupon = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(coupon.into()))
+    }
+
+    async fn disable_or_enable_coupon(
+        &self,
+        request: Request<DisableOrEnableCouponRequest>,
+    ) -> Result<Response<ProtoCoupon>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let coupon = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: DisableOrEnableCoupon {
+                        id: req.id,
+                        set_active: req.set_active,
+                    },
+                },
+            )
+            .await?;
+        Ok(Response::new(coupon.into()))
+    }
+
+    async fn list_coupons(
+        &self,
+        request: Request<ListCouponsRequest>,
+    ) -> Result<Response<ListCouponsResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let limit = match req.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            limit => limit.min(MAX_PAGE_SIZE),
+        };
+        let coupons = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: ListCouponsWithStats {
+                        set_active: req.set_active,
+                        limit: limit.into(),
+                        offset: req.offset.into(),
+                    },
+                },
+            )
+            .await?;
+        Ok(Response::new(ListCouponsResponse {
+            coupons: coupons.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn generate_single_use_coupons(
+        &self,
+        request: Request<GenerateSingleUseCouponsRequest>,
+    ) -> Result<Response<GenerateSingleUseCouponsResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let (limit_to_goods, first_order_only, new_user_within_days, stackable) =
+            parse_coupon_restrictions(req.restrictions)?;
+        let operation = GenerateSingleUseCoupons {
+            prefix: req.prefix,
+            count: req.count,
+            discount: req
+                .discount
+                .ok_or_else(|| Status::invalid_argument("Missing discount"))?
+                .try_into()?,
+            available_since: parse_optional_timestamp(req.available_since)?,
+            available_until: parse_optional_timestamp(req.available_until)?,
+            limit_to_category: req.limit_to_category,
+            limit_to_goods,
+            first_order_only,
+            new_user_within_days,
+            stackable,
+        };
+        let coupons = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(GenerateSingleUseCouponsResponse {
+            coupons: coupons.into_iter().map(Into::into).collect(),
+        }))
+    }
+}
@@ -0,0 +1,192 @@
+use crate::rpc::conversions::{
+    parse_optional_order_status, parse_optional_timestamp, parse_order_id,
+};
+use crate::services::order_manage::{
+    AddNoteToOrder, CancelOrder, ChangeOrderStatusResult, MarkOrderPaid, OrderManageService,
+    SearchOrders, ShowOrderForAdmin,
+};
+use admin::rpc::middleware::AdminId;
+use admin::utils::rbac::{AuthenticatedAdminOperation, AuthorizationAdapter, AuthorizationLayer};
+use phantom_shop_proto::v1::ordering::admin::{
+    AddOrderNoteRequest, AdminOrderDetail as ProtoAdminOrderDetail, ChangeOrderStatusRequest,
+    ChangeOrderStatusResponse, ChangeOrderStatusResult as ProtoChangeOrderStatusResult,
+    OrderNote as ProtoOrderNote, SearchOrdersRequest, SearchOrdersResponse, ShowOrderRequest,
+};
+use phantom_shop_proto::v1::ordering::common::OrderTracking as ProtoOrderTracking;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+pub struct OrderManageServiceImpl {
+    pub inner: OrderManageService,
+    pub authorization: AuthorizationAdapter,
+}
+
+impl OrderManageServiceImpl {
+    pub fn new(inner: OrderManageService, authorization: AuthorizationLayer) -> Self {
+        Self {
+            inner,
+            authorization: authorization.into_adapter(),
+        }
+    }
+}
+
+fn change_order_status_response(result: ChangeOrderStatusResult) -> ChangeOrderStatusResponse {
+    match result {
+        ChangeOrderStatusResult::Success(order) => ChangeOrderStatusResponse {
+            result: ProtoChangeOrderStatusResult::Success.into(),
+            order: Some((*order).into()),
+        },
+        ChangeOrderStatusResult::InvalidStatus => ChangeOrderStatusResponse {
+            result: ProtoChangeOrderStatusResult::InvalidStatus.into(),
+            order: None,
+        },
+    }
+}
+
+#[tonic::async_trait]
+impl phantom_shop_proto::v1::ordering::admin::order_manage_service_server::OrderManageService
+    for OrderManageServiceImpl
+{
+    async fn search_orders(
+        &self,
+        request: Request<SearchOrdersRequest>,
+    ) -> Result<Response<SearchOrdersResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let limit = match req.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            limit => limit.min(MAX_PAGE_SIZE),
+        };
+        let user_id = req
+            .user_id
+            .map(|id| Uuid::parse_str(&id))
+            .transpose()
+            .map_err(|_| Status::invalid_argument("Invalid user id"))?;
+        let operation = SearchOrders {
+            user_id,
+            email: req.email,
+            status: parse_optional_order_status(req.status)?,
+            created_since: parse_optional_timestamp(req.created_since)?,
+            created_until: parse_optional_timestamp(req.created_until)?,
+            txn_hash: req.txn_hash,
+            limit: limit.into(),
+            offset: req.offset.into(),
+        };
+        let orders = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation,
+                },
+            )
+            .await?;
+        Ok(Response::new(SearchOrdersResponse {
+            orders: orders.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn show_order(
+        &self,
+        request: Request<ShowOrderRequest>,
+    ) -> Result<Response<ProtoAdminOrderDetail>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let detail = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: ShowOrderForAdmin {
+                        order_id: parse_order_id(&req.order_id)?,
+                    },
+                },
+            )
+            .await?;
+        let tracking = ProtoOrderTracking {
+            tracking_number: detail.order.tracking_number.clone(),
+            items: detail.tracking.into_iter().map(Into::into).collect(),
+        };
+        Ok(Response::new(ProtoAdminOrderDetail {
+            order: Some(detail.order.into()),
+            coupons: detail.coupons.into_iter().map(Into::into).collect(),
+            tracking: Some(tracking),
+            status_history: detail.status_history.into_iter().map(Into::into).collect(),
+            notes: detail.notes.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn mark_order_paid(
+        &self,
+        request: Request<ChangeOrderStatusRequest>,
+    ) -> Result<Response<ChangeOrderStatusResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let result = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: MarkOrderPaid {
+                        admin_id: admin_id.into_inner(),
+                        order_id: parse_order_id(&req.order_id)?,
+                        reason: req.reason,
+                    },
+                },
+            )
+            .await?;
+        Ok(Response::new(change_order_status_response(result)))
+    }
+
+    async fn cancel_order(
+        &self,
+        request: Request<ChangeOrderStatusRequest>,
+    ) -> Result<Response<ChangeOrderStatusResponse>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let result = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: CancelOrder {
+                        admin_id: admin_id.into_inner(),
+                        order_id: parse_order_id(&req.order_id)?,
+                        reason: req.reason,
+                    },
+                },
+            )
+            .await?;
+        Ok(Response::new(change_order_status_response(result)))
+    }
+
+    async fn add_order_note(
+        &self,
+        request: Request<AddOrderNoteRequest>,
+    ) -> Result<Response<ProtoOrderNote>, Status> {
+        let (admin_id, req) = AdminId::from_request(request)?;
+        let note = self
+            .authorization
+            .wrap(
+                &self.inner,
+                AuthenticatedAdminOperation {
+                    admin_id: admin_id.into_inner(),
+                    client_ip: admin_id.client_ip(),
+                    operation: AddNoteToOrder {
+                        admin_id: admin_id.into_inner(),
+                        order_id: parse_order_id(&req.order_id)?,
+                        content: req.content,
+                    },
+                },
+            )
+            .await?;
+        Ok(Response::new(note.into()))
+    }
+}
@@ -0,0 +1,331 @@
+//! Type conversions between service/entity types and proto types.
+
+use crate::entities::coupon::{
+    AmountDiscount, BuyXGetYDiscount, Coupon, CouponRedemptionStats, Discount, RateDiscount,
+};
+use crate::entities::delivery_tracking::{DeliveryStatus, DeliveryTracking};
+use crate::entities::order::{OrderStatus, PaymentMethod, PaymentMethodInfo, UserOrder};
+use crate::entities::order_history::{OrderNote, OrderStatusChange};
+use crate::services::coupon::CouponWithStats;
+use crate::services::user_order::OrderTracking;
+use phantom_shop_proto::v1::common::Timestamp;
+use phantom_shop_proto::v1::ordering::admin as admin_proto;
+use phantom_shop_proto::v1::ordering::common as common_proto;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use uuid::Uuid;
+
+pub(crate) fn parse_decimal(value: &str) -> Result<Decimal, tonic::Status> {
+    Decimal::from_str(value).map_err(|_| tonic::Status::invalid_argument("Invalid decimal value"))
+}
+
+pub(crate) fn parse_order_id(value: &str) -> Result<Uuid, tonic::Status> {
+    Uuid::parse_str(value).map_err(|_| tonic::Status::invalid_argument("Invalid order id"))
+}
+
+/// Upper bound of `buy` and `get` of buy-X-get-Y discounts.
+const MAX_BUY_X_GET_Y_UNITS: u32 = 10_000;
+
+/// Restrictions of a coupon, `(limit_to_goods, first_order_only, new_user_within_days, stackable)`.
+pub(crate) type CouponRestrictions = (Option<Vec<i32>>, bool, Option<i32>, bool);
+
+pub(crate) fn parse_coupon_restrictions(
+    value: Option<admin_proto::CouponRestrictions>,
+) -> Result<CouponRestrictions, tonic::Status> {
+    let Some(value) = value else {
+        return Ok((None, false, None, false));
+    };
+    if value.new_user_within_days.is_some_and(|days| days <= 0) {
+        return Err(tonic::Status::invalid_argument(
+            "new_user_within_days must be positive",
+        ));
+    }
+    let limit_to_goods = (!value.goods_ids.is_empty()).then_some(value.goods_ids);
+    Ok((
+        limit_to_goods,
+        value.first_order_only,
+        value.new_user_within_days,
+        value.stackable,
+    ))
+}
+
+pub(crate) fn parse_optional_timestamp(
+    value: Option<Timestamp>,
+) -> Result<Option<time::PrimitiveDateTime>, tonic::Status> {
+    value
+        .map(time::PrimitiveDateTime::try_from)
+        .transpose()
+        .map_err(|_| tonic::Status::invalid_argument("Invalid timestamp"))
+}
+
+impl From<Discount> for admin_proto::Discount {
+    fn from(discount: Discount) -> Self {
+        let discount = match discount {
+            Discount::Rate(rate) => {
+                admin_proto::discount::Discount::Rate(admin_proto::RateDiscount {
+                    rate: rate.rate.to_string(),
+                    max_discount: rate.max_discount.map(|d| d.to_string()),
+                })
+            }
+            Discount::Amount(amount) => {
+                admin_proto::discount::Discount::Amount(admin_proto::AmountDiscount {
+                    min_amount: amount.min_amount.to_string(),
+                    discount: amount.discount.to_string(),
+                })
+            }
+            Discount::BuyXGetY(bogo) => {
+                admin_proto::discount::Discount::BuyXGetY(admin_proto::BuyXGetYDiscount {
+                    goods_id: bogo.goods_id,
+                    buy: bogo.buy,
+                    get: bogo.get,
+                })
+            }
+        };
+        Self {
+            discount: Some(discount),
+        }
+    }
+}
+
+impl TryFrom<admin_proto::Discount> for Discount {
+    type Error = tonic::Status;
+    fn try_from(discount: admin_proto::Discount) -> Result<Self, Self::Error> {
+        let discount = match discount.discount {
+            Some(admin_proto::discount::Discount::Rate(rate)) => Discount::Rate(RateDiscount {
+                rate: parse_decimal(&rate.rate)?,
+                max_discount: rate
+                    .max_discount
+                    .as_deref()
+                    .map(parse_decimal)
+                    .transpose()?,
+            }),
+            Some(admin_proto::discount::Discount::Amount(amount)) => {
+                Discount::Amount(AmountDiscount {
+                    min_amount: parse_decimal(&amount.min_amount)?,
+                    discount: parse_decimal(&amount.discount)?,
+                })
+            }
+            Some(admin_proto::discount::Discount::BuyXGetY(bogo)) => {
+                Discount::BuyXGetY(BuyXGetYDiscount {
+                    goods_id: bogo.goods_id,
+                    buy: bogo.buy,
+                    get: bogo.get,
+                })
+            }
+            None => return Err(tonic::Status::invalid_argument("Missing discount")),
+        };
+        let valid = match &discount {
+            Discount::Rate(rate) => {
+                rate.rate > Decimal::ZERO
+                    && rate.rate <= Decimal::ONE
+                    && rate.max_discount.is_none_or(|cap| cap > Decimal::ZERO)
+            }
+            Discount::Amount(amount) => {
+                amount.discount > Decimal::ZERO && amount.min_amount >= Decimal::ZERO
+            }
+            Discount::BuyXGetY(bogo) => {
+                bogo.buy <= MAX_BUY_X_GET_Y_UNITS
+                    && bogo.get > 0
+                    && bogo.get <= MAX_BUY_X_GET_Y_UNITS
+            }
+        };
+        if !valid {
+            return Err(tonic::Status::invalid_argument("Invalid discount"));
+        }
+        Ok(discount)
+    }
+}
+
+impl From<Coupon> for admin_proto::Coupon {
+    fn from(coupon: Coupon) -> Self {
+        Self {
+            id: coupon.id,
+            code: coupon.code,
+            set_active: coupon.set_active,
+            discount: Some(coupon.discount.0.into()),
+            available_since: coupon.available_since.map(Into::into),
+            available_until: coupon.available_until.map(Into::into),
+            limit_to_category: coupon.limit_to_category,
+            limit_per_user: coupon.limit_per_user,
+            limit_total: coupon.limit_total,
+            used_count: coupon.used_count,
+            created_at: Some(coupon.created_at.into()),
+            updated_at: Some(coupon.updated_at.into()),
+            restrictions: Some(admin_proto::CouponRestrictions {
+                goods_ids: coupon.limit_to_goods.unwrap_or_default(),
+                first_order_only: coupon.first_order_only,
+                new_user_within_days: coupon.new_user_within_days,
+                stackable: coupon.stackable,
+            }),
+        }
+    }
+}
+
+impl From<CouponRedemptionStats> for admin_proto::CouponRedemptionStats {
+    fn from(stats: CouponRedemptionStats) -> Self {
+        Self {
+            redemption_count: stats.redemption_count,
+            unique_users: stats.unique_users,
+            last_redeemed_at: stats.last_redeemed_at.map(Into::into),
+        }
+    }
+}
+
+impl From<CouponWithStats> for admin_proto::CouponWithStats {
+    fn from(item: CouponWithStats) -> Self {
+        Self {
+            coupon: Some(item.coupon.into()),
+            stats: Some(item.stats.into()),
+        }
+    }
+}
+
+impl From<DeliveryStatus> for common_proto::DeliveryStatus {
+    fn from(value: DeliveryStatus) -> Self {
+        match value {
+            DeliveryStatus::InTransit => Self::InTransit,
+            DeliveryStatus::OutForDelivery => Self::OutForDelivery,
+            DeliveryStatus::Delivered => Self::Delivered,
+            DeliveryStatus::Cancelled => Self::Cancelled,
+            DeliveryStatus::Returned => Self::Returned,
+        }
+    }
+}
+
+impl From<DeliveryTracking> for common_proto::DeliveryTrackingItem {
+    fn from(value: DeliveryTracking) -> Self {
+        Self {
+            status: common_proto::DeliveryStatus::from(value.status).into(),
+            location: value.location,
+            description: value.description,
+            created_at: Some(value.created_at.into()),
+        }
+    }
+}
+
+impl From<OrderTracking> for common_proto::OrderTracking {
+    fn from(value: OrderTracking) -> Self {
+        Self {
+            tracking_number: value.tracking_number,
+            items: value.items.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<OrderStatus> for common_proto::OrderStatus {
+    fn from(value: OrderStatus) -> Self {
+        match value {
+            OrderStatus::Unpaid => Self::Unpaid,
+            OrderStatus::Paid => Self::Paid,
+            OrderStatus::Delivered => Self::Delivered,
+            OrderStatus::Arrived => Self::Arrived,
+            OrderStatus::Cancelled => Self::Cancelled,
+            OrderStatus::Refunding => Self::Refunding,
+            OrderStatus::Refunded => Self::Refunded,
+        }
+    }
+}
+
+impl From<common_proto::OrderStatus> for OrderStatus {
+    fn from(value: common_proto::OrderStatus) -> Self {
+        match value {
+            common_proto::OrderStatus::Unpaid => Self::Unpaid,
+            common_proto::OrderStatus::Paid => Self::Paid,
+            common_proto::OrderStatus::Delivered => Self::Delivered,
+            common_proto::OrderStatus::Arrived => Self::Arrived,
+            common_proto::OrderStatus::Cancelled => Self::Cancelled,
+            common_proto::OrderStatus::Refunding => Self::Refunding,
+            common_proto::OrderStatus::Refunded => Self::Refunded,
+        }
+    }
+}
+
+pub(crate) fn parse_optional_order_status(
+    value: Option<i32>,
+) -> Result<Option<OrderStatus>, tonic::Status> {
+    value
+        .map(|status| {
+            common_proto::OrderStatus::try_from(status)
+                .map(Into::into)
+                .map_err(|_| tonic::Status::invalid_argument("Invalid order status"))
+        })
+        .transpose()
+}
+
+impl From<PaymentMethod> for common_proto::PaymentMethod {
+    fn from(value: PaymentMethod) -> Self {
+        match value {
+            PaymentMethod::StableCoin => Self::StableCoin,
+            PaymentMethod::CreditCard => Self::CreditCard,
+            PaymentMethod::PayPal => Self::PayPal,
+            PaymentMethod::AdminOperation => Self::AdminOperation,
+        }
+    }
+}
+
+impl From<PaymentMethodInfo> for common_proto::PaymentMethodInfo {
+    fn from(value: PaymentMethodInfo) -> Self {
+        let info = match value {
+            PaymentMethodInfo::StableCoin { txn_hash } => {
+                common_proto::payment_method_info::Info::StableCoin(
+                    common_proto::StableCoinPaymentInfo { txn_hash },
+                )
+            }
+            PaymentMethodInfo::AdminOperation => {
+                common_proto::payment_method_info::Info::AdminOperation(().into())
+            }
+        };
+        Self { info: Some(info) }
+    }
+}
+
+impl From<UserOrder> for common_proto::UserOrder {
+    fn from(value: UserOrder) -> Self {
+        Self {
+            id: value.id.to_string(),
+            user_id: value.user.to_string(),
+            production: value.production.to_string(),
+            total_amount: value.total_amount.to_string(),
+            coupon_used: value.coupon_used,
+            created_at: Some(value.created_at.into()),
+            order_status: common_proto::OrderStatus::from(value.order_status).into(),
+            paid_at: value.paid_at.map(Into::into),
+            delivered_at: value.delivered_at.map(Into::into),
+            arrived_at: value.arrived_at.map(Into::into),
+            cancelled_at: value.cancelled_at.map(Into::into),
+            refund_requested_at: value.refund_requested_at.map(Into::into),
+            refunded_at: value.refunded_at.map(Into::into),
+            payment_method: value
+                .payment_method
+                .map(|method| common_proto::PaymentMethod::from(method).into()),
+            payment_method_info: value.payment_method_info.0.map(Into::into),
+            tracking_number: value.tracking_number,
+            is_soft_deleted: value.is_soft_deleted,
+        }
+    }
+}
+
+impl From<OrderStatusChange> for admin_proto::OrderStatusChange {
+    fn from(value: OrderStatusChange) -> Self {
+        Self {
+            from_status: value
+                .from_status
+                .map(|status| common_proto::OrderStatus::from(status).into()),
+            to_status: common_proto::OrderStatus::from(value.to_status).into(),
+            changed_by: value.changed_by.map(|id| id.to_string()),
+            reason: value.reason,
+            changed_at: Some(value.changed_at.into()),
+        }
+    }
+}
+
+impl From<OrderNote> for admin_proto::OrderNote {
+    fn from(value: OrderNote) -> Self {
+        Self {
+            id: value.id,
+            admin_id: value.admin_id.map(|id| id.to_string()),
+            content: value.content,
+            created_at: Some(value.created_at.into()),
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod admin_coupon;
+pub mod admin_order;
+mod conversions;
+pub mod user_order;
@@ -0,0 +1,141 @@
+use crate::rpc::conversions::{parse_optional_order_status, parse_order_id};
+use crate::services::user_order::{
+    DeleteOrder, DeleteOrderResult, ListOrders, ShowOrderDetail, ShowOrderTracking,
+    UserOrderService,
+};
+use auth::rpc::middleware::UserId;
+use kanau::processor::Processor;
+use phantom_shop_proto::v1::ordering::common::{
+    OrderTracking as ProtoOrderTracking, UserOrder as ProtoUserOrder,
+};
+use phantom_shop_proto::v1::ordering::user::{
+    AppliedCoupon, DeleteOrderRequest, DeleteOrderResponse,
+    DeleteOrderResult as ProtoDeleteOrderResult, ListOrdersRequest, ListOrdersResponse,
+    OrderCursor, OrderDetail as ProtoOrderDetail, ShowOrderDetailRequest, ShowOrderTrackingRequest,
+};
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+pub struct UserOrderServiceImpl {
+    pub inner: UserOrderService,
+}
+
+impl UserOrderServiceImpl {
+    pub fn new(inner: UserOrderService) -> Self {
+        Self { inner }
+    }
+}
+
+fn parse_order_cursor(value: OrderCursor) -> Result<(time::PrimitiveDateTime, Uuid), Status> {
+    let created_at = value
+        .created_at
+        .ok_or_else(|| Status::invalid_argument("Missing cursor timestamp"))?
+        .try_into()
+        .map_err(|_| Status::invalid_argument("Invalid timestamp"))?;
+    Ok((created_at, parse_order_id(&value.order_id)?))
+}
+
+#[tonic::async_trait]
+impl phantom_shop_proto::v1::ordering::user::user_order_service_server::UserOrderService
+    for UserOrderServiceImpl
+{
+    async fn list_orders(
+        &self,
+        request: Request<ListOrdersRequest>,
+    ) -> Result<Response<ListOrdersResponse>, Status> {
+        let (user_id, req) = UserId::from_request(request)?;
+        let limit = match req.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            limit => limit.min(MAX_PAGE_SIZE),
+        };
+        let orders = self
+            .inner
+            .process(ListOrders {
+                user_id: user_id.into_inner(),
+                status: parse_optional_order_status(req.status)?,
+                before: req.after.map(parse_order_cursor).transpose()?,
+                limit: limit.into(),
+            })
+            .await?;
+        let next = match orders.last() {
+            Some(last) if orders.len() == limit as usize => Some(OrderCursor {
+                created_at: Some(last.created_at.into()),
+                order_id: last.id.to_string(),
+            }),
+            _ => None,
+        };
+        Ok(Response::new(ListOrdersResponse {
+            orders: orders.into_iter().map(Into::into).collect(),
+            next,
+        }))
+    }
+
+    async fn show_order_detail(
+        &self,
+        request: Request<ShowOrderDetailRequest>,
+    ) -> Result<Response<ProtoOrderDetail>, Status> {
+        let (user_id, req) = UserId::from_request(request)?;
+        let detail = self
+            .inner
+            .process(ShowOrderDetail {
+                user_id: user_id.into_inner(),
+                order_id: parse_order_id(&req.order_id)?,
+            })
+            .await?;
+        let tracking = ProtoOrderTracking {
+            tracking_number: detail.order.tracking_number.clone(),
+            items: detail.tracking.into_iter().map(Into::into).collect(),
+        };
+        Ok(Response::new(ProtoOrderDetail {
+            order: Some(ProtoUserOrder::from(detail.order)),
+            coupons: detail
+                .coupons
+                .into_iter()
+                .map(|coupon| AppliedCoupon {
+                    id: coupon.id,
+                    code: coupon.code,
+                })
+                .collect(),
+            tracking: Some(tracking),
+        }))
+    }
+
+    async fn show_order_tracking(
+        &self,
+        request: Request<ShowOrderTrackingRequest>,
+    ) -> Result<Response<ProtoOrderTracking>, Status> {
+        let (user_id, req) = UserId::from_request(request)?;
+        let tracking = self
+            .inner
+            .process(ShowOrderTracking {
+                user_id: user_id.into_inner(),
+                order_id: parse_order_id(&req.order_id)?,
+            })
+            .await?;
+        Ok(Response::new(tracking.into()))
+    }
+
+    async fn delete_order(
+        &self,
+        request: Request<DeleteOrderRequest>,
+    ) -> Result<Response<DeleteOrderResponse>, Status> {
+        let (user_id, req) = UserId::from_request(request)?;
+        let result = self
+            .inner
+            .process(DeleteOrder {
+                user_id: user_id.into_inner(),
+                order_id: parse_order_id(&req.order_id)?,
+            })
+            .await?;
+        let result = match result {
+            DeleteOrderResult::Success => ProtoDeleteOrderResult::Success,
+            DeleteOrderResult::OrderNotFinished => ProtoDeleteOrderResult::OrderNotFinished,
+        };
+        Ok(Response::new(DeleteOrderResponse {
+            result: result.into(),
+        }))
+    }
+}
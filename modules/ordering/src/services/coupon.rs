@@ -0,0 +1,291 @@
+use crate::entities::coupon::{
+    Coupon, CouponRedemptionStats, CreateSingleUseCoupons, Discount, FindCouponById,
+    FindCouponRedemptionStats, ListCoupons,
+};
+use admin::entities::admin_account::AdminRole;
+use admin::rbac;
+use admin::utils::rbac::Permission;
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use rand::Rng;
+use std::collections::HashSet;
+
+const COUPON_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const SINGLE_USE_COUPON_CODE_LENGTH: usize = 12;
+const MAX_SINGLE_USE_COUPONS_PER_BATCH: u32 = 1000;
+const MAX_SINGLE_USE_COUPON_GENERATION_ATTEMPTS: usize = 3;
+
+fn generate_coupon_code(prefix: &str) -> String {
+    let mut rng = rand::rng();
+    let mut code = String::with_capacity(prefix.len() + SINGLE_USE_COUPON_CODE_LENGTH);
+    code.push_str(prefix);
+    for _ in 0..SINGLE_USE_COUPON_CODE_LENGTH {
+        let index = rng.random_range(0..COUPON_CODE_ALPHABET.len());
+        code.push(COUPON_CODE_ALPHABET[index] as char);
+    }
+    code
+}
+
+/// Generate `count` distinct codes.
+fn generate_coupon_codes(prefix: &str, count: usize) -> Vec<String> {
+    let mut codes = HashSet::with_capacity(count);
+    while codes.len() < count {
+        codes.insert(generate_coupon_code(prefix));
+    }
+    codes.into_iter().collect()
+}
+
+#[derive(Clone)]
+pub struct CouponManageService {
+    pub db: DatabaseProcessor,
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateNewCoupon {
+    pub code: String,
+    pub set_active: bool,
+    pub discount: Discount,
+    pub available_since: Option<time::PrimitiveDateTime>,
+    pub available_until: Option<time::PrimitiveDateTime>,
+    pub limit_to_category: Option<i32>,
+    pub limit_per_user: Option<i32>,
+    pub limit_total: Option<i32>,
+    pub limit_to_goods: Option<Vec<i32>>,
+    pub first_order_only: bool,
+    pub new_user_within_days: Option<i32>,
+    pub stackable: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum CreateNewCouponResult {
+    Success(Coupon),
+    DuplicatedCode,
+}
+
+impl Processor<CreateNewCoupon> for CouponManageService {
+    type Output = CreateNewCouponResult;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: CreateNewCoupon,
+    ) -> Result<CreateNewCouponResult, framework::Error> {
+        let coupon = self
+            .db
+            .process(crate::entities::coupon::CreateNewCoupon {
+                code: input.code,
+                set_active: input.set_active,
+                discount: input.discount,
+                available_since: input.available_since,
+                available_until: input.available_until,
+                limit_to_category: input.limit_to_category,
+                limit_per_user: input.limit_per_user,
+                limit_total: input.limit_total,
+                limit_to_goods: input.limit_to_goods,
+                first_order_only: input.first_order_only,
+                new_user_within_days: input.new_user_within_days,
+                stackable: input.stackable,
+            })
+            .await?;
+        Ok(match coupon {
+            Some(coupon) => CreateNewCouponResult::Success(coupon),
+            None => CreateNewCouponResult::DuplicatedCode,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateCoupon {
+    pub id: i32,
+    pub discount: Discount,
+    pub available_since: Option<time::PrimitiveDateTime>,
+    pub available_until: Option<time::PrimitiveDateTime>,
+    pub limit_to_category: Option<i32>,
+    pub limit_per_user: Option<i32>,
+    pub limit_total: Option<i32>,
+    pub limit_to_goods: Option<Vec<i32>>,
+    pub first_order_only: bool,
+    pub new_user_within_days: Option<i32>,
+    pub stackable: bool,
+}
+
+impl Processor<UpdateCoupon> for CouponManageService {
+    type Output = Coupon;
+    type Error = framework::Error;
+    async fn process(&self, input: UpdateCoupon) -> Result<Coupon, framework::Error> {
+        self.db
+            .process(FindCouponById { id: input.id })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        self.db
+            .process(crate::entities::coupon::UpdateCoupon {
+                id: input.id,
+                discount: input.discount,
+                available_since: input.available_since,
+                available_until: input.available_until,
+                limit_to_category: input.limit_to_category,
+                limit_per_user: input.limit_per_user,
+                limit_total: input.limit_total,
+                limit_to_goods: input.limit_to_goods,
+                first_order_only: input.first_order_only,
+                new_user_within_days: input.new_user_within_days,
+                stackable: input.stackable,
+            })
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DisableOrEnableCoupon {
+    pub id: i32,
+    pub set_active: bool,
+}
+
+impl Processor<DisableOrEnableCoupon> for CouponManageService {
+    type Output = Coupon;
+    type Error = framework::Error;
+    async fn process(&self, input: DisableOrEnableCoupon) -> Result<Coupon, framework::Error> {
+        self.db
+            .process(FindCouponById { id: input.id })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        self.db
+            .process(crate::entities::coupon::DisableOrEnableCoupon {
+                id: input.id,
+                set_active: input.set_active,
+            })
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListCouponsWithStats {
+    pub set_active: Option<bool>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct CouponWithStats {
+    pub coupon: Coupon,
+    pub stats: CouponRedemptionStats,
+}
+
+impl Processor<ListCouponsWithStats> for CouponManageService {
+    type Output = Vec<CouponWithStats>;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: ListCouponsWithStats,
+    ) -> Result<Vec<CouponWithStats>, framework::Error> {
+        let coupons = self
+            .db
+            .process(ListCoupons {
+                set_active: input.set_active,
+                limit: input.limit,
+                offset: input.offset,
+            })
+            .await?;
+        let stats = self
+            .db
+            .process(FindCouponRedemptionStats {
+                coupon_ids: coupons.iter().map(|c| c.id).collect(),
+            })
+            .await?;
+        Ok(coupons
+            .into_iter()
+            .map(|coupon| {
+                let stats = stats
+                    .iter()
+                    .find(|s| s.coupon_id == coupon.id)
+                    .cloned()
+                    .unwrap_or(CouponRedemptionStats {
+                        coupon_id: coupon.id,
+                        redemption_count: 0,
+                        unique_users: 0,
+                        last_redeemed_at: None,
+                    });
+                CouponWithStats { coupon, stats }
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Generate a batch of random coupon codes that can each be redeemed once.
+pub struct GenerateSingleUseCoupons {
+    pub prefix: String,
+    pub count: u32,
+    pub discount: Discount,
+    pub available_since: Option<time::PrimitiveDateTime>,
+    pub available_until: Option<time::PrimitiveDateTime>,
+    pub limit_to_category: Option<i32>,
+    pub limit_to_goods: Option<Vec<i32>>,
+    pub first_order_only: bool,
+    pub new_user_within_days: Option<i32>,
+    pub stackable: bool,
+}
+
+impl Processor<GenerateSingleUseCoupons> for CouponManageService {
+    type Output = Vec<Coupon>;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: GenerateSingleUseCoupons,
+    ) -> Result<Vec<Coupon>, framework::Error> {
+        if input.count == 0 || input.count > MAX_SINGLE_USE_COUPONS_PER_BATCH {
+            return Err(framework::Error::InvalidInput);
+        }
+        // random codes may collide with existing ones, in which case the batch is regenerated
+        for _ in 0..MAX_SINGLE_USE_COUPON_GENERATION_ATTEMPTS {
+            let result = self
+                .db
+                .process(CreateSingleUseCoupons {
+                    codes: generate_coupon_codes(&input.prefix, input.count as usize),
+                    discount: input.discount,
+                    available_since: input.available_since,
+                    available_until: input.available_until,
+                    limit_to_category: input.limit_to_category,
+                    limit_to_goods: input.limit_to_goods.clone(),
+                    first_order_only: input.first_order_only,
+                    new_user_within_days: input.new_user_within_days,
+                    stackable: input.stackable,
+                })
+                .await;
+            match result {
+                Ok(coupons) => return Ok(coupons),
+                Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                    tracing::warn!("Generated coupon codes collided with existing ones, retrying");
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(framework::Error::BusinessPanic(anyhow::anyhow!(
+            "Failed to generate unique coupon codes"
+        )))
+    }
+}
+
+rbac! { CouponManageService : CreateNewCoupon => CreateNewCouponResult | [AdminRole::Owner, AdminRole::Moderator], Permission::CatalogWrite }
+rbac! { CouponManageService : UpdateCoupon => Coupon | [AdminRole::Owner, AdminRole::Moderator], Permission::CatalogWrite }
+rbac! { CouponManageService : DisableOrEnableCoupon => Coupon | [AdminRole::Owner, AdminRole::Moderator], Permission::CatalogWrite }
+rbac! { CouponManageService : ListCouponsWithStats => Vec<CouponWithStats> | [AdminRole::Owner, AdminRole::Moderator], Permission::CatalogRead }
+rbac! { CouponManageService : GenerateSingleUseCoupons => Vec<Coupon> | [AdminRole::Owner], Permission::CatalogWrite }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_coupon_codes_are_unique() {
+        let codes = generate_coupon_codes("SALE-", 200);
+        assert_eq!(codes.len(), 200);
+        assert_eq!(codes.iter().collect::<HashSet<_>>().len(), 200);
+        for code in &codes {
+            let random = code.strip_prefix("SALE-").unwrap_or_default();
+            assert_eq!(random.len(), SINGLE_USE_COUPON_CODE_LENGTH);
+            assert!(random.bytes().all(|c| COUPON_CODE_ALPHABET.contains(&c)));
+        }
+    }
+}
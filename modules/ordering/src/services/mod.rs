@@ -0,0 +1,3 @@
+pub mod coupon;
+pub mod order_manage;
+pub mod user_order;
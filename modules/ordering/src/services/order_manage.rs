@@ -0,0 +1,250 @@
+use crate::entities::coupon::{Coupon, ListOrderCoupons};
+use crate::entities::delivery_tracking::{DeliveryTracking, ListDeliveryTrackingByOrder};
+use crate::entities::order::{
+    AdminCancelOrder, AdminMarkOrderPaid, FindUserOrderById, OrderStatus, SearchUserOrders,
+    UserOrder,
+};
+use crate::entities::order_history::{
+    AddOrderNote, ListOrderNotes, ListOrderStatusHistory, OrderNote, OrderStatusChange,
+};
+use crate::events::order::{OrderPaidEvent, OrderStatusChangedEvent};
+use admin::entities::admin_account::AdminRole;
+use admin::rbac;
+use admin::utils::rbac::Permission;
+use framework::rabbitmq::{AmqpMessageSend, AmqpPool};
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use uuid::Uuid;
+
+const MAX_ORDER_NOTE_LENGTH: usize = 4096;
+
+#[derive(Clone)]
+pub struct OrderManageService {
+    pub db: DatabaseProcessor,
+    pub mq: AmqpPool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchOrders {
+    pub user_id: Option<Uuid>,
+    pub email: Option<String>,
+    pub status: Option<OrderStatus>,
+    pub created_since: Option<time::PrimitiveDateTime>,
+    pub created_until: Option<time::PrimitiveDateTime>,
+    pub txn_hash: Option<String>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Processor<SearchOrders> for OrderManageService {
+    type Output = Vec<UserOrder>;
+    type Error = framework::Error;
+    async fn process(&self, input: SearchOrders) -> Result<Vec<UserOrder>, framework::Error> {
+        self.db
+            .process(SearchUserOrders {
+                user: input.user_id,
+                email: input.email,
+                status: input.status,
+                created_since: input.created_since,
+                created_until: input.created_until,
+                txn_hash: input.txn_hash,
+                limit: input.limit,
+                offset: input.offset,
+            })
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShowOrderForAdmin {
+    pub order_id: Uuid,
+}
+
+#[derive(Debug, Clone)]
+pub struct AdminOrderDetail {
+    pub order: UserOrder,
+    /// In the order they were applied
+    pub coupons: Vec<Coupon>,
+    pub tracking: Vec<DeliveryTracking>,
+    pub status_history: Vec<OrderStatusChange>,
+    pub notes: Vec<OrderNote>,
+}
+
+impl Processor<ShowOrderForAdmin> for OrderManageService {
+    type Output = AdminOrderDetail;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: ShowOrderForAdmin,
+    ) -> Result<AdminOrderDetail, framework::Error> {
+        let order = self
+            .db
+            .process(FindUserOrderById { id: input.order_id })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        let coupons = self
+            .db
+            .process(ListOrderCoupons { order_id: order.id })
+            .await?;
+        let tracking = self
+            .db
+            .process(ListDeliveryTrackingByOrder { order_id: order.id })
+            .await?;
+        let status_history = self
+            .db
+            .process(ListOrderStatusHistory { order_id: order.id })
+            .await?;
+        let notes = self
+            .db
+            .process(ListOrderNotes { order_id: order.id })
+            .await?;
+        Ok(AdminOrderDetail {
+            order,
+            coupons,
+            tracking,
+            status_history,
+            notes,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Mark an unpaid order as paid with [`crate::entities::order::PaymentMethod::AdminOperation`].
+pub struct MarkOrderPaid {
+    pub admin_id: Uuid,
+    pub order_id: Uuid,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ChangeOrderStatusResult {
+    Success(Box<UserOrder>),
+    /// The order is not in a status the change can be made from.
+    InvalidStatus,
+}
+
+impl Processor<MarkOrderPaid> for OrderManageService {
+    type Output = ChangeOrderStatusResult;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: MarkOrderPaid,
+    ) -> Result<ChangeOrderStatusResult, framework::Error> {
+        self.db
+            .process(FindUserOrderById { id: input.order_id })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        let Some(order) = self
+            .db
+            .process(AdminMarkOrderPaid {
+                id: input.order_id,
+                admin_id: input.admin_id,
+                reason: input.reason,
+            })
+            .await?
+        else {
+            return Ok(ChangeOrderStatusResult::InvalidStatus);
+        };
+        let paid_at = order
+            .paid_at
+            .unwrap_or_else(framework::now_time)
+            .assume_utc()
+            .unix_timestamp();
+        OrderPaidEvent {
+            order_id: order.id,
+            paid_at,
+        }
+        .send(&self.mq)
+        .await?;
+        OrderStatusChangedEvent {
+            order_id: order.id,
+            new_status: order.order_status,
+            changed_at: paid_at,
+        }
+        .send(&self.mq)
+        .await?;
+        Ok(ChangeOrderStatusResult::Success(Box::new(order)))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Cancel an unpaid or paid order. Refunds of paid orders are handled out of band.
+pub struct CancelOrder {
+    pub admin_id: Uuid,
+    pub order_id: Uuid,
+    pub reason: Option<String>,
+}
+
+impl Processor<CancelOrder> for OrderManageService {
+    type Output = ChangeOrderStatusResult;
+    type Error = framework::Error;
+    async fn process(
+        &self,
+        input: CancelOrder,
+    ) -> Result<ChangeOrderStatusResult, framework::Error> {
+        self.db
+            .process(FindUserOrderById { id: input.order_id })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        let Some(order) = self
+            .db
+            .process(AdminCancelOrder {
+                id: input.order_id,
+                admin_id: input.admin_id,
+                reason: input.reason,
+            })
+            .await?
+        else {
+            return Ok(ChangeOrderStatusResult::InvalidStatus);
+        };
+        OrderStatusChangedEvent {
+            order_id: order.id,
+            new_status: order.order_status,
+            changed_at: order
+                .cancelled_at
+                .unwrap_or_else(framework::now_time)
+                .assume_utc()
+                .unix_timestamp(),
+        }
+        .send(&self.mq)
+        .await?;
+        Ok(ChangeOrderStatusResult::Success(Box::new(order)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AddNoteToOrder {
+    pub admin_id: Uuid,
+    pub order_id: Uuid,
+    pub content: String,
+}
+
+impl Processor<AddNoteToOrder> for OrderManageService {
+    type Output = OrderNote;
+    type Error = framework::Error;
+    async fn process(&self, input: AddNoteToOrder) -> Result<OrderNote, framework::Error> {
+        let content = input.content.trim();
+        if content.is_empty() || content.len() > MAX_ORDER_NOTE_LENGTH {
+            return Err(framework::Error::InvalidInput);
+        }
+        self.db
+            .process(FindUserOrderById { id: input.order_id })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        self.db
+            .process(AddOrderNote {
+                order_id: input.order_id,
+                admin_id: input.admin_id,
+                content: content.to_owned(),
+            })
+            .await
+            .map_err(Into::into)
+    }
+}
+
+rbac! { OrderManageService : SearchOrders => Vec<UserOrder> | [AdminRole::Owner, AdminRole::Moderator], Permission::OrdersRead }
+rbac! { OrderManageService : ShowOrderForAdmin => AdminOrderDetail | [AdminRole::Owner, AdminRole::Moderator], Permission::OrdersRead }
+rbac! { OrderManageService : MarkOrderPaid => ChangeOrderStatusResult | [AdminRole::Owner], Permission::OrdersWrite }
+rbac! { OrderManageService : CancelOrder => ChangeOrderStatusResult | [AdminRole::Owner, AdminRole::Moderator], Permission::OrdersRefund }
+rbac! { OrderManageService : AddNoteToOrder => OrderNote | [AdminRole::Owner, AdminRole::Moderator], Permission::OrdersWrite }
@@ -0,0 +1,292 @@
+use crate::entities::category::ListCategorySubtreeIds;
+use crate::entities::coupon::{Coupon, FindCouponByCode, ListOrderCoupons};
+use crate::entities::delivery_tracking::{DeliveryTracking, ListDeliveryTrackingByOrder};
+use crate::entities::goods::FindGoodsByIds;
+use crate::entities::order::{
+    FindUserOrderById, FindUserOrderingProfile, ListUserOrders, OrderStatus, PlaceUserOrder,
+    PlaceUserOrderResult, SoftDeleteUserOrder, UserOrder,
+};
+use crate::utils::price_engine::{CouponRejection, PriceLine, PricingContext, quote};
+use framework::sqlx::DatabaseProcessor;
+use kanau::processor::Processor;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use uuid::Uuid;
+
+const MAX_ORDER_PAGE_SIZE: i64 = 100;
+const MAX_COUPONS_PER_ORDER: usize = 5;
+
+#[derive(Clone)]
+pub struct UserOrderService {
+    pub db: DatabaseProcessor,
+}
+
+impl UserOrderService {
+    /// Find an order visible to the user, soft deleted ones are treated as missing.
+    async fn find_own_order(
+        &self,
+        user_id: Uuid,
+        order_id: Uuid,
+    ) -> Result<UserOrder, framework::Error> {
+        self.db
+            .process(FindUserOrderById { id: order_id })
+            .await?
+            .filter(|order| order.user == user_id && !order.is_soft_deleted)
+            .ok_or(framework::Error::NotFound)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OrderItem {
+    pub goods_id: i32,
+    pub quantity: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaceOrder {
+    pub user_id: Uuid,
+    pub production: Uuid,
+    pub items: Vec<OrderItem>,
+    /// Distinct codes, several only if all of them are stackable
+    pub coupon_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PlaceOrderResult {
+    Success(Box<UserOrder>),
+    GoodsNotAvailable,
+    CouponNotFound,
+    CouponRejected(CouponRejection),
+    CouponUsageLimitReached,
+    CouponUserLimitReached,
+}
+
+impl Processor<PlaceOrder> for UserOrderService {
+    type Output = PlaceOrderResult;
+    type Error = framework::Error;
+    async fn process(&self, input: PlaceOrder) -> Result<PlaceOrderResult, framework::Error> {
+        if input.items.is_empty() || input.items.iter().any(|item| item.quantity == 0) {
+            return Err(framework::Error::InvalidInput);
+        }
+        let mut codes = input.coupon_codes.iter().collect::<Vec<_>>();
+        codes.sort_unstable();
+        codes.dedup();
+        if codes.len() != input.coupon_codes.len() || codes.len() > MAX_COUPONS_PER_ORDER {
+            return Err(framework::Error::InvalidInput);
+        }
+        let goods = self
+            .db
+            .process(FindGoodsByIds {
+                ids: input.items.iter().map(|item| item.goods_id).collect(),
+            })
+            .await?;
+        let mut lines = Vec::with_capacity(input.items.len());
+        for item in &input.items {
+            let Some(goods) = goods
+                .iter()
+                .find(|goods| goods.id == item.goods_id && goods.on_sale)
+            else {
+                return Ok(PlaceOrderResult::GoodsNotAvailable);
+            };
+            lines.push(PriceLine {
+                goods_id: goods.id,
+                category_id: goods.category_id,
+                unit_price: goods.price,
+                quantity: item.quantity,
+            });
+        }
+
+        let mut coupons = Vec::with_capacity(input.coupon_codes.len());
+        for code in input.coupon_codes {
+            match self.db.process(FindCouponByCode { code }).await? {
+                Some(coupon) => coupons.push(coupon),
+                None => return Ok(PlaceOrderResult::CouponNotFound),
+            }
+        }
+        let user = self
+            .db
+            .process(FindUserOrderingProfile {
+                user: input.user_id,
+            })
+            .await?
+            .ok_or(framework::Error::NotFound)?;
+        let mut category_subtrees = HashMap::new();
+        for category_id in coupons.iter().filter_map(|c| c.limit_to_category) {
+            if let Entry::Vacant(entry) = category_subtrees.entry(category_id) {
+                entry.insert(
+                    self.db
+                        .process(ListCategorySubtreeIds { category_id })
+                        .await?,
+                );
+            }
+        }
+        let context = PricingContext {
+            now: framework::now_time(),
+            user,
+            category_subtrees,
+        };
+        let price = match quote(&lines, &coupons, &context) {
+            Ok(price) => price,
+            Err(rejection) => return Ok(PlaceOrderResult::CouponRejected(rejection)),
+        };
+
+        let result = self
+            .db
+            .process(PlaceUserOrder {
+                user: input.user_id,
+                production: input.production,
+                total_amount: price.total,
+                coupons_used: coupons.iter().map(|c| c.id).collect(),
+            })
+            .await?;
+        Ok(match result {
+            PlaceUserOrderResult::Placed(order) => PlaceOrderResult::Success(order),
+            PlaceUserOrderResult::CouponNotAvailable => {
+                PlaceOrderResult::CouponRejected(CouponRejection::Inactive)
+            }
+            PlaceUserOrderResult::CouponUsageLimitReached => {
+                PlaceOrderResult::CouponUsageLimitReached
+            }
+            PlaceUserOrderResult::CouponUserLimitReached => {
+                PlaceOrderResult::CouponUserLimitReached
+            }
+            PlaceUserOrderResult::NotFirstOrder => {
+                PlaceOrderResult::CouponRejected(CouponRejection::NotFirstOrder)
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShowOrderTracking {
+    pub user_id: Uuid,
+    pub order_id: Uuid,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderTracking {
+    pub tracking_number: Option<String>,
+    /// Oldest first.
+    pub items: Vec<DeliveryTracking>,
+}
+
+impl Processor<ShowOrderTracking> for UserOrderService {
+    type Output = OrderTracking;
+    type Error = framework::Error;
+    async fn process(&self, input: ShowOrderTracking) -> Result<OrderTracking, framework::Error> {
+        let order = self
+            .db
+            .process(FindUserOrderById { id: input.order_id })
+            .await?
+            .filter(|order| order.user == input.user_id && !order.is_soft_deleted)
+            .ok_or(framework::Error::NotFound)?;
+        let items = self
+            .db
+            .process(ListDeliveryTrackingByOrder { order_id: order.id })
+            .await?;
+        Ok(OrderTracking {
+            tracking_number: order.tracking_number,
+            items,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListOrders {
+    pub user_id: Uuid,
+    pub status: Option<OrderStatus>,
+    /// `(created_at, id)` of the last order of the previous page.
+    pub before: Option<(time::PrimitiveDateTime, Uuid)>,
+    pub limit: i64,
+}
+
+impl Processor<ListOrders> for UserOrderService {
+    type Output = Vec<UserOrder>;
+    type Error = framework::Error;
+    async fn process(&self, input: ListOrders) -> Result<Vec<UserOrder>, framework::Error> {
+        if input.limit <= 0 || input.limit > MAX_ORDER_PAGE_SIZE {
+            return Err(framework::Error::InvalidInput);
+        }
+        self.db
+            .process(ListUserOrders {
+                user: input.user_id,
+                status: input.status,
+                before: input.before,
+                limit: input.limit,
+            })
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShowOrderDetail {
+    pub user_id: Uuid,
+    pub order_id: Uuid,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderDetail {
+    pub order: UserOrder,
+    /// In the order they were applied
+    pub coupons: Vec<Coupon>,
+    /// Oldest first.
+    pub tracking: Vec<DeliveryTracking>,
+}
+
+impl Processor<ShowOrderDetail> for UserOrderService {
+    type Output = OrderDetail;
+    type Error = framework::Error;
+    async fn process(&self, input: ShowOrderDetail) -> Result<OrderDetail, framework::Error> {
+        let order = self.find_own_order(input.user_id, input.order_id).await?;
+        let coupons = self
+            .db
+            .process(ListOrderCoupons { order_id: order.id })
+            .await?;
+        let tracking = self
+            .db
+            .process(ListDeliveryTrackingByOrder { order_id: order.id })
+            .await?;
+        Ok(OrderDetail {
+            order,
+            coupons,
+            tracking,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DeleteOrder {
+    pub user_id: Uuid,
+    pub order_id: Uuid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteOrderResult {
+    Success,
+    OrderNotFinished,
+}
+
+impl Processor<DeleteOrder> for UserOrderService {
+    type Output = DeleteOrderResult;
+    type Error = framework::Error;
+    async fn process(&self, input: DeleteOrder) -> Result<DeleteOrderResult, framework::Error> {
+        let order = self.find_own_order(input.user_id, input.order_id).await?;
+        if !order.order_status.is_finished() {
+            return Ok(DeleteOrderResult::OrderNotFinished);
+        }
+        let deleted = self
+            .db
+            .process(SoftDeleteUserOrder {
+                id: order.id,
+                user: input.user_id,
+            })
+            .await?;
+        // the status may have changed concurrently
+        Ok(if deleted {
+            DeleteOrderResult::Success
+        } else {
+            DeleteOrderResult::OrderNotFinished
+        })
+    }
+}
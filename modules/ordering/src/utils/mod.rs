@@ -0,0 +1 @@
+pub mod price_engine;
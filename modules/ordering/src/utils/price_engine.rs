@@ -0,0 +1,512 @@
+//! Deterministic price calculation.
+//!
+//! Everything here is pure: the caller resolves goods prices, the user profile and the
+//! coupon category trees beforehand, so the same input always yields the same quote.
+
+use crate::entities::coupon::{Coupon, Discount};
+use crate::entities::order::UserOrderingProfile;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::HashMap;
+use time::PrimitiveDateTime;
+
+/// Scale of money columns in the database.
+const MONEY_SCALE: u32 = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceLine {
+    pub goods_id: i32,
+    pub category_id: Option<i32>,
+    pub unit_price: Decimal,
+    pub quantity: u32,
+}
+
+impl PriceLine {
+    fn amount(&self) -> Decimal {
+        self.unit_price * Decimal::from(self.quantity)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PricingContext {
+    pub now: PrimitiveDateTime,
+    pub user: UserOrderingProfile,
+    /// `limit_to_category` of every coupon, mapped to the category together with all of its
+    /// descendants.
+    pub category_subtrees: HashMap<i32, Vec<i32>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceQuote {
+    pub subtotal: Decimal,
+    pub discount: Decimal,
+    pub total: Decimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum CouponRejection {
+    #[error("coupon is disabled")]
+    Inactive,
+    #[error("coupon is not available yet")]
+    NotStarted,
+    #[error("coupon has expired")]
+    Expired,
+    #[error("coupon is only for the first order")]
+    NotFirstOrder,
+    #[error("coupon is only for new users")]
+    NotNewUser,
+    #[error("no goods in the order are covered by the coupon")]
+    NoEligibleGoods,
+    #[error("order does not meet the coupon condition")]
+    ConditionNotMet,
+    #[error("coupon can not be used together with other coupons")]
+    NotStackable,
+}
+
+/// Calculate the price of an order, applying the given distinct coupons.
+///
+/// Several coupons are only accepted if all of them are stackable. Each discount is calculated
+/// on the undiscounted lines, and their sum is capped at the subtotal.
+pub fn quote(
+    lines: &[PriceLine],
+    coupons: &[Coupon],
+    context: &PricingContext,
+) -> Result<PriceQuote, CouponRejection> {
+    let subtotal: Decimal = lines.iter().map(PriceLine::amount).sum();
+    if coupons.len() > 1 && coupons.iter().any(|coupon| !coupon.stackable) {
+        return Err(CouponRejection::NotStackable);
+    }
+    let mut discount = Decimal::ZERO;
+    for coupon in coupons {
+        discount += coupon_discount(lines, coupon, context)?;
+    }
+    // a discount never makes an order cheaper than free
+    let discount = discount.min(subtotal);
+    Ok(PriceQuote {
+        subtotal,
+        discount,
+        total: subtotal - discount,
+    })
+}
+
+fn coupon_discount(
+    lines: &[PriceLine],
+    coupon: &Coupon,
+    context: &PricingContext,
+) -> Result<Decimal, CouponRejection> {
+    check_eligibility(coupon, context)?;
+    let eligible: Vec<&PriceLine> = lines
+        .iter()
+        .filter(|line| covers(coupon, context, line))
+        .collect();
+    if eligible.is_empty() {
+        return Err(CouponRejection::NoEligibleGoods);
+    }
+    Ok(calculate_discount(&coupon.discount.0, &eligible)?
+        .round_dp_with_strategy(MONEY_SCALE, RoundingStrategy::ToZero))
+}
+
+fn check_eligibility(coupon: &Coupon, context: &PricingContext) -> Result<(), CouponRejection> {
+    if !coupon.set_active {
+        return Err(CouponRejection::Inactive);
+    }
+    if coupon
+        .available_since
+        .is_some_and(|since| context.now < since)
+    {
+        return Err(CouponRejection::NotStarted);
+    }
+    if coupon
+        .available_until
+        .is_some_and(|until| context.now >= until)
+    {
+        return Err(CouponRejection::Expired);
+    }
+    if coupon.first_order_only && context.user.placed_order_count > 0 {
+        return Err(CouponRejection::NotFirstOrder);
+    }
+    if let Some(days) = coupon.new_user_within_days {
+        let deadline = context.user.registered_at + time::Duration::days(days.into());
+        if context.now >= deadline {
+            return Err(CouponRejection::NotNewUser);
+        }
+    }
+    Ok(())
+}
+
+fn covers(coupon: &Coupon, context: &PricingContext, line: &PriceLine) -> bool {
+    let goods_allowed = coupon
+        .limit_to_goods
+        .as_ref()
+        .is_none_or(|goods| goods.contains(&line.goods_id));
+    let category_allowed = coupon.limit_to_category.is_none_or(|limit| {
+        line.category_id.is_some_and(|category| {
+            context
+                .category_subtrees
+                .get(&limit)
+                .is_some_and(|subtree| subtree.contains(&category))
+        })
+    });
+    goods_allowed && category_allowed
+}
+
+fn calculate_discount(
+    discount: &Discount,
+    eligible: &[&PriceLine],
+) -> Result<Decimal, CouponRejection> {
+    let eligible_amount: Decimal = eligible.iter().map(|line| line.amount()).sum();
+    match discount {
+        Discount::Rate(rate) => {
+            let off = eligible_amount * rate.rate;
+            Ok(match rate.max_discount {
+                Some(cap) => off.min(cap),
+                None => off,
+            })
+        }
+        Discount::Amount(amount) => {
+            if eligible_amount < amount.min_amount {
+                return Err(CouponRejection::ConditionNotMet);
+            }
+            Ok(amount.discount.min(eligible_amount))
+        }
+        Discount::BuyXGetY(bogo) => {
+            let Some(group_size) = bogo.buy.checked_add(bogo.get).filter(|size| *size > 0) else {
+                return Err(CouponRejection::ConditionNotMet);
+            };
+            let mut lines: Vec<&PriceLine> = eligible
+                .iter()
+                .copied()
+                .filter(|line| line.goods_id == bogo.goods_id)
+                .collect();
+            let Some(quantity) = lines
+                .iter()
+                .try_fold(0_u32, |sum, line| sum.checked_add(line.quantity))
+            else {
+                return Err(CouponRejection::ConditionNotMet);
+            };
+            // at most `quantity`, as `get` is at most `group_size`
+            let free = quantity / group_size * bogo.get;
+            if free == 0 {
+                return Err(CouponRejection::ConditionNotMet);
+            }
+            // the same goods may appear on several lines, make the cheapest units free
+            lines.sort_by_key(|line| line.unit_price);
+            let mut remaining = free;
+            let mut off = Decimal::ZERO;
+            for line in lines {
+                let taken = line.quantity.min(remaining);
+                off += line.unit_price * Decimal::from(taken);
+                remaining -= taken;
+                if remaining == 0 {
+                    break;
+                }
+            }
+            Ok(off)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::coupon::{AmountDiscount, BuyXGetYDiscount, RateDiscount};
+    use rust_decimal::dec;
+    use time::macros::datetime;
+
+    const NOW: PrimitiveDateTime = datetime!(2026-03-01 12:00);
+
+    fn coupon(discount: Discount) -> Coupon {
+        Coupon {
+            id: 1,
+            code: "TEST".to_string(),
+            set_active: true,
+            discount: sqlx::types::Json(discount),
+            available_since: None,
+            available_until: None,
+            limit_to_category: None,
+            limit_per_user: None,
+            limit_total: None,
+            limit_to_goods: None,
+            first_order_only: false,
+            new_user_within_days: None,
+            stackable: false,
+            used_count: 0,
+            created_at: NOW,
+            updated_at: NOW,
+        }
+    }
+
+    fn context() -> PricingContext {
+        PricingContext {
+            now: NOW,
+            user: UserOrderingProfile {
+                registered_at: datetime!(2026-02-20 00:00),
+                placed_order_count: 0,
+            },
+            category_subtrees: HashMap::new(),
+        }
+    }
+
+    fn line(
+        goods_id: i32,
+        category_id: Option<i32>,
+        unit_price: Decimal,
+        quantity: u32,
+    ) -> PriceLine {
+        PriceLine {
+            goods_id,
+            category_id,
+            unit_price,
+            quantity,
+        }
+    }
+
+    fn rate(rate: Decimal, max_discount: Option<Decimal>) -> Discount {
+        Discount::Rate(RateDiscount { rate, max_discount })
+    }
+
+    fn expected(subtotal: Decimal, discount: Decimal) -> Result<PriceQuote, CouponRejection> {
+        Ok(PriceQuote {
+            subtotal,
+            discount,
+            total: subtotal - discount,
+        })
+    }
+
+    #[test]
+    fn no_coupon_sums_lines() {
+        let lines = [line(1, None, dec!(9.99), 3), line(2, None, dec!(0.50), 1)];
+        assert_eq!(
+            quote(&lines, &[], &context()),
+            expected(dec!(30.47), Decimal::ZERO)
+        );
+    }
+
+    #[test]
+    fn rate_discount_is_rounded_towards_zero() {
+        let lines = [line(1, None, dec!(3.33), 1)];
+        let coupon = coupon(rate(dec!(0.333), None));
+        // 3.33 * 0.333 = 1.10889
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            expected(dec!(3.33), dec!(1.1088))
+        );
+    }
+
+    #[test]
+    fn rate_discount_respects_cap() {
+        let lines = [line(1, None, dec!(200), 1)];
+        let coupon = coupon(rate(dec!(0.2), Some(dec!(15))));
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            expected(dec!(200), dec!(15))
+        );
+    }
+
+    #[test]
+    fn amount_discount_requires_min_amount() {
+        let coupon = coupon(Discount::Amount(AmountDiscount {
+            min_amount: dec!(50),
+            discount: dec!(10),
+        }));
+        let lines = [line(1, None, dec!(49.99), 1)];
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            Err(CouponRejection::ConditionNotMet)
+        );
+        let lines = [line(1, None, dec!(50), 1)];
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            expected(dec!(50), dec!(10))
+        );
+    }
+
+    #[test]
+    fn amount_discount_only_counts_covered_goods() {
+        let mut coupon = coupon(Discount::Amount(AmountDiscount {
+            min_amount: dec!(5),
+            discount: dec!(10),
+        }));
+        coupon.limit_to_goods = Some(vec![1]);
+        let lines = [line(1, None, dec!(6), 1), line(2, None, dec!(100), 1)];
+        // the discount can not exceed the amount of covered goods
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            expected(dec!(106), dec!(6))
+        );
+    }
+
+    #[test]
+    fn buy_two_get_one_free() {
+        let coupon = coupon(Discount::BuyXGetY(BuyXGetYDiscount {
+            goods_id: 7,
+            buy: 2,
+            get: 1,
+        }));
+        let lines = [line(7, None, dec!(10), 7), line(8, None, dec!(4), 1)];
+        // 7 units make two complete groups of three
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            expected(dec!(74), dec!(20))
+        );
+    }
+
+    #[test]
+    fn buy_x_get_y_frees_cheapest_units_first() {
+        let coupon = coupon(Discount::BuyXGetY(BuyXGetYDiscount {
+            goods_id: 7,
+            buy: 1,
+            get: 1,
+        }));
+        let lines = [line(7, None, dec!(12), 2), line(7, None, dec!(8), 1)];
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            expected(dec!(32), dec!(8))
+        );
+    }
+
+    #[test]
+    fn buy_x_get_y_needs_a_complete_group() {
+        let coupon = coupon(Discount::BuyXGetY(BuyXGetYDiscount {
+            goods_id: 7,
+            buy: 2,
+            get: 1,
+        }));
+        let lines = [line(7, None, dec!(10), 2)];
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            Err(CouponRejection::ConditionNotMet)
+        );
+    }
+
+    #[test]
+    fn category_limit_covers_subtree() {
+        let mut coupon = coupon(rate(dec!(0.5), None));
+        coupon.limit_to_category = Some(3);
+        let mut context = context();
+        context.category_subtrees.insert(3, vec![3, 4]);
+        let lines = [line(1, Some(4), dec!(10), 1), line(2, Some(5), dec!(10), 1)];
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context),
+            expected(dec!(20), dec!(5))
+        );
+        let lines = [line(2, Some(5), dec!(10), 1), line(3, None, dec!(10), 1)];
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context),
+            Err(CouponRejection::NoEligibleGoods)
+        );
+    }
+
+    #[test]
+    fn availability_window() {
+        let lines = [line(1, None, dec!(10), 1)];
+        let mut coupon = coupon(rate(dec!(0.1), None));
+        coupon.available_since = Some(datetime!(2026-03-02 00:00));
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            Err(CouponRejection::NotStarted)
+        );
+        coupon.available_since = None;
+        coupon.available_until = Some(NOW);
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            Err(CouponRejection::Expired)
+        );
+        coupon.set_active = false;
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            Err(CouponRejection::Inactive)
+        );
+    }
+
+    #[test]
+    fn first_order_only() {
+        let lines = [line(1, None, dec!(10), 1)];
+        let mut coupon = coupon(rate(dec!(0.1), None));
+        coupon.first_order_only = true;
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            expected(dec!(10), dec!(1))
+        );
+        let mut context = context();
+        context.user.placed_order_count = 1;
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context),
+            Err(CouponRejection::NotFirstOrder)
+        );
+    }
+
+    #[test]
+    fn new_user_only() {
+        let lines = [line(1, None, dec!(10), 1)];
+        let mut coupon = coupon(rate(dec!(0.1), None));
+        // registered 9.5 days before now
+        coupon.new_user_within_days = Some(10);
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            expected(dec!(10), dec!(1))
+        );
+        coupon.new_user_within_days = Some(7);
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&coupon), &context()),
+            Err(CouponRejection::NotNewUser)
+        );
+    }
+
+    #[test]
+    fn stacked_coupons_add_up() {
+        let lines = [line(1, None, dec!(100), 1)];
+        let mut first = coupon(rate(dec!(0.1), None));
+        let mut second = coupon(Discount::Amount(AmountDiscount {
+            min_amount: dec!(50),
+            discount: dec!(5),
+        }));
+        second.id = 2;
+        assert_eq!(
+            quote(&lines, &[first.clone(), second.clone()], &context()),
+            Err(CouponRejection::NotStackable)
+        );
+        first.stackable = true;
+        second.stackable = true;
+        assert_eq!(
+            quote(&lines, &[first, second], &context()),
+            expected(dec!(100), dec!(15))
+        );
+    }
+
+    #[test]
+    fn stacked_discount_is_capped_at_subtotal() {
+        let lines = [line(1, None, dec!(10), 1)];
+        let mut first = coupon(rate(dec!(0.8), None));
+        first.stackable = true;
+        let mut second = first.clone();
+        second.id = 2;
+        assert_eq!(
+            quote(&lines, &[first, second], &context()),
+            expected(dec!(10), dec!(10))
+        );
+    }
+
+    #[test]
+    fn buy_x_get_y_does_not_overflow() {
+        let huge_group = coupon(Discount::BuyXGetY(BuyXGetYDiscount {
+            goods_id: 7,
+            buy: u32::MAX,
+            get: 1,
+        }));
+        let lines = [line(7, None, dec!(1), 3)];
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&huge_group), &context()),
+            Err(CouponRejection::ConditionNotMet)
+        );
+        let one_free = coupon(Discount::BuyXGetY(BuyXGetYDiscount {
+            goods_id: 7,
+            buy: 1,
+            get: 1,
+        }));
+        let lines = [line(7, None, dec!(1), u32::MAX), line(7, None, dec!(1), 1)];
+        assert_eq!(
+            quote(&lines, std::slice::from_ref(&one_free), &context()),
+            Err(CouponRejection::ConditionNotMet)
+        );
+    }
+}
@@ -5,6 +5,9 @@ fn main() -> std::io::Result<()> {
         .compile_protos(
             &[
                 "../../proto/v1/admin/admin-auth.proto",
+                "../../proto/v1/admin/admin-manage.proto",
+                "../../proto/v1/admin/audit-log.proto",
+                "../../proto/v1/admin/config-history.proto",
                 "../../proto/v1/common/values.proto",
                 "../../proto/v1/auth/admin/user-manage.proto",
                 "../../proto/v1/auth/common/account.proto",
@@ -12,6 +15,12 @@ fn main() -> std::io::Result<()> {
                 "../../proto/v1/auth/user/account-manage.proto",
                 "../../proto/v1/auth/user/auth.proto",
                 "../../proto/v1/auth/user/mfa.proto",
+                "../../proto/v1/ordering/admin/coupon.proto",
+                "../../proto/v1/ordering/admin/order.proto",
+                "../../proto/v1/ordering/common/order.proto",
+                "../../proto/v1/ordering/user/order.proto",
+                "../../proto/v1/notifications/admin/merchant-webhook.proto",
+                "../../proto/v1/notifications/user/preference.proto",
             ],
             &["../../proto"],
         )?;
@@ -5,9 +5,7 @@ impl From<()> for super::v1::common::Empty {
 }
 
 impl From<super::v1::common::Empty> for () {
-    fn from(_: super::v1::common::Empty) -> Self {
-        ()
-    }
+    fn from(_: super::v1::common::Empty) -> Self {}
 }
 
 impl From<time::Date> for super::v1::common::Date {
@@ -14,8 +14,7 @@ pub mod v1 {
     }
     pub mod auth {
         pub mod admin {
-            // it's empty
-            // tonic::include_proto!("phantom_store.v1.auth.admin");
+            tonic::include_proto!("phantom_store.v1.auth.admin");
         }
         pub mod common {
             tonic::include_proto!("phantom_store.v1.auth.common");
@@ -24,4 +23,23 @@ pub mod v1 {
             tonic::include_proto!("phantom_store.v1.auth.user");
         }
     }
+    pub mod ordering {
+        pub mod admin {
+            tonic::include_proto!("phantom_store.v1.ordering.admin");
+        }
+        pub mod common {
+            tonic::include_proto!("phantom_store.v1.ordering.common");
+        }
+        pub mod user {
+            tonic::include_proto!("phantom_store.v1.ordering.user");
+        }
+    }
+    pub mod notifications {
+        pub mod admin {
+            tonic::include_proto!("phantom_store.v1.notifications.admin");
+        }
+        pub mod user {
+            tonic::include_proto!("phantom_store.v1.notifications.user");
+        }
+    }
 }